@@ -2,6 +2,7 @@ media-browser = Media Browser
 empty-folder = Empty folder
 empty-folder-hidden = Empty folder (has hidden items)
 no-results = No results found
+searching = Searching…
 filesystem = Filesystem
 home = Home
 networks = Networks
@@ -14,6 +15,7 @@ today = Today
 # List view
 name = Name
 modified = Modified
+relevance = Relevance
 trashed-on = Trashed
 size = Size
 
@@ -48,9 +50,34 @@ name-hidden = Names starting with "." will be hidden.
 name-invalid = Name cannot be "{$filename}".
 name-no-slashes = Name cannot contain slashes.
 recursive-scan-directories = Scan all subdirectories for media
+recursive-scan-complete = Scanned {$files} files in {$name} ({$rate} files/s)
+verify-library = Verify library
+verify-library-complete = {$name}: {$added} added, {$changed} changed, {$removed} removed, {$unchanged} unchanged
+export = Export
+export-library = Export library
+export-search-results = Export {$count} search results
+export-search-results-menu = Export search results
+export-library-complete = Exported {$count} entries to {$path}
+export-library-failed = Export failed: {$error}
+write-nfo = Write NFO files
+write-nfo-report = {$changed} changed, {$created} new, {$unchanged} up to date, {$failed} failed
+write-nfo-clean = The NFO files already match the library.
+write-nfo-dry-run = Nothing was written yet, these lines would change.
+write-nfo-backup = The previous files were kept as .nfo.bak.
+exchange-file = File
+exchange-file-format = Files ending in .csv are written as CSV, all others as JSON Lines.
+import = Import
+import-library = Import library
+import-rewrite-from = Replace the path prefix
+import-rewrite-to = with
+import-rewrite-both = Enter both path prefixes or neither.
+import-merge-duplicates = Add tags to entries that are already in the library
+import-library-complete = {$imported} imported, {$merged} merged, {$skipped} skipped, {$missing} not found
+import-library-failed = Import failed: {$error}
 
 ## Open/Save Dialog
 cancel = Cancel
+close = Close
 create = Create
 open = Open
 open-file = Open file
@@ -149,6 +176,16 @@ copied = Copied {$items} {$items ->
     } from {$from} to {$to}
 emptying-trash = Emptying {trash}
 emptied-trash = Emptied {trash}
+maintaining-library = Checking library
+maintained-library = Checked library
+writing-nfo = Writing NFO files of {$items} {$items ->
+        [one] item
+        *[other] items
+    }
+wrote-nfo = Wrote NFO files of {$items} {$items ->
+        [one] item
+        *[other] items
+    }
 setting-executable-and-launching = Setting "{$name}" as executable and launching
 set-executable-and-launched = Set "{$name}" as executable and launched
 moving = Moving {$items} {$items ->
@@ -186,11 +223,29 @@ item-accessed = Accessed: {$accessed}
 calculating = Calculating...
 item-media-release-date = Release Date: {$text}
 item-media-size = Resolution: {$width} x {$height}
+item-video-frame-rate = Frame rate: {$fps} fps
+item-video-codec = Codec: {$text}
 item-media-runtime = Duration: {$text}
+item-video-episode = Episode: {$text}
+item-video-edition = Edition: {$text}
+item-video-source = Source: {$text}
+item-video-original-title = Original title: {$text}
+item-video-certification = Rated: {$text}
+item-video-rating = {$source}: {$value} of {$max}
+item-video-rating-votes = {$source}: {$value} of {$max} from {$votes} {$votes ->
+        [one] vote
+        *[other] votes
+    }
+item-video-set = Collection: {$text}
+item-video-studio = Studio: {$text}
+item-video-country = Country: {$text}
+item-video-id = {$kind}: {$text}
 item-audio-languange = Languages: {$text}
 item-subtitle-language = Subtitles: {$text}
 item-media-actor = Actor: {$text}
+item-media-actor-role = Actor: {$text} as {$role}
 item-media-director = Director: {$text}
+item-media-writer = Writer: {$text}
 item-media-tag = Tag: {$text}
 item-media-album = Album: {$text}
 item-media-composer = Composer: {$text}
@@ -204,13 +259,53 @@ item-image-fnumber = Lense crop factor: {$text}
 item-image-gps-latitude = GPS Latitude: {$text}
 item-image-gps-longitude = GPS Longitude: {$text}
 item-image-gps-altitude = GPS Altitude: {$text}
+item-image-gps-near = Photos taken near here
 item-media-chapter = Chapter: {$id}, from {$start} to {$end}
+related-items = Related items
+related-items-find = Find related items
+related-items-none = Nothing in the library shares a person, collection, tag or place with this item
+related-collection = Collection: {$text}
+related-same-day = Same day: {$text}
+related-nearby = Taken {$text} away
 
 ## Search
 search-context = Search the database
-search-previous = Previous search terms
+search-previous = Saved and previous searches
+search-previous-entry = {$search} · {$count} {$count ->
+        [one] result
+        *[other] results
+    } · {$date}
 search-select = Select
 search-delete = Delete
+search-duplicate = Duplicate
+search-copy = Copy of {$name}
+search-move-up = Move up
+search-move-down = Move down
+search-query = Search query
+search-query-tooltip = 
+    Words are looked up in titles, descriptions, people, albums, genres, tags and lyrics
+    Fields: title: actor: director: artist: album: composer: genre: tag: path: lens:
+        writer: studio: country: set: mpaa: id:tt0111161
+        duration:>90m  released:2010..2015  created:2024-05  lat:-34..-33
+        near:-33.86,151.21,2km  area:-34,151,-33.5,151.5
+        resolution:>=4k  fps:23.976  bitrate:<192k  codec:hevc  orientation:portrait
+    Case and accents are ignored, a leading ~ also accepts typos: ~tarentino  director:~"quentin tarentino"
+    Combine terms with AND, OR, NOT or a leading - and group them with ( )
+    A query replaces the fields below, only the media types still apply
+    Names of the library are suggested while typing, pick one with the arrow keys and Enter
+search-query-error = Query error: {$error}
+search-fuzzy = Accept typos
+search-tooltip-fuzzy = Also find titles, names, albums and paths that are spelled a little differently
+search-suggestion = {$field} · {$count} {$count ->
+        [one] file
+        *[other] files
+    }
+search-results-shown = Showing {$shown} of {$total} {$total ->
+        [one] result
+        *[other] results
+    }
+search-results-more = Load more
+search-results-stop = Stop
 search-mediatypes = Search Media types
 search-images = Images
 search-videos = Videos
@@ -250,7 +345,18 @@ search-fnumber = F-number
 search-gps_latitude = GPS latitude
 search-gps_longitude = GPS longitude
 search-gps_altitude = GPS altitude
+search-resolution = Resolution (e.g. 720p, 4k)
+search-frame_rate = Frame rate
+search-bitrate = Audio bitrate (kbit/s)
+search-codec = Video codec
+search-aspect_ratio = Aspect ratio
+search-tooltip-aspect_ratio = A ratio like 16:9 or 1.78, or portrait, landscape or square
 search-commit = Start Search
+search-save-as = Save as
+search-name = Name
+search-folder = Folder
+search-save = Save the search
+search-save-changes = Save the changes to the selected search
 
 ## Settings
 settings = Settings
@@ -267,6 +373,17 @@ metadata = Metadata / Database
 metadata-delete = Delete Metadata / Database
 metadata-details = {$items} Files with size {$size} MB
     at {$location}
+library-check = Check library and clean up
+poster-position = Take video posters at
+poster-position-percent = {$percent} % of the duration
+library-cleanup = Clean up library
+library-maintenance = Library maintenance
+library-maintenance-report = {$missing} missing files, {$orphans} orphaned entries, {$people} unused people, {$albums} unused albums, {$artists} unused artists, {$thumbnails} unused thumbnails ({$size})
+library-maintenance-clean = Nothing to clean up.
+library-maintenance-dry-run = Nothing was changed yet.
+library-maintenance-vacuum = The database was compacted, {$size} freed.
+library-integrity-ok = Integrity check passed.
+library-integrity-failed = Integrity check found problems:
 
 ### Appearance
 appearance = Appearance
@@ -280,6 +397,8 @@ add-to-sidebar = Add to sidebar
 add-new-tag = Add tag to sidebar
 create-new-tag = Create new tag
 tag-name = Tag name
+pin-search = Pin as smart collection
+smart-collection-name = Smart collection name
 compress = Compress
 extract-here = Extract
 new-file = New file...
@@ -289,9 +408,21 @@ move-to-trash = Move to trash
 restore-from-trash = Restore from trash
 remove-from-sidebar = Remove path from sidebar
 remove-tag-from-sidebar = Remove tag from sidebar
+remove-smart-collection = Remove smart collection from sidebar
 sort-by-name = Sort by name
 sort-by-modified = Sort by modified
 sort-by-size = Sort by size
+sort-by-relevance = Sort by relevance
+sort-by-release-date = Sort by release date
+sort-by-capture-time = Sort by capture time
+sort-by-duration = Sort by duration
+sort-by-resolution = Sort by resolution
+sort-by-track-number = Sort by track number
+sort-by-album = Sort by album
+sort-by-artist = Sort by artist
+sort-by-episode = Sort by season and episode
+sort-by-rating = Sort by rating
+sort-by-date-added = Sort by date added
 
 # Menu
 
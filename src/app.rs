@@ -24,7 +24,7 @@ use cosmic::{
         clipboard::dnd::DndAction,
         event,
         futures::{self, SinkExt},
-        keyboard::{Event as KeyEvent, Key, Modifiers},
+        keyboard::{self, Event as KeyEvent, Key, Modifiers},
         stream,
         window::{self, Event as WindowEvent, Id as WindowId},
         Alignment, Event, Length, Rectangle, Size, Subscription,
@@ -52,7 +52,7 @@ use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     env, fmt, fs,
     num::NonZeroU16,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
     sync::{Arc, Mutex},
     time::{self, Instant},
@@ -126,9 +126,12 @@ pub enum Action {
     EditHistory,
     EditLocation,
     EmptyTrash,
+    ExportLibrary,
+    ExportSearchResults,
     Fullscreen,
     HistoryNext,
     HistoryPrevious,
+    ImportLibrary,
     ItemDown,
     ItemLeft,
     ItemRight,
@@ -141,6 +144,7 @@ pub enum Action {
     OpenInNewWindow,
     OpenItemLocation,
     Paste,
+    PinSearch,
     PlayPause,
     PlayFromBeginning,
     //PlaySkip,
@@ -166,8 +170,10 @@ pub enum Action {
     ToggleFoldersFirst,
     ToggleShowHidden,
     ToggleSort(HeadingOptions),
+    VerifyLibrary,
     WindowClose,
     WindowNew,
+    WriteNfo,
     ZoomDefault,
     ZoomIn,
     ZoomOut,
@@ -195,9 +201,12 @@ impl Action {
                 Message::TabMessage(entity_opt, tab::Message::EditLocationEnable)
             }
             Action::EmptyTrash => Message::TabMessage(None, tab::Message::EmptyTrash),
+            Action::ExportLibrary => Message::ExportLibrary,
+            Action::ExportSearchResults => Message::ExportSearchResults(entity_opt),
             Action::Fullscreen => Message::Fullscreen,
             Action::HistoryNext => Message::TabMessage(entity_opt, tab::Message::GoNext),
             Action::HistoryPrevious => Message::TabMessage(entity_opt, tab::Message::GoPrevious),
+            Action::ImportLibrary => Message::ImportLibrary,
             Action::ItemDown => Message::TabMessage(entity_opt, tab::Message::ItemDown),
             Action::ItemLeft => Message::TabMessage(entity_opt, tab::Message::ItemLeft),
             Action::ItemRight => Message::TabMessage(entity_opt, tab::Message::ItemRight),
@@ -212,11 +221,14 @@ impl Action {
             Action::OpenInNewWindow => Message::OpenInNewWindow(entity_opt),
             Action::OpenItemLocation => Message::OpenItemLocation(entity_opt),
             Action::Paste => Message::Paste(entity_opt),
+            Action::PinSearch => Message::PinSearch(entity_opt),
             Action::PlayFromBeginning => Message::Seek(0.0),
             Action::PlayPause => Message::PlayPause,
             Action::Preview => Message::Preview(entity_opt),
             Action::Previous => Message::Previous(entity_opt),
             Action::RecursiveScanDirectories => Message::RecursiveScanDirectories(entity_opt),
+            Action::VerifyLibrary => Message::VerifyLibrary(entity_opt),
+            Action::WriteNfo => Message::WriteNfo(entity_opt),
             Action::Rename => Message::Rename(entity_opt),
             Action::RestoreFromTrash => Message::RestoreFromTrash(entity_opt),
             Action::SearchActivate => Message::SearchActivate,
@@ -285,6 +297,7 @@ pub enum NavMenuAction {
     Preview(segmented_button::Entity),
     RemoveFromSidebar(segmented_button::Entity),
     RemoveTagFromSidebar(segmented_button::Entity),
+    RemoveSmartCollection(segmented_button::Entity),
     EmptyTrash,
 }
 
@@ -331,14 +344,22 @@ pub enum Message {
     DialogPush(DialogPage),
     DialogUpdate(DialogPage),
     DialogUpdateComplete(DialogPage),
+    ExportComplete(PathBuf, Result<usize, String>),
+    ExportLibrary,
+    ExportSearchResults(Option<Entity>),
     ImageMessage(crate::image::image_view::Message),
+    ImportComplete(Result<crate::sql::exchange::Summary, String>),
+    ImportLibrary,
     Key(Modifiers, Key),
     LaunchUrl(String),
     LaunchSearch(crate::sql::SearchType, String),
+    LibraryMaintenance(bool),
     MaybeExit,
     MetadataDelete,
     Modifiers(Modifiers),
     MoveToTrash(Option<Entity>),
+    MovieSet(String),
+    MovieSetFound(crate::sql::CollectionMetadata),
     MounterItems(MounterKey, MounterItems),
     MountResult(MounterKey, MounterItem, Result<bool, String>),
     MouseScroll(cosmic::iced_core::mouse::ScrollDelta),
@@ -360,6 +381,7 @@ pub enum Message {
     OpenInNewWindow(Option<Entity>),
     OpenItemLocation(Option<Entity>),
     Paste(Option<Entity>),
+    PosterPosition(u8),
     PasteContents(PathBuf, ClipboardPaste),
     PendingCancel(u64),
     PendingCancelAll,
@@ -368,8 +390,12 @@ pub enum Message {
     PendingError(u64, String),
     PendingPause(u64, bool),
     PendingPauseAll(bool),
+    PinSearch(Option<Entity>),
     Preview(Option<Entity>),
     RecursiveScanDirectories(Option<Entity>),
+    RecursiveScanComplete(crate::tab::ScanSummary),
+    RelatedItems(String),
+    RelatedItemsFound(String, Vec<crate::sql::related::Related>),
     RescanTrash,
     Rename(Option<Entity>),
     RenameWithPattern(Option<Entity>, String, i32, i32),
@@ -382,11 +408,20 @@ pub enum Message {
     SearchPreviousPick(usize),
     SearchPreviousSelect,
     SearchPreviousDelete,
+    SearchPreviousDuplicate,
+    SearchPreviousDuplicated(u32, crate::sql::SearchData),
+    SearchPreviousMove(bool),
+    SearchName(String),
+    SearchFolder(String),
+    SearchSave,
     SearchImages(bool),
     SearchVideos(bool),
     SearchAudios(bool),
     SearchSearchString(String),
     SearchSearchStringSubmit,
+    SearchSuggestion(usize),
+    SearchSuggestionKey(Key),
+    SearchVocabulary(Vec<crate::sql::suggest::Entry>),
     SearchSearchFromString(String),
     SearchSearchFromStringSubmit,
     SearchSearchToString(String),
@@ -417,11 +452,21 @@ pub enum Message {
     SearchGpsLatitude(bool),
     SearchGpsLongitude(bool),
     SearchGpsAltitude(bool),
+    SearchResolution(bool),
+    SearchFrameRate(bool),
+    SearchBitrate(bool),
+    SearchCodec(bool),
+    SearchAspectRatio(bool),
+    SearchFuzzy(bool),
     SearchCommit,
+    SearchCommitted(crate::sql::SearchData),
+    SearchSaved(crate::sql::SearchData),
     SeekBackward,
     SeekForward,
     SetShowDetails(bool),
+    TagCreated(crate::sql::Tag),
     SkipToPosition(f64),
+    SmartCollectionCounts(Vec<(String, usize)>),
     SystemThemeModeChange(cosmic_theme::ThemeMode),
     Size(Size),
     TabActivate(Entity),
@@ -444,9 +489,12 @@ pub enum Message {
     Undo(usize),
     UndoTrash(widget::ToastId, Arc<[PathBuf]>),
     UndoTrashStart(Vec<TrashItem>),
+    VerifyLibrary(Option<Entity>),
+    VerifyLibraryComplete(crate::tab::VerifySummary),
     VideoMessage(crate::video::video_view::Message),
     WindowClose,
     WindowNew,
+    WriteNfo(Option<Entity>),
     ZoomDefault(Option<Entity>),
     ZoomIn(Option<Entity>),
     ZoomOut(Option<Entity>),
@@ -478,7 +526,18 @@ pub enum ContextPage {
 #[derive(Clone, Debug)]
 pub enum DialogPage {
     EmptyTrash,
+    Export {
+        path: String,
+        paths: Option<Vec<PathBuf>>,
+    },
     FailedOperation(u64),
+    Import {
+        path: String,
+        from: String,
+        to: String,
+        merge: bool,
+    },
+    MaintenanceReport(crate::sql::maintenance::Report),
     MountError {
         mounter_key: MounterKey,
         item: MounterItem,
@@ -503,6 +562,10 @@ pub enum DialogPage {
     NewTag {
         tag: String,
     },
+    NfoReport {
+        paths: Vec<PathBuf>,
+        report: crate::parsers::nfo::export::Report,
+    },
     OpenWith {
         path: PathBuf,
         mime: mime_guess::Mime,
@@ -510,6 +573,10 @@ pub enum DialogPage {
         selected: usize,
         store_opt: Option<mime_app::MimeApp>,
     },
+    PinSearch {
+        name: String,
+        search: crate::sql::SearchData,
+    },
     RenameItem {
         from: PathBuf,
         parent: PathBuf,
@@ -527,6 +594,8 @@ pub enum DialogPage {
 
 pub struct FavoriteIndex(usize);
 
+pub struct SmartCollectionIndex(usize);
+
 pub struct MounterData(MounterKey, MounterItem);
 
 #[derive(Clone, Debug)]
@@ -572,6 +641,7 @@ pub struct App {
     config: Config,
     mode: Mode,
     app_themes: Vec<String>,
+    poster_positions: Vec<String>,
     context_page: ContextPage,
     dialog_pages: VecDeque<DialogPage>,
     dialog_text_input: widget::Id,
@@ -594,9 +664,16 @@ pub struct App {
     search_previous: Vec<crate::sql::SearchData>,
     search_previous_str: Vec<String>,
     search_previous_pos: usize,
+    search_editing: Option<u32>,
     search_from_string: widget::Id,
     search_to_string: widget::Id,
+    search_query: widget::Id,
+    search_vocabulary: Vec<crate::sql::suggest::Entry>,
+    search_suggestions: Vec<crate::sql::suggest::Entry>,
+    search_suggestion_pos: Option<usize>,
+    related_items: Option<(String, Vec<crate::sql::related::Related>)>,
     size: Option<Size>,
+    smart_collection_counts: HashMap<String, usize>,
     #[cfg(feature = "wayland")]
     surface_ids: HashMap<WlOutput, WindowId>,
     #[cfg(feature = "wayland")]
@@ -609,7 +686,7 @@ pub struct App {
     tab_dnd_hover: Option<(Entity, Instant)>,
     nav_drag_id: DragId,
     tab_drag_id: DragId,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: crate::sql::Database,
 }
 
 impl App {
@@ -622,6 +699,13 @@ impl App {
         self.open_tab_entity(location, activate, selection_paths).1
     }
 
+    /// Sort order remembered for a location.
+    fn location_sort(&self, location: &Location) -> Option<tab::LocationSort> {
+        location
+            .sort_key()
+            .and_then(|key| self.config.location_sorts.get(&key).copied())
+    }
+
     fn open_tab_entity(
         &mut self,
         location: Location,
@@ -629,6 +713,7 @@ impl App {
         selection_paths: Option<Vec<PathBuf>>,
     ) -> (Entity, Task<Message>) {
         let mut tab = Tab::new(location.clone(), self.config.tab);
+        tab.restore_sort(self.location_sort(&location));
         tab.mode = match self.mode {
             Mode::App => tab::Mode::App,
             Mode::Desktop => {
@@ -705,15 +790,40 @@ impl App {
         selection_paths: Option<Vec<PathBuf>>,
     ) -> Task<Message> {
         log::info!("rescan_tab {entity:?} {location:?} {selection_paths:?}");
+        let database = self.database.clone();
+        let search = match &location {
+            Location::DBSearch(search) => Some(search.clone()),
+            Location::SmartCollection(collection) => Some(collection.search.clone()),
+            _ => None,
+        };
+        if let Some(search) = search {
+            // only the files are looked up here, the tab builds their items page by page
+            return Task::perform(
+                async move {
+                    match tokio::task::spawn_blocking(move || {
+                        database.read(|connection| crate::sql::search_files(connection, &search))
+                    })
+                    .await
+                    {
+                        Ok(found) => message::app(Message::TabMessage(
+                            Some(entity),
+                            tab::Message::DatabaseSearchFound(location, found),
+                        )),
+                        Err(err) => {
+                            log::warn!("failed to search database: {}", err);
+                            message::none()
+                        }
+                    }
+                },
+                |x| x,
+            );
+        }
         let icon_sizes = self.config.tab.icon_sizes;
-        let sql_connection = self.sql_connection.clone();
         Task::perform(
             async move {
                 let location2 = location.clone();
-                match tokio::task::spawn_blocking(move || {
-                    location2.scan(sql_connection, icon_sizes)
-                })
-                .await
+                match tokio::task::spawn_blocking(move || location2.scan(&database, icon_sizes))
+                    .await
                 {
                     Ok((parent_item_opt, items)) => message::app(Message::TabRescan(
                         entity,
@@ -749,6 +859,57 @@ impl App {
         Task::batch(commands)
     }
 
+    /// Evaluate the smart collections again after the library changed: recount them for the
+    /// sidebar and rescan the tabs that show one.
+    fn rescan_smart_collections(&mut self) -> Task<Message> {
+        let mut needs_reload = Vec::new();
+        for entity in self.tab_model.iter() {
+            if let Some(tab) = self.tab_model.data::<Tab>(entity) {
+                if let Location::SmartCollection(_) = &tab.location {
+                    needs_reload.push((entity, tab.location.clone()));
+                }
+            }
+        }
+
+        let mut commands = Vec::with_capacity(needs_reload.len() + 1);
+        for (entity, location) in needs_reload {
+            commands.push(self.rescan_tab(entity, location, None));
+        }
+        let collections = self.config.smart_collections.clone();
+        if !collections.is_empty() {
+            let database = self.database.clone();
+            commands.push(Task::perform(
+                async move {
+                    match tokio::task::spawn_blocking(move || {
+                        // include writes that were only submitted so far
+                        database.flush();
+                        collections
+                            .into_iter()
+                            .map(|collection| {
+                                let count = database
+                                    .read(|connection| {
+                                        crate::sql::search_files(connection, &collection.search)
+                                    })
+                                    .len();
+                                (collection.name, count)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .await
+                    {
+                        Ok(counts) => message::app(Message::SmartCollectionCounts(counts)),
+                        Err(err) => {
+                            log::warn!("failed to count smart collections: {}", err);
+                            message::none()
+                        }
+                    }
+                },
+                |x| x,
+            ));
+        }
+        Task::batch(commands)
+    }
+
     fn search(&mut self) -> Task<Message> {
         if let Some(term) = self.search_get() {
             self.search_set(Some(term.to_string()))
@@ -757,6 +918,197 @@ impl App {
         }
     }
 
+    /// Read the saved searches again, in the order of their folders.
+    fn search_previous_reload(&mut self) {
+        self.search_previous = self.database.read(crate::sql::searches);
+        self.search_previous_labels();
+    }
+
+    fn search_previous_labels(&mut self) {
+        self.search_previous_str = self
+            .search_previous
+            .iter()
+            .map(|search| {
+                match chrono::DateTime::from_timestamp(search.last_run, 0)
+                    .filter(|_| search.last_run > 0)
+                {
+                    Some(time) => fl!(
+                        "search-previous-entry",
+                        search = search.display(),
+                        count = search.result_count,
+                        date = time
+                            .with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string()
+                    ),
+                    None => search.display(),
+                }
+            })
+            .collect();
+        if self.search_previous_pos >= self.search_previous.len() {
+            self.search_previous_pos = self.search_previous.len().saturating_sub(1);
+        }
+    }
+
+    /// Store the order of the saved searches after one was moved or added.
+    fn search_previous_renumber(&mut self) {
+        for (i, search) in self.search_previous.iter_mut().enumerate() {
+            search.position = i as u32 + 1;
+        }
+        self.search_previous_labels();
+        let search_ids: Vec<u32> = self.search_previous.iter().map(|s| s.search_id).collect();
+        self.database
+            .submit(move |connection| crate::sql::reorder_searches(connection, &search_ids));
+    }
+
+    /// Run `f` with a blocking database write off the UI thread, a scan may keep the writer
+    /// busy for a while.
+    fn database_task(
+        &self,
+        f: impl FnOnce(&crate::sql::Database) -> Message + Send + 'static,
+    ) -> Task<Message> {
+        let database = self.database.clone();
+        cosmic::task::future(async move {
+            match tokio::task::spawn_blocking(move || f(&database)).await {
+                Ok(message) => message,
+                Err(err) => {
+                    log::warn!("failed to write to the database: {}", err);
+                    Message::None
+                }
+            }
+        })
+    }
+
+    /// Note when a saved search ran and how many files it found.
+    fn search_ran(&mut self, search_id: u32, result_count: usize) {
+        let last_run = chrono::Utc::now().timestamp();
+        let result_count = u32::try_from(result_count).unwrap_or(u32::MAX);
+        if let Some(search) = self
+            .search_previous
+            .iter_mut()
+            .find(|search| search.search_id == search_id)
+        {
+            search.last_run = last_run;
+            search.result_count = result_count;
+            self.search_previous_labels();
+        }
+        self.database.submit(move |connection| {
+            crate::sql::record_search_run(connection, search_id, last_run, result_count)
+        });
+    }
+
+    /// Update the names suggested for the last term of the search query. A term with a
+    /// field prefix only gets names of that field, other terms those of the checked fields.
+    fn search_suggest(&mut self) {
+        use crate::sql::suggest;
+        use crate::sql::SearchType as ST;
+        self.search_suggestion_pos = None;
+        self.search_suggestions.clear();
+        let Some(term) = suggest::last_term(&self.search.search_string) else {
+            return;
+        };
+        let fields: Vec<ST> = match term.field {
+            Some(field) => vec![field],
+            None => {
+                let checked: Vec<ST> = [
+                    (self.search.actor, ST::Actor),
+                    (self.search.director, ST::Director),
+                    (self.search.artist, ST::Artist),
+                    (self.search.album_artist, ST::AlbumArtist),
+                    (self.search.album, ST::Album),
+                    (self.search.composer, ST::Composer),
+                    (self.search.genre, ST::Genre),
+                    (self.search.tags, ST::Tag),
+                ]
+                .into_iter()
+                .filter(|(checked, _)| *checked)
+                .map(|(_, field)| field)
+                .collect();
+                if checked.is_empty() {
+                    suggest::FIELDS.to_vec()
+                } else {
+                    checked
+                }
+            }
+        };
+        self.search_suggestions = suggest::rank(&self.search_vocabulary, &term.text, &fields, 8);
+    }
+
+    /// Arrows move through the suggestions of the search panel instead of the items, escape
+    /// closes them. Returns whether the key was used.
+    fn search_suggestion_key(&mut self, key: &Key) -> bool {
+        if !self.core.window.show_context
+            || self.context_page != ContextPage::Search
+            || self.search_suggestions.is_empty()
+        {
+            return false;
+        }
+        let last = self.search_suggestions.len() - 1;
+        match key {
+            Key::Named(keyboard::key::Named::ArrowDown) => {
+                self.search_suggestion_pos = Some(match self.search_suggestion_pos {
+                    Some(pos) if pos < last => pos + 1,
+                    Some(_) => last,
+                    None => 0,
+                });
+            }
+            Key::Named(keyboard::key::Named::ArrowUp) => {
+                self.search_suggestion_pos = match self.search_suggestion_pos {
+                    Some(0) | None => None,
+                    Some(pos) => Some(pos - 1),
+                };
+            }
+            Key::Named(keyboard::key::Named::Escape) => {
+                self.search_suggestions.clear();
+                self.search_suggestion_pos = None;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn search_suggestion_accept(&mut self, pos: usize) -> Task<Message> {
+        use crate::sql::suggest;
+        if let (Some(entry), Some(term)) = (
+            self.search_suggestions.get(pos),
+            suggest::last_term(&self.search.search_string),
+        ) {
+            self.search.search_string = suggest::complete(&self.search.search_string, &term, entry);
+        }
+        self.search_suggestions.clear();
+        self.search_suggestion_pos = None;
+        widget::text_input::focus(self.search_query.clone())
+    }
+
+    /// Paths of the selected items of a tab, or of all of them if none is selected.
+    fn selected_or_all_paths(&self, entity_opt: Option<Entity>) -> Vec<PathBuf> {
+        let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
+        let mut paths = Vec::new();
+        if let Some(tab) = self.tab_model.data::<Tab>(entity) {
+            if let Some(items) = tab.items_opt() {
+                let selected = items.iter().any(|item| item.selected);
+                match &tab.database_search {
+                    // including the results which are not shown yet
+                    Some(search) if !selected => paths = search.paths(),
+                    _ => {
+                        for item in items.iter() {
+                            if item.selected || !selected {
+                                match &item.location_opt {
+                                    Some(Location::Path(path)) => paths.push(path.to_path_buf()),
+                                    Some(Location::Collection(collection)) => {
+                                        paths.push(collection.path.clone())
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        paths
+    }
+
     fn search_get(&self) -> Option<&str> {
         let entity = self.tab_model.active();
         let tab = self.tab_model.data::<Tab>(entity)?;
@@ -824,6 +1176,7 @@ impl App {
 
     fn update_config(&mut self) -> Task<Message> {
         self.update_nav_model();
+        crate::parsers::poster::set_position(self.config.poster_position);
         // Tabs are collected first to placate the borrowck
         let tabs: Vec<_> = self.tab_model.iter().collect();
         // Update main conf and each tab with the new config
@@ -953,6 +1306,24 @@ impl App {
                 });
             }
         }
+        for (collection_i, collection) in self.config.smart_collections.iter().enumerate() {
+            // the count appears once the collection has been evaluated in the background
+            let name = match self.smart_collection_counts.get(&collection.name) {
+                Some(count) => format!("{} ({})", collection.name, count),
+                None => collection.name.clone(),
+            };
+            nav_model = nav_model.insert(move |mut b| {
+                b = b
+                    .text(name.clone())
+                    .icon(widget::icon::from_name("folder-saved-search-symbolic"))
+                    .data(Location::SmartCollection(collection.to_owned()))
+                    .data(SmartCollectionIndex(collection_i));
+                if collection_i == 0 {
+                    b = b.divider_above();
+                }
+                b
+            });
+        }
 
         self.nav_model = nav_model.build();
 
@@ -1268,6 +1639,9 @@ impl App {
                 }
             }
         }
+        if let Some(related) = self.related_items_view(entity, kind) {
+            children.push(related);
+        }
         widget::column::with_children(children)
             .padding(if context_drawer {
                 [0, 0, 0, 0]
@@ -1277,8 +1651,112 @@ impl App {
             .into()
     }
 
+    /// Items sharing people, collections, tags, days or places with the previewed video,
+    /// song or photo, once they were looked up.
+    fn related_items_view(
+        &self,
+        entity: Entity,
+        kind: &PreviewKind,
+    ) -> Option<Element<'_, Message>> {
+        use crate::sql::related::Reason;
+        let cosmic_theme::Spacing {
+            space_xxxs,
+            space_s,
+            ..
+        } = theme::active().cosmic().spacing;
+
+        let media_path = |item: &tab::Item| {
+            if let Some(video) = &item.video_opt {
+                Some(video.path.clone())
+            } else if let Some(audio) = &item.audio_opt {
+                Some(audio.path.clone())
+            } else {
+                item.image_opt.as_ref().map(|image| image.path.clone())
+            }
+        };
+        let path = match kind {
+            PreviewKind::Custom(PreviewItem(item)) => media_path(item),
+            PreviewKind::Location(location) => {
+                let tab = self.tab_model.data::<Tab>(entity)?;
+                tab.items_opt()?
+                    .iter()
+                    .find(|item| item.location_opt.as_ref() == Some(location))
+                    .and_then(media_path)
+            }
+            PreviewKind::Selected => {
+                let tab = self.tab_model.data::<Tab>(entity)?;
+                tab.items_opt()?
+                    .iter()
+                    .find(|item| item.selected)
+                    .or(tab.parent_item_opt.as_ref())
+                    .and_then(media_path)
+            }
+        }?;
+
+        let mut column = widget::column().spacing(space_s);
+        column = column.push(widget::text::heading(fl!("related-items")));
+        let related = match &self.related_items {
+            Some((related_path, related)) if *related_path == path => related,
+            _ => {
+                column = column.push(
+                    widget::button::standard(fl!("related-items-find"))
+                        .on_press(Message::RelatedItems(path)),
+                );
+                return Some(column.into());
+            }
+        };
+        if related.is_empty() {
+            column = column.push(widget::text::body(fl!("related-items-none")));
+        }
+        for item in related.iter() {
+            let title = if item.title.is_empty() {
+                match item.path.file_name() {
+                    Some(name) => crate::parsers::osstr_to_string(name.to_os_string()),
+                    None => crate::parsers::osstr_to_string(item.path.clone().into_os_string()),
+                }
+            } else {
+                item.title.clone()
+            };
+            let reasons: Vec<String> = item
+                .shared
+                .iter()
+                .map(|shared| {
+                    let text = shared.value.clone();
+                    match shared.reason {
+                        Reason::Director => fl!("item-media-director", text = text),
+                        Reason::Actor => fl!("item-media-actor", text = text),
+                        Reason::Collection => fl!("related-collection", text = text),
+                        Reason::Tag => fl!("item-media-tag", text = text),
+                        Reason::AlbumArtist => fl!("item-media-albumartist", text = text),
+                        Reason::Composer => fl!("item-media-composer", text = text),
+                        Reason::Genre => fl!("item-media-genre", text = text),
+                        Reason::SameDay => fl!("related-same-day", text = text),
+                        Reason::Lens => fl!("item-image-lense-model", text = text),
+                        Reason::Nearby => fl!("related-nearby", text = text),
+                    }
+                })
+                .collect();
+            column = column.push(
+                widget::column::with_children(vec![
+                    widget::button::link(title)
+                        .on_press(Message::TabMessage(
+                            None,
+                            tab::Message::Open(Some(item.path.clone())),
+                        ))
+                        .padding(0)
+                        .into(),
+                    widget::text::caption(reasons.join(", ")).into(),
+                ])
+                .spacing(space_xxxs),
+            );
+        }
+        Some(column.into())
+    }
+
     fn search_database(&self) -> Element<'_, Message> {
-        let cosmic_theme::Spacing { space_m, .. } = theme::active().cosmic().spacing;
+        let cosmic_theme::Spacing {
+            space_s, space_m, ..
+        } = theme::active().cosmic().spacing;
 
         let mut column = widget::column().spacing(space_m);
         column = column.push(widget::text::heading(fl!("search-previous")));
@@ -1304,8 +1782,86 @@ impl App {
                 widget::tooltip::Position::Top,
             )
             .into(),
+            widget::tooltip(
+                widget::button::icon(widget::icon::from_name("edit-copy-symbolic").size(16))
+                    .on_press(Message::SearchPreviousDuplicate),
+                widget::text::body(fl!("search-duplicate")),
+                widget::tooltip::Position::Top,
+            )
+            .into(),
+            widget::tooltip(
+                widget::button::icon(widget::icon::from_name("go-up-symbolic").size(16))
+                    .on_press(Message::SearchPreviousMove(true)),
+                widget::text::body(fl!("search-move-up")),
+                widget::tooltip::Position::Top,
+            )
+            .into(),
+            widget::tooltip(
+                widget::button::icon(widget::icon::from_name("go-down-symbolic").size(16))
+                    .on_press(Message::SearchPreviousMove(false)),
+                widget::text::body(fl!("search-move-down")),
+                widget::tooltip::Position::Top,
+            )
+            .into(),
         ]));
 
+        column = column.push(widget::text::heading(fl!("search-query")));
+        column = column.push(widget::tooltip(
+            widget::text_input("".to_string(), self.search.search_string.as_str())
+                .id(self.search_query.clone())
+                .on_input(Message::SearchSearchString)
+                .on_submit(Message::SearchSearchStringSubmit),
+            widget::text::body(fl!("search-query-tooltip")),
+            widget::tooltip::Position::Top,
+        ));
+        if !self.search_suggestions.is_empty() {
+            let mut suggestions = widget::column();
+            for (i, entry) in self.search_suggestions.iter().enumerate() {
+                let field = crate::sql::expression::field_name(entry.field).unwrap_or_default();
+                suggestions = suggestions.push(
+                    widget::button::custom(
+                        widget::row::with_children(vec![
+                            widget::text::body(entry.name.clone()).into(),
+                            widget::horizontal_space().into(),
+                            widget::text::caption(fl!(
+                                "search-suggestion",
+                                field = field,
+                                count = entry.count
+                            ))
+                            .into(),
+                            if self.search_suggestion_pos == Some(i) {
+                                widget::icon::from_name("checkbox-checked-symbolic")
+                                    .size(16)
+                                    .into()
+                            } else {
+                                widget::Space::with_width(Length::Fixed(16.0)).into()
+                            },
+                        ])
+                        .spacing(space_s)
+                        .align_y(Alignment::Center),
+                    )
+                    .width(Length::Fill)
+                    .class(theme::Button::MenuItem)
+                    .on_press(Message::SearchSuggestion(i)),
+                );
+            }
+            column = column.push(suggestions);
+        }
+        if !self.search.search_string.trim().is_empty() {
+            if let Err(error) = crate::sql::expression::parse(&self.search.search_string) {
+                column = column.push(widget::text::caption(fl!(
+                    "search-query-error",
+                    error = error.to_string()
+                )));
+            }
+        }
+        column = column.push(widget::tooltip(
+            widget::checkbox(fl!("search-fuzzy"), self.search.fuzzy)
+                .on_toggle(move |value| Message::SearchFuzzy(value)),
+            widget::text::body(fl!("search-tooltip-fuzzy")),
+            widget::tooltip::Position::Top,
+        ));
+
         column = column.push(widget::text::heading(fl!("search-mediatypes")));
         column = column.push(widget::row::with_children(vec![
             widget::checkbox(fl!("search-images"), self.search.image)
@@ -1444,13 +2000,69 @@ impl App {
                 .on_toggle(move |value| Message::SearchGpsAltitude(value))
                 .into(),
         ]));
-        column = column.push(widget::tooltip(
-            widget::button::icon(widget::icon::from_name("media-playback-start-symbolic"))
-                .on_press(Message::SearchCommit)
-                .padding(8),
-            widget::text::body(fl!("search-commit")),
-            widget::tooltip::Position::Top,
-        ));
+        column = column.push(widget::row::with_children(vec![
+            widget::checkbox(fl!("search-resolution"), self.search.resolution)
+                .on_toggle(move |value| Message::SearchResolution(value))
+                .into(),
+            widget::horizontal_space().into(),
+            widget::checkbox(fl!("search-frame_rate"), self.search.frame_rate)
+                .on_toggle(move |value| Message::SearchFrameRate(value))
+                .into(),
+            widget::horizontal_space().into(),
+            widget::checkbox(fl!("search-codec"), self.search.codec)
+                .on_toggle(move |value| Message::SearchCodec(value))
+                .into(),
+        ]));
+        column = column.push(widget::row::with_children(vec![
+            widget::checkbox(fl!("search-bitrate"), self.search.bitrate)
+                .on_toggle(move |value| Message::SearchBitrate(value))
+                .into(),
+            widget::horizontal_space().into(),
+            widget::tooltip(
+                widget::checkbox(fl!("search-aspect_ratio"), self.search.aspect_ratio)
+                    .on_toggle(move |value| Message::SearchAspectRatio(value)),
+                widget::text::body(fl!("search-tooltip-aspect_ratio")),
+                widget::tooltip::Position::Top,
+            )
+            .into(),
+        ]));
+        column = column.push(widget::text::heading(fl!("search-save-as")));
+        column = column.push(
+            widget::row::with_children(vec![
+                widget::text_input(fl!("search-name"), self.search.name.as_str())
+                    .on_input(Message::SearchName)
+                    .on_submit(Message::SearchSave)
+                    .into(),
+                widget::text_input(fl!("search-folder"), self.search.folder.as_str())
+                    .on_input(Message::SearchFolder)
+                    .on_submit(Message::SearchSave)
+                    .into(),
+            ])
+            .spacing(space_s),
+        );
+        column = column.push(widget::row::with_children(vec![
+            widget::tooltip(
+                widget::button::icon(widget::icon::from_name("media-playback-start-symbolic"))
+                    .on_press(Message::SearchCommit)
+                    .padding(8),
+                widget::text::body(fl!("search-commit")),
+                widget::tooltip::Position::Top,
+            )
+            .into(),
+            widget::horizontal_space().into(),
+            widget::tooltip(
+                widget::button::icon(widget::icon::from_name("document-save-symbolic"))
+                    .on_press(Message::SearchSave)
+                    .padding(8),
+                widget::text::body(if self.search_editing.is_some() {
+                    fl!("search-save-changes")
+                } else {
+                    fl!("search-save")
+                }),
+                widget::tooltip::Position::Top,
+            )
+            .into(),
+        ]));
         widget::column::with_children(vec![
             widget::text::body(fl!("search-context")).into(),
             column.into(),
@@ -1531,6 +2143,30 @@ impl App {
                                             )
                                             .on_press(Message::MetadataDelete),
                                         ),
+                                )
+                                .add(
+                                    widget::settings::item::builder(fl!("poster-position"))
+                                        .control(widget::dropdown(
+                                            &self.poster_positions,
+                                            crate::parsers::poster::POSITIONS.iter().position(
+                                                |percent| *percent == self.config.poster_position,
+                                            ),
+                                            |index| {
+                                                Message::PosterPosition(
+                                                    crate::parsers::poster::POSITIONS[index],
+                                                )
+                                            },
+                                        )),
+                                )
+                                .add(
+                                    widget::settings::item::builder(fl!("library-check"))
+                                        .control(
+                                            widget::button::custom(
+                                                widget::icon::from_name("system-search-symbolic")
+                                                    .size(16),
+                                            )
+                                            .on_press(Message::LibraryMaintenance(true)),
+                                        ),
                                 );
                             widget::column::with_children(vec![
                                 appearance_section.into(),
@@ -2735,6 +3371,10 @@ impl Application for App {
         }
 
         let app_themes = vec![fl!("match-desktop"), fl!("dark"), fl!("light")];
+        let poster_positions = crate::parsers::poster::POSITIONS
+            .iter()
+            .map(|percent| fl!("poster-position-percent", percent = *percent))
+            .collect();
 
         let key_binds = key_binds(&match flags.mode {
             Mode::App => tab::Mode::App,
@@ -2747,11 +3387,11 @@ impl Application for App {
 
         let window_id_opt = core.main_window_id();
 
-        let sql_conmnection: rusqlite::Connection = match crate::sql::connect() {
-            Ok(conn) => conn,
+        let database = match crate::sql::Database::open() {
+            Ok(database) => database,
             Err(error) => {
                 log::error!("Failed to open Database! {}", error);
-                panic!("Ending the program as a database is required!");
+                panic!("Ending the program as a database is required! {}", error);
             }
         };
 
@@ -2769,6 +3409,7 @@ impl Application for App {
             config: flags.config,
             mode: flags.mode,
             app_themes,
+            poster_positions,
             context_page: ContextPage::Preview(None, PreviewKind::Selected),
             dialog_pages: VecDeque::new(),
             dialog_text_input: widget::Id::unique(),
@@ -2793,9 +3434,16 @@ impl Application for App {
             search_previous: Vec::new(),
             search_previous_str: Vec::new(),
             search_previous_pos: 0,
+            search_editing: None,
             search_from_string: widget::Id::unique(),
             search_to_string: widget::Id::unique(),
+            search_query: widget::Id::unique(),
+            search_vocabulary: Vec::new(),
+            search_suggestions: Vec::new(),
+            search_suggestion_pos: None,
+            related_items: None,
             size: None,
+            smart_collection_counts: HashMap::new(),
             #[cfg(feature = "wayland")]
             surface_ids: HashMap::new(),
             #[cfg(feature = "wayland")]
@@ -2808,10 +3456,10 @@ impl Application for App {
             tab_dnd_hover: None,
             nav_drag_id: DragId::new(),
             tab_drag_id: DragId::new(),
-            sql_connection: std::sync::Arc::new(std::sync::Mutex::new(sql_conmnection)),
+            database,
         };
         app.tab_model_id = app.tab_model.active();
-        let mut commands = vec![app.update_config()];
+        let mut commands = vec![app.update_config(), app.rescan_smart_collections()];
 
         for location in flags.locations {
             commands.push(app.open_tab(location, true, None));
@@ -2920,6 +3568,19 @@ impl Application for App {
                         ));
                     }
                 }
+                Location::SmartCollection(_) => {
+                    items.push(cosmic::widget::menu::Item::Button(
+                        fl!("open-in-new-tab"),
+                        None,
+                        NavMenuAction::Open(entity),
+                    ));
+                    items.push(cosmic::widget::menu::Item::Divider);
+                    items.push(cosmic::widget::menu::Item::Button(
+                        fl!("remove-smart-collection"),
+                        None,
+                        NavMenuAction::RemoveSmartCollection(entity),
+                    ));
+                }
                 _ => {}
             }
         }
@@ -3167,16 +3828,14 @@ impl Application for App {
             }
             Message::AddTagToContents(to, contents) => {
                 for p in contents.paths {
-                    let file = crate::sql::file(
-                        self.sql_connection.clone(),
-                        &crate::parsers::osstr_to_string(p.clone().into_os_string()),
-                    );
-                    crate::sql::insert_media_tag(
-                        self.sql_connection.clone(),
-                        file.metadata_id as u32,
-                        to.tag_id,
-                    );
+                    let path = crate::parsers::osstr_to_string(p.clone().into_os_string());
+                    let tag_id = to.tag_id;
+                    self.database.submit(move |connection| {
+                        let file = crate::sql::file(connection.clone(), &path);
+                        crate::sql::insert_media_tag(connection, file.metadata_id as u32, tag_id);
+                    });
                 }
+                return self.rescan_smart_collections();
             }
             Message::AddTagToSidebar(_entity_opt) => {
                 self.dialog_pages.push_back(DialogPage::NewTag {
@@ -3188,6 +3847,10 @@ impl Application for App {
                 config_set!(app_theme, app_theme);
                 return self.update_config();
             }
+            Message::PosterPosition(poster_position) => {
+                config_set!(poster_position, poster_position);
+                return self.update_config();
+            }
             Message::AudioMuteToggle => {
                 if self.active_view == Mode::Video {
                     let _ = self.update(Message::VideoMessage(
@@ -3428,6 +4091,7 @@ impl Application for App {
             }
             Message::RecursiveScanDirectories(entity_opt) => {
                 let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
+                let mut commands = Vec::new();
                 if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
                     if let Location::Path(_parent) = &tab.location {
                         if let Some(items) = tab.items_opt() {
@@ -3435,18 +4099,105 @@ impl Application for App {
                                 if item.selected {
                                     if let Some(Location::Path(path)) = &item.location_opt {
                                         let pathbuf = path.to_path_buf();
-                                        let sql_connection = self.sql_connection.clone();
-                                        let _joinhandle = std::thread::spawn(move || {
-                                            crate::tab::scan_path_recursive(sql_connection, pathbuf)
-                                        });
+                                        let database = self.database.clone();
+                                        commands.push(cosmic::task::future(async move {
+                                            match tokio::task::spawn_blocking(move || {
+                                                crate::tab::scan_path_recursive(&database, pathbuf)
+                                            })
+                                            .await
+                                            {
+                                                Ok(summary) => {
+                                                    Message::RecursiveScanComplete(summary)
+                                                }
+                                                Err(err) => {
+                                                    log::warn!("failed to scan: {}", err);
+                                                    Message::None
+                                                }
+                                            }
+                                        }));
                                     }
                                 }
                             }
                         }
                     }
                 }
+                return Task::batch(commands);
             }
-            Message::MissingPlugin(element) => {
+            Message::RecursiveScanComplete(summary) => {
+                let name = match summary.path.file_name() {
+                    Some(name) => crate::parsers::osstr_to_string(name.to_os_string()),
+                    None => crate::parsers::osstr_to_string(summary.path.into_os_string()),
+                };
+                let description = fl!(
+                    "recursive-scan-complete",
+                    files = summary.files,
+                    name = name,
+                    rate = format!("{:.1}", summary.files_per_second())
+                );
+                return Task::batch([
+                    self.toasts
+                        .push(widget::toaster::Toast::new(description))
+                        .map(cosmic::app::Message::App),
+                    self.rescan_smart_collections(),
+                ]);
+            }
+            Message::RelatedItems(path) => {
+                let database = self.database.clone();
+                return Task::perform(
+                    async move {
+                        let path2 = path.clone();
+                        match tokio::task::spawn_blocking(move || {
+                            database.read(|connection| {
+                                crate::sql::related::related(
+                                    connection,
+                                    &path2,
+                                    crate::sql::related::LIMIT,
+                                )
+                            })
+                        })
+                        .await
+                        {
+                            Ok(related) => message::app(Message::RelatedItemsFound(path, related)),
+                            Err(err) => {
+                                log::warn!("failed to look up related items: {}", err);
+                                message::none()
+                            }
+                        }
+                    },
+                    |x| x,
+                );
+            }
+            Message::RelatedItemsFound(path, related) => {
+                self.related_items = Some((path, related));
+            }
+            Message::MovieSet(name) => {
+                let database = self.database.clone();
+                return Task::perform(
+                    async move {
+                        match tokio::task::spawn_blocking(move || {
+                            database
+                                .read(|connection| crate::sql::nfo::movie_set(connection, &name))
+                        })
+                        .await
+                        {
+                            Ok(collection) => message::app(Message::MovieSetFound(collection)),
+                            Err(err) => {
+                                log::warn!("failed to look up movie set: {}", err);
+                                message::none()
+                            }
+                        }
+                    },
+                    |x| x,
+                );
+            }
+            Message::MovieSetFound(collection) => {
+                if collection.episodes.is_empty() {
+                    log::warn!("movie set {} has no movies", collection.name);
+                    return Task::none();
+                }
+                return self.open_tab(Location::Collection(collection), true, None);
+            }
+            Message::MissingPlugin(element) => {
                 if self.active_view == Mode::Video {
                     if let Some(video) = &mut self.video_view.video_opt {
                         video.set_paused(true);
@@ -3707,9 +4458,92 @@ impl Application for App {
                         DialogPage::EmptyTrash => {
                             self.operation(Operation::EmptyTrash);
                         }
+                        DialogPage::Export { path, paths } => {
+                            let database = self.database.clone();
+                            return cosmic::task::future(async move {
+                                let file = PathBuf::from(&path);
+                                match tokio::task::spawn_blocking(move || {
+                                    let records = database.read(|connection| {
+                                        crate::sql::exchange::records(connection, paths.as_deref())
+                                    });
+                                    let format = crate::sql::exchange::Format::from_path(&file);
+                                    std::fs::File::create(&file)
+                                        .map_err(crate::sql::exchange::Error::from)
+                                        .and_then(|f| {
+                                            crate::sql::exchange::write(
+                                                &records,
+                                                format,
+                                                std::io::BufWriter::new(f),
+                                            )
+                                        })
+                                        .map(|()| records.len())
+                                        .map_err(|err| err.to_string())
+                                })
+                                .await
+                                {
+                                    Ok(result) => {
+                                        Message::ExportComplete(PathBuf::from(path), result)
+                                    }
+                                    Err(err) => {
+                                        log::warn!("failed to export library: {}", err);
+                                        Message::None
+                                    }
+                                }
+                            });
+                        }
                         DialogPage::FailedOperation(id) => {
                             log::warn!("TODO: retry operation {}", id);
                         }
+                        DialogPage::Import {
+                            path,
+                            from,
+                            to,
+                            merge,
+                        } => {
+                            let database = self.database.clone();
+                            return cosmic::task::future(async move {
+                                match tokio::task::spawn_blocking(move || {
+                                    let file = PathBuf::from(&path);
+                                    let format = crate::sql::exchange::Format::from_path(&file);
+                                    let records = std::fs::File::open(&file)
+                                        .map_err(crate::sql::exchange::Error::from)
+                                        .and_then(|f| crate::sql::exchange::read(f, format))
+                                        .map_err(|err| err.to_string())?;
+                                    let rewrite = crate::sql::exchange::Rewrite::new(from, to);
+                                    let duplicates = if merge {
+                                        crate::sql::exchange::Duplicates::Merge
+                                    } else {
+                                        crate::sql::exchange::Duplicates::Skip
+                                    };
+                                    Ok(database.write(move |connection| {
+                                        crate::sql::exchange::import(
+                                            connection, records, &rewrite, duplicates,
+                                        )
+                                    }))
+                                })
+                                .await
+                                {
+                                    Ok(result) => Message::ImportComplete(result),
+                                    Err(err) => {
+                                        log::warn!("failed to import library: {}", err);
+                                        Message::None
+                                    }
+                                }
+                            });
+                        }
+                        DialogPage::MaintenanceReport(report) => {
+                            if report.dry_run {
+                                self.operation(Operation::Maintenance { dry_run: false });
+                            }
+                        }
+                        DialogPage::NfoReport { paths, report } => {
+                            if report.dry_run {
+                                self.operation(Operation::WriteNfo {
+                                    paths,
+                                    dry_run: false,
+                                });
+                            }
+                        }
                         DialogPage::MountError {
                             mounter_key,
                             item,
@@ -3752,21 +4586,35 @@ impl Application for App {
                                 Operation::NewFolder { path }
                             });
                         }
-                        DialogPage::NewTag { tag } => {
-                            let tag_id =
-                                crate::sql::insert_tag(self.sql_connection.clone(), 0, tag.clone())
-                                    as u32;
-                            let t = crate::sql::Tag {
-                                tag_id,
-                                tag: tag.clone(),
+                        DialogPage::PinSearch { name, search } => {
+                            let collection = crate::sql::SmartCollection {
+                                name: name.trim().to_string(),
+                                search,
                             };
-                            let mut tags = self.config.tags.clone();
-                            //let taglocation = Location::Tag(t);
-                            if !tags.iter().any(|f| f == &t) {
-                                tags.push(t);
+                            let mut smart_collections = self.config.smart_collections.clone();
+                            // pinning under an existing name replaces that collection
+                            match smart_collections
+                                .iter()
+                                .position(|c| c.name == collection.name)
+                            {
+                                Some(i) => smart_collections[i] = collection,
+                                None => smart_collections.push(collection),
                             }
-                            config_set!(tags, tags);
-                            return self.update_config();
+                            config_set!(smart_collections, smart_collections);
+                            return Task::batch([
+                                self.update_config(),
+                                self.rescan_smart_collections(),
+                            ]);
+                        }
+                        DialogPage::NewTag { tag } => {
+                            let tag = tag.clone();
+                            return self.database_task(move |database| {
+                                let new_tag = tag.clone();
+                                let tag_id = database.write(move |connection| {
+                                    crate::sql::insert_tag(connection, 0, new_tag)
+                                }) as u32;
+                                Message::TagCreated(crate::sql::Tag { tag_id, tag })
+                            });
                         }
                         DialogPage::OpenWith {
                             path,
@@ -3829,6 +4677,41 @@ impl Application for App {
                     self.update(Message::DialogComplete),
                 ]);
             }
+            Message::ExportComplete(path, result) => {
+                let description = match result {
+                    Ok(count) => fl!(
+                        "export-library-complete",
+                        count = count,
+                        path = path.display().to_string()
+                    ),
+                    Err(err) => fl!("export-library-failed", error = err),
+                };
+                return self
+                    .toasts
+                    .push(widget::toaster::Toast::new(description))
+                    .map(cosmic::app::Message::App);
+            }
+            Message::ExportLibrary => {
+                let path = dirs::home_dir()
+                    .unwrap_or_default()
+                    .join("media-browser-library.jsonl");
+                self.dialog_pages.push_back(DialogPage::Export {
+                    path: path.display().to_string(),
+                    paths: None,
+                });
+                return widget::text_input::focus(self.dialog_text_input.clone());
+            }
+            Message::ExportSearchResults(entity_opt) => {
+                let paths = self.selected_or_all_paths(entity_opt);
+                let path = dirs::home_dir()
+                    .unwrap_or_default()
+                    .join("media-browser-search.jsonl");
+                self.dialog_pages.push_back(DialogPage::Export {
+                    path: path.display().to_string(),
+                    paths: Some(paths),
+                });
+                return widget::text_input::focus(self.dialog_text_input.clone());
+            }
             Message::ImageMessage(image_message) => match image_message {
                 crate::image::image_view::Message::ToBrowser => {
                     self.active_view = Mode::Browser;
@@ -3884,7 +4767,40 @@ impl Application for App {
                     self.image_view.update(image_message);
                 }
             },
+            Message::ImportComplete(result) => {
+                let description = match result {
+                    Ok(summary) => fl!(
+                        "import-library-complete",
+                        imported = summary.imported,
+                        merged = summary.merged,
+                        skipped = summary.skipped,
+                        missing = summary.missing
+                    ),
+                    Err(err) => fl!("import-library-failed", error = err),
+                };
+                return Task::batch([
+                    self.toasts
+                        .push(widget::toaster::Toast::new(description))
+                        .map(cosmic::app::Message::App),
+                    self.rescan_smart_collections(),
+                ]);
+            }
+            Message::ImportLibrary => {
+                let path = dirs::home_dir()
+                    .unwrap_or_default()
+                    .join("media-browser-library.jsonl");
+                self.dialog_pages.push_back(DialogPage::Import {
+                    path: path.display().to_string(),
+                    from: String::new(),
+                    to: String::new(),
+                    merge: false,
+                });
+                return widget::text_input::focus(self.dialog_text_input.clone());
+            }
             Message::Key(modifiers, key) => {
+                if modifiers.is_empty() && self.search_suggestion_key(&key) {
+                    return Task::none();
+                }
                 let entity = self.tab_model.active();
                 for (key_bind, action) in self.key_binds.iter() {
                     if key_bind.matches(modifiers, &key) {
@@ -3923,6 +4839,9 @@ impl Application for App {
                     self.operation(Operation::Delete { paths });
                 }
             }
+            Message::LibraryMaintenance(dry_run) => {
+                self.operation(Operation::Maintenance { dry_run });
+            }
             Message::LaunchUrl(url) => match open::that_detached(&url) {
                 Ok(()) => {}
                 Err(err) => {
@@ -3934,9 +4853,8 @@ impl Application for App {
                 self.search = crate::sql::SearchData {
                     ..Default::default()
                 };
-                self.search_previous.clear();
-                self.search_previous
-                    .extend(crate::sql::searches(self.sql_connection.clone()));
+                self.search_previous_reload();
+                self.search_editing = None;
                 match search_type {
                     ST::Director => {
                         self.search.director = true;
@@ -3969,9 +4887,12 @@ impl Application for App {
                         self.search.from_string = search_term;
                     }
                     ST::Genre => {
+                        // songs and videos both have genres, the query looks in both
                         self.search.genre = true;
                         self.search.audio = true;
-                        self.search.from_string = search_term;
+                        self.search.video = true;
+                        self.search.search_string =
+                            format!("genre:\"{}\"", search_term.replace('"', ""));
                     }
                     ST::Tag => {
                         self.search.tags = true;
@@ -3980,6 +4901,30 @@ impl Application for App {
                         self.search.image = true;
                         self.search.from_string = search_term;
                     }
+                    ST::Codec => {
+                        self.search.codec = true;
+                        self.search.video = true;
+                        self.search.from_string = search_term;
+                    }
+                    ST::GPSLocation => {
+                        // the term is a near: query
+                        self.search.image = true;
+                        self.search.search_string = search_term;
+                    }
+                    ST::Writer
+                    | ST::Studio
+                    | ST::Country
+                    | ST::MovieSet
+                    | ST::Certification
+                    | ST::ExternalId => {
+                        // details from NFO files only have a query field
+                        let Some(field) = crate::sql::expression::field_name(search_type) else {
+                            return Task::none();
+                        };
+                        self.search.video = true;
+                        self.search.search_string =
+                            format!("{}:\"{}\"", field, search_term.replace('"', ""));
+                    }
 
                     _ => return Task::none(),
                 }
@@ -4357,6 +5302,8 @@ impl Application for App {
                 commands.push(self.rescan_operation_selection(op_sel));
                 // Manually rescan any trash tabs after any operation is completed
                 commands.push(self.rescan_trash());
+                // moved, renamed or deleted files change what smart collections contain
+                commands.push(self.rescan_smart_collections());
                 // if search is active, update "search" tab view
                 commands.push(self.search());
                 return Task::batch(commands);
@@ -4403,6 +5350,19 @@ impl Application for App {
                     }
                 }
             }
+            Message::PinSearch(entity_opt) => {
+                let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
+                let (name, search) = match self.tab_model.data::<Tab>(entity) {
+                    Some(tab) => match &tab.location {
+                        Location::DBSearch(search) => (tab.title(), search.clone()),
+                        _ => (String::new(), self.search.clone()),
+                    },
+                    None => (String::new(), self.search.clone()),
+                };
+                self.dialog_pages
+                    .push_back(DialogPage::PinSearch { name, search });
+                return widget::text_input::focus(self.dialog_text_input.clone());
+            }
             Message::Preview(entity_opt) => {
                 match self.mode {
                     Mode::App => {
@@ -4604,27 +5564,181 @@ impl Application for App {
                 self.search = crate::sql::SearchData {
                     ..Default::default()
                 };
-                self.search_previous.clear();
-                self.search_previous
-                    .extend(crate::sql::searches(self.sql_connection.clone()));
-                for s in self.search_previous.iter() {
-                    self.search_previous_str.push(s.display());
-                }
+                self.search_previous_reload();
+                self.search_editing = None;
+                self.search_suggestions.clear();
+                self.search_suggestion_pos = None;
                 self.context_page = ContextPage::Search;
                 self.core.window.show_context = true;
+                // the names of a large library take a moment to read
+                let database = self.database.clone();
+                return Task::perform(
+                    async move {
+                        match tokio::task::spawn_blocking(move || {
+                            database.read(crate::sql::suggest::vocabulary)
+                        })
+                        .await
+                        {
+                            Ok(vocabulary) => message::app(Message::SearchVocabulary(vocabulary)),
+                            Err(err) => {
+                                log::warn!("failed to read the search suggestions: {}", err);
+                                message::none()
+                            }
+                        }
+                    },
+                    |x| x,
+                );
             }
             Message::SearchPreviousPick(pos) => {
                 self.search_previous_pos = pos;
             }
             Message::SearchPreviousSelect => {
-                let search = self.search_previous[self.search_previous_pos].clone();
-                self.search = search.clone();
+                if let Some(search) = self.search_previous.get(self.search_previous_pos) {
+                    // saving the search again replaces it
+                    self.search_editing = Some(search.search_id);
+                    self.search = search.clone();
+                }
             }
             Message::SearchPreviousDelete => {
-                let search = self.search_previous[self.search_previous_pos].clone();
-                self.search_previous_str.remove(self.search_previous_pos);
-                self.search_previous.remove(self.search_previous_pos);
-                crate::sql::delete_search(self.sql_connection.clone(), search);
+                if self.search_previous_pos < self.search_previous.len() {
+                    let search = self.search_previous.remove(self.search_previous_pos);
+                    if self.search_editing == Some(search.search_id) {
+                        self.search_editing = None;
+                    }
+                    self.search_previous_labels();
+                    self.database
+                        .submit(move |connection| crate::sql::delete_search(connection, search));
+                }
+            }
+            Message::SearchPreviousDuplicate => {
+                if let Some(search) = self.search_previous.get(self.search_previous_pos) {
+                    let name = if search.name.is_empty() {
+                        search.display()
+                    } else {
+                        search.name.clone()
+                    };
+                    let mut copy = crate::sql::SearchData {
+                        search_id: 0,
+                        name: fl!("search-copy", name = name),
+                        position: 0,
+                        last_run: 0,
+                        result_count: 0,
+                        ..search.clone()
+                    };
+                    let search_id = search.search_id;
+                    return self.database_task(move |database| {
+                        copy = database.write(move |connection| {
+                            copy.store(connection);
+                            copy
+                        });
+                        Message::SearchPreviousDuplicated(search_id, copy)
+                    });
+                }
+            }
+            Message::SearchPreviousDuplicated(search_id, copy) => {
+                // the copy goes right below the original
+                let pos = self
+                    .search_previous
+                    .iter()
+                    .position(|search| search.search_id == search_id)
+                    .map_or(self.search_previous.len(), |pos| pos + 1);
+                self.search_previous_pos = pos;
+                self.search_previous.insert(pos, copy);
+                self.search_previous_renumber();
+            }
+            Message::SearchPreviousMove(up) => {
+                // searches only move within their folder
+                let pos = self.search_previous_pos;
+                let other = if up {
+                    pos.checked_sub(1)
+                } else {
+                    Some(pos + 1)
+                };
+                if let Some(other) = other.filter(|other| {
+                    pos < self.search_previous.len()
+                        && *other < self.search_previous.len()
+                        && self.search_previous[pos].folder == self.search_previous[*other].folder
+                }) {
+                    self.search_previous.swap(pos, other);
+                    self.search_previous_pos = other;
+                    self.search_previous_renumber();
+                }
+            }
+            Message::SearchName(name) => {
+                self.search.name = name;
+            }
+            Message::SearchFolder(folder) => {
+                self.search.folder = folder;
+            }
+            Message::SearchSave => {
+                // the parse error is already shown below the query
+                if !self.search.search_string.trim().is_empty()
+                    && crate::sql::expression::parse(&self.search.search_string).is_err()
+                {
+                    return Task::none();
+                }
+                let mut s = self.search.clone();
+                s.name = s.name.trim().to_string();
+                s.folder = s.folder.trim().to_string();
+                let saved = self.search_editing.and_then(|search_id| {
+                    self.search_previous
+                        .iter()
+                        .find(|saved| saved.search_id == search_id)
+                });
+                let update = match saved {
+                    Some(saved) => {
+                        s.search_id = saved.search_id;
+                        // a search moved to another folder goes to its end
+                        s.position = if s.folder == saved.folder {
+                            saved.position
+                        } else {
+                            0
+                        };
+                        if &s == saved {
+                            s.last_run = saved.last_run;
+                            s.result_count = saved.result_count;
+                        } else {
+                            s.last_run = 0;
+                            s.result_count = 0;
+                        }
+                        true
+                    }
+                    None => {
+                        s.search_id = 0;
+                        s.position = 0;
+                        s.last_run = 0;
+                        s.result_count = 0;
+                        false
+                    }
+                };
+                return self.database_task(move |database| {
+                    let s = if update {
+                        let search = s.clone();
+                        database
+                            .write(move |connection| crate::sql::update_search(connection, search));
+                        s
+                    } else {
+                        database.write(move |connection| {
+                            s.store(connection);
+                            s
+                        })
+                    };
+                    // committed before the list of searches is read again
+                    database.flush();
+                    Message::SearchSaved(s)
+                });
+            }
+            Message::SearchSaved(s) => {
+                self.search = s.clone();
+                self.search_editing = Some(s.search_id);
+                self.search_previous_reload();
+                if let Some(pos) = self
+                    .search_previous
+                    .iter()
+                    .position(|saved| saved.search_id == s.search_id)
+                {
+                    self.search_previous_pos = pos;
+                }
             }
             Message::SearchImages(is_checked) => {
                 self.search.search_id = 0;
@@ -4641,10 +5755,30 @@ impl Application for App {
             Message::SearchSearchString(input) => {
                 self.search.search_id = 0;
                 self.search.search_string = input.clone();
+                self.search_suggest();
             }
             Message::SearchSearchStringSubmit => {
                 self.search.search_id = 0;
-                log::warn!("{}", self.search.search_string);
+                if let Some(pos) = self.search_suggestion_pos {
+                    return self.search_suggestion_accept(pos);
+                }
+                if crate::sql::expression::parse(&self.search.search_string).is_ok() {
+                    return self.update(Message::SearchCommit);
+                }
+            }
+            Message::SearchSuggestionKey(key) => {
+                self.search_suggestion_key(&key);
+            }
+            Message::SearchVocabulary(vocabulary) => {
+                self.search_vocabulary = vocabulary;
+                // a query typed while the names were read gets its suggestions now
+                if self.search_suggestion_pos.is_none() {
+                    self.search_suggest();
+                }
+            }
+            Message::SearchSuggestion(pos) => {
+                self.search.search_id = 0;
+                return self.search_suggestion_accept(pos);
             }
             Message::SearchSearchFromString(input) => {
                 self.search.search_id = 0;
@@ -4831,26 +5965,90 @@ impl Application for App {
                     self.search.image = true;
                 }
             }
+            Message::SearchResolution(is_checked) => {
+                self.search.search_id = 0;
+                self.search.resolution = is_checked;
+                if !self.search.image && !self.search.video {
+                    self.search.video = true;
+                }
+            }
+            Message::SearchFrameRate(is_checked) => {
+                self.search.search_id = 0;
+                self.search.frame_rate = is_checked;
+                if !self.search.video {
+                    self.search.video = true;
+                }
+            }
+            Message::SearchBitrate(is_checked) => {
+                self.search.search_id = 0;
+                self.search.bitrate = is_checked;
+                if !self.search.audio {
+                    self.search.audio = true;
+                }
+            }
+            Message::SearchCodec(is_checked) => {
+                self.search.search_id = 0;
+                self.search.codec = is_checked;
+                if !self.search.video {
+                    self.search.video = true;
+                }
+            }
+            Message::SearchAspectRatio(is_checked) => {
+                self.search.search_id = 0;
+                self.search.aspect_ratio = is_checked;
+                if !self.search.image && !self.search.video {
+                    self.search.image = true;
+                }
+            }
+            Message::SearchFuzzy(is_checked) => {
+                self.search.search_id = 0;
+                self.search.fuzzy = is_checked;
+            }
             Message::SearchCommit => {
+                // the parse error is already shown below the query
+                if !self.search.search_string.trim().is_empty()
+                    && crate::sql::expression::parse(&self.search.search_string).is_err()
+                {
+                    return Task::none();
+                }
                 let mut s = self.search.clone();
-                for s2 in self.search_previous.iter() {
+                // the saved search being edited comes first, if it is run unchanged
+                let editing = self.search_editing.and_then(|search_id| {
+                    self.search_previous
+                        .iter()
+                        .find(|s2| s2.search_id == search_id && &s == *s2)
+                });
+                for s2 in editing.into_iter().chain(self.search_previous.iter()) {
                     if &s == s2 {
                         s.search_id = s2.search_id;
+                        s.name = s2.name.clone();
+                        s.folder = s2.folder.clone();
+                        break;
                     }
                 }
                 if s.search_id == 0 {
-                    s.store(self.sql_connection.clone());
+                    return self.database_task(move |database| {
+                        s = database.write(move |connection| {
+                            s.store(connection);
+                            s
+                        });
+                        Message::SearchCommitted(s)
+                    });
+                }
+                return self.update(Message::SearchCommitted(s));
+            }
+            Message::SearchCommitted(s) => {
+                if !self
+                    .search_previous
+                    .iter()
+                    .any(|saved| saved.search_id == s.search_id)
+                {
                     self.search_previous.push(s.clone());
+                    self.search_previous_labels();
                 }
                 self.search = s.clone();
-                let location = Location::DBSearch(s);
-                let (parent_item_opt, items) =
-                    location.scan(self.sql_connection.clone(), IconSizes::default());
-                let (entity, command) = self.open_tab_entity(location, true, None);
-                if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
-                    tab.parent_item_opt = parent_item_opt;
-                    tab.set_items(items);
-                }
+                // the new tab searches in the background and shows the results page by page
+                let (_, command) = self.open_tab_entity(Location::DBSearch(s), true, None);
                 return command;
             }
             Message::SeekBackward => {
@@ -4922,6 +6120,19 @@ impl Application for App {
                 config_set!(show_details, show_details);
                 return self.update_config();
             }
+            Message::TagCreated(tag) => {
+                let mut tags = self.config.tags.clone();
+                if !tags.iter().any(|f| f == &tag) {
+                    tags.push(tag);
+                }
+                config_set!(tags, tags);
+                return self.update_config();
+            }
+            Message::SmartCollectionCounts(counts) => {
+                self.smart_collection_counts.clear();
+                self.smart_collection_counts.extend(counts);
+                self.update_nav_model();
+            }
             Message::SystemThemeModeChange(_theme_mode) => {
                 return self.update_config();
             }
@@ -5018,6 +6229,14 @@ impl Application for App {
                 //    self.set_show_context(false);
                 //}
 
+                if let tab::Message::DatabaseSearchFound(Location::DBSearch(search), found) =
+                    &tab_message
+                {
+                    if search.search_id > 0 {
+                        self.search_ran(search.search_id, found.len());
+                    }
+                }
+
                 let tab_commands = match self.tab_model.data_mut::<Tab>(entity) {
                     Some(tab) => tab.update(tab_message, self.modifiers),
                     _ => Vec::new(),
@@ -5044,6 +6263,10 @@ impl Application for App {
                         }
                         tab::Command::ChangeLocation(tab_title, tab_path, selection_paths) => {
                             self.activate_nav_model_location(&tab_path);
+                            let sort_opt = self.location_sort(&tab_path);
+                            if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
+                                tab.restore_sort(sort_opt);
+                            }
 
                             self.tab_model.text_set(entity, tab_title);
                             commands.push(Task::batch([
@@ -5052,6 +6275,37 @@ impl Application for App {
                                 self.rescan_tab(entity, tab_path, selection_paths),
                             ]));
                         }
+                        tab::Command::DatabaseSearchPage(location, started, page, controller) => {
+                            let database = self.database.clone();
+                            commands.push(Task::perform(
+                                async move {
+                                    match tokio::task::spawn_blocking(move || {
+                                        database.read(|connection| {
+                                            crate::sql::found_items(
+                                                connection,
+                                                &database,
+                                                &page,
+                                                &controller,
+                                            )
+                                        })
+                                    })
+                                    .await
+                                    {
+                                        Ok(items) => message::app(Message::TabMessage(
+                                            Some(entity),
+                                            tab::Message::DatabaseSearchItems(
+                                                location, started, items,
+                                            ),
+                                        )),
+                                        Err(err) => {
+                                            log::warn!("failed to build search results: {}", err);
+                                            message::none()
+                                        }
+                                    }
+                                },
+                                |x| x,
+                            ));
+                        }
                         tab::Command::DropFiles(to, from) => {
                             commands.push(self.update(Message::PasteContents(to, from)));
                         }
@@ -5151,6 +6405,19 @@ impl Application for App {
                             self.context_page = ContextPage::Preview(Some(entity), kind);
                             self.set_show_context(true);
                         }
+                        tab::Command::RememberSort(location, sort) => {
+                            if let Some(key) = location.sort_key() {
+                                let mut location_sorts = self.config.location_sorts.clone();
+                                if sort == location.default_sort() {
+                                    location_sorts.remove(&key);
+                                } else {
+                                    location_sorts.insert(key, sort);
+                                }
+                                if location_sorts != self.config.location_sorts {
+                                    config_set!(location_sorts, location_sorts);
+                                }
+                            }
+                        }
                         tab::Command::WindowDrag => {
                             if let Some(window_id) = &self.window_id_opt {
                                 commands.push(window::drag(*window_id));
@@ -5213,11 +6480,11 @@ impl Application for App {
 
                 let mut paths = Vec::with_capacity(recently_trashed.len());
                 let icon_sizes = self.config.tab.icon_sizes;
-                let sql_connection = self.sql_connection.clone();
+                let database = self.database.clone();
 
                 return cosmic::task::future(async move {
                     match tokio::task::spawn_blocking(move || {
-                        Location::Trash.scan(sql_connection, icon_sizes)
+                        Location::Trash.scan(&database, icon_sizes)
                     })
                     .await
                     {
@@ -5244,6 +6511,72 @@ impl Application for App {
             Message::UndoTrashStart(items) => {
                 self.operation(Operation::Restore { items });
             }
+            Message::VerifyLibrary(entity_opt) => {
+                let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
+                let mut paths = Vec::new();
+                if let Some(tab) = self.tab_model.data::<Tab>(entity) {
+                    if let Location::Path(parent) = &tab.location {
+                        if let Some(items) = tab.items_opt() {
+                            for item in items.iter() {
+                                if item.selected {
+                                    if let Some(Location::Path(path)) = &item.location_opt {
+                                        paths.push(path.to_path_buf());
+                                    }
+                                }
+                            }
+                        }
+                        if paths.is_empty() {
+                            paths.push(parent.to_path_buf());
+                        }
+                    }
+                }
+                let mut commands = Vec::with_capacity(paths.len());
+                for path in paths {
+                    let database = self.database.clone();
+                    commands.push(cosmic::task::future(async move {
+                        match tokio::task::spawn_blocking(move || {
+                            crate::tab::verify_path(&database, path)
+                        })
+                        .await
+                        {
+                            Ok(summary) => Message::VerifyLibraryComplete(summary),
+                            Err(err) => {
+                                log::warn!("failed to verify library: {}", err);
+                                Message::None
+                            }
+                        }
+                    }));
+                }
+                return Task::batch(commands);
+            }
+            Message::WriteNfo(entity_opt) => {
+                // the changes are shown before anything is written
+                let paths = self.selected_or_all_paths(entity_opt);
+                if !paths.is_empty() {
+                    self.operation(Operation::WriteNfo {
+                        paths,
+                        dry_run: true,
+                    });
+                }
+            }
+            Message::VerifyLibraryComplete(summary) => {
+                let name = match summary.path.file_name() {
+                    Some(name) => crate::parsers::osstr_to_string(name.to_os_string()),
+                    None => crate::parsers::osstr_to_string(summary.path.into_os_string()),
+                };
+                let description = fl!(
+                    "verify-library-complete",
+                    name = name,
+                    added = summary.added,
+                    changed = summary.changed,
+                    removed = summary.removed,
+                    unchanged = summary.unchanged
+                );
+                return self
+                    .toasts
+                    .push(widget::toaster::Toast::new(description))
+                    .map(cosmic::app::Message::App);
+            }
             Message::VideoMessage(video_message) => match video_message {
                 crate::video::video_view::Message::ToBrowser => {
                     self.active_view = Mode::Browser;
@@ -5594,6 +6927,9 @@ impl Application for App {
                                     t.tag.clone(),
                                 ));
                             }
+                            Location::SmartCollection(_) => {
+                                return self.open_tab(location.clone(), true, None);
+                            }
                             _ => {}
                         }
                     }
@@ -5625,15 +6961,25 @@ impl Application for App {
                     if let Some(FavoriteIndex(tag_i)) = self.nav_model.data::<FavoriteIndex>(entity)
                     {
                         let mut tags = self.config.tags.clone();
-                        crate::sql::delete_tag(
-                            self.sql_connection.clone(),
-                            tags[*tag_i].tag.clone(),
-                        );
+                        let tag = tags[*tag_i].tag.clone();
+                        self.database
+                            .submit(move |connection| crate::sql::delete_tag(connection, tag));
                         tags.remove(*tag_i);
                         config_set!(tags, tags);
                         return self.update_config();
                     }
                 }
+                NavMenuAction::RemoveSmartCollection(entity) => {
+                    if let Some(SmartCollectionIndex(collection_i)) =
+                        self.nav_model.data::<SmartCollectionIndex>(entity)
+                    {
+                        let mut smart_collections = self.config.smart_collections.clone();
+                        let collection = smart_collections.remove(*collection_i);
+                        self.smart_collection_counts.remove(&collection.name);
+                        config_set!(smart_collections, smart_collections);
+                        return self.update_config();
+                    }
+                }
 
                 NavMenuAction::EmptyTrash => {
                     self.dialog_pages.push_front(DialogPage::EmptyTrash);
@@ -5852,6 +7198,43 @@ impl Application for App {
                 .secondary_action(
                     widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
                 ),
+            DialogPage::Export { path, paths } => {
+                let title = match paths {
+                    Some(paths) => fl!("export-search-results", count = paths.len()),
+                    None => fl!("export-library"),
+                };
+                let complete_maybe = if path.trim().is_empty() {
+                    None
+                } else {
+                    Some(Message::DialogComplete)
+                };
+                widget::dialog()
+                    .title(title)
+                    .primary_action(
+                        widget::button::suggested(fl!("export"))
+                            .on_press_maybe(complete_maybe.clone()),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    )
+                    .control(
+                        widget::column::with_children(vec![
+                            widget::text::body(fl!("exchange-file")).into(),
+                            widget::text_input("", path.as_str())
+                                .id(self.dialog_text_input.clone())
+                                .on_input(move |path| {
+                                    Message::DialogUpdate(DialogPage::Export {
+                                        path,
+                                        paths: paths.clone(),
+                                    })
+                                })
+                                .on_submit_maybe(complete_maybe)
+                                .into(),
+                            widget::text::caption(fl!("exchange-file-format")).into(),
+                        ])
+                        .spacing(space_xxs),
+                    )
+            }
             DialogPage::FailedOperation(id) => {
                 //TODO: try next dialog page (making sure index is used by Dialog messages)?
                 let (operation, _, err) = self.failed_operations.get(id)?;
@@ -5866,6 +7249,114 @@ impl Application for App {
                         widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
                     )
             }
+            DialogPage::Import {
+                path,
+                from,
+                to,
+                merge,
+            } => {
+                let mut dialog = widget::dialog().title(fl!("import-library"));
+                let complete_maybe = if !Path::new(path).is_file() {
+                    None
+                } else if from.trim().is_empty() != to.trim().is_empty() {
+                    dialog = dialog.tertiary_action(widget::text::body(fl!("import-rewrite-both")));
+                    None
+                } else {
+                    Some(Message::DialogComplete)
+                };
+                let page = |path: &str, from: &str, to: &str, merge: bool| DialogPage::Import {
+                    path: path.to_string(),
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    merge,
+                };
+                dialog
+                    .primary_action(
+                        widget::button::suggested(fl!("import"))
+                            .on_press_maybe(complete_maybe.clone()),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    )
+                    .control(
+                        widget::column::with_children(vec![
+                            widget::text::body(fl!("exchange-file")).into(),
+                            widget::text_input("", path.as_str())
+                                .id(self.dialog_text_input.clone())
+                                .on_input(move |value| {
+                                    Message::DialogUpdate(page(&value, from, to, *merge))
+                                })
+                                .on_submit_maybe(complete_maybe)
+                                .into(),
+                            widget::text::caption(fl!("exchange-file-format")).into(),
+                            widget::text::body(fl!("import-rewrite-from")).into(),
+                            widget::text_input("/home/alice/Videos", from.as_str())
+                                .on_input(move |value| {
+                                    Message::DialogUpdate(page(path, &value, to, *merge))
+                                })
+                                .into(),
+                            widget::text::body(fl!("import-rewrite-to")).into(),
+                            widget::text_input("/mnt/media/Videos", to.as_str())
+                                .on_input(move |value| {
+                                    Message::DialogUpdate(page(path, from, &value, *merge))
+                                })
+                                .into(),
+                            widget::checkbox(fl!("import-merge-duplicates"), *merge)
+                                .on_toggle(move |value| {
+                                    Message::DialogUpdate(page(path, from, to, value))
+                                })
+                                .into(),
+                        ])
+                        .spacing(space_xxs),
+                    )
+            }
+            DialogPage::MaintenanceReport(report) => {
+                let mut body = vec![fl!(
+                    "library-maintenance-report",
+                    missing = report.missing_files,
+                    orphans = report.orphan_rows,
+                    people = report.unused_people,
+                    albums = report.unused_albums,
+                    artists = report.unused_artists,
+                    thumbnails = report.unused_thumbnails,
+                    size = crate::tab::format_size(report.thumbnail_bytes)
+                )];
+                if report.integrity_ok() {
+                    body.push(fl!("library-integrity-ok"));
+                } else {
+                    body.push(fl!("library-integrity-failed"));
+                    body.extend(report.integrity.iter().cloned());
+                }
+                if !report.dry_run {
+                    body.push(fl!(
+                        "library-maintenance-vacuum",
+                        size = crate::tab::format_size(report.vacuum_bytes)
+                    ));
+                } else if report.is_clean() {
+                    body.push(fl!("library-maintenance-clean"));
+                } else {
+                    body.push(fl!("library-maintenance-dry-run"));
+                }
+                let dialog = widget::dialog()
+                    .title(fl!("library-maintenance"))
+                    .body(body.join("\n"))
+                    .icon(widget::icon::from_name("edit-clear-all-symbolic").size(64));
+                if report.dry_run && !report.is_clean() {
+                    dialog
+                        .primary_action(
+                            widget::button::suggested(fl!("library-cleanup"))
+                                .on_press(Message::DialogComplete),
+                        )
+                        .secondary_action(
+                            widget::button::standard(fl!("cancel"))
+                                .on_press(Message::DialogCancel),
+                        )
+                } else {
+                    dialog.primary_action(
+                        widget::button::standard(fl!("close")).on_press(Message::DialogCancel),
+                    )
+                }
+            }
             DialogPage::MountError {
                 mounter_key: _,
                 item: _,
@@ -6112,6 +7603,97 @@ impl Application for App {
                         .spacing(space_xxs),
                     )
             }
+            DialogPage::NfoReport { report, .. } => {
+                let created = report
+                    .changes
+                    .iter()
+                    .filter(|change| change.created)
+                    .count();
+                let mut body = vec![fl!(
+                    "write-nfo-report",
+                    changed = report.changes.len() - created,
+                    created = created,
+                    unchanged = report.unchanged,
+                    failed = report.failed.len()
+                )];
+                for (nfo, error) in report.failed.iter() {
+                    body.push(format!("{}: {}", nfo.display(), error));
+                }
+                if report.changes.is_empty() {
+                    body.push(fl!("write-nfo-clean"));
+                } else if report.dry_run {
+                    body.push(fl!("write-nfo-dry-run"));
+                } else {
+                    body.push(fl!("write-nfo-backup"));
+                }
+                let mut diffs: Vec<Element<_>> = Vec::new();
+                for change in report.changes.iter() {
+                    diffs.push(widget::text::body(change.nfo.display().to_string()).into());
+                    diffs.push(
+                        widget::text::caption(change.diff.clone())
+                            .font(font::mono())
+                            .into(),
+                    );
+                }
+                let mut dialog = widget::dialog()
+                    .title(fl!("write-nfo"))
+                    .body(body.join("\n"))
+                    .icon(widget::icon::from_name("document-save-symbolic").size(64));
+                if !diffs.is_empty() {
+                    dialog = dialog.control(
+                        widget::container(widget::scrollable(
+                            widget::column::with_children(diffs).spacing(space_xxs),
+                        ))
+                        .max_height(400.0),
+                    );
+                }
+                if report.dry_run && !report.changes.is_empty() {
+                    dialog
+                        .primary_action(
+                            widget::button::suggested(fl!("write-nfo"))
+                                .on_press(Message::DialogComplete),
+                        )
+                        .secondary_action(
+                            widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                        )
+                } else {
+                    dialog.primary_action(
+                        widget::button::standard(fl!("close")).on_press(Message::DialogCancel),
+                    )
+                }
+            }
+            DialogPage::PinSearch { name, search } => {
+                let complete_maybe = if name.trim().is_empty() {
+                    None
+                } else {
+                    Some(Message::DialogComplete)
+                };
+                widget::dialog()
+                    .title(fl!("pin-search"))
+                    .primary_action(
+                        widget::button::suggested(fl!("save"))
+                            .on_press_maybe(complete_maybe.clone()),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    )
+                    .control(
+                        widget::column::with_children(vec![
+                            widget::text::body(fl!("smart-collection-name")).into(),
+                            widget::text_input("", name.as_str())
+                                .id(self.dialog_text_input.clone())
+                                .on_input(move |name| {
+                                    Message::DialogUpdate(DialogPage::PinSearch {
+                                        name,
+                                        search: search.clone(),
+                                    })
+                                })
+                                .on_submit_maybe(complete_maybe)
+                                .into(),
+                        ])
+                        .spacing(space_xxs),
+                    )
+            }
             DialogPage::OpenWith {
                 path,
                 apps,
@@ -6603,6 +8185,16 @@ impl Application for App {
             event::listen_with(|event, status, _| match event {
                 Event::Keyboard(KeyEvent::KeyPressed { key, modifiers, .. }) => match status {
                     event::Status::Ignored => Some(Message::Key(modifiers, key)),
+                    // the focused search query keeps these keys to itself, they move through
+                    // and close its suggestions
+                    event::Status::Captured if modifiers.is_empty() => match key {
+                        Key::Named(
+                            keyboard::key::Named::ArrowDown
+                            | keyboard::key::Named::ArrowUp
+                            | keyboard::key::Named::Escape,
+                        ) => Some(Message::SearchSuggestionKey(key)),
+                        _ => None,
+                    },
                     event::Status::Captured => None,
                 },
                 Event::Keyboard(KeyEvent::ModifiersChanged(modifiers)) => {
@@ -6863,11 +8455,15 @@ impl Application for App {
             let id = *id;
             let pending_operation = pending_operation.clone();
             let controller = controller.clone();
+            let database = self.database.clone();
             subscriptions.push(Subscription::run_with_id(
                 id,
                 stream::channel(16, move |msg_tx| async move {
                     let msg_tx = Arc::new(tokio::sync::Mutex::new(msg_tx));
-                    match pending_operation.perform(&msg_tx, controller).await {
+                    match pending_operation
+                        .perform(&msg_tx, controller, database)
+                        .await
+                    {
                         Ok(result_paths) => {
                             let _ = msg_tx
                                 .lock()
@@ -7091,14 +8687,14 @@ pub(crate) mod test_utils {
         dirs: usize,
         nested: usize,
         name_len: usize,
-        sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+        database: &crate::sql::Database,
     ) -> io::Result<(TempDir, Tab)> {
         let fs = simple_fs(files, hidden, dirs, nested, name_len)?;
         let path = fs.path();
 
         // New tab with items
         let location = Location::Path(path.to_owned());
-        let (parent_item_opt, items) = location.scan(sql_connection.clone(), IconSizes::default());
+        let (parent_item_opt, items) = location.scan(database, IconSizes::default());
         let mut tab = Tab::new(location, TabConfig::default());
         tab.parent_item_opt = parent_item_opt;
         tab.set_items(items);
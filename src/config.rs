@@ -3,7 +3,7 @@
 // Modifications:
 // Copyright 2024 Alexander Schwarzkopf
 
-use std::{any::TypeId, num::NonZeroU16, path::PathBuf};
+use std::{any::TypeId, collections::BTreeMap, num::NonZeroU16, path::PathBuf};
 
 use cosmic::{
     cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry},
@@ -12,7 +12,10 @@ use cosmic::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{app::App, tab::View};
+use crate::{
+    app::App,
+    tab::{LocationSort, View},
+};
 
 pub const CONFIG_VERSION: u64 = 1;
 
@@ -96,11 +99,18 @@ impl MediaFavorite {
 #[serde(default)]
 pub struct Config {
     pub app_theme: AppTheme,
+    /// Metadata database to use instead of the default one, overridden by `--database`
+    pub database: Option<PathBuf>,
     pub desktop: DesktopConfig,
     pub favorites: Vec<MediaFavorite>,
     pub tags: Vec<crate::sql::Tag>,
+    pub smart_collections: Vec<crate::sql::SmartCollection>,
     pub show_details: bool,
     pub tab: MediaTabConfig,
+    /// Sort order picked per location, by `Location::sort_key()`
+    pub location_sorts: BTreeMap<String, LocationSort>,
+    /// Where posters of videos without one are taken, in percent of the duration
+    pub poster_position: u8,
 }
 
 impl Config {
@@ -137,6 +147,7 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             app_theme: AppTheme::System,
+            database: None,
             desktop: DesktopConfig::default(),
             favorites: vec![
                 MediaFavorite::Home,
@@ -147,8 +158,11 @@ impl Default for Config {
                 MediaFavorite::Videos,
             ],
             tags: Vec::new(),
+            smart_collections: Vec::new(),
             show_details: false,
             tab: MediaTabConfig::default(),
+            location_sorts: BTreeMap::new(),
+            poster_position: crate::parsers::poster::DEFAULT_POSITION,
         }
     }
 }
@@ -513,16 +513,15 @@ impl App {
         Task::perform(
             async move {
                 let location2 = location.clone();
-                let sql_conmnection: rusqlite::Connection = match crate::sql::connect() {
-                    Ok(conn) => conn,
+                let database = match crate::sql::Database::open() {
+                    Ok(database) => database,
                     Err(error) => {
                         log::error!("Failed to open Database! {}", error);
                         panic!("Ending the program as a database is required!");
                     },
                 };
-                let sql_connection = std::sync::Arc::new(std::sync::Mutex::new(sql_conmnection));
 
-                match tokio::task::spawn_blocking(move || location2.scan(sql_connection, icon_sizes)).await {
+                match tokio::task::spawn_blocking(move || location2.scan(&database, icon_sizes)).await {
                     Ok((parent_item_opt, items)) => {
                         message::app(Message::TabRescan(location, parent_item_opt, items))
                     }
@@ -1418,6 +1417,9 @@ impl Application for App {
                             self.context_page = ContextPage::Preview(None, kind);
                             self.set_show_context(true);
                         }
+                        tab::Command::RememberSort(..) => {
+                            // the dialog does not remember sort orders
+                        }
                         tab::Command::WindowDrag => {
                             commands.push(window::drag(self.flags.window_id));
                         }
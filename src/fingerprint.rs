@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Bytes read from the start and from the end of a file.
+const BLOCK_SIZE: u64 = 64 * 1024;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a, unlike `DefaultHasher` it gives the same value on every build and platform,
+/// so it can be stored in the database.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Cheap content fingerprint: the file size and a hash of the first and the last 64 KiB.
+/// Renaming or moving a file keeps the fingerprint, editing it almost always changes it.
+pub fn of_file(path: &Path) -> Option<String> {
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(error) => {
+            log::warn!(
+                "Failed to open {} for fingerprinting: {}",
+                path.display(),
+                error
+            );
+            return None;
+        }
+    };
+    let size = match file.metadata() {
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => return None,
+    };
+    let mut hash = fnv1a(FNV_OFFSET, &size.to_le_bytes());
+    let mut buffer = Vec::with_capacity(BLOCK_SIZE as usize);
+    if let Err(error) = (&mut file).take(BLOCK_SIZE).read_to_end(&mut buffer) {
+        log::warn!(
+            "Failed to read {} for fingerprinting: {}",
+            path.display(),
+            error
+        );
+        return None;
+    }
+    hash = fnv1a(hash, &buffer);
+    if size > BLOCK_SIZE {
+        buffer.clear();
+        let tail = BLOCK_SIZE.min(size - BLOCK_SIZE);
+        let read = file
+            .seek(SeekFrom::End(-(tail as i64)))
+            .and_then(|_| file.take(tail).read_to_end(&mut buffer));
+        if let Err(error) = read {
+            log::warn!(
+                "Failed to read {} for fingerprinting: {}",
+                path.display(),
+                error
+            );
+            return None;
+        }
+        hash = fnv1a(hash, &buffer);
+    }
+    Some(format!("{:x}-{:016x}", size, hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &tempfile::TempDir, name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn fingerprint_follows_content_not_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, contents: &[u8]| write(&dir, name, contents);
+        let mut contents = vec![7u8; 3 * BLOCK_SIZE as usize];
+        let original = write("original.mkv", &contents);
+        let renamed = write("renamed.mkv", &contents);
+        assert_eq!(of_file(&original), of_file(&renamed));
+        // a change in the tail block
+        *contents.last_mut().unwrap() = 8;
+        let edited = write("edited.mkv", &contents);
+        assert_ne!(of_file(&original), of_file(&edited));
+        let small = write("small.jpeg", b"small");
+        assert_eq!(of_file(&small), of_file(&write("copy.jpeg", b"small")));
+        assert_ne!(of_file(&small), of_file(&write("other.jpeg", b"other")));
+        assert_eq!(of_file(&original.with_file_name("missing.mkv")), None);
+        assert_eq!(of_file(original.parent().unwrap()), None);
+    }
+}
@@ -15,6 +15,7 @@ use config::Config;
 pub mod cmd;
 pub mod config;
 pub mod dialog;
+mod fingerprint;
 mod image;
 mod key_bind;
 mod localize;
@@ -47,6 +48,17 @@ pub fn home_dir() -> PathBuf {
     }
 }
 
+fn absolute_path(path: PathBuf) -> PathBuf {
+    if path.is_absolute() {
+        path
+    } else {
+        match env::current_dir() {
+            Ok(dir) => dir.join(path),
+            Err(_) => path,
+        }
+    }
+}
+
 /// Runs application in desktop mode
 #[rustfmt::skip]
 pub fn desktop() -> Result<(), Box<dyn std::error::Error>> {
@@ -55,6 +67,10 @@ pub fn desktop() -> Result<(), Box<dyn std::error::Error>> {
     localize::localize();
 
     let (config_handler, config) = Config::load();
+    if let Some(database) = config.database.clone() {
+        sql::set_database_file(database);
+    }
+    parsers::poster::set_position(config.poster_position);
 
     let locations = vec![
         match dirs::desktop_dir() {
@@ -108,9 +124,20 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (config_handler, config) = Config::load();
 
     let mut locations = Vec::new();
-    for arg in env::args().skip(1) {
+    let mut database = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
         let location = if &arg == "--trash" {
             Location::Trash
+        } else if &arg == "--database" {
+            match args.next() {
+                Some(file) => database = Some(absolute_path(PathBuf::from(file))),
+                None => log::warn!("--database requires a file name"),
+            }
+            continue;
+        } else if let Some(file) = arg.strip_prefix("--database=") {
+            database = Some(absolute_path(PathBuf::from(file)));
+            continue;
         } else {
             match fs::canonicalize(&arg) {
                 Ok(absolute) => Location::Path(absolute),
@@ -122,6 +149,10 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
         };
         locations.push(location);
     }
+    if let Some(database) = database.or_else(|| config.database.clone()) {
+        sql::set_database_file(database);
+    }
+    parsers::poster::set_position(config.poster_position);
 
     let mut settings = Settings::default();
     settings = settings.theme(config.app_theme.theme());
@@ -62,6 +62,7 @@ pub fn context_menu<'a>(
     };
 
     let (sort_name, sort_direction, _) = tab.sort_options();
+    let media_sorts = tab.media_sort_options();
     let sort_item = |label, variant| {
         menu_item(
             format!(
@@ -99,7 +100,7 @@ pub fn context_menu<'a>(
     match (&tab.mode, &tab.location) {
         (
             tab::Mode::Audio | tab::Mode::Image | tab::Mode::Video,
-            Location::DBSearch(_) | Location::Tag(_) | Location::Collection(_) | Location::Path(_) | Location::Search(_, _, _, _) | Location::Recents,
+            Location::DBSearch(_) | Location::SmartCollection(_) | Location::Tag(_) | Location::Collection(_) | Location::Path(_) | Location::Search(_, _, _, _) | Location::Recents,
         ) => {
         }
         (
@@ -107,15 +108,46 @@ pub fn context_menu<'a>(
             Location::DBSearch(_),
         ) => {
             children.push(menu_item(fl!("search-context"), Action::SearchDB).into());
+            children.push(
+                menu_item(fl!("export-search-results-menu"), Action::ExportSearchResults).into(),
+            );
+            children.push(menu_item(fl!("write-nfo"), Action::WriteNfo).into());
+            children.push(menu_item(fl!("pin-search"), Action::PinSearch).into());
             children.push(divider::horizontal::light().into());
             children.push(menu_item(fl!("zoom-in"), Action::ZoomIn).into());
             children.push(menu_item(fl!("zoom-out"), Action::ZoomOut).into());
             children.push(menu_item(fl!("default-size"), Action::ZoomDefault).into());
             children.push(divider::horizontal::light().into());
+            children.push(sort_item(fl!("sort-by-relevance"), HeadingOptions::Relevance));
             children.push(sort_item(fl!("media-browser"), HeadingOptions::MediaSpecific));
             children.push(sort_item(fl!("sort-by-name"), HeadingOptions::Name));
             children.push(sort_item(fl!("sort-by-modified"), HeadingOptions::Modified));
             children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
+            for heading in media_sorts.iter() {
+                children.push(sort_item(heading.to_string(), *heading));
+            }
+        }
+        (
+            tab::Mode::App | tab::Mode::Desktop | tab::Mode::Browser,
+            Location::SmartCollection(_),
+        ) => {
+            children.push(menu_item(fl!("search-context"), Action::SearchDB).into());
+            children.push(
+                menu_item(fl!("export-search-results-menu"), Action::ExportSearchResults).into(),
+            );
+            children.push(menu_item(fl!("write-nfo"), Action::WriteNfo).into());
+            children.push(divider::horizontal::light().into());
+            children.push(menu_item(fl!("zoom-in"), Action::ZoomIn).into());
+            children.push(menu_item(fl!("zoom-out"), Action::ZoomOut).into());
+            children.push(menu_item(fl!("default-size"), Action::ZoomDefault).into());
+            children.push(divider::horizontal::light().into());
+            children.push(sort_item(fl!("media-browser"), HeadingOptions::MediaSpecific));
+            children.push(sort_item(fl!("sort-by-name"), HeadingOptions::Name));
+            children.push(sort_item(fl!("sort-by-modified"), HeadingOptions::Modified));
+            children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
+            for heading in media_sorts.iter() {
+                children.push(sort_item(heading.to_string(), *heading));
+            }
         }
         (
             tab::Mode::App | tab::Mode::Desktop | tab::Mode::Browser,
@@ -142,12 +174,14 @@ pub fn context_menu<'a>(
                         .push(menu_item(fl!("open-in-new-window"), Action::OpenInNewWindow).into());
                     children
                         .push(menu_item(fl!("recursive-scan-directories"), Action::RecursiveScanDirectories).into());
+                    children.push(menu_item(fl!("verify-library"), Action::VerifyLibrary).into());
                 }
                 children.push(divider::horizontal::light().into());
                 children.push(menu_item(fl!("rename"), Action::Rename).into());
                 children.push(menu_item(fl!("cut"), Action::Cut).into());
                 children.push(menu_item(fl!("copy"), Action::Copy).into());
                 children.push(menu_item(fl!("show-details"), Action::Preview).into());
+                children.push(menu_item(fl!("write-nfo"), Action::WriteNfo).into());
                 children.push(divider::horizontal::light().into());
                 //TODO: Print?
                 children.push(menu_item(fl!("add-to-sidebar"), Action::AddToSidebar).into());
@@ -169,11 +203,14 @@ pub fn context_menu<'a>(
                 children.push(sort_item(fl!("sort-by-name"), HeadingOptions::Name));
                 children.push(sort_item(fl!("sort-by-modified"), HeadingOptions::Modified));
                 children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
+                for heading in media_sorts.iter() {
+                    children.push(sort_item(heading.to_string(), *heading));
+                }
             }
         }
         (
             tab::Mode::Dialog(dialog_kind),
-            Location::DBSearch(_) | Location::Tag(_) | Location::Collection(_) | Location::Path(_) | Location::Search(_, _, _, _) | Location::Recents,
+            Location::DBSearch(_) | Location::SmartCollection(_) | Location::Tag(_) | Location::Collection(_) | Location::Path(_) | Location::Search(_, _, _, _) | Location::Recents,
         ) => {
             if selected > 0 {
                 if selected_dir == 1 && selected == 1 || selected_dir == 0 {
@@ -200,6 +237,9 @@ pub fn context_menu<'a>(
                 children.push(sort_item(fl!("sort-by-name"), HeadingOptions::Name));
                 children.push(sort_item(fl!("sort-by-modified"), HeadingOptions::Modified));
                 children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
+                for heading in media_sorts.iter() {
+                    children.push(sort_item(heading.to_string(), *heading));
+                }
             }
         }
         (_, Location::Network(_, _)) => {
@@ -218,6 +258,9 @@ pub fn context_menu<'a>(
                 children.push(sort_item(fl!("sort-by-name"), HeadingOptions::Name));
                 children.push(sort_item(fl!("sort-by-modified"), HeadingOptions::Modified));
                 children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
+                for heading in media_sorts.iter() {
+                    children.push(sort_item(heading.to_string(), *heading));
+                }
             }
         }
         (_, Location::Trash) => {
@@ -237,6 +280,9 @@ pub fn context_menu<'a>(
                 children.push(sort_item(fl!("sort-by-name"), HeadingOptions::Name));
                 children.push(sort_item(fl!("sort-by-modified"), HeadingOptions::Modified));
                 children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
+                for heading in media_sorts.iter() {
+                    children.push(sort_item(heading.to_string(), *heading));
+                }
             }
         }
     }
@@ -269,6 +315,7 @@ pub fn dialog_menu<'a>(
     show_details: bool,
 ) -> Element<'static, Message> {
     let (sort_name, sort_direction, _) = tab.sort_options();
+    let media_sorts = tab.media_sort_options();
     let sort_item = |label, sort, dir| {
         menu::Item::CheckBox(
             label,
@@ -351,7 +398,12 @@ pub fn dialog_menu<'a>(
                         false,
                     ),
                     //TODO: sort by type
-                ],
+                ]
+                .into_iter()
+                .chain(media_sorts.iter().map(|heading| {
+                    sort_item(heading.to_string(), *heading, heading.ascending_first())
+                }))
+                .collect(),
             ),
         ),
         menu::Tree::with_children(
@@ -395,6 +447,9 @@ pub fn menu_bar<'a>(
     key_binds: &HashMap<KeyBind, Action>,
 ) -> Element<'a, Message> {
     let sort_options = tab_opt.map(|tab| tab.sort_options());
+    let media_sorts = tab_opt
+        .map(|tab| tab.media_sort_options())
+        .unwrap_or_default();
     let sort_item = |label, sort, dir| {
         menu::Item::CheckBox(
             label,
@@ -435,8 +490,14 @@ pub fn menu_bar<'a>(
                     menu::Item::Button(fl!("rename"), None, Action::Rename),
                     menu::Item::Divider,
                     menu::Item::Button(fl!("recursive-scan-directories"), None, Action::RecursiveScanDirectories),
+                    menu::Item::Button(fl!("verify-library"), None, Action::VerifyLibrary),
                     menu::Item::Button(fl!("search-context"), None, Action::SearchDB),
                     menu::Item::Divider,
+                    menu::Item::Button(fl!("export-library"), None, Action::ExportLibrary),
+                    menu::Item::Button(fl!("export-search-results-menu"), None, Action::ExportSearchResults),
+                    menu::Item::Button(fl!("import-library"), None, Action::ImportLibrary),
+                    menu::Item::Button(fl!("write-nfo"), None, Action::WriteNfo),
+                    menu::Item::Divider,
                     menu::Item::Button(fl!("add-to-sidebar"), None, Action::AddToSidebar),
                     menu::Item::Button(fl!("add-new-tag"), None, Action::AddTagToSidebar),
                     menu::Item::Divider,
@@ -538,7 +599,12 @@ pub fn menu_bar<'a>(
                         false,
                     ),
                     //TODO: sort by type
-                ],
+                ]
+                .into_iter()
+                .chain(media_sorts.iter().map(|heading| {
+                    sort_item(heading.to_string(), *heading, heading.ascending_first())
+                }))
+                .collect(),
             ),
         ),
     ])
@@ -420,6 +420,10 @@ pub enum Operation {
     },
     /// Empty the trash
     EmptyTrash,
+    /// Clean up the library database and the thumbnails, or only report what would be removed
+    Maintenance {
+        dry_run: bool,
+    },
     /// Move items
     Move {
         paths: Vec<PathBuf>,
@@ -436,6 +440,12 @@ pub enum Operation {
     Restore {
         items: Vec<trash::TrashItem>,
     },
+    /// Write the library metadata of videos and TV shows to their NFO files, or only
+    /// report the changes
+    WriteNfo {
+        paths: Vec<PathBuf>,
+        dry_run: bool,
+    },
 }
 
 impl Operation {
@@ -465,6 +475,7 @@ impl Operation {
                 "emptying-trash" 
                 //progress = progress()
                 ),
+            Self::Maintenance { .. } => fl!("maintaining-library"),
             Self::Move { paths, to } => fl!(
                 "moving",
                 items = paths.len(),
@@ -484,6 +495,7 @@ impl Operation {
                 "restoring", items = items.len()
                 //, progress = progress()
         ),
+            Self::WriteNfo { paths, .. } => fl!("writing-nfo", items = paths.len()),
         }
     }
 
@@ -502,6 +514,7 @@ impl Operation {
                 to = fl!("trash")
             ),
             Self::EmptyTrash => fl!("emptied-trash"),
+            Self::Maintenance { .. } => fl!("maintained-library"),
             Self::Move { paths, to } => fl!(
                 "moved",
                 items = paths.len(),
@@ -515,6 +528,7 @@ impl Operation {
             ),
             Self::Rename { from, to } => fl!("renamed", from = file_name(from), to = file_name(to)),
             Self::Restore { items } => fl!("restored", items = items.len()),
+            Self::WriteNfo { paths, .. } => fl!("wrote-nfo", items = paths.len()),
         }
     }
 
@@ -524,8 +538,10 @@ impl Operation {
             Self::Copy { .. }
             | Self::Delete { .. }
             | Self::EmptyTrash
+            | Self::Maintenance { .. }
             | Self::Move { .. }
-            | Self::Restore { .. } => true,
+            | Self::Restore { .. }
+            | Self::WriteNfo { .. } => true,
             Self::NewFolder { .. }
             | Self::Rename { .. } => false,
         }
@@ -544,6 +560,7 @@ impl Operation {
         self,
         msg_tx: &Arc<TokioMutex<Sender<Message>>>,
         controller: Controller,
+        database: crate::sql::Database,
     ) -> Result<OperationSelection, String> {
         let controller_clone = controller.clone();
 
@@ -593,6 +610,27 @@ impl Operation {
                 }
                 OperationSelection::default()
             }
+            Self::Maintenance { dry_run } => {
+                let report = tokio::task::spawn_blocking(
+                    move || -> Result<crate::sql::maintenance::Report, String> {
+                        let thumbs = crate::thumbnails::thumbnail_dir();
+                        crate::sql::maintenance::run(
+                            &database,
+                            thumbs.as_deref(),
+                            dry_run,
+                            &controller,
+                        )
+                    },
+                )
+                .await
+                .map_err(err_str)??;
+                let _ = msg_tx
+                    .lock()
+                    .await
+                    .send(Message::DialogPush(DialogPage::MaintenanceReport(report)))
+                    .await;
+                OperationSelection::default()
+            }
             Self::Move { paths, to } => copy_or_move(paths, to, true, msg_tx, controller).await?,
             Self::NewFolder { path } => {
                 tokio::task::spawn_blocking(move || -> Result<OperationSelection, String> {
@@ -644,6 +682,28 @@ impl Operation {
                     selected: paths,
                 }
             }
+            Self::WriteNfo { paths, dry_run } => {
+                let selected = paths.clone();
+                let report = tokio::task::spawn_blocking(
+                    move || -> Result<crate::parsers::nfo::export::Report, String> {
+                        let records = database.read(|handle| {
+                            crate::sql::exchange::records(handle, Some(paths.as_slice()))
+                        });
+                        crate::parsers::nfo::export::run(&records, dry_run, &controller)
+                    },
+                )
+                .await
+                .map_err(err_str)??;
+                let _ = msg_tx
+                    .lock()
+                    .await
+                    .send(Message::DialogPush(DialogPage::NfoReport {
+                        paths: selected,
+                        report,
+                    }))
+                    .await;
+                OperationSelection::default()
+            }
         };
 
         controller_clone.set_progress(100.0);
@@ -694,7 +754,11 @@ mod tests {
                 paths: paths_clone,
                 to: to_clone,
             }
-            .perform(&sync::Mutex::new(tx).into(), Controller::new())
+            .perform(
+                &sync::Mutex::new(tx).into(),
+                Controller::new(),
+                crate::sql::Database::in_memory().unwrap(),
+            )
             .await
         });
 
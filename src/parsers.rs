@@ -15,183 +15,10 @@ use std::ops::ControlFlow;
 use std::path::PathBuf;
 use std::time::UNIX_EPOCH;
 
-/// Read video metadata from XML files movie.nfo (XBMC/Kodi format)
-fn parse_nfo(nfo_file: &PathBuf, metadata: &mut crate::sql::VideoMetadata) {
-    use std::fs::File;
-    use std::io::BufReader;
-
-    use xml::reader::XmlEvent;
-    let file = match File::open(nfo_file) {
-        Ok(ok) => ok,
-        Err(err) => {
-            log::warn!("failed to read entry in {:?}: {}", nfo_file.display(), err);
-            return;
-        }
-    };
-
-    let mut reader = xml::ParserConfig::default()
-        .ignore_root_level_whitespace(false)
-        .create_reader(BufReader::new(file));
-
-    let mut prevtag = String::new();
-    let mut tag = String::new();
-    let mut _level = 0;
-    loop {
-        match reader.next() {
-            Ok(e) => match e {
-                XmlEvent::StartDocument { .. } => {
-                    //println!("StartDocument({version}, {encoding})");
-                }
-                XmlEvent::EndDocument => {
-                    //println!("EndDocument");
-                    break;
-                }
-                XmlEvent::StartElement { name, .. } => {
-                    tag = name.to_string().to_ascii_lowercase();
-                    match &tag as &str {
-                        "actor" => {
-                            prevtag = tag.clone();
-                        }
-                        "video" => {
-                            prevtag = tag.clone();
-                        }
-                        "audio" => {
-                            prevtag = tag.clone();
-                        }
-                        "subtitle" => {
-                            prevtag = tag.clone();
-                        }
-                        _ => {}
-                    }
-                    _level += 1;
-                    /*
-                    if attributes.is_empty() {
-                        println!("StartElement({name})");
-                    } else {
-                        let attrs: Vec<_> = attributes
-                            .iter()
-                            .map(|a| format!("{}={:?}", &a.name, a.value))
-                            .collect();
-                        println!("StartElement({name} [{}])", attrs.join(", "));
-                    }*/
-                }
-                XmlEvent::EndElement { name } => {
-                    tag = name.to_string().to_ascii_lowercase();
-                    if tag == prevtag {
-                        prevtag.clear();
-                    }
-                    //println!("EndElement({name})");
-                    _level -= 1;
-                }
-                XmlEvent::Comment(_data) => {}
-                XmlEvent::CData(_data) => {}
-                XmlEvent::Characters(data) => {
-                    let value = data.escape_debug().to_string();
-                    match &tag as &str {
-                        "title" => {
-                            metadata.title = value.clone();
-                        }
-                        "plot" => {
-                            metadata.description = value.clone();
-                        }
-                        "runtime" => {
-                            let duration = match u32::from_str_radix(&value, 10) {
-                                Ok(ok) => ok,
-                                Err(err) => {
-                                    log::warn!("failed to parse number {:?}: {}", value, err);
-                                    continue;
-                                }
-                            };
-                            metadata.duration = duration * 60;
-                        }
-                        "season" => {
-                            let season = match i32::from_str_radix(&value, 10) {
-                                Ok(ok) => ok,
-                                Err(err) => {
-                                    log::warn!("failed to parse number {:?}: {}", value, err);
-                                    continue;
-                                }
-                            };
-                            metadata.season = season;
-                        }
-                        "episode" => {
-                            let episode = match i32::from_str_radix(&value, 10) {
-                                Ok(ok) => ok,
-                                Err(err) => {
-                                    log::warn!("failed to parse number {:?}: {}", value, err);
-                                    continue;
-                                }
-                            };
-                            metadata.episode = episode;
-                        }
-                        "premiered" => {
-                            let ret = NaiveDate::parse_from_str(&value, "%Y-%m-%d");
-                            if ret.is_ok() {
-                                metadata.date = ret.unwrap();
-                            }
-                        }
-                        "aired" => {
-                            let ret = NaiveDate::parse_from_str(&value, "%Y-%m-%d");
-                            if ret.is_ok() {
-                                metadata.date = ret.unwrap();
-                            }
-                        }
-                        "director" => {
-                            metadata.director.push(value.clone());
-                        }
-                        "name" => {
-                            metadata.actors.push(value.clone());
-                            prevtag.clear();
-                        }
-                        "width" => {
-                            let val = match u32::from_str_radix(&value, 10) {
-                                Ok(ok) => ok,
-                                Err(err) => {
-                                    log::warn!("failed to parse number {:?}: {}", value, err);
-                                    continue;
-                                }
-                            };
-                            metadata.width = val;
-                        }
-                        "height" => {
-                            let val = match u32::from_str_radix(&value, 10) {
-                                Ok(ok) => ok,
-                                Err(err) => {
-                                    log::warn!("failed to parse number {:?}: {}", value, err);
-                                    continue;
-                                }
-                            };
-                            metadata.height = val;
-                        }
-                        "durationinseconds" => {
-                            let val = match u32::from_str_radix(&value, 10) {
-                                Ok(ok) => ok,
-                                Err(err) => {
-                                    log::warn!("failed to parse number {:?}: {}", value, err);
-                                    continue;
-                                }
-                            };
-                            metadata.duration = val;
-                        }
-                        "language" => {
-                            if &prevtag == "audio" {
-                                metadata.audiolangs.push(value.clone());
-                            }
-                            if &prevtag == "subtitle" {
-                                metadata.sublangs.push(value.clone());
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                _ => {}
-            },
-            Err(_e) => {
-                break;
-            }
-        }
-    }
-}
+pub mod discover;
+pub mod filename;
+pub mod nfo;
+pub mod poster;
 
 /// Read audio metadata from supported audio formats
 fn parse_audiotags(file: &PathBuf, metadata: &mut crate::sql::AudioMetadata) {
@@ -223,6 +50,10 @@ fn parse_audiotags(file: &PathBuf, metadata: &mut crate::sql::AudioMetadata) {
         Some(value) => metadata.track_id = value.into(),
         None => {}
     };
+    match tag.disc_number() {
+        Some(value) => metadata.disc = value.into(),
+        None => {}
+    };
     match tag.genre() {
         Some(value) => metadata.genre = value.to_string(),
         None => {}
@@ -459,6 +290,23 @@ fn parse_audiotags(file: &PathBuf, metadata: &mut crate::sql::AudioMetadata) {
 
 /// Read Image metadata form EXIF format
 ///
+/// Clock time of the camera from an EXIF date, which is written as "2024:07:01 18:30:00"
+/// in the file and may come with a time zone.
+fn exif_time(text: &str) -> Option<chrono::NaiveDateTime> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.naive_local());
+    }
+    let formats = [
+        "%Y:%m:%d %H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+    ];
+    formats
+        .iter()
+        .find_map(|format| chrono::NaiveDateTime::parse_from_str(text, format).ok())
+}
+
 fn parse_exif(path: &PathBuf, metadata: &mut crate::sql::ImageMetadata) {
     use nom_exif::*;
     match imagesize::size(path.to_path_buf()) {
@@ -487,35 +335,41 @@ fn parse_exif(path: &PathBuf, metadata: &mut crate::sql::ImageMetadata) {
                 if let Ok(optres) = iter.parse_gps_info() {
                     if let Some(gps_info) = optres {
                         metadata.gps_string = gps_info.format_iso6709();
-                        metadata.gps_latitude =
-                            (gps_info.latitude.0 .0 / gps_info.latitude.0 .1) as f32;
-                        metadata.gps_latitude =
-                            (gps_info.latitude.1 .0 / gps_info.latitude.1 .1) as f32 / 60.0;
-                        metadata.gps_latitude =
-                            (gps_info.latitude.2 .0 / gps_info.latitude.2 .1) as f32 / 3600.0;
+                        // degrees, minutes and seconds are stored as fractions
+                        let ratio = |n: f32, d: f32| if d == 0.0 { 0.0 } else { n / d };
+                        let lat = &gps_info.latitude;
+                        metadata.gps_latitude = ratio(lat.0 .0 as f32, lat.0 .1 as f32)
+                            + ratio(lat.1 .0 as f32, lat.1 .1 as f32) / 60.0
+                            + ratio(lat.2 .0 as f32, lat.2 .1 as f32) / 3600.0;
                         if gps_info.latitude_ref == 'S' {
                             metadata.gps_latitude *= -1.0;
                         }
-                        metadata.gps_longitude =
-                            (gps_info.longitude.0 .0 / gps_info.longitude.0 .1) as f32;
-                        metadata.gps_longitude =
-                            (gps_info.longitude.1 .0 / gps_info.longitude.1 .1) as f32 / 60.0;
-                        metadata.gps_longitude =
-                            (gps_info.longitude.2 .0 / gps_info.longitude.2 .1) as f32 / 3600.0;
+                        let lon = &gps_info.longitude;
+                        metadata.gps_longitude = ratio(lon.0 .0 as f32, lon.0 .1 as f32)
+                            + ratio(lon.1 .0 as f32, lon.1 .1 as f32) / 60.0
+                            + ratio(lon.2 .0 as f32, lon.2 .1 as f32) / 3600.0;
                         if gps_info.longitude_ref == 'W' {
                             metadata.gps_longitude *= -1.0;
                         }
-                        metadata.gps_altitude = (gps_info.altitude.0 / gps_info.altitude.0) as f32;
+                        metadata.gps_altitude =
+                            ratio(gps_info.altitude.0 as f32, gps_info.altitude.1 as f32);
                     }
                 }
                 let exif: Exif = iter.into();
-                if let Some(val) = exif.get(ExifTag::DateTimeOriginal) {
-                    if let Some(s) = val.as_str() {
-                        if let Ok(date) = DateTime::parse_from_rfc3339(s) {
-                            metadata.date = date.date_naive();
+                // rotated photos are stored sideways, keep the size they are shown with
+                if let Some(val) = exif.get(ExifTag::Orientation) {
+                    if let Ok(orientation) = val.to_string().parse::<u32>() {
+                        if (5..=8).contains(&orientation) {
+                            std::mem::swap(&mut metadata.width, &mut metadata.height);
                         }
                     }
                 }
+                if let Some(val) = exif.get(ExifTag::DateTimeOriginal) {
+                    if let Some(taken) = exif_time(&val.to_string()) {
+                        metadata.date = taken.date();
+                        metadata.taken = taken.and_utc().timestamp();
+                    }
+                }
                 if let Some(val) = exif.get(ExifTag::LensModel) {
                     if let Some(s) = val.as_str() {
                         metadata.lense_model = s.to_string();
@@ -644,6 +498,8 @@ pub fn item_from_entry(
         video_opt: None,
         audio_opt: None,
         collection_opt: None,
+        search_rank: None,
+        search_snippet: None,
     };
     item.thumbnail_opt = Some(crate::tab::ItemThumbnail::new(item.clone()));
     item
@@ -700,7 +556,7 @@ pub fn slice_u8_to_vec_string(utfvec: Vec<u8>) -> Vec<String> {
 
 /// Read video metadata directly from the video file.
 /// Mainly used to get languages of internal streams and chapter information.
-/// Utilizes a local install of ffmpeg. The only requirement is that the ffmpeg can read the video files.
+/// Uses the GStreamer discoverer, a local install of ffmpeg is only tried for files GStreamer can not read.
 fn video_metadata(meta: &mut crate::sql::VideoMetadata) {
     let basename;
     let fp = PathBuf::from(&meta.path);
@@ -717,77 +573,11 @@ fn video_metadata(meta: &mut crate::sql::VideoMetadata) {
     if meta.title.len() == 0 {
         meta.title = basename.clone();
     }
-    let command = format!("ffmpeg -i \"{}\"", meta.path);
-    log::warn!("Executing command {}.", command);
-    let mut cmd_runner = crate::cmd::CmdRunner::new(&command);
-    if let Ok((stdout, _stderr)) = cmd_runner.run_with_output() {
-        // let ffmpeg_output = std::process::Command::new("ffmpeg")
-        //    .args(["-i", filepath])
-        //    .output()
-        //    .expect("failed to execute process");
-        //let lines = slice_u8_to_vec_string(ffmpeg_output.stderr);
-        let mut i = 0;
-        let num_lines = stdout.len();
-        while i < num_lines {
-            let line = stdout[i].clone();
-            if let Ok(re_duration) = regex::Regex::new(
-                r"(?i)\s*Duration:\s+(?P<hours>\d+):(?P<minutes>\d+):(?P<seconds>\d+).\d+\s*,",
-            ) {
-                if re_duration.is_match(&line) {
-                    let caps = re_duration.captures(&line).unwrap();
-                    let hours = string_to_uint(&caps["hours"]);
-                    let minutes = string_to_uint(&caps["minutes"]);
-                    let seconds = string_to_uint(&caps["seconds"]);
-                    meta.duration = hours * 3600 + minutes * 60 + seconds;
-                }
-            }
-            if let Ok(re_video) = regex::Regex::new(r"(?i), (?P<width>\d+)x(?P<height>\d+)") {
-                if re_video.is_match(&line) {
-                    let caps = re_video.captures(&line).unwrap();
-                    meta.width = string_to_uint(&caps["width"]);
-                    meta.height = string_to_uint(&caps["height"]);
-                }
-            }
-            if let Ok(re_chapter) =
-                regex::Regex::new(r"(?i)start (?P<start>\d+\.\d+), end (?P<end>\d+\.\d+)")
-            {
-                if re_chapter.is_match(&line) {
-                    let caps = re_chapter.captures(&line).unwrap();
-                    let start = string_to_float(&caps["start"]);
-                    let end = string_to_float(&caps["end"]);
-                    let mut chapter = crate::sql::Chapter {
-                        ..Default::default()
-                    };
-                    chapter.start = start;
-                    chapter.end = end;
-                    if i + 2 < num_lines {
-                        let v: Vec<&str> = stdout[i + 2].split(" : ").collect();
-                        if v.len() > 1 {
-                            chapter.title = v[1].to_string();
-                        }
-                    }
-                    if chapter.title.len() == 0 {
-                        chapter.title = format!("Chapter_{:02}", meta.chapters.len() + 1);
-                    }
-                    meta.chapters.push(chapter);
-                }
-            }
-
-            if let Ok(re_audio) = regex::Regex::new(r"(?i), \((?P<language>\w+)\):\s*Audio") {
-                if re_audio.is_match(&line) {
-                    let caps = re_audio.captures(&line).unwrap();
-                    meta.audiolangs.push(caps["language"].to_string());
-                }
-            }
-            if let Ok(re_sub) = regex::Regex::new(r"(?i), \((?P<language>\w+)\):\s*Subtitle") {
-                if re_sub.is_match(&line) {
-                    let caps = re_sub.captures(&line).unwrap();
-                    meta.sublangs.push(caps["language"].to_string());
-                }
-            }
-            i += 1;
-        }
+    if let Some(media) = discover::discover(&fp).or_else(|| discover::ffmpeg(&fp)) {
+        media.apply(meta);
     }
+    // title, year, episode... of videos without an NFO file
+    filename::parse(&fp).apply(meta);
 }
 
 pub fn string_to_uint(mystring: &str) -> u32 {
@@ -826,6 +616,50 @@ pub fn timecode_to_ffmpeg_time(timecode: u32) -> String {
     format!("{:02}:{:02}:{:02}.000", hours, minutes, seconds)
 }
 
+/// Read the plain text of a .lrc lyrics or .srt subtitle file without timecodes and markup
+pub fn timed_text(path: &str) -> String {
+    let content = match std::fs::read(path) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+        Err(err) => {
+            log::warn!("failed to read {}: {}", path, err);
+            return String::new();
+        }
+    };
+    let mut text = Vec::new();
+    for line in content.lines() {
+        let mut line = line.trim_start_matches('\u{feff}').trim();
+        // lyrics: [mm:ss.xx] timestamps and [ar:Artist] style id tags
+        while line.starts_with('[') {
+            match line.find(']') {
+                Some(end) => line = line[end + 1..].trim_start(),
+                None => break,
+            }
+        }
+        // subtitles: cue numbers, timecodes and the WebVTT header
+        if line.is_empty()
+            || line == "WEBVTT"
+            || line.contains("-->")
+            || line.chars().all(|c| c.is_ascii_digit())
+        {
+            continue;
+        }
+        let mut plain = String::new();
+        let mut in_markup = false;
+        for c in line.chars() {
+            match c {
+                '<' | '{' => in_markup = true,
+                '>' | '}' if in_markup => in_markup = false,
+                _ if !in_markup => plain.push(c),
+                _ => {}
+            }
+        }
+        if !plain.trim().is_empty() {
+            text.push(plain.trim().to_string());
+        }
+    }
+    text.join("\n")
+}
+
 pub fn poster_path(path: &std::path::PathBuf) -> std::path::PathBuf {
     let posterpath;
     let hashvalue: u64 = crate::thumbnails::calculate_hash(path);
@@ -883,69 +717,18 @@ pub fn album_path(path: &std::path::PathBuf) -> std::path::PathBuf {
 }
 
 /// for a video without external metadata create a screenshot
-/// and store it next to the video file.
-/// Utilizes a local install of ffmpeg. The only requirement is that the ffmpeg can read the video files.
+/// and store it as its poster in the thumbs cache.
+/// The frame is decoded with GStreamer, the directory of the video is never written to.
 fn create_screenshots(meta: &mut crate::sql::VideoMetadata) {
     video_metadata(meta);
-    let timecode = meta.duration / 10;
-    let outputpattern = format!("{}_%03d.jpeg", meta.path);
-    let localoutput = format!("{}_001.jpeg", meta.path);
-    let localoutputpath = PathBuf::from(&localoutput);
     let inputpath = PathBuf::from(&meta.path);
     let outputpath = poster_path(&inputpath);
     let output = osstr_to_string(outputpath.clone().into_os_string());
-    let time = timecode_to_ffmpeg_time(timecode);
-    if outputpath.is_file() {
-        //let ret = std::fs::remove_file(&output);
-        //if ret.is_err() {
-        //    log::error!("could not delete file {}", output);
-        //}
-        meta.poster = output.clone();
-        return;
-    }
-
-    match std::process::Command::new("ffmpeg")
-        .args([
-            "-ss",
-            &time,
-            "-i",
-            &meta.path,
-            "-frames:v",
-            "1",
-            "-q:v",
-            "2",
-            &outputpattern,
-        ])
-        .output()
-    {
-        Ok(out) => match String::from_utf8(out.stderr) {
-            Ok(text) => log::warn!("{}", &text),
-            Err(_error) => {}
-        },
-        Err(error) => log::error!("Failed to generate Screenshot {}: {}", output, error),
-    }
-    if !localoutputpath.is_file() {
-        log::error!(
-            "Failed to create screenshot: {}",
-            format!("ffmpeg -ss {} -i {} -frames:v 1 -q:v 2 ", time, meta.path)
-        );
-        return;
+    if outputpath.is_file() || poster::create(&inputpath, &outputpath) {
+        meta.poster = output;
     } else {
-        crate::thumbnails::create_thumbnail(&localoutputpath, 256);
-        match std::fs::remove_file(&localoutputpath) {
-            Ok(()) => {}
-            Err(error) => {
-                log::error!(
-                    "could not write poster to metadata storage for file {}!: {}",
-                    meta.path,
-                    error
-                );
-                return;
-            }
-        }
+        log::error!("Failed to create screenshot for {}", meta.path);
     }
-    //meta.poster = output.clone();
-    meta.thumb = output;
 }
 
 /// create an item to put into our tabmodel from a video wihtout external metadata
@@ -955,20 +738,17 @@ pub fn item_from_video(
     statdata: &std::fs::Metadata,
     sizes: IconSizes,
     data: &crate::scanmetadata::ScanMetaData,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     from_db: bool,
 ) -> Item {
     let mut refresh = false;
     let filepath = osstr_to_string(path.clone().into_os_string());
     if !from_db {
         if data.known_files_contains(path.clone()) {
-            if let Ok(modified) = statdata.modified() {
-                if let Ok(new_date) = modified.duration_since(UNIX_EPOCH) {
-                    let filedata = &data.known_files_get(path.clone());
-                    let new_seconds_since_epoch = new_date.as_secs();
-                    if new_seconds_since_epoch > filedata.modification_time {
-                        refresh = true;
-                    }
+            if statdata.modified().is_ok() {
+                // re-parse when the modification time or the size changed
+                if data.known_files_get(path.clone()).changed(statdata) {
+                    refresh = true;
                 }
                 if refresh {
                     // file is newer
@@ -988,9 +768,10 @@ pub fn item_from_video(
                         &PathBuf::from(&videometadata.poster),
                         254,
                     );
-                    crate::sql::update_video(sql_connection.clone(), videometadata, statdata, data);
+                    database.store(crate::sql::update_video, videometadata, statdata, data);
                 } else {
-                    *videometadata = crate::sql::video(sql_connection.clone(), &filepath, data);
+                    *videometadata = database
+                        .read(|connection| crate::sql::video(connection, &filepath, data));
                 }
             }
         } else {
@@ -1008,7 +789,7 @@ pub fn item_from_video(
             }
             videometadata.thumb =
                 crate::thumbnails::create_thumbnail(&PathBuf::from(&videometadata.poster), 254);
-            crate::sql::insert_video(sql_connection.clone(), videometadata, statdata, data);
+            database.store(crate::sql::insert_video, videometadata, statdata, data);
         }
     }
 
@@ -1129,6 +910,8 @@ pub fn item_from_video(
         video_opt: Some(videometadata.to_owned()),
         audio_opt: None,
         collection_opt: None,
+        search_rank: None,
+        search_snippet: None,
     };
     item.thumbnail_opt = Some(crate::tab::ItemThumbnail::new(item.clone()));
     item
@@ -1141,7 +924,7 @@ pub fn item_from_nfo(
     statdata: &std::fs::Metadata,
     sizes: IconSizes,
     data: &crate::scanmetadata::ScanMetaData,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     from_db: bool,
 ) -> Item {
     let filepath = PathBuf::from(&metadata.path);
@@ -1158,18 +941,15 @@ pub fn item_from_nfo(
     if !from_db {
         if data.known_files_contains(filepath.clone()) {
             let mut refresh = false;
-            if let Ok(modified) = statdata.modified() {
-                if let Ok(new_date) = modified.duration_since(UNIX_EPOCH) {
-                    let filedata = &data.known_files_get(filepath.clone());
-                    let new_seconds_since_epoch = new_date.as_secs();
-                    if new_seconds_since_epoch > filedata.modification_time {
-                        refresh = true;
-                    }
+            if statdata.modified().is_ok() {
+                // re-parse when the modification time or the size changed
+                if data.known_files_get(filepath.clone()).changed(statdata) {
+                    refresh = true;
                 }
                 if refresh {
                     // file is newer
                     video_metadata(metadata);
-                    parse_nfo(&nfo_file, metadata);
+                    nfo::parse_nfo(&nfo_file, metadata);
                     if metadata.date.num_days_from_ce() < 100 {
                         if let Ok(created) = statdata.created() {
                             if let Ok(nsecs) = created.duration_since(UNIX_EPOCH) {
@@ -1184,14 +964,15 @@ pub fn item_from_nfo(
                     metadata.thumb =
                         crate::thumbnails::create_thumbnail(&PathBuf::from(&metadata.poster), 254);
                     metadata.name = basename.clone();
-                    crate::sql::update_video(sql_connection.clone(), metadata, statdata, data);
+                    database.store(crate::sql::update_video, metadata, statdata, data);
                 } else {
-                    *metadata = crate::sql::video(sql_connection.clone(), &metadata.path, data);
+                    *metadata = database
+                        .read(|connection| crate::sql::video(connection, &metadata.path, data));
                 }
             }
         } else {
             video_metadata(metadata);
-            parse_nfo(&nfo_file, metadata);
+            nfo::parse_nfo(&nfo_file, metadata);
             if metadata.date.num_days_from_ce() < 100 {
                 if let Ok(created) = statdata.created() {
                     if let Ok(nsecs) = created.duration_since(UNIX_EPOCH) {
@@ -1206,7 +987,7 @@ pub fn item_from_nfo(
             metadata.thumb =
                 crate::thumbnails::create_thumbnail(&PathBuf::from(&metadata.poster), 254);
             metadata.name = basename.clone();
-            crate::sql::insert_video(sql_connection.clone(), metadata, statdata, data);
+            database.store(crate::sql::insert_video, metadata, statdata, data);
         }
     }
 
@@ -1289,6 +1070,8 @@ pub fn item_from_nfo(
         video_opt: Some(metadata.clone()),
         audio_opt: None,
         collection_opt: None,
+        search_rank: None,
+        search_snippet: None,
     };
     item.thumbnail_opt = Some(crate::tab::ItemThumbnail::new(item.clone()));
     item
@@ -1347,7 +1130,7 @@ pub fn item_from_audiotags(
     metadata: &mut crate::sql::AudioMetadata,
     statdata: &std::fs::Metadata,
     sizes: IconSizes,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     from_db: bool,
 ) -> Item {
     let filepath = PathBuf::from(&metadata.path);
@@ -1367,18 +1150,15 @@ pub fn item_from_audiotags(
     } else {
         if data.known_files_contains(filepath.clone()) {
             let mut refresh = false;
-            if let Ok(modified) = statdata.modified() {
-                if let Ok(new_date) = modified.duration_since(UNIX_EPOCH) {
-                    let filedata = &data.known_files_get(filepath.clone());
-                    let new_seconds_since_epoch = new_date.as_secs();
-                    if new_seconds_since_epoch > filedata.modification_time {
-                        refresh = true;
-                    }
+            if statdata.modified().is_ok() {
+                // re-parse when the modification time or the size changed
+                if data.known_files_get(filepath.clone()).changed(statdata) {
+                    refresh = true;
                 }
                 if refresh {
                     // file is newer
                     parse_audiotags(&audio, metadata);
-                    // read chapter data from ffmpeg
+                    // read chapter data from the audio file
                     let mut temp = crate::sql::VideoMetadata {
                         ..Default::default()
                     };
@@ -1405,15 +1185,16 @@ pub fn item_from_audiotags(
                             }
                         }
                     }
-                    crate::sql::update_audio(sql_connection.clone(), metadata, statdata, data);
+                    database.store(crate::sql::update_audio, metadata, statdata, data);
                 } else {
-                    *metadata = crate::sql::audio(sql_connection.clone(), &metadata.path, data);
+                    *metadata = database
+                        .read(|connection| crate::sql::audio(connection, &metadata.path, data));
                     audio_metadata(audio, data, metadata);
                 }
             }
         } else {
             parse_audiotags(&audio, metadata);
-            // read chapter data from ffmpeg
+            // read chapter data from the audio file
             let mut temp = crate::sql::VideoMetadata {
                 ..Default::default()
             };
@@ -1440,7 +1221,7 @@ pub fn item_from_audiotags(
                     }
                 }
             }
-            crate::sql::insert_audio(sql_connection.clone(), metadata, statdata, data);
+            database.store(crate::sql::insert_audio, metadata, statdata, data);
         }
     }
 
@@ -1542,6 +1323,8 @@ pub fn item_from_audiotags(
         video_opt: None,
         audio_opt: Some(metadata.clone()),
         collection_opt: None,
+        search_rank: None,
+        search_snippet: None,
     };
     item.thumbnail_opt = Some(crate::tab::ItemThumbnail::new(item.clone()));
     item
@@ -1554,7 +1337,7 @@ pub fn item_from_exif(
     statdata: &std::fs::Metadata,
     sizes: IconSizes,
     data: &crate::scanmetadata::ScanMetaData,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     from_db: bool,
 ) -> Item {
     let filepath = PathBuf::from(&metadata.path);
@@ -1571,13 +1354,10 @@ pub fn item_from_exif(
     if !from_db {
         if data.known_files_contains(filepath.clone()) {
             let mut refresh = false;
-            if let Ok(modified) = statdata.modified() {
-                if let Ok(new_date) = modified.duration_since(UNIX_EPOCH) {
-                    let filedata = &data.known_files_get(filepath.clone());
-                    let new_seconds_since_epoch = new_date.as_secs();
-                    if new_seconds_since_epoch > filedata.modification_time {
-                        refresh = true;
-                    }
+            if statdata.modified().is_ok() {
+                // re-parse when the modification time or the size changed
+                if data.known_files_get(filepath.clone()).changed(statdata) {
+                    refresh = true;
                 }
                 if refresh {
                     // file is newer
@@ -1607,9 +1387,10 @@ pub fn item_from_exif(
                         }
                     }
                     metadata.name = basename.clone();
-                    crate::sql::update_image(sql_connection.clone(), metadata, statdata, data);
+                    database.store(crate::sql::update_image, metadata, statdata, data);
                 } else {
-                    *metadata = crate::sql::image(sql_connection.clone(), &metadata.path, data);
+                    *metadata = database
+                        .read(|connection| crate::sql::image(connection, &metadata.path, data));
                 }
             }
         } else {
@@ -1635,7 +1416,7 @@ pub fn item_from_exif(
                 }
             }
             metadata.name = basename.clone();
-            crate::sql::insert_image(sql_connection.clone(), metadata, statdata, data);
+            database.store(crate::sql::insert_image, metadata, statdata, data);
         }
     }
 
@@ -1744,6 +1525,8 @@ pub fn item_from_exif(
         video_opt: None,
         audio_opt: None,
         collection_opt: None,
+        search_rank: None,
+        search_snippet: None,
     };
     item.thumbnail_opt = Some(crate::tab::ItemThumbnail::new(item.clone()));
     item
@@ -1755,7 +1538,8 @@ pub fn osstr_to_string(osstr: std::ffi::OsString) -> String {
         Some(str) => return str.to_string(),
         None => {}
     }
-    String::new()
+    // keep non UTF-8 names apart instead of collapsing them all into ""
+    osstr.to_string_lossy().to_string()
 }
 
 /// Scanners to process the filesystem
@@ -1818,7 +1602,7 @@ pub fn scan_videos(
     path: PathBuf,
     data: &crate::scanmetadata::ScanMetaData,
     sizes: IconSizes,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
 ) -> ControlFlow<()> {
     if data.special_files_contains(path.clone()) {
         return ControlFlow::Break(());
@@ -1846,17 +1630,13 @@ pub fn scan_videos(
         }
     };
     data.special_files_insert(path.clone());
-    let mut poster = osstr_to_string(path.clone().into_os_string());
-    poster.push_str("_001.jpeg");
-    meta_data.poster = poster.clone();
-    data.special_files_insert(PathBuf::from(&poster));
     let item = crate::parsers::item_from_video(
         path,
         &mut meta_data,
         &statdata,
         sizes,
         data,
-        sql_connection.clone(),
+        database,
         false,
     );
     data.items_push(item);
@@ -1867,7 +1647,7 @@ pub fn scan_audiotags(
     audio: PathBuf,
     data: &crate::scanmetadata::ScanMetaData,
     sizes: IconSizes,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
 ) -> ControlFlow<()> {
     if data.special_files_contains(audio.clone()) {
         return ControlFlow::Break(());
@@ -1899,7 +1679,7 @@ pub fn scan_audiotags(
         &mut meta_data,
         &statdata,
         sizes,
-        sql_connection.clone(),
+        database,
         false,
     );
     data.items_push(item);
@@ -1911,7 +1691,7 @@ pub fn scan_exif(
     path: PathBuf,
     data: &crate::scanmetadata::ScanMetaData,
     sizes: IconSizes,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
 ) -> ControlFlow<()> {
     if data.special_files_contains(path.clone()) {
         return ControlFlow::Break(());
@@ -1947,7 +1727,7 @@ pub fn scan_exif(
         &statdata,
         sizes,
         data,
-        sql_connection.clone(),
+        database,
         false,
     );
     data.items_push(item);
@@ -1960,7 +1740,7 @@ pub fn scan_single_nfo_dir(
     data: &crate::scanmetadata::ScanMetaData,
     sizes: IconSizes,
     justdirs: &mut Vec<PathBuf>,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
 ) -> ControlFlow<()> {
     if data.special_files_contains(dp.clone()) {
         return ControlFlow::Break(());
@@ -2129,7 +1909,7 @@ pub fn scan_single_nfo_dir(
         &statdata,
         sizes,
         data,
-        sql_connection.clone(),
+        database,
         false,
     );
     data.items_push(item);
@@ -2143,7 +1923,7 @@ pub fn scan_nfos_in_dir(
     all: &Vec<PathBuf>,
     data: &crate::scanmetadata::ScanMetaData,
     sizes: IconSizes,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
 ) -> ControlFlow<()> {
     let mut meta_data = crate::sql::VideoMetadata {
         ..Default::default()
@@ -2204,7 +1984,7 @@ pub fn scan_nfos_in_dir(
         &statdata,
         sizes,
         data,
-        sql_connection.clone(),
+        database,
         false,
     );
     data.items_push(item);
@@ -2216,7 +1996,7 @@ pub fn parse_tv_show_episodes(
     meta_data: &mut crate::sql::CollectionMetadata,
     data: &crate::scanmetadata::ScanMetaData,
     sizes: IconSizes,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
 ) {
     let path = match nfo_file.parent() {
         Some(ok) => ok.to_path_buf(),
@@ -2285,7 +2065,7 @@ pub fn parse_tv_show_episodes(
     let mut videometadata = crate::sql::VideoMetadata {
         ..Default::default()
     };
-    parse_nfo(&nfo_file, &mut videometadata);
+    nfo::parse_nfo(&nfo_file, &mut videometadata);
     meta_data.description = videometadata.description;
     meta_data.name = videometadata.title;
     // parse episodes in subdirectories
@@ -2344,7 +2124,7 @@ pub fn parse_tv_show_episodes(
             &mut contents,
             &localdata,
             sizes,
-            sql_connection.clone(),
+            database,
         );
         if ret == ControlFlow::Break(()) {
             continue;
@@ -2395,7 +2175,7 @@ pub fn item_from_tvshow(
     statdata: &std::fs::Metadata,
     sizes: IconSizes,
     data: &crate::scanmetadata::ScanMetaData,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     from_db: bool,
 ) -> Item {
     let filepath = PathBuf::from(&metadata.path);
@@ -2415,18 +2195,15 @@ pub fn item_from_tvshow(
         };
         if data.known_files_contains(filepath.clone()) {
             let mut refresh = false;
-            if let Ok(modified) = statdata.modified() {
-                if let Ok(new_date) = modified.duration_since(UNIX_EPOCH) {
-                    let filedata = &data.known_files_get(filepath.clone());
-                    let new_seconds_since_epoch = new_date.as_secs();
-                    if new_seconds_since_epoch > filedata.modification_time {
-                        refresh = true;
-                    }
+            if statdata.modified().is_ok() {
+                // re-parse when the modification time or the size changed
+                if data.known_files_get(filepath.clone()).changed(statdata) {
+                    refresh = true;
                 }
                 if refresh {
                     // file is newer
-                    parse_tv_show_episodes(nfo_file.clone(), metadata, data, sizes, sql_connection.clone());
-                    parse_nfo(&nfo_file, &mut video);
+                    parse_tv_show_episodes(nfo_file.clone(), metadata, data, sizes, database);
+                    nfo::parse_nfo(&nfo_file, &mut video);
                     if video.date.num_days_from_ce() < 100 {
                         if let Ok(created) = statdata.created() {
                             if let Ok(nsecs) = created.duration_since(UNIX_EPOCH) {
@@ -2440,19 +2217,17 @@ pub fn item_from_tvshow(
                     }
                     metadata.thumb =
                         crate::thumbnails::create_thumbnail(&PathBuf::from(&metadata.poster), 254);
-                    crate::sql::update_collection(sql_connection.clone(), metadata, statdata, data);
+                    database.store(crate::sql::update_collection, metadata, statdata, data);
                 } else {
-                    *metadata = crate::sql::collection(
-                        sql_connection.clone(),
-                        &osstr_to_string(metadata.path.clone().into_os_string()),
-                        data,
-                    );
+                    let path = osstr_to_string(metadata.path.clone().into_os_string());
+                    *metadata =
+                        database.read(|connection| crate::sql::collection(connection, &path, data));
                 }
             }
         } else {
-            parse_tv_show_episodes(nfo_file.clone(), metadata, data, sizes, sql_connection.clone());
+            parse_tv_show_episodes(nfo_file.clone(), metadata, data, sizes, database);
             video.poster = metadata.poster.clone();
-            parse_nfo(&nfo_file, &mut video);
+            nfo::parse_nfo(&nfo_file, &mut video);
             if video.date.num_days_from_ce() < 100 {
                 if let Ok(created) = statdata.created() {
                     if let Ok(nsecs) = created.duration_since(UNIX_EPOCH) {
@@ -2466,14 +2241,11 @@ pub fn item_from_tvshow(
             }
             metadata.thumb =
                 crate::thumbnails::create_thumbnail(&PathBuf::from(&video.poster), 254);
-            crate::sql::insert_collection(sql_connection.clone(), metadata, statdata, data);
+            database.store(crate::sql::insert_collection, metadata, statdata, data);
         }
     } else {
-        *metadata = crate::sql::collection(
-            sql_connection.clone(),
-            &osstr_to_string(metadata.path.clone().into_os_string()),
-            data,
-        );
+        let path = osstr_to_string(metadata.path.clone().into_os_string());
+        *metadata = database.read(|connection| crate::sql::collection(connection, &path, data));
     }
 
     let name;
@@ -2555,6 +2327,8 @@ pub fn item_from_tvshow(
         video_opt: None,
         audio_opt: None,
         collection_opt: Some(metadata.clone()),
+        search_rank: None,
+        search_snippet: None,
     };
     item.thumbnail_opt = Some(crate::tab::ItemThumbnail::new(item.clone()));
     item
@@ -2564,7 +2338,7 @@ pub fn scan_tvshow(
     path: PathBuf,
     data: &crate::scanmetadata::ScanMetaData,
     sizes: IconSizes,
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
 ) -> ControlFlow<()> {
     let nfo_file = path.clone().join("tvshow.nfo");
     let mut meta_data = crate::sql::CollectionMetadata {
@@ -2589,7 +2363,7 @@ pub fn scan_tvshow(
         &statdata,
         sizes,
         data,
-        sql_connection.clone(),
+        database,
         false,
     );
     data.items_push(item);
@@ -2622,10 +2396,16 @@ pub fn item_from_collection_episode(
         }
     };
     let name;
-    if metadata.title.len() == 0 {
-        name = format!("S{:02}E{:02} {}", metadata.season, metadata.episode, metadata.name);
+    let title = if metadata.title.len() == 0 {
+        &metadata.name
+    } else {
+        &metadata.title
+    };
+    if metadata.season == 0 {
+        // a movie of a set, numbered in the order of release
+        name = format!("{:02} {}", metadata.episode, title);
     } else {
-        name = format!("S{:02}E{:02} {}", metadata.season, metadata.episode, metadata.title);
+        name = format!("S{:02}E{:02} {}", metadata.season, metadata.episode, title);
     }
 
     let display_name = Item::display_name(&name);
@@ -2700,6 +2480,8 @@ pub fn item_from_collection_episode(
         video_opt: Some(metadata.clone()),
         audio_opt: None,
         collection_opt: None,
+        search_rank: None,
+        search_snippet: None,
     };
     item.thumbnail_opt = Some(crate::tab::ItemThumbnail::new(item.clone()));
     data.items_push(item);
@@ -0,0 +1,658 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Technical metadata of video and audio files: container, streams and chapters.
+//!
+//! `discover()` asks GStreamer's discoverer, which reads the file in-process with the
+//! same plugins the player uses. Only if that fails, for example because a plugin is
+//! missing, `ffmpeg()` runs a local install of ffmpeg and reads its report instead. Both
+//! return a `MediaInfo`, `MediaInfo::apply()` copies what the database keeps into the
+//! `VideoMetadata` of the file.
+
+use std::ffi::{CStr, CString};
+use std::path::Path;
+
+use gstreamer as gst;
+use gstreamer_pbutils as gst_pbutils;
+use gstreamer_pbutils::prelude::*;
+
+/// Time the discoverer may take for one file.
+const TIMEOUT_SECONDS: u64 = 15;
+
+/// High dynamic range signalled by a video stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Hdr {
+    /// Perceptual quantizer transfer (SMPTE ST 2084), used by HDR10 and Dolby Vision.
+    pub pq: bool,
+    /// Hybrid log-gamma transfer (ARIB STD-B67).
+    pub hlg: bool,
+    /// Static mastering display metadata (SMPTE ST 2086) is present.
+    pub mastering_display: bool,
+}
+
+impl Hdr {
+    pub fn is_hdr(&self) -> bool {
+        self.pq || self.hlg
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VideoStream {
+    /// Short ffmpeg name of the codec, e.g. "h264" or "hevc".
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    pub framerate: f32,
+    /// Bits per second, 0 if unknown.
+    pub bitrate: u32,
+    pub hdr: Hdr,
+    pub language: String,
+    pub title: String,
+    /// Cover art stored as a video stream with a single picture.
+    pub is_image: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AudioStream {
+    /// Short ffmpeg name of the codec, e.g. "aac" or "ac3".
+    pub codec: String,
+    pub channels: u32,
+    pub sample_rate: u32,
+    /// Bits per second, 0 if unknown.
+    pub bitrate: u32,
+    pub language: String,
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SubtitleStream {
+    pub codec: String,
+    pub language: String,
+    pub title: String,
+}
+
+/// What was found out about a media file. Languages are ISO 639-2 codes like "eng".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaInfo {
+    /// Short name of the container format, e.g. "matroska" or "mp4".
+    pub container: String,
+    /// Duration in seconds.
+    pub duration: u32,
+    pub video: Vec<VideoStream>,
+    pub audio: Vec<AudioStream>,
+    pub subtitles: Vec<SubtitleStream>,
+    pub chapters: Vec<crate::sql::Chapter>,
+}
+
+impl MediaInfo {
+    /// Copy what the database keeps about the file into `meta`.
+    pub fn apply(&self, meta: &mut crate::sql::VideoMetadata) {
+        if self.duration > 0 {
+            meta.duration = self.duration;
+        }
+        // the first video stream is the film, later ones are cover art
+        if let Some(video) = self.video.iter().find(|video| !video.is_image) {
+            meta.width = video.width;
+            meta.height = video.height;
+            meta.framerate = video.framerate;
+            if meta.codec.is_empty() {
+                meta.codec = video.codec.clone();
+            }
+        }
+        for audio in self.audio.iter() {
+            if !audio.language.is_empty() {
+                meta.audiolangs.push(audio.language.clone());
+            }
+        }
+        for subtitle in self.subtitles.iter() {
+            if !subtitle.language.is_empty() {
+                meta.sublangs.push(subtitle.language.clone());
+            }
+        }
+        meta.chapters.extend(self.chapters.iter().cloned());
+    }
+}
+
+/// Short ffmpeg name of the codec of a caps structure, so the codec search finds files
+/// the same way whichever way they were read.
+pub fn codec_name(caps_name: &str, mpegversion: Option<i32>, layer: Option<i32>) -> String {
+    let name = match (caps_name, mpegversion) {
+        ("video/x-h264", _) => "h264",
+        ("video/x-h265", _) => "hevc",
+        ("video/x-h266", _) => "vvc",
+        ("video/x-av1", _) => "av1",
+        ("video/x-vp8", _) => "vp8",
+        ("video/x-vp9", _) => "vp9",
+        ("video/x-theora", _) => "theora",
+        ("video/x-prores", _) => "prores",
+        ("video/x-divx", _) | ("video/x-xvid", _) => "mpeg4",
+        ("video/mpeg", Some(1)) => "mpeg1video",
+        ("video/mpeg", Some(2)) => "mpeg2video",
+        ("video/mpeg", Some(4)) => "mpeg4",
+        ("video/x-wmv", _) => "wmv",
+        ("image/jpeg", _) => "mjpeg",
+        ("image/png", _) => "png",
+        ("audio/mpeg", Some(1)) => match layer {
+            Some(2) => "mp2",
+            _ => "mp3",
+        },
+        ("audio/mpeg", Some(2)) | ("audio/mpeg", Some(4)) => "aac",
+        ("audio/x-ac3", _) => "ac3",
+        ("audio/x-eac3", _) => "eac3",
+        ("audio/x-dts", _) => "dts",
+        ("audio/x-true-hd", _) => "truehd",
+        ("audio/x-opus", _) => "opus",
+        ("audio/x-vorbis", _) => "vorbis",
+        ("audio/x-flac", _) => "flac",
+        ("audio/x-alac", _) => "alac",
+        ("audio/x-wma", _) => "wma",
+        ("audio/x-raw", _) => "pcm",
+        ("application/x-subtitle", _) | ("text/x-raw", _) => "subrip",
+        ("application/x-ssa", _) | ("application/x-ass", _) => "ass",
+        ("subpicture/x-pgs", _) => "hdmv_pgs_subtitle",
+        ("subpicture/x-dvd", _) => "dvd_subtitle",
+        ("subpicture/x-dvb", _) => "dvb_subtitle",
+        _ => {
+            // e.g. "video/x-foo" becomes "foo"
+            let short = caps_name.rsplit('/').next().unwrap_or(caps_name);
+            return short.trim_start_matches("x-").to_string();
+        }
+    };
+    name.to_string()
+}
+
+/// Short name of a container format from the caps of the file.
+pub fn container_name(caps_name: &str) -> String {
+    let name = match caps_name {
+        "video/x-matroska" | "audio/x-matroska" => "matroska",
+        "video/webm" | "audio/webm" => "webm",
+        "video/quicktime" | "audio/x-m4a" => "mp4",
+        "video/x-msvideo" => "avi",
+        "video/mpegts" => "mpegts",
+        "video/mpeg" => "mpeg",
+        "video/x-flv" => "flv",
+        "application/ogg" | "video/ogg" | "audio/ogg" => "ogg",
+        "video/x-ms-asf" => "asf",
+        _ => {
+            let short = caps_name.rsplit('/').next().unwrap_or(caps_name);
+            return short.trim_start_matches("x-").to_string();
+        }
+    };
+    name.to_string()
+}
+
+/// HDR signalled by the colorimetry of a stream, which GStreamer writes either by name,
+/// e.g. "bt2100-pq", or as "range:matrix:transfer:primaries" numbers.
+pub fn hdr_from_colorimetry(colorimetry: &str, mastering_display: bool) -> Hdr {
+    // GST_VIDEO_TRANSFER_SMPTE2084 and GST_VIDEO_TRANSFER_ARIB_STD_B67
+    let transfer = colorimetry.split(':').nth(2);
+    Hdr {
+        pq: colorimetry.contains("pq")
+            || colorimetry.contains("smpte2084")
+            || transfer == Some("14"),
+        hlg: colorimetry.contains("hlg")
+            || colorimetry.contains("arib-std-b67")
+            || transfer == Some("15"),
+        mastering_display,
+    }
+}
+
+/// ISO 639-2 code like ffmpeg prints it, "eng" for "en".
+fn language_code(code: &str) -> String {
+    let Ok(code_c) = CString::new(code) else {
+        return code.to_string();
+    };
+    let iso_c = unsafe {
+        let iso_ptr = gstreamer_tag::ffi::gst_tag_get_language_code_iso_639_2B(code_c.as_ptr());
+        if iso_ptr.is_null() {
+            return code.to_string();
+        }
+        CStr::from_ptr(iso_ptr)
+    };
+    iso_c.to_string_lossy().into_owned()
+}
+
+fn caps_structure<T: IsA<gst_pbutils::DiscovererStreamInfo>>(
+    stream: &T,
+) -> Option<(String, Option<i32>, Option<i32>, Hdr)> {
+    let caps = stream.caps()?;
+    let structure = caps.structure(0)?;
+    let hdr = match structure.get::<&str>("colorimetry") {
+        Ok(colorimetry) => {
+            hdr_from_colorimetry(colorimetry, structure.has_field("mastering-display-info"))
+        }
+        Err(_) => Hdr::default(),
+    };
+    Some((
+        structure.name().as_str().to_string(),
+        structure.get::<i32>("mpegversion").ok(),
+        structure.get::<i32>("layer").ok(),
+        hdr,
+    ))
+}
+
+fn stream_codec<T: IsA<gst_pbutils::DiscovererStreamInfo>>(stream: &T) -> (String, Hdr) {
+    match caps_structure(stream) {
+        Some((name, mpegversion, layer, hdr)) => (codec_name(&name, mpegversion, layer), hdr),
+        None => (String::new(), Hdr::default()),
+    }
+}
+
+/// Language and title tags of a stream.
+fn stream_tags<T: IsA<gst_pbutils::DiscovererStreamInfo>>(stream: &T) -> (String, String) {
+    let Some(tags) = stream.tags() else {
+        return (String::new(), String::new());
+    };
+    let language = tags
+        .get::<gst::tags::LanguageCode>()
+        .map(|value| language_code(value.get()))
+        .unwrap_or_default();
+    let title = tags
+        .get::<gst::tags::Title>()
+        .map(|value| value.get().to_string())
+        .unwrap_or_default();
+    (language, title)
+}
+
+/// Chapters of a table of contents. Editions hold the chapters of Matroska files.
+fn toc_chapters(entries: Vec<gst::TocEntry>, chapters: &mut Vec<crate::sql::Chapter>) {
+    for entry in entries {
+        if entry.entry_type() != gst::TocEntryType::Chapter {
+            toc_chapters(entry.sub_entries(), chapters);
+            continue;
+        }
+        let Some((start, end)) = entry.start_stop_times() else {
+            continue;
+        };
+        let mut title = entry
+            .tags()
+            .and_then(|tags| {
+                tags.get::<gst::tags::Title>()
+                    .map(|value| value.get().to_string())
+            })
+            .unwrap_or_default();
+        if title.is_empty() {
+            title = format!("Chapter_{:02}", chapters.len() + 1);
+        }
+        chapters.push(crate::sql::Chapter {
+            title,
+            start: start as f32 / 1_000_000_000.0,
+            end: end as f32 / 1_000_000_000.0,
+        });
+    }
+}
+
+/// Read the streams and chapters of the file at `path` with GStreamer.
+pub fn discover(path: &Path) -> Option<MediaInfo> {
+    if let Err(error) = gst::init() {
+        log::error!("Failed to initialize GStreamer: {}", error);
+        return None;
+    }
+    let uri = match gst::glib::filename_to_uri(path, None) {
+        Ok(uri) => uri,
+        Err(error) => {
+            log::warn!("No URI for {}: {}", path.display(), error);
+            return None;
+        }
+    };
+    let discoverer =
+        match gst_pbutils::Discoverer::new(gst::ClockTime::from_seconds(TIMEOUT_SECONDS)) {
+            Ok(discoverer) => discoverer,
+            Err(error) => {
+                log::error!("Failed to create the GStreamer discoverer: {}", error);
+                return None;
+            }
+        };
+    let info = match discoverer.discover_uri(&uri) {
+        Ok(info) => info,
+        Err(error) => {
+            log::warn!("GStreamer could not read {}: {}", path.display(), error);
+            return None;
+        }
+    };
+    match info.result() {
+        gst_pbutils::DiscovererResult::Ok => {}
+        gst_pbutils::DiscovererResult::MissingPlugins => {
+            log::warn!(
+                "GStreamer plugins are missing to read {}: {:?}",
+                path.display(),
+                info.missing_elements_installer_details()
+            );
+            return None;
+        }
+        result => {
+            log::warn!("GStreamer could not read {}: {:?}", path.display(), result);
+            return None;
+        }
+    }
+
+    let mut media = MediaInfo {
+        duration: info
+            .duration()
+            .map(|duration| duration.seconds() as u32)
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    if let Some(top) = info.stream_info() {
+        if top.is::<gst_pbutils::DiscovererContainerInfo>() {
+            if let Some((name, ..)) = caps_structure(&top) {
+                media.container = container_name(&name);
+            }
+        }
+    }
+    for stream in info.video_streams() {
+        let (codec, hdr) = stream_codec(&stream);
+        let (language, title) = stream_tags(&stream);
+        let framerate = stream.framerate();
+        media.video.push(VideoStream {
+            codec,
+            width: stream.width(),
+            height: stream.height(),
+            framerate: if framerate.denom() > 0 {
+                framerate.numer() as f32 / framerate.denom() as f32
+            } else {
+                0.0
+            },
+            bitrate: stream.bitrate(),
+            hdr,
+            language,
+            title,
+            is_image: stream.is_image(),
+        });
+    }
+    for stream in info.audio_streams() {
+        let (codec, _) = stream_codec(&stream);
+        let (mut language, title) = stream_tags(&stream);
+        if let Some(code) = stream.language() {
+            language = language_code(&code);
+        }
+        media.audio.push(AudioStream {
+            codec,
+            channels: stream.channels(),
+            sample_rate: stream.sample_rate(),
+            bitrate: stream.bitrate(),
+            language,
+            title,
+        });
+    }
+    for stream in info.subtitle_streams() {
+        let (codec, _) = stream_codec(&stream);
+        let (mut language, title) = stream_tags(&stream);
+        if let Some(code) = stream.language() {
+            language = language_code(&code);
+        }
+        media.subtitles.push(SubtitleStream {
+            codec,
+            language,
+            title,
+        });
+    }
+    if let Some(toc) = info.toc() {
+        toc_chapters(toc.entries(), &mut media.chapters);
+    }
+    Some(media)
+}
+
+/// Read the report of `ffmpeg -i` on the file at `path`, for files GStreamer can not read.
+pub fn ffmpeg(path: &Path) -> Option<MediaInfo> {
+    // the report is parsed, so it must not be translated
+    let output = std::process::Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-i")
+        .arg(path)
+        .env("LC_ALL", "C")
+        .output();
+    match output {
+        // ffmpeg reports an error as no output file was given, the report is on stderr
+        Ok(output) => Some(parse_ffmpeg(&String::from_utf8_lossy(&output.stderr))),
+        Err(error) => {
+            log::warn!(
+                "Could not run ffmpeg to read {}, is it installed? {}",
+                path.display(),
+                error
+            );
+            None
+        }
+    }
+}
+
+/// Where `title` lines of the ffmpeg report belong to.
+enum Section {
+    Chapter,
+    Video,
+    Audio,
+    Subtitle,
+    Other,
+}
+
+/// Parse the report of `ffmpeg -i`.
+pub fn parse_ffmpeg(report: &str) -> MediaInfo {
+    let mut media = MediaInfo::default();
+    let (
+        Ok(re_input),
+        Ok(re_duration),
+        Ok(re_chapter),
+        Ok(re_stream),
+        Ok(re_title),
+        Ok(re_size),
+        Ok(re_fps),
+        Ok(re_bitrate),
+        Ok(re_sample_rate),
+    ) = (
+        regex::Regex::new(r"^Input #\d+, (?P<container>[^,]+)"),
+        regex::Regex::new(r"(?i)^\s*Duration:\s+(?P<hours>\d+):(?P<minutes>\d+):(?P<seconds>\d+)"),
+        regex::Regex::new(r"(?i)start (?P<start>-?\d+\.\d+), end (?P<end>\d+\.\d+)"),
+        regex::Regex::new(
+            r"^\s*Stream #\d+:\d+(\[\w+\])?(\((?P<language>\w+)\))?(\[\w+\])?: (?P<kind>Video|Audio|Subtitle): (?P<codec>\w+)",
+        ),
+        regex::Regex::new(r"^\s*title\s*: (?P<title>.*)$"),
+        regex::Regex::new(r", (?P<width>\d+)x(?P<height>\d+)"),
+        regex::Regex::new(r", (?P<fps>\d+(\.\d+)?) fps"),
+        regex::Regex::new(r", (?P<kbits>\d+) kb/s"),
+        regex::Regex::new(r", (?P<hz>\d+) Hz"),
+    )
+    else {
+        log::error!("Failed to build the regular expressions for ffmpeg reports");
+        return media;
+    };
+    let mut section = Section::Other;
+    for line in report.lines() {
+        if let Some(caps) = re_input.captures(line) {
+            // "matroska,webm" lists every name of the demuxer
+            media.container = caps["container"].to_string();
+            if media.container == "mov" {
+                media.container = "mp4".to_string();
+            }
+        } else if let Some(caps) = re_duration.captures(line) {
+            let hours = super::string_to_uint(&caps["hours"]);
+            let minutes = super::string_to_uint(&caps["minutes"]);
+            let seconds = super::string_to_uint(&caps["seconds"]);
+            media.duration = hours * 3600 + minutes * 60 + seconds;
+        } else if let Some(caps) = re_chapter.captures(line) {
+            media.chapters.push(crate::sql::Chapter {
+                title: String::new(),
+                start: super::string_to_float(&caps["start"]),
+                end: super::string_to_float(&caps["end"]),
+            });
+            section = Section::Chapter;
+        } else if let Some(caps) = re_stream.captures(line) {
+            let language = caps
+                .name("language")
+                .map(|language| language.as_str().to_string())
+                .unwrap_or_default();
+            let codec = caps["codec"].to_string();
+            let bitrate = re_bitrate
+                .captures(line)
+                .map(|caps| super::string_to_uint(&caps["kbits"]) * 1000)
+                .unwrap_or_default();
+            match &caps["kind"] {
+                "Video" => {
+                    let mut video = VideoStream {
+                        codec,
+                        bitrate,
+                        language,
+                        hdr: Hdr {
+                            pq: line.contains("smpte2084"),
+                            hlg: line.contains("arib-std-b67"),
+                            mastering_display: false,
+                        },
+                        is_image: line.contains("(attached pic)"),
+                        ..Default::default()
+                    };
+                    if let Some(caps) = re_size.captures(line) {
+                        video.width = super::string_to_uint(&caps["width"]);
+                        video.height = super::string_to_uint(&caps["height"]);
+                    }
+                    if let Some(caps) = re_fps.captures(line) {
+                        video.framerate = super::string_to_float(&caps["fps"]);
+                    }
+                    media.video.push(video);
+                    section = Section::Video;
+                }
+                "Audio" => {
+                    media.audio.push(AudioStream {
+                        codec,
+                        sample_rate: re_sample_rate
+                            .captures(line)
+                            .map(|caps| super::string_to_uint(&caps["hz"]))
+                            .unwrap_or_default(),
+                        bitrate,
+                        language,
+                        ..Default::default()
+                    });
+                    section = Section::Audio;
+                }
+                _ => {
+                    media.subtitles.push(SubtitleStream {
+                        codec,
+                        language,
+                        ..Default::default()
+                    });
+                    section = Section::Subtitle;
+                }
+            }
+        } else if let Some(caps) = re_title.captures(line) {
+            let title = caps["title"].trim().to_string();
+            match section {
+                Section::Chapter => {
+                    if let Some(chapter) = media.chapters.last_mut() {
+                        chapter.title = title;
+                    }
+                }
+                Section::Video => {
+                    if let Some(video) = media.video.last_mut() {
+                        video.title = title;
+                    }
+                }
+                Section::Audio => {
+                    if let Some(audio) = media.audio.last_mut() {
+                        audio.title = title;
+                    }
+                }
+                Section::Subtitle => {
+                    if let Some(subtitle) = media.subtitles.last_mut() {
+                        subtitle.title = title;
+                    }
+                }
+                Section::Other => {}
+            }
+        }
+    }
+    for (i, chapter) in media.chapters.iter_mut().enumerate() {
+        if chapter.title.is_empty() {
+            chapter.title = format!("Chapter_{:02}", i + 1);
+        }
+    }
+    media
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = "Input #0, matroska,webm, from '/media/O'Brien \"cut\".mkv':
+  Metadata:
+    title           : The Movie
+  Duration: 01:58:10.05, start: 0.000000, bitrate: 5612 kb/s
+  Chapters:
+    Chapter #0:0: start 0.000000, end 300.000000
+      Metadata:
+        title           : Opening
+    Chapter #0:1: start 300.000000, end 7090.050000
+      Metadata:
+  Stream #0:0(eng): Video: hevc (Main 10), yuv420p10le(tv, bt2020nc/bt2020/smpte2084), 3840x2160 [SAR 1:1 DAR 16:9], 23.98 fps, 23.98 tbr, 1k tbn (default)
+  Stream #0:1(ger): Audio: ac3, 48000 Hz, 5.1(side), fltp, 448 kb/s (default)
+    Metadata:
+      title           : Deutsch
+  Stream #0:2(eng): Audio: aac (LC), 48000 Hz, stereo, fltp
+  Stream #0:3(eng): Subtitle: subrip
+    Metadata:
+      title           : English SDH
+  Stream #0:4: Video: mjpeg (Baseline), yuvj420p(pc, bt470bg/unknown/unknown), 600x900, 90k tbr, 90k tbn (attached pic)
+At least one output file must be specified
+";
+
+    #[test]
+    fn ffmpeg_report_is_parsed_into_streams() {
+        let media = parse_ffmpeg(REPORT);
+        assert_eq!(media.container, "matroska");
+        assert_eq!(media.duration, 7090);
+        assert_eq!(media.video.len(), 2);
+        let film = &media.video[0];
+        assert_eq!(
+            (film.codec.as_str(), film.width, film.height),
+            ("hevc", 3840, 2160)
+        );
+        assert_eq!(film.framerate, 23.98);
+        assert!(film.hdr.pq && !film.hdr.hlg);
+        assert!(media.video[1].is_image);
+        assert_eq!(media.audio[0].language, "ger");
+        assert_eq!(media.audio[0].title, "Deutsch");
+        assert_eq!(media.audio[0].bitrate, 448_000);
+        assert_eq!(media.audio[0].sample_rate, 48_000);
+        assert_eq!(media.audio[1].codec, "aac");
+        assert_eq!(media.subtitles[0].title, "English SDH");
+        let chapters: Vec<&str> = media.chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(chapters, ["Opening", "Chapter_02"]);
+        assert_eq!(media.chapters[1].start, 300.0);
+    }
+
+    #[test]
+    fn media_info_fills_video_metadata() {
+        let mut meta = crate::sql::VideoMetadata::default();
+        parse_ffmpeg(REPORT).apply(&mut meta);
+        // the cover art does not count as the film
+        assert_eq!(
+            (meta.width, meta.height, meta.codec.as_str()),
+            (3840, 2160, "hevc")
+        );
+        assert_eq!(meta.duration, 7090);
+        assert_eq!(meta.audiolangs, ["ger", "eng"]);
+        assert_eq!(meta.sublangs, ["eng"]);
+        assert_eq!(meta.chapters.len(), 2);
+    }
+
+    #[test]
+    fn caps_are_named_like_ffmpeg() {
+        assert_eq!(codec_name("video/x-h265", None, None), "hevc");
+        assert_eq!(codec_name("video/mpeg", Some(2), None), "mpeg2video");
+        assert_eq!(codec_name("audio/mpeg", Some(1), Some(3)), "mp3");
+        assert_eq!(codec_name("audio/mpeg", Some(4), None), "aac");
+        assert_eq!(codec_name("audio/x-eac3", None, None), "eac3");
+        assert_eq!(codec_name("video/x-something", None, None), "something");
+        assert_eq!(container_name("video/x-matroska"), "matroska");
+        assert_eq!(container_name("video/quicktime"), "mp4");
+    }
+
+    #[test]
+    fn hdr_is_read_from_the_colorimetry() {
+        assert!(hdr_from_colorimetry("bt2100-pq", true).pq);
+        assert!(hdr_from_colorimetry("bt2100-hlg", false).hlg);
+        assert!(hdr_from_colorimetry("2:9:14:9", false).pq);
+        assert!(hdr_from_colorimetry("2:9:15:9", false).hlg);
+        assert!(!hdr_from_colorimetry("bt709", false).is_hdr());
+        assert!(hdr_from_colorimetry("bt2100-pq", true).mastering_display);
+    }
+}
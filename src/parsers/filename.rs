@@ -0,0 +1,688 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Metadata from the names of video files without an NFO file, like
+//! `Heat (1995).mkv`, `The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv` or
+//! `Breaking.Bad.S01E02.Cats.in.the.Bag.720p.WEB-DL.mkv`.
+//!
+//! The title ends at the first word that is not part of it: the year, the episode, the
+//! resolution, the source, the edition or a codec. Episodes are `S01E02`, `1x02` or
+//! `Season 1`/`Episode 2`, also in the names of the folders, and `S01E01E02`, `S01E01-E03`
+//! or `1x01-03` for files with several. A year at the start belongs to the title, as in
+//! `1917 (2019)`. Languages only count after the title, "The Good German" is a title.
+//! Editions and ids in braces, `{edition-Director's Cut}` and `{imdb-tt0110413}`, are
+//! understood as well.
+
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::sql::{UniqueId, VideoMetadata};
+
+/// What the name of a video file says about it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParsedName {
+    pub title: String,
+    pub year: Option<i32>,
+    pub season: Option<u32>,
+    /// All episodes in the file, several for double episodes.
+    pub episodes: Vec<u32>,
+    pub episode_title: String,
+    /// `2160p`, `1080p`, `720p`...
+    pub resolution: String,
+    /// `BluRay`, `WEB-DL`, `HDTV`, `DVD`...
+    pub source: String,
+    pub edition: String,
+    /// ISO 639-2 codes of the languages.
+    pub languages: Vec<String>,
+    pub unique_ids: Vec<UniqueId>,
+}
+
+/// `{edition-Extended Cut}`, `[imdbid-tt0110413]`, `{tmdb-101}`
+static TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)[\[{](edition|imdb|imdbid|tmdb|tmdbid|tvdb|tvdbid)-([^\]}]+)[\]}]").unwrap()
+});
+/// `S01E02`, `S01E01E02`, `S01E01-E03`, `S01E01-03`
+static SEASON_EPISODE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^s(\d{1,2})[ .]?e(\d{1,3})((?:-?e\d{1,3})*)(?:-(\d{1,3}))?$").unwrap()
+});
+/// `1x02`, `1x01-03`, `1x01-1x03`
+static CROSS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^(\d{1,2})x(\d{2,3})(?:-(?:\d{1,2}x)?(\d{2,3}))?$").unwrap());
+static SEASON: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^s(\d{1,2})$").unwrap());
+static EPISODE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^e(\d{1,3})$").unwrap());
+static SEASON_FOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^(?:season|series|staffel|saison|s)[ ._]*(\d{1,2})$").unwrap());
+static YEAR: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(19\d{2}|20\d{2})$").unwrap());
+static RESOLUTION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^(480|576|720|1080|2160|4320)[pi]$").unwrap());
+
+/// Words of releases that are no part of a title, besides resolutions, sources, editions
+/// and languages.
+const NOISE: [&str; 32] = [
+    "x264", "x265", "h264", "h265", "hevc", "avc", "xvid", "divx", "10bit", "8bit", "hdr", "hdr10",
+    "dv", "dts", "ac3", "aac", "eac3", "ddp", "dd", "atmos", "truehd", "remux", "proper", "repack",
+    "internal", "limited", "multi", "dl", "subbed", "dubbed", "complete", "uhd",
+];
+
+/// Lower case words of sources and how they are called.
+const SOURCES: [(&str, &str); 18] = [
+    ("bluray", "BluRay"),
+    ("blu-ray", "BluRay"),
+    ("bdrip", "BluRay"),
+    ("brrip", "BluRay"),
+    ("bdremux", "BluRay"),
+    ("web-dl", "WEB-DL"),
+    ("webdl", "WEB-DL"),
+    ("webrip", "WEBRip"),
+    ("web-rip", "WEBRip"),
+    ("web", "WEB"),
+    ("hdtv", "HDTV"),
+    ("pdtv", "PDTV"),
+    ("dvdrip", "DVD"),
+    ("dvd", "DVD"),
+    ("dvd5", "DVD"),
+    ("dvd9", "DVD"),
+    ("hdrip", "HDRip"),
+    ("vhsrip", "VHS"),
+];
+
+/// Editions that may also end a title that has no year.
+const EDITIONS: [(&[&str], &str); 8] = [
+    (&["directors", "cut"], "Director's Cut"),
+    (&["extended", "edition"], "Extended Edition"),
+    (&["extended", "cut"], "Extended Cut"),
+    (&["theatrical", "cut"], "Theatrical Cut"),
+    (&["special", "edition"], "Special Edition"),
+    (&["ultimate", "edition"], "Ultimate Edition"),
+    (&["ultimate", "cut"], "Ultimate Cut"),
+    (&["collectors", "edition"], "Collector's Edition"),
+];
+
+/// Editions only after the title, "The Final Cut" is a movie.
+const LATE_EDITIONS: [(&[&str], &str); 9] = [
+    (&["final", "cut"], "Final Cut"),
+    (&["extended"], "Extended"),
+    (&["theatrical"], "Theatrical"),
+    (&["unrated"], "Unrated"),
+    (&["uncut"], "Uncut"),
+    (&["remastered"], "Remastered"),
+    (&["imax"], "IMAX"),
+    (&["criterion"], "Criterion"),
+    (&["anniversary", "edition"], "Anniversary Edition"),
+];
+
+const LANGUAGES: [(&str, &str); 30] = [
+    ("english", "eng"),
+    ("eng", "eng"),
+    ("german", "ger"),
+    ("deutsch", "ger"),
+    ("ger", "ger"),
+    ("french", "fre"),
+    ("truefrench", "fre"),
+    ("fre", "fre"),
+    ("spanish", "spa"),
+    ("castellano", "spa"),
+    ("spa", "spa"),
+    ("italian", "ita"),
+    ("ita", "ita"),
+    ("japanese", "jpn"),
+    ("jpn", "jpn"),
+    ("korean", "kor"),
+    ("chinese", "chi"),
+    ("russian", "rus"),
+    ("rus", "rus"),
+    ("dutch", "dut"),
+    ("swedish", "swe"),
+    ("swe", "swe"),
+    ("danish", "dan"),
+    ("norwegian", "nor"),
+    ("finnish", "fin"),
+    ("polish", "pol"),
+    ("portuguese", "por"),
+    ("hindi", "hin"),
+    ("turkish", "tur"),
+    ("hungarian", "hun"),
+];
+
+fn number(value: &str) -> u32 {
+    value.parse().unwrap_or(0)
+}
+
+/// Lower case word without apostrophes, "Director's" is "directors".
+fn plain(word: &str) -> String {
+    word.to_lowercase().replace(['\'', '’'], "")
+}
+
+/// Length and name of an edition starting at `words[i]`.
+fn edition(words: &[String], i: usize, late: bool) -> Option<(usize, &'static str)> {
+    let table: &[(&[&str], &str)] = if late { &LATE_EDITIONS } else { &EDITIONS };
+    table.iter().find_map(|(phrase, name)| {
+        let matches = phrase.len() <= words.len() - i
+            && phrase
+                .iter()
+                .zip(&words[i..])
+                .all(|(part, word)| plain(word) == *part);
+        matches.then_some((phrase.len(), *name))
+    })
+}
+
+fn source(word: &str) -> Option<&'static str> {
+    let word = word.to_lowercase();
+    SOURCES
+        .iter()
+        .find(|(tag, _)| *tag == word)
+        .map(|(_, name)| *name)
+}
+
+fn language(word: &str) -> Option<&'static str> {
+    let word = word.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(tag, _)| *tag == word)
+        .map(|(_, code)| *code)
+}
+
+fn resolution(word: &str) -> Option<String> {
+    match word.to_lowercase().as_str() {
+        "4k" => Some("2160p".to_string()),
+        word if RESOLUTION.is_match(word) => Some(word.to_string()),
+        _ => None,
+    }
+}
+
+/// Season and episodes of a word like `S01E02` or `1x02`.
+fn episode(word: &str) -> Option<(u32, Vec<u32>)> {
+    if let Some(captures) = SEASON_EPISODE.captures(word) {
+        let first = number(&captures[2]);
+        let mut episodes = vec![first];
+        if let Some(more) = captures.get(3).filter(|more| !more.as_str().is_empty()) {
+            let more: Vec<u32> = more
+                .as_str()
+                .to_lowercase()
+                .split('e')
+                .map(|part| part.trim_matches('-'))
+                .filter(|part| !part.is_empty())
+                .map(number)
+                .collect();
+            // S01E01-E03 is a range, S01E01E02 a list
+            match more.last() {
+                Some(last) if more.len() == 1 && word.contains('-') => {
+                    episodes = (first..=*last).collect()
+                }
+                _ => episodes.extend(more),
+            }
+        }
+        if let Some(last) = captures.get(4) {
+            episodes = (first..=number(last.as_str())).collect();
+        }
+        return Some((number(&captures[1]), episodes));
+    }
+    let captures = CROSS.captures(word)?;
+    let first = number(&captures[2]);
+    let episodes = match captures.get(3) {
+        Some(last) => (first..=number(last.as_str())).collect(),
+        None => vec![first],
+    };
+    Some((number(&captures[1]), episodes))
+}
+
+/// Split a name into words. Names with spaces keep their dots, "Mr. Robot", scene names
+/// separate words with dots. Brackets around words are dropped.
+fn words(name: &str) -> Vec<String> {
+    let spaced = name.replace('_', " ");
+    let separated = if spaced.contains(' ') {
+        spaced
+    } else {
+        spaced.replace('.', " ")
+    };
+    separated
+        .split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| "()[]{}-,".contains(c))
+                .to_string()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Parse the name of a video file, and its folders for episodes like
+/// `Show/Season 1/Episode 2.mkv`.
+pub fn parse(path: &Path) -> ParsedName {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut parsed = parse_name(&stem);
+    let parent = path.parent();
+    let folder = |dir: Option<&Path>| {
+        dir.and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().to_string())
+    };
+    let season_folder = folder(parent).and_then(|name| {
+        SEASON_FOLDER
+            .captures(name.trim())
+            .map(|captures| number(&captures[1]))
+    });
+    if parsed.season.is_none() && !parsed.episodes.is_empty() {
+        parsed.season = season_folder;
+    }
+    if parsed.title.is_empty() {
+        // the show is the folder above the season
+        let show = match season_folder {
+            Some(_) => folder(parent.and_then(Path::parent)),
+            None => folder(parent),
+        };
+        if let Some(show) = show {
+            let show = parse_name(&show);
+            parsed.title = show.title;
+            parsed.year = parsed.year.or(show.year);
+        }
+    }
+    parsed
+}
+
+/// Parse a single name, without an extension.
+pub fn parse_name(name: &str) -> ParsedName {
+    let mut parsed = ParsedName::default();
+    for captures in TAG.captures_iter(name) {
+        let value = captures[2].trim().to_string();
+        let kind = captures[1].to_lowercase();
+        match kind.trim_end_matches("id") {
+            "edition" => parsed.edition = value,
+            kind => parsed.unique_ids.push(UniqueId {
+                kind: kind.to_string(),
+                default: parsed.unique_ids.is_empty(),
+                value,
+            }),
+        }
+    }
+    let name = TAG.replace_all(name, " ");
+    // [Group] in front of anime releases
+    let name = name
+        .trim_start()
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map_or(name.as_ref(), |(_, rest)| rest);
+    let words = words(name);
+
+    // where the title ends, and where the episode title does
+    let mut title_end = words.len();
+    let mut episode_at = None;
+    let mut episode_end = words.len();
+    let mut years = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let word = &words[i];
+        let lower = word.to_lowercase();
+        let mut marker = true;
+        let mut length = 1;
+        if let Some((season, episodes)) = episode(word) {
+            if parsed.episodes.is_empty() {
+                parsed.season = Some(season);
+                parsed.episodes = episodes;
+                episode_at = Some(i);
+            }
+        } else if SEASON.is_match(word)
+            && words.get(i + 1).is_some_and(|next| EPISODE.is_match(next))
+        {
+            // S01 E02
+            parsed.season = Some(number(&word[1..]));
+            parsed.episodes = vec![number(&words[i + 1][1..])];
+            episode_at = Some(i + 1);
+            length = 2;
+        } else if (lower == "season" || lower == "episode")
+            && words
+                .get(i + 1)
+                .is_some_and(|next| next.parse::<u32>().is_ok())
+        {
+            let value = number(&words[i + 1]);
+            if lower == "season" {
+                parsed.season = Some(value);
+            } else {
+                parsed.episodes = vec![value];
+                episode_at = Some(i + 1);
+            }
+            length = 2;
+        } else if YEAR.is_match(word) && i > 0 {
+            years.push(i);
+            marker = false;
+        } else if let Some(resolution) = resolution(word) {
+            parsed.resolution = resolution;
+        } else if let Some(source) = source(word) {
+            parsed.source = source.to_string();
+        } else if let Some((phrase, name)) = edition(&words, i, false).filter(|_| i > 0) {
+            parsed.edition = name.to_string();
+            length = phrase;
+        } else if NOISE.contains(&lower.as_str()) {
+            // codecs, audio and release groups end the title
+        } else if i >= title_end.min(years.first().map_or(words.len(), |year| year + 1)) {
+            // only after the title
+            if let Some((phrase, name)) = edition(&words, i, true) {
+                parsed.edition = name.to_string();
+                length = phrase;
+            } else if let Some(language) = language(word) {
+                if !parsed.languages.iter().any(|known| known == language) {
+                    parsed.languages.push(language.to_string());
+                }
+            } else {
+                marker = false;
+            }
+        } else {
+            marker = false;
+        }
+        if marker {
+            title_end = title_end.min(i);
+            if episode_at.is_some_and(|at| at + 1 < i || (at < i && length == 1)) {
+                episode_end = episode_end.min(i);
+            }
+        }
+        i += length;
+    }
+
+    // the last year before the other markers, 2001 in "2001 A Space Odyssey 1968" is
+    // part of the title
+    if let Some(year) = years.into_iter().rfind(|year| *year <= title_end) {
+        parsed.year = words[year].parse().ok();
+        title_end = year;
+    }
+    parsed.title = words[..title_end].join(" ");
+    if let Some(at) = episode_at {
+        let start = at + 1;
+        if start < episode_end {
+            parsed.episode_title = words[start..episode_end].join(" ");
+        }
+    }
+    parsed
+}
+
+impl ParsedName {
+    /// The title of a video, with the episode title after the show.
+    pub fn full_title(&self) -> String {
+        match (self.title.is_empty(), self.episode_title.is_empty()) {
+            (false, false) => format!("{} - {}", self.title, self.episode_title),
+            (true, false) => self.episode_title.clone(),
+            _ => self.title.clone(),
+        }
+    }
+
+    /// Fill the fields of `meta` that are still empty. The title is also replaced if it
+    /// is only the name of the file.
+    pub fn apply(&self, meta: &mut VideoMetadata) {
+        let path = Path::new(&meta.path);
+        let file_names = [path.file_name(), path.file_stem()];
+        let named_after_file = meta.title.is_empty()
+            || file_names
+                .iter()
+                .flatten()
+                .any(|name| name.to_string_lossy() == meta.title);
+        let title = self.full_title();
+        if named_after_file && !title.is_empty() {
+            meta.title = title;
+        }
+        if let Some(year) = self.year {
+            // not read from the file, 1970-01-01 is the default
+            if meta.date.num_days_from_ce() < 100 || meta.date == NaiveDate::default() {
+                if let Some(date) = NaiveDate::from_ymd_opt(year, 1, 1) {
+                    meta.date = date;
+                }
+            }
+        }
+        if meta.season == 0 && meta.episode == 0 {
+            if let (Some(season), Some(first)) = (self.season, self.episodes.first()) {
+                meta.season = season as i32;
+                meta.episode = *first as i32;
+                if let Some(last) = self.episodes.last().filter(|last| *last > first) {
+                    meta.last_episode = *last as i32;
+                }
+            }
+        }
+        if meta.height == 0 {
+            // until the video itself can be read
+            if let Some(lines) = self
+                .resolution
+                .strip_suffix(['p', 'i'])
+                .and_then(|lines| lines.parse::<u32>().ok())
+            {
+                meta.height = lines;
+                meta.width = lines * 16 / 9;
+            }
+        }
+        if meta.source.is_empty() {
+            meta.source = self.source.clone();
+        }
+        if meta.edition.is_empty() {
+            meta.edition = self.edition.clone();
+        }
+        if meta.audiolangs.is_empty() {
+            meta.audiolangs = self.languages.clone();
+        }
+        if meta.unique_ids.is_empty() {
+            meta.unique_ids = self.unique_ids.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(path: &str) -> ParsedName {
+        parse(Path::new(path))
+    }
+
+    #[test]
+    fn movies_have_title_year_and_release_tags() {
+        let matrix = parsed("/movies/The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv");
+        assert_eq!(matrix.title, "The Matrix");
+        assert_eq!(matrix.year, Some(1999));
+        assert_eq!(matrix.resolution, "1080p");
+        assert_eq!(matrix.source, "BluRay");
+        assert!(matrix.episodes.is_empty());
+
+        let heat = parsed("/movies/Heat (1995)/Heat (1995).mkv");
+        assert_eq!((heat.title.as_str(), heat.year), ("Heat", Some(1995)));
+
+        let blade_runner = parsed("Blade Runner 2049 (2017) 2160p.mkv");
+        assert_eq!(blade_runner.title, "Blade Runner 2049");
+        assert_eq!(blade_runner.year, Some(2017));
+        assert_eq!(blade_runner.resolution, "2160p");
+
+        let odyssey = parsed("2001.A.Space.Odyssey.1968.REMASTERED.1080p.BluRay.mkv");
+        assert_eq!(odyssey.title, "2001 A Space Odyssey");
+        assert_eq!(odyssey.year, Some(1968));
+        assert_eq!(odyssey.edition, "Remastered");
+
+        let war = parsed("1917 (2019).mkv");
+        assert_eq!((war.title.as_str(), war.year), ("1917", Some(2019)));
+        let only_year = parsed("1917.mkv");
+        assert_eq!((only_year.title.as_str(), only_year.year), ("1917", None));
+        let apocalypse = parsed("2012.2009.720p.WEBRip.mkv");
+        assert_eq!(apocalypse.title, "2012");
+        assert_eq!(apocalypse.year, Some(2009));
+        assert_eq!(apocalypse.source, "WEBRip");
+
+        let dune = parsed("Dune.Part.Two.2024.4K.UHD.BluRay.REMUX.HDR.mkv");
+        assert_eq!(dune.title, "Dune Part Two");
+        assert_eq!(dune.resolution, "2160p");
+
+        let plain = parsed("holiday video.mp4");
+        assert_eq!(plain.title, "holiday video");
+        assert_eq!(
+            plain,
+            ParsedName {
+                title: "holiday video".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn editions_languages_and_ids_follow_the_title() {
+        let aliens = parsed("Aliens.1986.Directors.Cut.GERMAN.DL.1080p.BluRay.mkv");
+        assert_eq!(aliens.title, "Aliens");
+        assert_eq!(aliens.edition, "Director's Cut");
+        assert_eq!(aliens.languages, ["ger"]);
+
+        let blade_runner = parsed("Blade Runner Director's Cut.mkv");
+        assert_eq!(blade_runner.title, "Blade Runner");
+        assert_eq!(blade_runner.edition, "Director's Cut");
+
+        // only after the title
+        let final_cut = parsed("The.Final.Cut.2004.1080p.mkv");
+        assert_eq!(final_cut.title, "The Final Cut");
+        assert_eq!(final_cut.edition, "");
+        let german = parsed("The.Good.German.2006.ENGLISH.720p.mkv");
+        assert_eq!(german.title, "The Good German");
+        assert_eq!(german.languages, ["eng"]);
+
+        let amelie = parsed("Amélie (2001) {edition-Extended Cut} {imdb-tt0211915}.mkv");
+        assert_eq!(amelie.title, "Amélie");
+        assert_eq!(amelie.year, Some(2001));
+        assert_eq!(amelie.edition, "Extended Cut");
+        assert_eq!(
+            amelie.unique_ids,
+            [UniqueId {
+                kind: "imdb".to_string(),
+                value: "tt0211915".to_string(),
+                default: true,
+            }]
+        );
+        let jellyfin = parsed("Up (2009) [tmdbid-14160].mkv");
+        assert_eq!(jellyfin.title, "Up");
+        assert_eq!(jellyfin.unique_ids[0].kind, "tmdb");
+        assert_eq!(jellyfin.unique_ids[0].value, "14160");
+    }
+
+    #[test]
+    fn episodes_in_all_their_spellings() {
+        let bad = parsed("Breaking.Bad.S01E02.Cats.in.the.Bag.720p.WEB-DL.mkv");
+        assert_eq!(bad.title, "Breaking Bad");
+        assert_eq!((bad.season, bad.episodes.as_slice()), (Some(1), &[2][..]));
+        assert_eq!(bad.episode_title, "Cats in the Bag");
+        assert_eq!(bad.source, "WEB-DL");
+        assert_eq!(bad.full_title(), "Breaking Bad - Cats in the Bag");
+
+        let friends = parsed("Friends - 1x02 - The One with the Sonogram at the End.avi");
+        assert_eq!(friends.title, "Friends");
+        assert_eq!(
+            (friends.season, friends.episodes.as_slice()),
+            (Some(1), &[2][..])
+        );
+        assert_eq!(
+            friends.episode_title,
+            "The One with the Sonogram at the End"
+        );
+
+        let fargo = parsed("Fargo.S01.E03.720p.HDTV.mkv");
+        assert_eq!(fargo.title, "Fargo");
+        assert_eq!(
+            (fargo.season, fargo.episodes.as_slice()),
+            (Some(1), &[3][..])
+        );
+
+        let robot = parsed("Mr. Robot - S01E01 - eps1.0_hellofriend.mov");
+        assert_eq!(robot.title, "Mr. Robot");
+        assert_eq!(robot.episode_title, "eps1.0 hellofriend");
+
+        let anime = parsed("[SubsPlease] Frieren - S01E05 (1080p) [ABCD1234].mkv");
+        assert_eq!(anime.title, "Frieren");
+        assert_eq!(anime.episodes, [5]);
+        assert_eq!(anime.resolution, "1080p");
+        assert_eq!(anime.episode_title, "");
+
+        // not an episode
+        let sample = parsed("Sample 1920x1080.mkv");
+        assert!(sample.episodes.is_empty());
+    }
+
+    #[test]
+    fn double_episodes_and_ranges() {
+        let who = parsed("Doctor.Who.2005.S01E01-E02.HDTV.mkv");
+        assert_eq!(who.title, "Doctor Who");
+        assert_eq!(who.year, Some(2005));
+        assert_eq!(
+            (who.season, who.episodes.as_slice()),
+            (Some(1), &[1, 2][..])
+        );
+        let office = parsed("the.office.us.s02e01e02.720p.mkv");
+        assert_eq!(office.title, "the office us");
+        assert_eq!(office.episodes, [1, 2]);
+        let sherlock = parsed("Sherlock.S03E01-03.1080p.mkv");
+        assert_eq!(sherlock.episodes, [1, 2, 3]);
+        let range = parsed("Lost.S01E01-E03.mkv");
+        assert_eq!(range.episodes, [1, 2, 3]);
+        let cross = parsed("Seinfeld 3x01-03.mkv");
+        assert_eq!(
+            (cross.season, cross.episodes.as_slice()),
+            (Some(3), &[1, 2, 3][..])
+        );
+    }
+
+    #[test]
+    fn folders_name_shows_and_seasons() {
+        let thrones = parsed("/tv/Game of Thrones/Season 1/Episode 2.mkv");
+        assert_eq!(thrones.title, "Game of Thrones");
+        assert_eq!(
+            (thrones.season, thrones.episodes.as_slice()),
+            (Some(1), &[2][..])
+        );
+
+        let lost = parsed("/tv/Lost (2004)/Season 02/S02E03 - Orientation.mkv");
+        assert_eq!(lost.title, "Lost");
+        assert_eq!(lost.year, Some(2004));
+        assert_eq!(lost.season, Some(2));
+        assert_eq!(lost.episode_title, "Orientation");
+
+        let words = parsed("/tv/Dark/Staffel 1/Season 1 Episode 4.mkv");
+        assert_eq!(words.title, "Dark");
+        assert_eq!(
+            (words.season, words.episodes.as_slice()),
+            (Some(1), &[4][..])
+        );
+    }
+
+    #[test]
+    fn empty_fields_are_filled_and_known_ones_kept() {
+        let mut meta = VideoMetadata {
+            path: "/tv/Doctor.Who.2005.S01E01-E02.GERMAN.720p.HDTV.mkv".to_string(),
+            title: "Doctor.Who.2005.S01E01-E02.GERMAN.720p.HDTV.mkv".to_string(),
+            audiolangs: vec!["eng".to_string()],
+            ..Default::default()
+        };
+        parse(Path::new(&meta.path.clone())).apply(&mut meta);
+        assert_eq!(meta.title, "Doctor Who");
+        assert_eq!(meta.date.year(), 2005);
+        assert_eq!((meta.season, meta.episode, meta.last_episode), (1, 1, 2));
+        assert_eq!((meta.width, meta.height), (1280, 720));
+        assert_eq!(meta.source, "HDTV");
+        // the streams know better
+        assert_eq!(meta.audiolangs, ["eng"]);
+
+        let mut meta = VideoMetadata {
+            path: "/movies/Heat.1995.Directors.Cut.mkv".to_string(),
+            title: "Heat".to_string(),
+            width: 1920,
+            height: 800,
+            ..Default::default()
+        };
+        parse(Path::new(&meta.path.clone())).apply(&mut meta);
+        assert_eq!(meta.title, "Heat");
+        assert_eq!(meta.edition, "Director's Cut");
+        assert_eq!((meta.width, meta.height), (1920, 800));
+        assert_eq!(meta.last_episode, 0);
+
+        // no resolution in the name, the size stays unknown
+        let mut meta = VideoMetadata {
+            path: "/movies/Alien.1979.BluRay.mkv".to_string(),
+            ..Default::default()
+        };
+        parse(Path::new(&meta.path.clone())).apply(&mut meta);
+        assert_eq!(meta.title, "Alien");
+        assert_eq!((meta.width, meta.height), (0, 0));
+    }
+}
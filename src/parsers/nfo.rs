@@ -0,0 +1,431 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Video metadata from the NFO files of a Kodi library, `movie.nfo`, `<video>.nfo`,
+//! `tvshow.nfo` and the files of the episodes.
+//!
+//! Besides title, plot, dates, runtime and people, the original and sort titles,
+//! tagline, age rating, genres, studios, countries, writers (`<credits>`), tags, the
+//! movie set, the ratings of several sites, IMDb, TMDB and TVDB ids, the roles and
+//! pictures of the actors and the stream details of `<fileinfo>` are read. Elements
+//! that are not known are skipped.
+
+pub mod export;
+
+use std::io::Read;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use xml::reader::XmlEvent;
+
+use crate::sql::{Rating, Role, Tag, UniqueId, VideoMetadata};
+
+/// Read video metadata from an NFO file in the XBMC/Kodi format.
+pub fn parse_nfo(nfo_file: &Path, metadata: &mut VideoMetadata) {
+    let file = match std::fs::File::open(nfo_file) {
+        Ok(ok) => ok,
+        Err(err) => {
+            log::warn!("failed to read entry in {:?}: {}", nfo_file.display(), err);
+            return;
+        }
+    };
+    // what was read up to the error is kept
+    if let Err(err) = parse(std::io::BufReader::new(file), metadata) {
+        log::warn!("failed to parse {:?}: {}", nfo_file.display(), err);
+    }
+}
+
+fn number<T>(value: &str) -> Option<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match value.parse::<T>() {
+        Ok(ok) => Some(ok),
+        Err(err) => {
+            log::warn!("failed to parse number {:?}: {}", value, err);
+            None
+        }
+    }
+}
+
+/// Add the names of an element, older scrapers put several into one separated by " / ".
+fn push_names(names: &mut Vec<String>, value: &str) {
+    for name in value.split(" / ").map(str::trim) {
+        if !name.is_empty() && !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+}
+
+/// Add an id unless the video already has one of the same site.
+fn push_id(metadata: &mut VideoMetadata, unique_id: UniqueId) {
+    if unique_id.value.is_empty()
+        || metadata
+            .unique_ids
+            .iter()
+            .any(|known| known.kind == unique_id.kind)
+    {
+        return;
+    }
+    metadata.unique_ids.push(unique_id);
+}
+
+/// Read an NFO document into `metadata`. Fields the document has replace those read from
+/// the video file itself, except for the codec.
+pub fn parse<R: Read>(source: R, metadata: &mut VideoMetadata) -> Result<(), xml::reader::Error> {
+    let mut reader = xml::ParserConfig::default()
+        .ignore_root_level_whitespace(false)
+        .create_reader(source);
+
+    // names of the open elements, in lower case
+    let mut open: Vec<String> = Vec::new();
+    // open <rating>, <actor> and <uniqueid> elements
+    let mut rating: Option<Rating> = None;
+    let mut role: Option<Role> = None;
+    let mut unique_id: Option<UniqueId> = None;
+    loop {
+        match reader.next()? {
+            XmlEvent::EndDocument => break,
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let element = name.local_name.to_ascii_lowercase();
+                let attribute = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|a| a.name.local_name.eq_ignore_ascii_case(key))
+                        .map(|a| a.value.trim().to_string())
+                };
+                match element.as_str() {
+                    // either with the value as text or in a <value> child
+                    "rating" => {
+                        rating = Some(Rating {
+                            source: attribute("name").unwrap_or_default(),
+                            max: attribute("max")
+                                .and_then(|max| max.parse::<f32>().ok())
+                                .filter(|max| *max > 0.0)
+                                .unwrap_or(10.0),
+                            default: attribute("default").is_some_and(|d| d == "true"),
+                            ..Default::default()
+                        })
+                    }
+                    "actor" => role = Some(Role::default()),
+                    "uniqueid" => {
+                        unique_id = Some(UniqueId {
+                            kind: attribute("type")
+                                .unwrap_or_else(|| "unknown".to_string())
+                                .to_ascii_lowercase(),
+                            default: attribute("default").is_some_and(|d| d == "true"),
+                            ..Default::default()
+                        })
+                    }
+                    _ => {}
+                }
+                open.push(element);
+            }
+            XmlEvent::EndElement { .. } => match open.pop().as_deref() {
+                Some("rating") => {
+                    if let Some(rating) = rating.take().filter(|rating| rating.value > 0.0) {
+                        // the default of several ratings wins, otherwise the first one
+                        if rating.default || metadata.rating == 0.0 {
+                            metadata.rating = rating.value * 10.0 / rating.max;
+                        }
+                        metadata.ratings.push(rating);
+                    }
+                }
+                Some("actor") => {
+                    if let Some(role) = role.take().filter(|role| !role.actor.is_empty()) {
+                        metadata.actors.push(role.actor.clone());
+                        if !role.role.is_empty() || !role.thumb.is_empty() {
+                            metadata.roles.push(role);
+                        }
+                    }
+                }
+                Some("uniqueid") => {
+                    if let Some(unique_id) = unique_id.take() {
+                        push_id(metadata, unique_id);
+                    }
+                }
+                _ => {}
+            },
+            XmlEvent::Characters(data) | XmlEvent::CData(data) => {
+                let value = data.trim();
+                let depth = open.len();
+                let tag = open.last().map(String::as_str).unwrap_or_default();
+                let parent = match depth {
+                    0 | 1 => "",
+                    _ => open[depth - 2].as_str(),
+                };
+                match (parent, tag) {
+                    ("actor", "name") => {
+                        if let Some(role) = role.as_mut() {
+                            role.actor = value.to_string();
+                        }
+                    }
+                    ("actor", "role") => {
+                        if let Some(role) = role.as_mut() {
+                            role.role = value.to_string();
+                        }
+                    }
+                    ("actor", "thumb") => {
+                        if let Some(role) = role.as_mut() {
+                            role.thumb = value.to_string();
+                        }
+                    }
+                    (_, "rating") | ("rating", "value") => {
+                        if let Some(rating) = rating.as_mut() {
+                            rating.value = value.parse::<f32>().unwrap_or_default();
+                        }
+                    }
+                    ("rating", "votes") => {
+                        if let Some(rating) = rating.as_mut() {
+                            // "1,234,567" in some files
+                            rating.votes = value.replace([',', '.'], "").parse().unwrap_or(0);
+                        }
+                    }
+                    (_, "uniqueid") => {
+                        if let Some(unique_id) = unique_id.as_mut() {
+                            unique_id.value = value.to_string();
+                        }
+                    }
+                    ("set", "name") => metadata.movie_set = value.to_string(),
+                    ("set", "overview") => metadata.movie_set_overview = value.to_string(),
+                    // the codec read from the file itself is more precise
+                    ("video", "codec") if metadata.codec.is_empty() => {
+                        metadata.codec = value.to_ascii_lowercase()
+                    }
+                    ("video", "width") => {
+                        if let Some(width) = number(value) {
+                            metadata.width = width;
+                        }
+                    }
+                    ("video", "height") => {
+                        if let Some(height) = number(value) {
+                            metadata.height = height;
+                        }
+                    }
+                    ("video", "durationinseconds") => {
+                        if let Some(duration) = number(value) {
+                            metadata.duration = duration;
+                        }
+                    }
+                    ("audio", "language") => metadata.audiolangs.push(value.to_string()),
+                    ("subtitle", "language") => metadata.sublangs.push(value.to_string()),
+                    // everything else is a child of <movie>, <tvshow> or <episodedetails>
+                    _ if depth != 2 => {}
+                    (_, "title") => metadata.title = value.to_string(),
+                    (_, "originaltitle") => metadata.original_title = value.to_string(),
+                    (_, "sorttitle") => metadata.sort_title = value.to_string(),
+                    (_, "plot") => metadata.description = value.to_string(),
+                    (_, "tagline") => metadata.tagline = value.to_string(),
+                    (_, "mpaa") => metadata.mpaa = value.to_string(),
+                    (_, "runtime") => {
+                        if let Some(minutes) = number::<u32>(value) {
+                            metadata.duration = minutes * 60;
+                        }
+                    }
+                    (_, "season") => {
+                        if let Some(season) = number(value) {
+                            metadata.season = season;
+                        }
+                    }
+                    (_, "episode") => {
+                        if let Some(episode) = number(value) {
+                            metadata.episode = episode;
+                        }
+                    }
+                    (_, "premiered") | (_, "aired") => {
+                        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                            metadata.date = date;
+                        }
+                    }
+                    (_, "director") => push_names(&mut metadata.director, value),
+                    (_, "credits") | (_, "writer") => push_names(&mut metadata.writers, value),
+                    (_, "genre") => push_names(&mut metadata.genres, value),
+                    (_, "studio") => push_names(&mut metadata.studios, value),
+                    (_, "country") => push_names(&mut metadata.countries, value),
+                    (_, "tag")
+                        if !value.is_empty() && !metadata.tags.iter().any(|t| t.tag == value) =>
+                    {
+                        metadata.tags.push(Tag {
+                            tag_id: 0,
+                            tag: value.to_string(),
+                        })
+                    }
+                    // <set>Name</set> of older files
+                    (_, "set") => metadata.movie_set = value.to_string(),
+                    // <id> of older files, IMDb ids start with "tt"
+                    (_, "id") if value.starts_with("tt") => push_id(
+                        metadata,
+                        UniqueId {
+                            kind: "imdb".to_string(),
+                            value: value.to_string(),
+                            default: metadata.unique_ids.is_empty(),
+                        },
+                    ),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVIE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes" ?>
+<movie>
+    <title>Léon: The Professional</title>
+    <originaltitle>Léon</originaltitle>
+    <sorttitle>Leon</sorttitle>
+    <ratings>
+        <rating name="imdb" max="10">
+            <value>8.5</value>
+            <votes>1,234,567</votes>
+        </rating>
+        <rating name="themoviedb" max="100" default="true">
+            <value>83</value>
+            <votes>14000</votes>
+        </rating>
+    </ratings>
+    <plot><![CDATA[Mathilda's family is killed & Léon takes her in.]]></plot>
+    <tagline>If you want the job done right, hire a professional.</tagline>
+    <runtime>110</runtime>
+    <mpaa>Rated R</mpaa>
+    <uniqueid type="imdb" default="true">tt0110413</uniqueid>
+    <uniqueid type="tmdb">101</uniqueid>
+    <genre>Crime</genre>
+    <genre>Drama / Thriller</genre>
+    <country>France</country>
+    <set>
+        <name>Léon Collection</name>
+        <overview>Movies about Léon.</overview>
+    </set>
+    <tag>hitman</tag>
+    <credits>Luc Besson</credits>
+    <director>Luc Besson</director>
+    <premiered>1994-09-14</premiered>
+    <studio>Gaumont</studio>
+    <fileinfo>
+        <streamdetails>
+            <video>
+                <codec>h264</codec>
+                <width>1920</width>
+                <height>1040</height>
+                <durationinseconds>6600</durationinseconds>
+            </video>
+            <audio>
+                <codec>ac3</codec>
+                <language>eng</language>
+            </audio>
+            <subtitle>
+                <language>fre</language>
+            </subtitle>
+        </streamdetails>
+    </fileinfo>
+    <actor>
+        <name>Jean Reno</name>
+        <role>Léon</role>
+        <order>0</order>
+        <thumb>https://image.tmdb.org/t/p/original/reno.jpg</thumb>
+    </actor>
+    <actor>
+        <name>Natalie Portman</name>
+    </actor>
+</movie>
+"#;
+
+    #[test]
+    fn kodi_movie_files_are_read_completely() {
+        let mut metadata = VideoMetadata::default();
+        parse(MOVIE.as_bytes(), &mut metadata).unwrap();
+        assert_eq!(metadata.title, "Léon: The Professional");
+        assert_eq!(metadata.original_title, "Léon");
+        assert_eq!(metadata.sort_title, "Leon");
+        assert_eq!(
+            metadata.description,
+            "Mathilda's family is killed & Léon takes her in."
+        );
+        assert_eq!(
+            metadata.tagline,
+            "If you want the job done right, hire a professional."
+        );
+        assert_eq!(metadata.mpaa, "Rated R");
+        assert_eq!(metadata.genres, ["Crime", "Drama", "Thriller"]);
+        assert_eq!(metadata.studios, ["Gaumont"]);
+        assert_eq!(metadata.countries, ["France"]);
+        assert_eq!(metadata.writers, ["Luc Besson"]);
+        assert_eq!(metadata.director, ["Luc Besson"]);
+        assert_eq!(metadata.actors, ["Jean Reno", "Natalie Portman"]);
+        assert_eq!(
+            metadata.roles,
+            [Role {
+                actor: "Jean Reno".to_string(),
+                role: "Léon".to_string(),
+                thumb: "https://image.tmdb.org/t/p/original/reno.jpg".to_string(),
+            }]
+        );
+        assert_eq!(metadata.tags.len(), 1);
+        assert_eq!(metadata.tags[0].tag, "hitman");
+        assert_eq!(metadata.movie_set, "Léon Collection");
+        assert_eq!(metadata.movie_set_overview, "Movies about Léon.");
+        assert_eq!(metadata.ratings.len(), 2);
+        assert_eq!(metadata.ratings[0].source, "imdb");
+        assert_eq!(metadata.ratings[0].votes, 1_234_567);
+        assert_eq!(metadata.ratings[1].max, 100.0);
+        // the default rating is shown, out of 10
+        assert!((metadata.rating - 8.3).abs() < 0.001);
+        assert_eq!(
+            metadata.unique_ids,
+            [
+                UniqueId {
+                    kind: "imdb".to_string(),
+                    value: "tt0110413".to_string(),
+                    default: true,
+                },
+                UniqueId {
+                    kind: "tmdb".to_string(),
+                    value: "101".to_string(),
+                    default: false,
+                }
+            ]
+        );
+        assert_eq!(metadata.date, NaiveDate::from_ymd_opt(1994, 9, 14).unwrap());
+        assert_eq!(metadata.codec, "h264");
+        assert_eq!((metadata.width, metadata.height), (1920, 1040));
+        assert_eq!(metadata.duration, 6600);
+        assert_eq!(metadata.audiolangs, ["eng"]);
+        assert_eq!(metadata.sublangs, ["fre"]);
+    }
+
+    #[test]
+    fn older_files_and_streams_read_before_are_understood() {
+        let mut metadata = VideoMetadata {
+            codec: "hevc".to_string(),
+            ..Default::default()
+        };
+        let nfo = "<movie><title>Heat</title><rating>8.2</rating><id>tt0113277</id>\
+            <set>Michael Mann</set><genre>Crime</genre><genre>Crime</genre>\
+            <fileinfo><streamdetails><video><codec>h264</codec></video></streamdetails></fileinfo>\
+            </movie>";
+        parse(nfo.as_bytes(), &mut metadata).unwrap();
+        assert_eq!(metadata.title, "Heat");
+        assert!((metadata.rating - 8.2).abs() < 0.001);
+        assert_eq!(metadata.ratings[0].source, "");
+        assert_eq!(metadata.unique_ids[0].kind, "imdb");
+        assert_eq!(metadata.movie_set, "Michael Mann");
+        assert_eq!(metadata.genres, ["Crime"]);
+        assert_eq!(metadata.codec, "hevc");
+        assert!(metadata.actors.is_empty());
+
+        let mut metadata = VideoMetadata::default();
+        assert!(parse("<movie><title>Broken</title>".as_bytes(), &mut metadata).is_err());
+        assert_eq!(metadata.title, "Broken");
+    }
+}
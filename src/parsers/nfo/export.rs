@@ -0,0 +1,720 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Library metadata written back into the NFO files next to the media, so Kodi and
+//! Jellyfin show what was curated here.
+//!
+//! Existing files are updated rather than replaced: only the elements read by
+//! `nfo::parse()` are rewritten and everything else, comments included, stays where it
+//! was. A field the library has no value for leaves the file alone, libraries scanned
+//! before a field was read would otherwise wipe it. The release date is only updated,
+//! since videos without one get the date of the file. Files are written in the layout
+//! Kodi writes, so the first export of a file in another layout reformats all of it.
+//! Before a file is overwritten for the first time it is copied to `<name>.nfo.bak`,
+//! later exports keep that copy. A dry run only reports the lines that would change.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::Datelike;
+use thiserror::Error;
+use xml::reader::XmlEvent;
+use xml::writer::XmlEvent as WriterEvent;
+
+use crate::operation::Controller;
+use crate::sql::exchange::Record;
+use crate::sql::{CollectionMetadata, VideoMetadata};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Read(#[from] xml::reader::Error),
+    #[error("{0}")]
+    Write(#[from] xml::writer::Error),
+    #[error("no root element")]
+    Empty,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Element(Element),
+    Text(String),
+    CData(String),
+    Comment(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(name: &str) -> Self {
+        Element {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// `<name>value</name>`
+    fn text(name: &str, value: &str) -> Self {
+        Element::new(name).child(Node::Text(value.to_string()))
+    }
+
+    fn attribute(mut self, key: &str, value: &str) -> Self {
+        self.attributes.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    fn child(mut self, node: Node) -> Self {
+        self.children.push(node);
+        self
+    }
+
+    fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    fn find(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.is(name))
+    }
+
+    fn content(&self) -> String {
+        let mut content = String::new();
+        for node in self.children.iter() {
+            if let Node::Text(text) | Node::CData(text) = node {
+                content.push_str(text);
+            }
+        }
+        content.trim().to_string()
+    }
+
+    /// Put `elements` in place of the children called `name`, where the first of them
+    /// was, or at the end. Children that already are the same are left as they are.
+    fn replace(&mut self, name: &str, elements: Vec<Element>) {
+        let existing: Vec<&Element> = self.elements().filter(|e| e.is(name)).collect();
+        if existing.len() == elements.len() && existing.iter().zip(&elements).all(|(a, b)| *a == b)
+        {
+            return;
+        }
+        let position = self
+            .children
+            .iter()
+            .position(|node| matches!(node, Node::Element(e) if e.is(name)));
+        self.children
+            .retain(|node| !matches!(node, Node::Element(e) if e.is(name)));
+        let nodes = elements.into_iter().map(Node::Element);
+        match position {
+            Some(position) => {
+                self.children.splice(position..position, nodes);
+            }
+            None => self.children.extend(nodes),
+        }
+    }
+
+    /// Write a single value, unless the library does not know it.
+    fn set(&mut self, name: &str, value: &str) {
+        if !value.is_empty() {
+            self.replace(name, vec![Element::text(name, value)]);
+        }
+    }
+
+    /// Write a list of values, unless the library has none.
+    fn set_all(&mut self, name: &str, values: &[String]) {
+        if !values.is_empty() {
+            self.replace(
+                name,
+                values
+                    .iter()
+                    .map(|value| Element::text(name, value))
+                    .collect(),
+            );
+        }
+    }
+}
+
+/// An NFO document, with the comments around the root element.
+#[derive(Clone, Debug, PartialEq)]
+struct Document {
+    before: Vec<Node>,
+    root: Element,
+    after: Vec<Node>,
+}
+
+impl Document {
+    fn new(root: &str) -> Self {
+        Document {
+            before: Vec::new(),
+            root: Element::new(root),
+            after: Vec::new(),
+        }
+    }
+
+    fn read(source: &str) -> Result<Self, Error> {
+        let reader = xml::ParserConfig::new()
+            .trim_whitespace(true)
+            .ignore_comments(false)
+            .create_reader(source.as_bytes());
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        let mut root = None;
+        let mut open: Vec<Element> = Vec::new();
+        for event in reader {
+            let node = match event? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    let mut element = Element::new(&name.local_name);
+                    if let Some(prefix) = name.prefix {
+                        element.name = format!("{}:{}", prefix, name.local_name);
+                    }
+                    for attribute in attributes {
+                        element
+                            .attributes
+                            .push((attribute.name.local_name, attribute.value));
+                    }
+                    open.push(element);
+                    continue;
+                }
+                XmlEvent::EndElement { .. } => match open.pop() {
+                    Some(element) => Node::Element(element),
+                    None => continue,
+                },
+                XmlEvent::Characters(text) => Node::Text(text),
+                XmlEvent::CData(text) => Node::CData(text),
+                XmlEvent::Comment(text) => Node::Comment(text),
+                _ => continue,
+            };
+            match (open.last_mut(), node) {
+                (Some(parent), node) => parent.children.push(node),
+                (None, Node::Element(element)) => root = Some(element),
+                (None, node) if root.is_none() => before.push(node),
+                (None, node) => after.push(node),
+            }
+        }
+        Ok(Document {
+            before,
+            root: root.ok_or(Error::Empty)?,
+            after,
+        })
+    }
+
+    /// The document in the layout Kodi writes.
+    fn render(&self) -> Result<String, Error> {
+        let mut output = Vec::new();
+        let mut writer = xml::EmitterConfig::new()
+            .perform_indent(true)
+            .indent_string("    ")
+            .create_writer(&mut output);
+        writer.write(WriterEvent::StartDocument {
+            version: xml::common::XmlVersion::Version10,
+            encoding: Some("UTF-8"),
+            standalone: Some(true),
+        })?;
+        for node in self.before.iter() {
+            write_node(&mut writer, node)?;
+        }
+        write_element(&mut writer, &self.root)?;
+        for node in self.after.iter() {
+            write_node(&mut writer, node)?;
+        }
+        drop(writer);
+        output.push(b'\n');
+        Ok(String::from_utf8_lossy(&output).to_string())
+    }
+}
+
+fn write_node<W: Write>(writer: &mut xml::EventWriter<W>, node: &Node) -> Result<(), Error> {
+    match node {
+        Node::Element(element) => write_element(writer, element)?,
+        Node::Text(text) => writer.write(WriterEvent::characters(text))?,
+        Node::CData(text) => writer.write(WriterEvent::cdata(text))?,
+        Node::Comment(text) => writer.write(WriterEvent::comment(text))?,
+    }
+    Ok(())
+}
+
+fn write_element<W: Write>(
+    writer: &mut xml::EventWriter<W>,
+    element: &Element,
+) -> Result<(), Error> {
+    let mut start = WriterEvent::start_element(element.name.as_str());
+    for (key, value) in element.attributes.iter() {
+        start = start.attr(key.as_str(), value);
+    }
+    writer.write(start)?;
+    for node in element.children.iter() {
+        write_node(writer, node)?;
+    }
+    writer.write(WriterEvent::end_element())?;
+    Ok(())
+}
+
+fn update_video(root: &mut Element, metadata: &VideoMetadata) {
+    let episode = root.is("episodedetails");
+    root.set("title", &metadata.title);
+    root.set("originaltitle", &metadata.original_title);
+    root.set("sorttitle", &metadata.sort_title);
+    if !metadata.ratings.is_empty() {
+        let mut ratings = Element::new("ratings");
+        for rating in metadata.ratings.iter() {
+            let mut element = Element::new("rating");
+            if !rating.source.is_empty() {
+                element = element.attribute("name", &rating.source);
+            }
+            element = element.attribute("max", &rating.max.to_string());
+            if rating.default {
+                element = element.attribute("default", "true");
+            }
+            element = element.child(Node::Element(Element::text(
+                "value",
+                &rating.value.to_string(),
+            )));
+            if rating.votes > 0 {
+                element = element.child(Node::Element(Element::text(
+                    "votes",
+                    &rating.votes.to_string(),
+                )));
+            }
+            ratings = ratings.child(Node::Element(element));
+        }
+        root.replace("ratings", vec![ratings]);
+    }
+    root.set("plot", &metadata.description);
+    root.set("tagline", &metadata.tagline);
+    if metadata.duration > 0 {
+        root.set("runtime", &((metadata.duration + 30) / 60).to_string());
+    }
+    root.set("mpaa", &metadata.mpaa);
+    if !metadata.unique_ids.is_empty() {
+        let ids = metadata
+            .unique_ids
+            .iter()
+            .map(|id| {
+                let element = Element::text("uniqueid", &id.value).attribute("type", &id.kind);
+                if id.default {
+                    element.attribute("default", "true")
+                } else {
+                    element
+                }
+            })
+            .collect();
+        root.replace("uniqueid", ids);
+    }
+    root.set_all("genre", &metadata.genres);
+    root.set_all("country", &metadata.countries);
+    if !metadata.movie_set.is_empty() && !episode {
+        let mut set =
+            Element::new("set").child(Node::Element(Element::text("name", &metadata.movie_set)));
+        if !metadata.movie_set_overview.is_empty() {
+            set = set.child(Node::Element(Element::text(
+                "overview",
+                &metadata.movie_set_overview,
+            )));
+        }
+        root.replace("set", vec![set]);
+    }
+    let tags: Vec<String> = metadata.tags.iter().map(|tag| tag.tag.clone()).collect();
+    root.set_all("tag", &tags);
+    root.set_all("credits", &metadata.writers);
+    root.set_all("director", &metadata.director);
+    // only updated, see the module documentation
+    for name in ["premiered", "aired"] {
+        if root.find(name).is_some() && metadata.date.num_days_from_ce() >= 100 {
+            root.set(name, &metadata.date.format("%Y-%m-%d").to_string());
+        }
+    }
+    root.set_all("studio", &metadata.studios);
+    if !metadata.actors.is_empty() {
+        let actors = metadata
+            .actors
+            .iter()
+            .map(|name| actor(root, metadata, name))
+            .collect();
+        root.replace("actor", actors);
+    }
+    if episode {
+        if metadata.season > 0 {
+            root.set("season", &metadata.season.to_string());
+        }
+        if metadata.episode > 0 {
+            root.set("episode", &metadata.episode.to_string());
+        }
+    }
+}
+
+/// The `<actor>` element of `name`, based on the one in the file to keep its order and
+/// pictures.
+fn actor(root: &Element, metadata: &VideoMetadata, name: &str) -> Element {
+    let mut actor = root
+        .elements()
+        .find(|e| e.is("actor") && e.find("name").is_some_and(|n| n.content() == name))
+        .cloned()
+        .unwrap_or_else(|| Element::new("actor").child(Node::Element(Element::text("name", name))));
+    if let Some(role) = metadata.roles.iter().find(|role| role.actor == name) {
+        actor.set("role", &role.role);
+        actor.set("thumb", &role.thumb);
+    }
+    actor
+}
+
+fn update_collection(root: &mut Element, collection: &CollectionMetadata) {
+    root.set("title", &collection.name);
+    root.set("plot", &collection.description);
+    let tags: Vec<String> = collection.tags.iter().map(|tag| tag.tag.clone()).collect();
+    root.set_all("tag", &tags);
+}
+
+/// NFO file of a video: `<video>.nfo`, or the `movie.nfo` of its folder if only that one
+/// exists.
+pub fn video_nfo(video: &Path) -> PathBuf {
+    let own = video.with_extension("nfo");
+    let folder = video.with_file_name("movie.nfo");
+    if !own.exists() && folder.exists() {
+        return folder;
+    }
+    own
+}
+
+/// The lines of `old` missing in `new` with a leading "- ", those added with "+ ".
+pub fn diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // length of the longest common subsequence of the rests from i and j on
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+/// An NFO file that differs from the library.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Change {
+    pub nfo: PathBuf,
+    /// Whether the file does not exist yet.
+    pub created: bool,
+    pub diff: String,
+    contents: String,
+}
+
+/// What an export wrote, or would write on a dry run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub dry_run: bool,
+    pub changes: Vec<Change>,
+    /// Files that already match the library.
+    pub unchanged: usize,
+    /// Files that could not be read or written, with the reason.
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// The file `nfo` should have for a record, if any. `None` for records without an NFO
+/// file, or when the file says the same as the library.
+fn change(nfo: &Path, record: &Record) -> Result<Option<Change>, Error> {
+    let existing = match std::fs::read_to_string(nfo) {
+        Ok(text) => Some(text),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+    let before = match &existing {
+        Some(text) => Document::read(text)?,
+        None => match record {
+            Record::Video(video) if video.season > 0 || video.episode > 0 => {
+                Document::new("episodedetails")
+            }
+            Record::Collection(_) => Document::new("tvshow"),
+            _ => Document::new("movie"),
+        },
+    };
+    let mut after = before.clone();
+    match record {
+        Record::Video(video) => update_video(&mut after.root, video),
+        Record::Collection(collection) => update_collection(&mut after.root, collection),
+        _ => return Ok(None),
+    }
+    if existing.is_some() && after == before {
+        return Ok(None);
+    }
+    let contents = after.render()?;
+    // against the file as it is, so lines which are only reformatted show up too
+    let old = existing.unwrap_or_default();
+    Ok(Some(Change {
+        nfo: nfo.to_path_buf(),
+        created: old.is_empty(),
+        diff: diff(&old, &contents),
+        contents,
+    }))
+}
+
+fn write(change: &Change) -> Result<(), Error> {
+    // the backup is of the file before the first export, later ones keep it
+    let backup = change.nfo.with_extension("nfo.bak");
+    if !change.created && !backup.exists() {
+        std::fs::copy(&change.nfo, backup)?;
+    }
+    std::fs::write(&change.nfo, &change.contents)?;
+    Ok(())
+}
+
+/// Write the NFO files of the videos and TV shows among `records`, or only report the
+/// changes on a dry run. Songs, images and movie sets have no NFO file of their own.
+pub fn run(records: &[Record], dry_run: bool, controller: &Controller) -> Result<Report, String> {
+    let mut report = Report {
+        dry_run,
+        ..Default::default()
+    };
+    let total = records.len().max(1);
+    for (i, record) in records.iter().enumerate() {
+        controller.check()?;
+        controller.set_progress(i as f32 / total as f32);
+        let nfo = match record {
+            Record::Video(video) => video_nfo(Path::new(&video.path)),
+            Record::Collection(collection) if collection.path.is_dir() => {
+                collection.path.join("tvshow.nfo")
+            }
+            _ => continue,
+        };
+        let result = change(&nfo, record).and_then(|change| match change {
+            Some(change) if !dry_run => write(&change).map(|()| Some(change)),
+            change => Ok(change),
+        });
+        match result {
+            Ok(Some(change)) => report.changes.push(change),
+            Ok(None) => report.unchanged += 1,
+            Err(err) => {
+                log::warn!("failed to write {}: {}", nfo.display(), err);
+                report.failed.push((nfo, err.to_string()));
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::{Rating, Role, Tag, UniqueId};
+
+    const MOVIE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes" ?>
+<!-- created by a scraper -->
+<movie>
+	<title>Heat</title>
+	<userrating>9</userrating>
+	<genre>Crime</genre>
+	<premiered>1995-12-15</premiered>
+	<actor>
+		<name>Al Pacino</name>
+		<role>Vincent Hanna</role>
+		<order>0</order>
+	</actor>
+	<fanart><thumb preview="small.jpg">big.jpg</thumb></fanart>
+</movie>
+"#;
+
+    fn video(path: &Path) -> VideoMetadata {
+        let mut metadata = VideoMetadata::default();
+        crate::parsers::nfo::parse(MOVIE.as_bytes(), &mut metadata).unwrap();
+        metadata.path = path.with_extension("mkv").display().to_string();
+        metadata
+    }
+
+    #[test]
+    fn unknown_elements_survive_and_old_files_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let nfo = dir.path().join("heat.nfo");
+        std::fs::write(&nfo, MOVIE).unwrap();
+        let mut metadata = video(&nfo);
+        metadata.genres.push("Thriller".to_string());
+        metadata.writers.push("Michael Mann".to_string());
+        metadata.roles[0].role = "Lt. Vincent Hanna".to_string();
+        metadata.tags.push(Tag {
+            tag_id: 0,
+            tag: "heist".to_string(),
+        });
+        metadata.unique_ids.push(UniqueId {
+            kind: "imdb".to_string(),
+            value: "tt0113277".to_string(),
+            default: true,
+        });
+        let records = [Record::Video(Box::new(metadata.clone()))];
+
+        let report = run(&records, true, &Controller::new()).unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.changes.len(), 1);
+        assert!(!report.changes[0].created);
+        let diff = &report.changes[0].diff;
+        assert!(diff.contains("+     <genre>Thriller</genre>"), "{}", diff);
+        assert!(
+            diff.contains("- \t\t<role>Vincent Hanna</role>"),
+            "{}",
+            diff
+        );
+        // the file is reformatted, which is shown as well
+        assert!(diff.contains("- \t<userrating>9</userrating>"), "{}", diff);
+        assert!(
+            diff.contains("+     <userrating>9</userrating>"),
+            "{}",
+            diff
+        );
+        // nothing is touched on a dry run
+        assert_eq!(std::fs::read_to_string(&nfo).unwrap(), MOVIE);
+        assert!(!nfo.with_extension("nfo.bak").exists());
+
+        let report = run(&records, false, &Controller::new()).unwrap();
+        assert_eq!(report.changes.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(nfo.with_extension("nfo.bak")).unwrap(),
+            MOVIE
+        );
+        let written = std::fs::read_to_string(&nfo).unwrap();
+        for kept in [
+            "<!-- created by a scraper -->",
+            "<userrating>9</userrating>",
+            "<order>0</order>",
+            r#"<thumb preview="small.jpg">big.jpg</thumb>"#,
+            "<premiered>1995-12-15</premiered>",
+        ] {
+            assert!(written.contains(kept), "{}", written);
+        }
+        // new elements go where the old ones of the kind were
+        assert!(written.find("<genre>Thriller").unwrap() < written.find("<premiered>").unwrap());
+        let mut again = VideoMetadata::default();
+        crate::parsers::nfo::parse(written.as_bytes(), &mut again).unwrap();
+        assert_eq!(again.genres, ["Crime", "Thriller"]);
+        assert_eq!(again.writers, ["Michael Mann"]);
+        assert_eq!(again.roles[0].role, "Lt. Vincent Hanna");
+        assert_eq!(again.tags[0].tag, "heist");
+        assert_eq!(again.unique_ids, metadata.unique_ids);
+
+        // the file says the same as the library now
+        let report = run(&records, true, &Controller::new()).unwrap();
+        assert!(report.changes.is_empty());
+        assert_eq!(report.unchanged, 1);
+
+        // once in the layout written here only the changed lines show up, and the
+        // backup stays the file from before the first export
+        metadata.genres.push("Drama".to_string());
+        let records = [Record::Video(Box::new(metadata))];
+        let report = run(&records, false, &Controller::new()).unwrap();
+        assert_eq!(report.changes[0].diff, "+     <genre>Drama</genre>");
+        assert_eq!(
+            std::fs::read_to_string(nfo.with_extension("nfo.bak")).unwrap(),
+            MOVIE
+        );
+    }
+
+    #[test]
+    fn missing_files_are_created_and_broken_ones_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut episode = VideoMetadata {
+            path: dir.path().join("s01e02.mkv").display().to_string(),
+            title: "Pilot & Co".to_string(),
+            season: 1,
+            episode: 2,
+            duration: 2700,
+            ..Default::default()
+        };
+        episode.ratings.push(Rating {
+            source: "tvdb".to_string(),
+            value: 7.5,
+            max: 10.0,
+            votes: 12,
+            default: true,
+        });
+        episode.actors.push("Kyle MacLachlan".to_string());
+        episode.roles.push(Role {
+            actor: "Kyle MacLachlan".to_string(),
+            role: "Dale Cooper".to_string(),
+            thumb: String::new(),
+        });
+        let collection = CollectionMetadata {
+            name: "Twin Peaks".to_string(),
+            path: dir.path().to_path_buf(),
+            description: "A murder in a small town.".to_string(),
+            ..Default::default()
+        };
+        let broken = dir.path().join("broken.mkv");
+        std::fs::write(broken.with_extension("nfo"), "<movie><title>Broken").unwrap();
+        let records = [
+            Record::Video(Box::new(episode.clone())),
+            Record::Collection(collection),
+            Record::Video(Box::new(VideoMetadata {
+                path: broken.display().to_string(),
+                title: "Fixed".to_string(),
+                ..Default::default()
+            })),
+        ];
+
+        let report = run(&records, false, &Controller::new()).unwrap();
+        assert_eq!(report.changes.len(), 2);
+        assert!(report.changes.iter().all(|change| change.created));
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, broken.with_extension("nfo"));
+        assert_eq!(
+            std::fs::read_to_string(broken.with_extension("nfo")).unwrap(),
+            "<movie><title>Broken"
+        );
+
+        let written = std::fs::read_to_string(dir.path().join("s01e02.nfo")).unwrap();
+        assert!(written.contains("<episodedetails>"), "{}", written);
+        assert!(
+            written.contains("<title>Pilot &amp; Co</title>"),
+            "{}",
+            written
+        );
+        let mut again = VideoMetadata::default();
+        crate::parsers::nfo::parse(written.as_bytes(), &mut again).unwrap();
+        assert_eq!((again.season, again.episode), (1, 2));
+        assert_eq!(again.duration, 2700);
+        assert_eq!(again.ratings, episode.ratings);
+        assert_eq!(again.roles, episode.roles);
+        let show = std::fs::read_to_string(dir.path().join("tvshow.nfo")).unwrap();
+        assert!(
+            show.contains("<plot>A murder in a small town.</plot>"),
+            "{}",
+            show
+        );
+    }
+
+    #[test]
+    fn diffs_show_removed_and_added_lines() {
+        assert_eq!(diff("a\nb\nc", "a\nc\nd"), "- b\n+ d");
+        assert_eq!(diff("a\nb", "a\nc"), "- b\n+ c");
+        assert_eq!(diff("", "a"), "+ a");
+        assert_eq!(diff("a", "a"), "");
+    }
+}
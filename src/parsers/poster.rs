@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Posters of videos without one of their own, taken from a frame of the video.
+//!
+//! `create()` decodes the video in-process with GStreamer, seeks to `position()` percent
+//! of its duration and writes the frame into the thumbs cache; the media directory is
+//! only read. Frames that are black or of a single colour, like fades and title cards,
+//! are skipped for one a little later in the video.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+
+/// Percentages of the duration offered in the settings.
+pub const POSITIONS: [u8; 5] = [5, 10, 25, 33, 50];
+/// Percentage of the duration posters are taken at unless configured otherwise.
+pub const DEFAULT_POSITION: u8 = 10;
+/// How many frames are looked at before the most detailed one is taken.
+const ATTEMPTS: u32 = 5;
+/// Percentage points between two frames looked at.
+const STEP: u32 = 7;
+/// Mean brightness below which a frame counts as black.
+const DARK: f64 = 24.0;
+/// Deviation of the brightness below which a frame counts as uniform.
+const FLAT: f64 = 12.0;
+/// Time GStreamer may take to open the video or to reach a frame.
+const TIMEOUT_SECONDS: u64 = 10;
+
+static POSITION: AtomicU8 = AtomicU8::new(DEFAULT_POSITION);
+
+/// Take posters at `percent` of the duration of the videos from now on.
+pub fn set_position(percent: u8) {
+    POSITION.store(percent.min(95), Ordering::Relaxed);
+}
+
+/// Percentage of the duration posters are taken at.
+pub fn position() -> u8 {
+    POSITION.load(Ordering::Relaxed)
+}
+
+/// Percentages of the duration to look at, starting at `percent`.
+pub fn candidates(percent: u8) -> Vec<u32> {
+    (0..ATTEMPTS)
+        .map(|i| (percent as u32 + i * STEP) % 100)
+        .collect()
+}
+
+/// Mean and standard deviation of the brightness of an RGBA frame. Every pixel is not
+/// needed for that, a few thousand spread over the frame are enough.
+pub fn brightness(rgba: &[u8]) -> (f64, f64) {
+    let pixels = rgba.len() / 4;
+    if pixels == 0 {
+        return (0.0, 0.0);
+    }
+    let step = (pixels / 4096).max(1);
+    let mut count = 0.0;
+    let mut sum = 0.0;
+    let mut sum_squares = 0.0;
+    for pixel in rgba.chunks_exact(4).step_by(step) {
+        let luma = 0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64;
+        count += 1.0;
+        sum += luma;
+        sum_squares += luma * luma;
+    }
+    let mean = sum / count;
+    let variance = (sum_squares / count - mean * mean).max(0.0);
+    (mean, variance.sqrt())
+}
+
+/// Whether a frame shows something, rather than being black or of a single colour.
+pub fn is_usable(rgba: &[u8]) -> bool {
+    let (mean, deviation) = brightness(rgba);
+    mean >= DARK && deviation >= FLAT
+}
+
+struct Frame {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+fn pull_frame(sink: &gst_app::AppSink) -> Option<Frame> {
+    let sample = sink.try_pull_preroll(gst::ClockTime::from_seconds(TIMEOUT_SECONDS))?;
+    let caps = sample.caps()?;
+    let structure = caps.structure(0)?;
+    let width = structure.get::<i32>("width").ok()? as u32;
+    let height = structure.get::<i32>("height").ok()? as u32;
+    let buffer = sample.buffer()?;
+    let map = buffer.map_readable().ok()?;
+    // RGBA rows need no padding, the buffer is exactly the pixels
+    let size = (width * height * 4) as usize;
+    if map.size() < size {
+        return None;
+    }
+    Some(Frame {
+        width,
+        height,
+        rgba: map.as_slice()[..size].to_vec(),
+    })
+}
+
+/// The most telling of a few frames from `percent` of the video on.
+fn grab(video: &Path, percent: u8) -> Result<Option<Frame>, Box<dyn std::error::Error>> {
+    gst::init()?;
+    let uri = gst::glib::filename_to_uri(video, None)?;
+    let sink_bin = gst::parse::bin_from_description(
+        "videoconvert ! videoscale ! appsink name=poster sync=false caps=video/x-raw,format=RGBA,pixel-aspect-ratio=1/1",
+        true,
+    )?;
+    let sink = sink_bin
+        .by_name("poster")
+        .and_then(|sink| sink.downcast::<gst_app::AppSink>().ok())
+        .ok_or("no appsink in the poster pipeline")?;
+    // video only, sound and subtitles are not decoded
+    let pipeline = gst::ElementFactory::make("playbin")
+        .property("uri", uri.as_str())
+        .property("video-sink", &sink_bin)
+        .property_from_str("flags", "video")
+        .build()?;
+
+    let frame = (|| -> Result<Option<Frame>, Box<dyn std::error::Error>> {
+        pipeline.set_state(gst::State::Paused)?;
+        pipeline
+            .state(gst::ClockTime::from_seconds(TIMEOUT_SECONDS))
+            .0?;
+        let Some(duration) = pipeline.query_duration::<gst::ClockTime>() else {
+            // nothing to seek in, the first frame has to do
+            return Ok(pull_frame(&sink));
+        };
+        let mut best: Option<(f64, Frame)> = None;
+        for candidate in candidates(percent) {
+            let time = duration.nseconds() / 100 * candidate as u64;
+            pipeline.seek_simple(
+                gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
+                gst::ClockTime::from_nseconds(time),
+            )?;
+            pipeline
+                .state(gst::ClockTime::from_seconds(TIMEOUT_SECONDS))
+                .0?;
+            let Some(frame) = pull_frame(&sink) else {
+                continue;
+            };
+            if is_usable(&frame.rgba) {
+                return Ok(Some(frame));
+            }
+            let (_, deviation) = brightness(&frame.rgba);
+            let better = match &best {
+                Some((most, _)) => deviation > *most,
+                None => true,
+            };
+            if better {
+                best = Some((deviation, frame));
+            }
+        }
+        Ok(best.map(|(_, frame)| frame))
+    })();
+    let _ = pipeline.set_state(gst::State::Null);
+    frame
+}
+
+/// Write a poster for `video` to `poster`, a JPEG file in the thumbs cache.
+pub fn create(video: &Path, poster: &Path) -> bool {
+    let frame = match grab(video, position()) {
+        Ok(Some(frame)) => frame,
+        Ok(None) => {
+            log::warn!("No frame found for the poster of {}", video.display());
+            return false;
+        }
+        Err(error) => {
+            log::warn!(
+                "Failed to decode {} for a poster: {}",
+                video.display(),
+                error
+            );
+            return false;
+        }
+    };
+    let Some(image) = image::RgbaImage::from_raw(frame.width, frame.height, frame.rgba) else {
+        log::error!("Frame of {} does not match its size", video.display());
+        return false;
+    };
+    // JPEG has no alpha channel
+    let image = image::DynamicImage::ImageRgba8(image).to_rgb8();
+    match image.save_with_format(poster, image::ImageFormat::Jpeg) {
+        Ok(()) => true,
+        Err(error) => {
+            log::error!("Failed to write poster {}: {}", poster.display(), error);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(pixel: impl Fn(usize) -> [u8; 4]) -> Vec<u8> {
+        (0..64 * 36).flat_map(pixel).collect()
+    }
+
+    #[test]
+    fn black_and_uniform_frames_are_skipped() {
+        assert!(!is_usable(&frame(|_| [0, 0, 0, 255])));
+        assert!(!is_usable(&frame(|_| [8, 10, 12, 255])));
+        // a blue title card
+        assert!(!is_usable(&frame(|_| [20, 40, 200, 255])));
+        // stripes of light and shadow
+        assert!(is_usable(&frame(|i| {
+            let value = if i % 64 < 32 { 40 } else { 220 };
+            [value, value, value, 255]
+        })));
+        assert!(!is_usable(&[]));
+    }
+
+    #[test]
+    fn later_frames_are_tried_after_the_position() {
+        assert_eq!(candidates(10), [10, 17, 24, 31, 38]);
+        assert_eq!(candidates(90), [90, 97, 4, 11, 18]);
+        set_position(200);
+        assert_eq!(position(), 95);
+        set_position(DEFAULT_POSITION);
+    }
+}
@@ -1,15 +1,19 @@
 //use std::cell::{Ref, RefCell, RefMut};
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use crate::tab::Item;
 
-#[derive(Debug, Default)]
+/// State of one scan. Clones share the state, so it can be handed to the database writer.
+#[derive(Clone, Debug, Default)]
 pub struct ScanMetaData {
-    known_files: Mutex<std::collections::BTreeMap<PathBuf, crate::sql::FileMetadata>>,
-    special_files: Mutex<std::collections::BTreeSet<PathBuf>>,
-    items: Mutex<Vec<Item>>,
-    tvshows: Mutex<Vec<PathBuf>>,
+    known_files: Arc<Mutex<std::collections::BTreeMap<PathBuf, crate::sql::FileMetadata>>>,
+    special_files: Arc<Mutex<std::collections::BTreeSet<PathBuf>>>,
+    items: Arc<Mutex<Vec<Item>>>,
+    tvshows: Arc<Mutex<Vec<PathBuf>>>,
+    scanned_files: Arc<Mutex<usize>>,
+    fingerprints: Arc<Mutex<std::collections::BTreeMap<PathBuf, String>>>,
+    missing_files: Arc<Mutex<Vec<crate::sql::FileMetadata>>>,
 }
 
 impl ScanMetaData {
@@ -77,6 +81,21 @@ impl ScanMetaData {
             }
         }
     }
+    /// Stored files directly inside `dir`.
+    pub fn known_files_in(&self, dir: &std::path::Path) -> Vec<crate::sql::FileMetadata> {
+        match self.known_files.lock() {
+            Ok(bm) => bm
+                .range(dir.to_path_buf()..)
+                .take_while(|(k, _)| k.starts_with(dir))
+                .filter(|(k, v)| k.parent() == Some(dir) && **k == v.filepath)
+                .map(|(_, v)| v.clone())
+                .collect(),
+            Err(error) => {
+                log::error!("could not lock known_files for reading! {}", error);
+                Vec::new()
+            }
+        }
+    }
     pub fn known_files_remove(&self, p: PathBuf) {
         match self.known_files.lock() {
             Ok(mut bm) => {
@@ -198,4 +217,56 @@ impl ScanMetaData {
             Err(error) => log::error!("could not lock justdirs for push! {}", error),
         }
     }
+
+    pub fn scanned_files_add(&self, count: usize) {
+        match self.scanned_files.lock() {
+            Ok(mut n) => *n += count,
+            Err(error) => log::error!("could not lock scanned_files for add! {}", error),
+        }
+    }
+    pub fn scanned_files(&self) -> usize {
+        match self.scanned_files.lock() {
+            Ok(n) => *n,
+            Err(error) => {
+                log::error!("could not lock scanned_files for reading! {}", error);
+                0
+            }
+        }
+    }
+
+    pub fn fingerprints_insert(&self, p: PathBuf, fingerprint: String) {
+        match self.fingerprints.lock() {
+            Ok(mut bm) => {
+                bm.insert(p, fingerprint);
+            }
+            Err(error) => log::error!("could not lock fingerprints for insert! {}", error),
+        }
+    }
+    pub fn fingerprints_get(&self, p: PathBuf) -> Option<String> {
+        match self.fingerprints.lock() {
+            Ok(bm) => bm.get(&p).cloned(),
+            Err(error) => {
+                log::error!("could not lock fingerprints for reading! {}", error);
+                None
+            }
+        }
+    }
+
+    /// Stored files that were not found on disk. They are removed when the scan is done, so
+    /// a file that was moved to a directory scanned later can still be re-linked.
+    pub fn missing_files_extend(&self, files: Vec<crate::sql::FileMetadata>) {
+        match self.missing_files.lock() {
+            Ok(mut v) => v.extend(files),
+            Err(error) => log::error!("could not lock missing_files for insert! {}", error),
+        }
+    }
+    pub fn missing_files_take(&self) -> Vec<crate::sql::FileMetadata> {
+        match self.missing_files.lock() {
+            Ok(mut v) => std::mem::take(&mut *v),
+            Err(error) => {
+                log::error!("could not lock missing_files for reading! {}", error);
+                Vec::new()
+            }
+        }
+    }
 }
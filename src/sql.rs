@@ -7,10 +7,28 @@
 use chrono::{NaiveDate, Timelike};
 use rusqlite::{params, Connection, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 
-#[derive(Clone, Debug, Hash)]
+use crate::operation::Controller;
+
+pub mod exchange;
+pub mod expression;
+pub mod fold;
+pub mod fts;
+pub mod geo;
+pub mod maintenance;
+pub mod migrations;
+pub mod nfo;
+pub mod query;
+pub mod related;
+pub mod service;
+pub mod suggest;
+
+use query::Query;
+pub use service::Database;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum SearchType {
     FilePath,
     Title,
@@ -34,10 +52,23 @@ pub enum SearchType {
     GPSLatitude,
     GPSLongitude,
     GPSAltitude,
+    GPSLocation,
+    Resolution,
+    FrameRate,
+    Bitrate,
+    Codec,
+    AspectRatio,
     Tag,
+    Writer,
+    Studio,
+    Country,
+    MovieSet,
+    Certification,
+    ExternalId,
 }
 
-#[derive(Clone, Debug, Eq, PartialOrd, Hash)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialOrd, Hash, Serialize)]
+#[serde(default)]
 pub struct SearchData {
     pub search_id: u32,
     pub search_string: String,
@@ -74,6 +105,23 @@ pub struct SearchData {
     pub gps_longitude: bool,
     pub gps_altitude: bool,
     pub tags: bool,
+    pub resolution: bool,
+    pub frame_rate: bool,
+    pub bitrate: bool,
+    pub codec: bool,
+    pub aspect_ratio: bool,
+    /// Accept typos in the searched text.
+    pub fuzzy: bool,
+    /// Name the search was saved under, empty for one that is only kept in the history.
+    pub name: String,
+    /// Folder the search is grouped into in the list of saved searches, empty for none.
+    pub folder: String,
+    /// Order of the saved searches within their folder.
+    pub position: u32,
+    /// Seconds since the epoch when the search was last run, 0 if it never was.
+    pub last_run: i64,
+    /// Number of files found when the search was last run.
+    pub result_count: u32,
 }
 
 impl Default for SearchData {
@@ -114,6 +162,17 @@ impl Default for SearchData {
             gps_longitude: false,
             gps_altitude: false,
             tags: false,
+            resolution: false,
+            frame_rate: false,
+            bitrate: false,
+            codec: false,
+            aspect_ratio: false,
+            fuzzy: false,
+            name: String::new(),
+            folder: String::new(),
+            position: 0,
+            last_run: 0,
+            result_count: 0,
         }
     }
 }
@@ -122,6 +181,9 @@ impl SearchData {
     pub fn display(&self) -> String {
         let mut s;
         s = format!("{}", self.search_id);
+        if self.search_string.len() > 0 {
+            s = format!("{} {}", s, self.search_string);
+        }
         if self.image {
             s = format!("{} Image", s);
         }
@@ -149,6 +211,12 @@ impl SearchData {
         if self.to_date > 0 {
             s = format!("{} to {}", s, self.to_date);
         }
+        if !self.name.is_empty() {
+            s = self.name.clone();
+        }
+        if !self.folder.is_empty() {
+            s = format!("{} / {}", self.folder, s);
+        }
         s
     }
 
@@ -161,9 +229,20 @@ impl SearchData {
     }
 }
 
+/// A named search pinned to the sidebar. It is run again whenever it is opened or the
+/// library changes, so its items are never a snapshot.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, PartialOrd, Serialize)]
+#[serde(default)]
+pub struct SmartCollection {
+    pub name: String,
+    pub search: SearchData,
+}
+
+/// Searches with the same conditions are equal, whatever name or folder they were saved in.
 impl PartialEq for SearchData {
     fn eq(&self, other: &Self) -> bool {
-        let res = self.from_string.to_ascii_lowercase() == other.from_string.to_ascii_lowercase()
+        let res = self.search_string.trim() == other.search_string.trim()
+            && self.from_string.to_ascii_lowercase() == other.from_string.to_ascii_lowercase()
             && self.from_value == other.from_value
             && self.from_date == other.from_date
             && self.to_string.to_ascii_lowercase() == other.to_string.to_ascii_lowercase()
@@ -191,7 +270,13 @@ impl PartialEq for SearchData {
             && self.gps_longitude == other.gps_longitude
             && self.gps_longitude == other.gps_longitude
             && self.gps_altitude == other.gps_altitude
-            && self.tags == other.tags;
+            && self.tags == other.tags
+            && self.resolution == other.resolution
+            && self.frame_rate == other.frame_rate
+            && self.bitrate == other.bitrate
+            && self.codec == other.codec
+            && self.aspect_ratio == other.aspect_ratio
+            && self.fuzzy == other.fuzzy;
         if !res {
             return false;
         }
@@ -201,7 +286,7 @@ impl PartialEq for SearchData {
 
 fn search_video_metadata(
     sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
-    query: String,
+    query: Query,
 ) -> (Vec<VideoMetadata>, Vec<FileMetadata>) {
     let mut files = Vec::new();
     let mut videos = Vec::new();
@@ -215,9 +300,9 @@ fn search_video_metadata(
             return (videos, files);
         }
     };
-    match connection.prepare(&query) {
+    match connection.prepare(&query.sql()) {
         Ok(mut statement) => {
-            match statement.query(params![]) {
+            match statement.query(query.params()) {
                 Ok(mut rows) => {
                     loop {
                         match rows.next() {
@@ -275,69 +360,12 @@ pub fn search_video(
     if !search.video {
         return (videos, files);
     }
-    if search.title {
-        let query = format!(
-            "SELECT video_id FROM video_metadata WHERE title LIKE '%{}%'",
-            search.from_string
-        );
-        let (newvideos, newfiles) = search_video_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                videos.push(newvideos[i].clone());
-            }
-        }
-    }
-    if search.description {
-        let query = format!(
-            "SELECT video_id FROM video_metadata WHERE description LIKE '%{}%'",
-            search.from_string
-        );
-        let (newvideos, newfiles) = search_video_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                videos.push(newvideos[i].clone());
-            }
-        }
-    }
     if search.duration && search.from_value != 0 {
-        let query;
+        let mut query = Query::select("SELECT video_id FROM video_metadata")
+            .filter("duration > ?", search.from_value as u32);
         if search.to_value != 0 {
-            query = format!(
-                "SELECT video_id FROM video_metadata WHERE duration > {} AND duration < {}",
-                search.from_value as u32, search.to_value as u32
-            );
-        } else {
-            query = format!(
-                "SELECT video_id FROM video_metadata WHERE duration > {}",
-                search.from_value as u32
-            );
-        }
-        let (newvideos, newfiles) = search_video_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                videos.push(newvideos[i].clone());
-            }
-        }
-    }
-    if search.actor {
-        let query = format!("SELECT video_id FROM actors INNER JOIN people ON people.person_id = actors.actor_id WHERE people.person_name LIKE '%{}%'", search.from_string);
-        let (newvideos, newfiles) = search_video_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                videos.push(newvideos[i].clone());
-            }
+            query = query.filter("duration < ?", search.to_value as u32);
         }
-    }
-    if search.director {
-        let query = format!("SELECT video_id FROM directors INNER JOIN people ON people.person_id = directors.director_id WHERE people.person_name LIKE '%{}%'", search.from_string);
         let (newvideos, newfiles) = search_video_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
             if !used_files.contains(&newfiles[i].filepath) {
@@ -348,29 +376,11 @@ pub fn search_video(
         }
     }
     if search.release_date {
-        let query;
+        let mut query = Query::select("SELECT video_id FROM video_metadata")
+            .filter("released > ?", search.from_date);
         if search.to_date != 0 {
-            query = format!(
-                "SELECT video_id FROM video_metadata WHERE released > {} AND released < {}",
-                search.from_date, search.to_date
-            );
-        } else {
-            query = format!(
-                "SELECT video_id FROM video_metadata WHERE released > {}",
-                search.from_date
-            );
-        }
-        let (newvideos, newfiles) = search_video_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                videos.push(newvideos[i].clone());
-            }
+            query = query.filter("released < ?", search.to_date);
         }
-    }
-    if search.tags {
-        let query = format!("SELECT media_id FROM tags_media_map INNER JOIN tags ON tags_media_map.tagmap_id = tags.tag_id WHERE tags.tag LIKE '%{}%'", search.from_string);
         let (newvideos, newfiles) = search_video_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
             if !used_files.contains(&newfiles[i].filepath) {
@@ -386,7 +396,7 @@ pub fn search_video(
 
 fn search_audio_metadata(
     sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
-    query: String,
+    query: Query,
 ) -> (Vec<AudioMetadata>, Vec<FileMetadata>) {
     let mut files = Vec::new();
     let mut audios = Vec::new();
@@ -400,9 +410,9 @@ fn search_audio_metadata(
         }
     };
 
-    match connection.prepare(&query) {
+    match connection.prepare(&query.sql()) {
         Ok(mut statement) => {
-            match statement.query(params![]) {
+            match statement.query(query.params()) {
                 Ok(mut rows) => {
                     loop {
                         match rows.next() {
@@ -460,112 +470,12 @@ pub fn search_audio(
     if !search.audio {
         return (audios, files);
     }
-    if search.title {
-        let query = format!(
-            "SELECT audio_id FROM audio_metadata WHERE title LIKE '%{}%'",
-            search.from_string
-        );
-        let (newvideos, newfiles) = search_audio_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                audios.push(newvideos[i].clone());
-            }
-        }
-    }
     if search.duration && search.from_value != 0 {
-        let query;
+        let mut query = Query::select("SELECT audio_id FROM audio_metadata")
+            .filter("duration > ?", search.from_value as f64 / 1000000.0);
         if search.to_value != 0 {
-            query = format!(
-                "SELECT audio_id FROM audio_metadata WHERE duration > {} AND duration < {}",
-                search.from_value / 1000000,
-                search.to_value / 1000000
-            );
-        } else {
-            query = format!(
-                "SELECT audio_id FROM audio_metadata WHERE duration > {}",
-                search.from_value / 1000000
-            );
-        }
-        let (newvideos, newfiles) = search_audio_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                audios.push(newvideos[i].clone());
-            }
-        }
-    }
-    if search.artist {
-        let query = format!("SELECT audio_id FROM artist_audio_map INNER JOIN artists ON artists.artist_id  = artist_audio_map.artist_id WHERE artists.artist_name LIKE '%{}%'", search.from_string);
-        let (newvideos, newfiles) = search_audio_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                audios.push(newvideos[i].clone());
-            }
-        }
-    }
-    if search.title {
-        let query = format!("SELECT audio_id FROM album_audio_map INNER JOIN albums ON albums.album_id  = album_audio_map.album_id WHERE albums.album_name LIKE '%{}%'", search.from_string);
-        let (newvideos, newfiles) = search_audio_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                audios.push(newvideos[i].clone());
-            }
-        }
-    }
-    if search.album_artist {
-        let query = format!("SELECT audio_id FROM albumartist_audio_map INNER JOIN artists ON artists.artist_id  = albumartist_audio_map.albumartist_id WHERE artists.artist_name LIKE '%{}%'", search.from_string);
-        let (newvideos, newfiles) = search_audio_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                audios.push(newvideos[i].clone());
-            }
-        }
-    }
-    if search.album {
-        let query = format!(
-            "SELECT audio_id FROM album_audio_map 
-                        INNER JOIN albums
-                        ON album_audio_map.album_id = albums.album_id 
-                        WHERE albums.album_name LIKE '%{}%'",
-            search.from_string
-        );
-        let (newvideos, newfiles) = search_audio_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                audios.push(newvideos[i].clone());
-            }
-        }
-    }
-    if search.composer {
-        let query = format!(
-            "SELECT audio_id FROM audio_metadata composer LIKE '%{}%'",
-            search.from_string
-        );
-        let (newvideos, newfiles) = search_audio_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                audios.push(newvideos[i].clone());
-            }
+            query = query.filter("duration < ?", search.to_value as f64 / 1000000.0);
         }
-    }
-    if search.genre {
-        let query = format!(
-            "SELECT audio_id FROM audio_metadata genre LIKE '%{}%'",
-            search.from_string
-        );
         let (newvideos, newfiles) = search_audio_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
             if !used_files.contains(&newfiles[i].filepath) {
@@ -576,17 +486,10 @@ pub fn search_audio(
         }
     }
     if search.release_date {
-        let query;
+        let mut query = Query::select("SELECT audio_id FROM audio_metadata")
+            .filter("released > ?", search.from_date);
         if search.to_date != 0 {
-            query = format!(
-                "SELECT audio_id FROM audio_metadata WHERE released > {} AND released < {}",
-                search.from_date, search.to_date
-            );
-        } else {
-            query = format!(
-                "SELECT audio_id FROM audio_metadata WHERE released > {}",
-                search.from_date
-            );
+            query = query.filter("released < ?", search.to_date);
         }
         let (newvideos, newfiles) = search_audio_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
@@ -597,24 +500,13 @@ pub fn search_audio(
             }
         }
     }
-    if search.tags {
-        let query = format!("SELECT media_id FROM tags_media_map INNER JOIN tags ON tags_media_map.tagmap_id = tags.tag_id WHERE tags.tag LIKE '%{}%'", search.from_string);
-        let (newaudios, newfiles) = search_audio_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                audios.push(newaudios[i].clone());
-            }
-        }
-    }
 
     (audios, files)
 }
 
 fn search_image_metadata(
     sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
-    query: String,
+    query: Query,
 ) -> (Vec<ImageMetadata>, Vec<FileMetadata>) {
     let mut files = Vec::new();
     let mut images = Vec::new();
@@ -628,9 +520,9 @@ fn search_image_metadata(
             return (images, files);
         }
     };
-    match connection.prepare(&query) {
+    match connection.prepare(&query.sql()) {
         Ok(mut statement) => {
-            match statement.query(params![]) {
+            match statement.query(query.params()) {
                 Ok(mut rows) => {
                     loop {
                         match rows.next() {
@@ -689,25 +581,9 @@ pub fn search_image(
     if !search.image {
         return (images, files);
     }
-    if search.title {
-        let query = format!(
-            "SELECT image_id FROM image_metadata WHERE name LIKE '%{}%'",
-            search.from_string
-        );
-        let (newvideos, newfiles) = search_image_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                images.push(newvideos[i].clone());
-            }
-        }
-    }
     if search.lense_model {
-        let query = format!(
-            "SELECT image_id FROM image_metadata WHERE LenseModel LIKE '%{}%'",
-            search.from_string
-        );
+        let query = Query::select("SELECT image_id FROM image_metadata")
+            .like("LenseModel", &search.from_string);
         let (newvideos, newfiles) = search_image_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
             if !used_files.contains(&newfiles[i].filepath) {
@@ -718,10 +594,8 @@ pub fn search_image(
         }
     }
     if search.focal_length {
-        let query = format!(
-            "SELECT image_id FROM image_metadata WHERE Focallength LIKE '%{}%'",
-            search.from_string
-        );
+        let query = Query::select("SELECT image_id FROM image_metadata")
+            .like("Focallength", &search.from_string);
         let (newvideos, newfiles) = search_image_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
             if !used_files.contains(&newfiles[i].filepath) {
@@ -732,10 +606,8 @@ pub fn search_image(
         }
     }
     if search.exposure_time {
-        let query = format!(
-            "SELECT image_id FROM image_metadata WHERE Exposuretime LIKE '%{}%'",
-            search.from_string
-        );
+        let query = Query::select("SELECT image_id FROM image_metadata")
+            .like("Exposuretime", &search.from_string);
         let (newvideos, newfiles) = search_image_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
             if !used_files.contains(&newfiles[i].filepath) {
@@ -746,10 +618,8 @@ pub fn search_image(
         }
     }
     if search.fnumber {
-        let query = format!(
-            "SELECT image_id FROM image_metadata WHERE FNumber LIKE '%{}%'",
-            search.from_string
-        );
+        let query = Query::select("SELECT image_id FROM image_metadata")
+            .like("FNumber", &search.from_string);
         let (newvideos, newfiles) = search_image_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
             if !used_files.contains(&newfiles[i].filepath) {
@@ -760,18 +630,10 @@ pub fn search_image(
         }
     }
     if search.gps_latitude && search.from_value != 0 {
-        let query;
+        let mut query = Query::select("SELECT image_id FROM image_metadata")
+            .filter("GPSLatitude > ?", search.from_value as f64 / 1000000.0);
         if search.to_value != 0 {
-            query = format!(
-                "SELECT image_id FROM image_metadata WHERE GPSLatitude > {} AND GPSLatitude < {}",
-                search.from_value / 1000000,
-                search.to_value / 1000000
-            );
-        } else {
-            query = format!(
-                "SELECT image_id FROM image_metadata WHERE GPSLatitude > {}",
-                search.from_value / 1000000
-            );
+            query = query.filter("GPSLatitude < ?", search.to_value as f64 / 1000000.0);
         }
         let (newvideos, newfiles) = search_image_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
@@ -783,18 +645,10 @@ pub fn search_image(
         }
     }
     if search.gps_longitude && search.from_value != 0 {
-        let query;
+        let mut query = Query::select("SELECT image_id FROM image_metadata")
+            .filter("GPSLongitude > ?", search.from_value as f64 / 1000000.0);
         if search.to_value != 0 {
-            query = format!(
-                "SELECT image_id FROM image_metadata WHERE GPSLongitude > {} AND GPSLongitude < {}",
-                search.from_value / 1000000,
-                search.to_value / 1000000
-            );
-        } else {
-            query = format!(
-                "SELECT image_id FROM image_metadata WHERE GPSLongitude > {}",
-                search.from_value / 1000000
-            );
+            query = query.filter("GPSLongitude < ?", search.to_value as f64 / 1000000.0);
         }
         let (newvideos, newfiles) = search_image_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
@@ -806,18 +660,10 @@ pub fn search_image(
         }
     }
     if search.gps_altitude && search.from_value != 0 {
-        let query;
+        let mut query = Query::select("SELECT image_id FROM image_metadata")
+            .filter("GPSAltitude > ?", search.from_value as f64 / 1000000.0);
         if search.to_value != 0 {
-            query = format!(
-                "SELECT image_id FROM image_metadata WHERE GPSAltitude > {} AND GPSAltitude < {}",
-                search.from_value / 1000000,
-                search.to_value / 1000000
-            );
-        } else {
-            query = format!(
-                "SELECT image_id FROM image_metadata WHERE GPSAltitude > {}",
-                search.from_value / 1000000
-            );
+            query = query.filter("GPSAltitude < ?", search.to_value as f64 / 1000000.0);
         }
         let (newvideos, newfiles) = search_image_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
@@ -829,17 +675,10 @@ pub fn search_image(
         }
     }
     if search.release_date && search.from_string.len() != 0 {
-        let query;
+        let mut query = Query::select("SELECT image_id FROM image_metadata")
+            .filter("created > ?", search.from_date);
         if search.to_date != 0 {
-            query = format!(
-                "SELECT image_id FROM image_metadata WHERE created > {} AND created < {}",
-                search.from_date, search.to_date
-            );
-        } else {
-            query = format!(
-                "SELECT image_id FROM image_metadata WHERE created > {}",
-                search.from_date
-            );
+            query = query.filter("created < ?", search.to_date);
         }
         let (newvideos, newfiles) = search_image_metadata(sql_connection.clone(), query);
         for i in 0..newfiles.len() {
@@ -850,24 +689,13 @@ pub fn search_image(
             }
         }
     }
-    if search.tags {
-        let query = format!("SELECT media_id FROM tags_media_map INNER JOIN tags ON tags_media_map.tagmap_id = tags.tag_id WHERE tags.tag LIKE '%{}%'", search.from_string);
-        let (newimages, newfiles) = search_image_metadata(sql_connection.clone(), query);
-        for i in 0..newfiles.len() {
-            if !used_files.contains(&newfiles[i].filepath) {
-                used_files.insert(newfiles[i].filepath.clone());
-                files.push(newfiles[i].clone());
-                images.push(newimages[i].clone());
-            }
-        }
-    }
 
     (images, files)
 }
 
 fn search_file_metadata(
     sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
-    query: String,
+    query: Query,
 ) -> Vec<FileMetadata> {
     let mut files = Vec::new();
     let mut ids = Vec::new();
@@ -880,9 +708,9 @@ fn search_file_metadata(
             return files;
         }
     };
-    match connection.prepare(&query) {
+    match connection.prepare(&query.sql()) {
         Ok(mut statement) => {
-            match statement.query(params![]) {
+            match statement.query(query.params()) {
                 Ok(mut rows) => {
                     loop {
                         match rows.next() {
@@ -929,10 +757,11 @@ fn search_file_metadata(
 
 fn stuff_items(
     sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &Database,
     search: &SearchData,
     data: &crate::scanmetadata::ScanMetaData,
     file: FileMetadata,
-) {
+) -> Option<crate::tab::Item> {
     if !data.special_files_contains(file.filepath.clone()) {
         data.special_files_insert(file.filepath.clone());
         if file.file_type == 2 && search.video {
@@ -945,10 +774,10 @@ fn stuff_items(
                     &metadata,
                     crate::config::IconSizes::default(),
                     data,
-                    sql_connection.clone(),
+                    database,
                     true,
                 );
-                data.items_push(item);
+                return Some(item);
             }
         }
         if file.file_type == 3 && search.audio {
@@ -961,10 +790,10 @@ fn stuff_items(
                     &mut audio,
                     &metadata,
                     crate::config::IconSizes::default(),
-                    sql_connection.clone(),
+                    database,
                     true,
                 );
-                data.items_push(item);
+                return Some(item);
             }
         }
         if file.file_type == 1 && search.image {
@@ -977,13 +806,14 @@ fn stuff_items(
                     &metadata,
                     crate::config::IconSizes::default(),
                     data,
-                    sql_connection.clone(),
+                    database,
                     true,
                 );
-                data.items_push(item);
+                return Some(item);
             }
         }
     }
+    None
 }
 
 /// date has to be in the following format
@@ -998,11 +828,93 @@ pub fn string_to_linux_time(date: &str) -> i64 {
     linuxtime
 }
 
-pub fn search_items(
+/// A file found by a search. Files found in the full-text index bring their rank and a
+/// snippet of the matching text along.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Found {
+    pub file: FileMetadata,
+    pub rank: Option<f64>,
+    pub snippet: Option<String>,
+}
+
+impl Found {
+    fn new(file: FileMetadata) -> Found {
+        Found {
+            file,
+            rank: None,
+            snippet: None,
+        }
+    }
+}
+
+/// Found files in the order they were found, each once and only of the searched media
+/// types.
+struct FoundFiles {
+    found: Vec<Found>,
+    seen: HashSet<i64>,
+    image: bool,
+    video: bool,
+    audio: bool,
+}
+
+impl FoundFiles {
+    fn new(search: &SearchData) -> FoundFiles {
+        FoundFiles {
+            found: Vec::new(),
+            seen: HashSet::new(),
+            image: search.image,
+            video: search.video,
+            audio: search.audio,
+        }
+    }
+
+    fn push(&mut self, found: Found) {
+        let wanted = match found.file.file_type {
+            1 => self.image,
+            2 => self.video,
+            3 => self.audio,
+            _ => false,
+        };
+        if wanted && self.seen.insert(found.file.metadata_id) {
+            self.found.push(found);
+        }
+    }
+
+    fn extend(&mut self, files: Vec<FileMetadata>) {
+        for file in files {
+            self.push(Found::new(file));
+        }
+    }
+}
+
+/// Files matching the query text of a search, see `expression` for the syntax.
+fn query_files(
     sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
     s: &SearchData,
-) -> Vec<crate::tab::Item> {
-    let data = crate::scanmetadata::ScanMetaData::new();
+) -> Vec<Found> {
+    let query = match expression::parse(&s.search_string) {
+        Ok(expression) if s.fuzzy => expression.fuzzy().query(),
+        Ok(expression) => expression.query(),
+        Err(error) => {
+            log::error!("Failed to parse search query {}: {}", s.search_string, error);
+            return Vec::new();
+        }
+    };
+    let mut search = s.to_owned();
+    // without a media type selected all types are searched
+    if !search.image && !search.video && !search.audio {
+        search.image = true;
+        search.video = true;
+        search.audio = true;
+    }
+    let mut found = FoundFiles::new(&search);
+    found.extend(search_file_metadata(sql_connection, query));
+    found.found
+}
+
+/// The search fields with dates and numbers typed as text converted, or None if there
+/// is nothing to search for.
+fn prepare_search(s: &SearchData) -> Option<SearchData> {
     let mut search = s.to_owned();
     // if the search term was entered into the to box switch the boxes
     if search.from_string.trim().len() == 0 && search.to_string.len() > 0 {
@@ -1089,129 +1001,223 @@ pub fn search_items(
         && search.to_date == 0
     {
         log::error!("Please enter some value to search for!");
-        return Vec::new();
+        return None;
     }
     // if we don't search for anything, exit with warning
     if !search.image && !search.video && !search.audio {
         log::error!("Please select some media to search for!");
-        return Vec::new();
+        return None;
     }
+    Some(search)
+}
 
-    let mut items: Vec<crate::tab::Item> = Vec::new();
-    if search.video {
-        let (mut newmetadata, newfiles) = search_video(sql_connection.clone(), &search);
-        for i in 0..newfiles.len() {
-            if newfiles[i].file_type != 2 {
-                continue;
-            }
-            if !data.special_files_contains(newfiles[i].filepath.clone()) {
-                data.special_files_insert(newfiles[i].filepath.clone());
-                if let Ok(metadata) = std::fs::metadata(newfiles[i].filepath.clone()) {
-                    let item = crate::parsers::item_from_nfo(
-                        PathBuf::new(),
-                        &mut newmetadata[i],
-                        &metadata,
-                        crate::config::IconSizes::default(),
-                        &data,
-                        sql_connection.clone(),
-                        true,
-                    );
-                    items.push(item);
-                }
-            }
+/// Files matching a search, looked up in the database only. This is cheap next to
+/// building their items, so the number of results is known before the first page of
+/// them is shown.
+pub fn search_files(
+    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    s: &SearchData,
+) -> Vec<Found> {
+    // a query replaces the search fields, only the media types still apply
+    if !s.search_string.trim().is_empty() {
+        return query_files(sql_connection, s);
+    }
+    let Some(search) = prepare_search(s) else {
+        return Vec::new();
+    };
+    let mut found = FoundFiles::new(&search);
+    // text fields are looked up in the full-text index, best matches first
+    let columns = fts::Column::from_search(&search);
+    if search.from_string.len() > 0 && !columns.is_empty() {
+        let connection = match sql_connection.lock() {
+            Ok(conn) => conn,
+            Err(error) => {
+                log::error!("Failed to lock sql connection for use! {}", error);
+                return found.found;
+            }
+        };
+        let hits = fts::search(&connection, &search.from_string, &columns);
+        drop(connection);
+        for hit in hits {
+            found.push(Found {
+                file: file_by_id(sql_connection.clone(), hit.id as i64),
+                rank: Some(hit.rank),
+                snippet: Some(hit.snippet),
+            });
+        }
+        // names with typos, which the full-text index doesn't find
+        if let Some(expression) = similar_expression(&search) {
+            found.extend(search_file_metadata(
+                sql_connection.clone(),
+                expression.query(),
+            ));
         }
     }
+    if search.video {
+        let (_, files) = search_video(sql_connection.clone(), &search);
+        found.extend(files);
+    }
     if search.audio {
-        let (mut newmetadata, newfiles) = search_audio(sql_connection.clone(), &search);
-        for i in 0..newfiles.len() {
-            if !data.special_files_contains(newfiles[i].filepath.clone()) {
-                if newfiles[i].file_type != 3 {
-                    continue;
-                }
-                data.special_files_insert(newfiles[i].filepath.clone());
-                if let Ok(metadata) = std::fs::metadata(newfiles[i].filepath.clone()) {
-                    let item = crate::parsers::item_from_audiotags(
-                        newfiles[i].filepath.clone(),
-                        &data,
-                        &mut newmetadata[i],
-                        &metadata,
-                        crate::config::IconSizes::default(),
-                        sql_connection.clone(),
-                        true,
-                    );
-                    items.push(item);
-                }
-            }
-        }
+        let (_, files) = search_audio(sql_connection.clone(), &search);
+        found.extend(files);
     }
     if search.image {
-        let (mut newmetadata, newfiles) = search_image(sql_connection.clone(), &search);
-        for i in 0..newfiles.len() {
-            if newfiles[i].file_type != 1 {
-                continue;
-            }
-            if !data.special_files_contains(newfiles[i].filepath.clone()) {
-                if newfiles[i].file_type != 3 {
-                    continue;
-                }
-                data.special_files_insert(newfiles[i].filepath.clone());
-                if let Ok(metadata) = std::fs::metadata(newfiles[i].filepath.clone()) {
-                    let item = crate::parsers::item_from_exif(
-                        newfiles[i].filepath.clone(),
-                        &mut newmetadata[i],
-                        &metadata,
-                        crate::config::IconSizes::default(),
-                        &data,
-                        sql_connection.clone(),
-                        true,
-                    );
-                    items.push(item);
-                }
-            }
-        }
+        let (_, files) = search_image(sql_connection.clone(), &search);
+        found.extend(files);
     }
     if search.creation_date && search.from_string.len() != 0 {
-        let query;
+        let mut query = Query::select("SELECT metadata_id FROM file_metadata")
+            .filter("creation_time > ?", search.from_date);
         if search.to_date != 0 {
-            query = format!("SELECT metadata_id FROM file_metadata WHERE creation_time > {} AND creation_time < {}", search.from_date, search.to_date);
-        } else {
-            query = format!(
-                "SELECT metadata_id FROM file_metadata WHERE creation_time > {}",
-                search.from_date
-            );
-        }
-        let newfiles: Vec<FileMetadata> = search_file_metadata(sql_connection.clone(), query);
-        for file in newfiles {
-            stuff_items(sql_connection.clone(), &search, &data, file);
+            query = query.filter("creation_time < ?", search.to_date);
         }
+        found.extend(search_file_metadata(sql_connection.clone(), query));
     }
     if search.modification_date && search.from_string.len() != 0 {
-        let query;
+        let mut query = Query::select("SELECT metadata_id FROM file_metadata")
+            .filter("modification_time > ?", search.from_date);
         if search.to_date != 0 {
-            query = format!("SELECT metadata_id FROM file_metadata WHERE modification_time > {} AND modification_time < {}", search.from_date, search.to_date);
-        } else {
-            query = format!(
-                "SELECT metadata_id FROM file_metadata WHERE modification_time > {}",
-                search.from_date
-            );
-        }
-        let newfiles: Vec<FileMetadata> = search_file_metadata(sql_connection.clone(), query);
-        for file in newfiles {
-            stuff_items(sql_connection.clone(), &search, &data, file);
+            query = query.filter("modification_time < ?", search.to_date);
         }
+        found.extend(search_file_metadata(sql_connection.clone(), query));
     }
     if search.filepath {
-        let query = format!(
-            "SELECT metadata_id FROM file_metadata WHERE filepath LIKE '%{}%'",
-            search.from_string
-        );
-        let newfiles: Vec<FileMetadata> = search_file_metadata(sql_connection.clone(), query);
-        for file in newfiles {
-            stuff_items(sql_connection.clone(), &search, &data, file);
-        }
+        let path = search.from_string.clone();
+        let condition = if search.fuzzy {
+            expression::Condition::Similar(path)
+        } else {
+            expression::Condition::Text(path)
+        };
+        let query = expression::Expression::Field(SearchType::FilePath, condition).query();
+        found.extend(search_file_metadata(sql_connection.clone(), query));
+    }
+    if let Some(expression) = technical_expression(s) {
+        found.extend(search_file_metadata(
+            sql_connection.clone(),
+            expression.query(),
+        ));
+    }
+    found.found
+}
+
+/// Items of found files. `controller` is checked before each one, so building a page of
+/// results can be paused or stopped; the items built until then are returned.
+pub fn found_items(
+    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &Database,
+    found: &[Found],
+    controller: &Controller,
+) -> Vec<crate::tab::Item> {
+    let data = crate::scanmetadata::ScanMetaData::new();
+    // the files are already narrowed down to the searched media types
+    let search = SearchData {
+        image: true,
+        video: true,
+        audio: true,
+        ..Default::default()
+    };
+    let mut items = Vec::new();
+    for found in found {
+        if let Err(error) = controller.check() {
+            log::info!("Stopped building search results: {}", error);
+            break;
+        }
+        let item = stuff_items(
+            sql_connection.clone(),
+            database,
+            &search,
+            &data,
+            found.file.clone(),
+        );
+        if let Some(mut item) = item {
+            item.search_rank = found.rank;
+            item.search_snippet = found.snippet.clone();
+            items.push(item);
+        }
+    }
+    items
+}
+
+/// All items matching a search at once, for callers which need the whole result.
+pub fn search_items(
+    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &Database,
+    s: &SearchData,
+) -> Vec<crate::tab::Item> {
+    let found = search_files(sql_connection.clone(), s);
+    found_items(sql_connection, database, &found, &Controller::new())
+}
+
+/// The checked text fields of a typo tolerant search as a query.
+fn similar_expression(s: &SearchData) -> Option<expression::Expression> {
+    if !s.fuzzy || s.from_string.trim().is_empty() {
+        return None;
+    }
+    let fields = [
+        (s.title, SearchType::Title),
+        (s.description, SearchType::Description),
+        (s.actor, SearchType::Actor),
+        (s.director, SearchType::Director),
+        (s.artist, SearchType::Artist),
+        (s.album_artist, SearchType::AlbumArtist),
+        (s.album, SearchType::Album),
+        (s.composer, SearchType::Composer),
+        (s.genre, SearchType::Genre),
+        (s.tags, SearchType::Tag),
+    ];
+    fields
+        .into_iter()
+        .filter(|(checked, _)| *checked)
+        .map(|(_, field)| {
+            let condition = expression::Condition::Similar(s.from_string.trim().to_string());
+            expression::Expression::Field(field, condition)
+        })
+        .reduce(|left, right| expression::Expression::Or(Box::new(left), Box::new(right)))
+}
+
+/// The technical fields of a search as a query, so "720p", "4k", "192k" or "portrait"
+/// typed into the from and to boxes are understood like in the query text.
+fn technical_expression(s: &SearchData) -> Option<expression::Expression> {
+    let from = s.from_string.trim().replace('"', "");
+    let to = s.to_string.trim().replace('"', "");
+    let value = match (from.is_empty(), to.is_empty()) {
+        (true, true) => return None,
+        (false, true) => from.clone(),
+        (true, false) => to.clone(),
+        (false, false) => format!("{}..{}", from, to),
+    };
+    let fields = [
+        (s.resolution, SearchType::Resolution),
+        (s.frame_rate, SearchType::FrameRate),
+        (s.bitrate, SearchType::Bitrate),
+        (s.codec, SearchType::Codec),
+        (s.aspect_ratio, SearchType::AspectRatio),
+    ];
+    let mut terms = Vec::new();
+    for (checked, field) in fields {
+        if !checked {
+            continue;
+        }
+        if let Some(name) = expression::field_name(field) {
+            // a codec is a single name, not a range
+            let value = match field {
+                SearchType::Codec if !from.is_empty() => &from,
+                SearchType::Codec => &to,
+                _ => &value,
+            };
+            terms.push(format!("{}:\"{}\"", name, value));
+        }
+    }
+    if terms.is_empty() {
+        return None;
+    }
+    match expression::parse(&terms.join(" OR ")) {
+        Ok(expression) => Some(expression),
+        Err(error) => {
+            log::error!("Failed to search technical properties: {}", error);
+            None
+        }
     }
-
-    items
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, PartialOrd, Serialize)]
@@ -1220,7 +1226,8 @@ pub struct Tag {
     pub tag: String,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, PartialOrd, Serialize)]
+#[serde(default)]
 pub struct Chapter {
     pub title: String,
     pub start: f32,
@@ -1234,6 +1241,39 @@ impl std::fmt::Display for Chapter {
     }
 }
 
+/// Part an actor plays in a video, from the `<actor>` elements of its NFO file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, PartialOrd, Serialize)]
+#[serde(default)]
+pub struct Role {
+    pub actor: String,
+    pub role: String,
+    /// Picture of the actor, usually a URL.
+    pub thumb: String,
+}
+
+/// Rating of a video on one site, e.g. "imdb" or "themoviedb".
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, PartialOrd, Serialize)]
+#[serde(default)]
+pub struct Rating {
+    pub source: String,
+    pub value: f32,
+    /// Best possible value, 10 unless the NFO file says otherwise.
+    pub max: f32,
+    pub votes: u32,
+    /// The rating Kodi shows.
+    pub default: bool,
+}
+
+/// Id of a video on a site like IMDb, TMDB or TVDB.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, PartialOrd, Serialize)]
+#[serde(default)]
+pub struct UniqueId {
+    /// "imdb", "tmdb", "tvdb", ...
+    pub kind: String,
+    pub value: String,
+    pub default: bool,
+}
+
 pub fn fill_chapters(
     chapters: Vec<crate::sql::Chapter>,
     duration: u32,
@@ -1264,7 +1304,8 @@ pub fn fill_chapters(
     (v, s)
 }
 
-#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Hash)]
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Eq, Hash, Serialize)]
+#[serde(default)]
 pub struct CollectionMetadata {
     pub id: u32,
     pub name: String,
@@ -1293,7 +1334,8 @@ impl Default for CollectionMetadata {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Hash)]
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Eq, Hash, Serialize)]
+#[serde(default)]
 pub struct EpisodeMetadata {
     pub series: i32,
     pub episode: i32,
@@ -1340,7 +1382,8 @@ pub fn insert_collection(
     };
     metadata.id = file_id;
     let path = crate::parsers::osstr_to_string(metadata.path.clone().into_os_string());
-    match connection.execute(
+    match execute_cached(
+        &connection,
         "INSERT INTO collections (file_id, collection_name, poster, description, path, thumb) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
         params![
             &metadata.id,
@@ -1365,7 +1408,7 @@ pub fn insert_collection(
     }
     let mut collection_id = 0;
     let query = "SELECT last_insert_rowid()";
-    match connection.prepare(query) {
+    match connection.prepare_cached(query) {
         Ok(mut statement) => match statement.query(params![]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
@@ -1386,7 +1429,8 @@ pub fn insert_collection(
     }
     for i in 0..metadata.episodes.len() {
         let path = crate::parsers::osstr_to_string(metadata.episodes[i].path.clone().into_os_string());
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO collections_map (collection_id, episode_id, series, episode, title, path, poster, thumb) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             params![
                 &collection_id,
@@ -1432,7 +1476,7 @@ pub fn delete_collection(
         )]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
-                    let s_opt = row.get(1);
+                    let s_opt = row.get(0);
                     if s_opt.is_ok() {
                         collection_id = s_opt.unwrap();
                     }
@@ -1759,7 +1803,8 @@ pub fn collection(
     v
 }
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
+#[serde(default)]
 pub struct VideoMetadata {
     pub id: u32,
     pub name: String,
@@ -1775,6 +1820,8 @@ pub struct VideoMetadata {
     pub width: u32,
     pub height: u32,
     pub framerate: f32,
+    /// Short ffmpeg name of the first video stream's codec, e.g. "h264".
+    pub codec: String,
     pub description: String,
     pub director: Vec<String>,
     pub actors: Vec<String>,
@@ -1782,6 +1829,37 @@ pub struct VideoMetadata {
     pub tags: Vec<Tag>,
     pub season: i32,
     pub episode: i32,
+    /// Last episode of a file with several, like `S01E01-E03`, 0 for a single one.
+    pub last_episode: i32,
+    /// Rating out of 10 from the NFO file, 0 if unrated.
+    pub rating: f32,
+    /// Title in the original language.
+    pub original_title: String,
+    /// Title the video is sorted by, e.g. "Godfather, The".
+    pub sort_title: String,
+    pub tagline: String,
+    /// Age rating, e.g. "Rated PG-13" or "FSK 12".
+    pub mpaa: String,
+    pub genres: Vec<String>,
+    pub studios: Vec<String>,
+    pub countries: Vec<String>,
+    /// Screenwriters, `<credits>` in the NFO file.
+    pub writers: Vec<String>,
+    /// Parts and pictures of the `actors` that have one.
+    pub roles: Vec<Role>,
+    pub ratings: Vec<Rating>,
+    pub unique_ids: Vec<UniqueId>,
+    /// Movie collection the video belongs to, `<set>` in the NFO file.
+    pub movie_set: String,
+    pub movie_set_overview: String,
+    /// Cut of the movie, e.g. "Director's Cut".
+    pub edition: String,
+    /// Where the video was taken from, e.g. "BluRay" or "WEB-DL".
+    pub source: String,
+    /// When the file was first scanned, in seconds since the epoch, 0 if unknown. It
+    /// belongs to this library, so it is not exported.
+    #[serde(skip)]
+    pub added: u64,
 }
 
 impl Default for VideoMetadata {
@@ -1802,6 +1880,7 @@ impl Default for VideoMetadata {
             width: 0,
             height: 0,
             framerate: 0.0,
+            codec: String::new(),
             description: String::new(),
             director: Vec::new(),
             actors: Vec::new(),
@@ -1809,6 +1888,24 @@ impl Default for VideoMetadata {
             tags: Vec::new(),
             season: 0,
             episode: 0,
+            last_episode: 0,
+            rating: 0.0,
+            original_title: String::new(),
+            sort_title: String::new(),
+            tagline: String::new(),
+            mpaa: String::new(),
+            genres: Vec::new(),
+            studios: Vec::new(),
+            countries: Vec::new(),
+            writers: Vec::new(),
+            roles: Vec::new(),
+            ratings: Vec::new(),
+            unique_ids: Vec::new(),
+            movie_set: String::new(),
+            movie_set_overview: String::new(),
+            edition: String::new(),
+            source: String::new(),
+            added: 0,
         }
     }
 }
@@ -1828,9 +1925,10 @@ pub fn insert_video(
         }
     };
     metadata.id = video_id;
-    match connection.execute(
-        "INSERT INTO video_metadata (video_id, name, title, released, poster, thumb, duration, width, height, framerate, description) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-        params![&metadata.id, &metadata.name, &metadata.title, &metadata.date, &metadata.poster, &metadata.thumb, &metadata.duration, &metadata.width, &metadata.height, &metadata.framerate, &metadata.description],
+    match execute_cached(
+        &connection,
+        "INSERT INTO video_metadata (video_id, name, title, released, poster, thumb, duration, width, height, framerate, description, codec, rating) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![&metadata.id, &metadata.name, &metadata.title, &metadata.date, &metadata.poster, &metadata.thumb, &metadata.duration, &metadata.width, &metadata.height, &metadata.framerate, &metadata.description, &metadata.codec, &metadata.rating],
     ) {
         Ok(_retval) => {}, //log::warn!("Inserted {} video with ID {} and location {} into candidates.", video.id, video.index, candidate_id),
         Err(error) => {
@@ -1843,7 +1941,7 @@ pub fn insert_video(
     }
     let mut video_id = 0;
     let query = "SELECT last_insert_rowid()";
-    match connection.prepare(query) {
+    match connection.prepare_cached(query) {
         Ok(mut statement) => match statement.query(params![]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
@@ -1863,7 +1961,8 @@ pub fn insert_video(
         }
     }
     for i in 0..metadata.subtitles.len() {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO subtitles (video_id, subpath) VALUES (?1, ?2)",
             params![&video_id, &metadata.subtitles[i]],
         ) {
@@ -1875,7 +1974,8 @@ pub fn insert_video(
         }
     }
     for i in 0..metadata.audiolangs.len() {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO audiolangs (video_id, audiolang) VALUES (?1, ?2)",
             params![&video_id, &metadata.audiolangs[i]],
         ) {
@@ -1887,7 +1987,8 @@ pub fn insert_video(
         }
     }
     for i in 0..metadata.sublangs.len() {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO sublangs (video_id, sublang) VALUES (?1, ?2)",
             params![&video_id, &metadata.sublangs[i]],
         ) {
@@ -1899,7 +2000,8 @@ pub fn insert_video(
         }
     }
     for i in 0..metadata.chapters.len() {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO chapters (video_id, title, start, end) VALUES (?1, ?2, ?3, ?4)",
             params![
                 &video_id,
@@ -1918,11 +2020,11 @@ pub fn insert_video(
     for i in 0..metadata.director.len() {
         let mut director_id = -1;
         let query = "SELECT person_id FROM people WHERE person_name = ?1";
-        match connection.prepare(query) {
+        match connection.prepare_cached(query) {
             Ok(mut statement) => match statement.query(params![&metadata.director[i]]) {
                 Ok(mut rows) => {
                     while let Ok(Some(row)) = rows.next() {
-                        let s_opt = row.get(1);
+                        let s_opt = row.get(0);
                         if s_opt.is_ok() {
                             director_id = s_opt.unwrap();
                         }
@@ -1941,7 +2043,8 @@ pub fn insert_video(
             }
         }
         if director_id == -1 {
-            match connection.execute(
+            match execute_cached(
+                &connection,
                 "INSERT INTO people (person_name) VALUES (?1)",
                 params![&metadata.director[i]],
             ) {
@@ -1952,7 +2055,7 @@ pub fn insert_video(
             }
 
             let query = "SELECT last_insert_rowid()";
-            match connection.prepare(query) {
+            match connection.prepare_cached(query) {
                 Ok(mut statement) => match statement.query(params![]) {
                     Ok(mut rows) => {
                         while let Ok(Some(row)) = rows.next() {
@@ -1975,7 +2078,8 @@ pub fn insert_video(
         if director_id == -1 {
             continue;
         }
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO directors (director_id, video_id) VALUES (?1, ?2)",
             params![&director_id, &video_id],
         ) {
@@ -1990,11 +2094,11 @@ pub fn insert_video(
     for i in 0..metadata.actors.len() {
         let mut actor_id = -1;
         let query = "SELECT person_id FROM people WHERE person_name = ?1";
-        match connection.prepare(query) {
+        match connection.prepare_cached(query) {
             Ok(mut statement) => match statement.query(params![&metadata.actors[i]]) {
                 Ok(mut rows) => {
                     while let Ok(Some(row)) = rows.next() {
-                        let s_opt = row.get(1);
+                        let s_opt = row.get(0);
                         if s_opt.is_ok() {
                             actor_id = s_opt.unwrap();
                         }
@@ -2014,7 +2118,8 @@ pub fn insert_video(
             }
         }
         if actor_id == -1 {
-            match connection.execute(
+            match execute_cached(
+                &connection,
                 "INSERT INTO people (person_name) VALUES (?1)",
                 params![&metadata.actors[i]],
             ) {
@@ -2026,7 +2131,7 @@ pub fn insert_video(
             }
 
             let query = "SELECT last_insert_rowid()";
-            match connection.prepare(query) {
+            match connection.prepare_cached(query) {
                 Ok(mut statement) => match statement.query(params![]) {
                     Ok(mut rows) => {
                         while let Ok(Some(row)) = rows.next() {
@@ -2049,7 +2154,8 @@ pub fn insert_video(
         if actor_id == -1 {
             continue;
         }
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO actors (actor_id, video_id) VALUES (?1, ?2)",
             params![&actor_id, &video_id],
         ) {
@@ -2060,6 +2166,8 @@ pub fn insert_video(
             }
         }
     }
+    nfo::insert(&connection, metadata);
+    fts::index_video(&connection, metadata);
     drop(connection);
     for i in 0..metadata.tags.len() {
         let tag_id = insert_tag(
@@ -2153,7 +2261,7 @@ pub fn delete_tag(
         Ok(mut statement) => match statement.query(params![&tag]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
-                    let s_opt = row.get(1);
+                    let s_opt = row.get(0);
                     if s_opt.is_ok() {
                         tag_id = s_opt.unwrap();
                     }
@@ -2168,20 +2276,42 @@ pub fn delete_tag(
             return;
         }
     }
-    if tag_id == -1 {
+    if tag_id != -1 {
+        let mut media_ids: Vec<u32> = Vec::new();
+        let query = "SELECT media_id FROM tags_media_map WHERE tagmap_id = ?1";
+        match connection.prepare(query) {
+            Ok(mut statement) => match statement.query(params![&tag_id]) {
+                Ok(mut rows) => {
+                    while let Ok(Some(row)) = rows.next() {
+                        if let Ok(media_id) = row.get(0) {
+                            media_ids.push(media_id);
+                        }
+                    }
+                }
+                Err(err) => {
+                    log::error!("could not read line from tags_media_map database: {}", err);
+                }
+            },
+            Err(error) => {
+                log::error!("Failed to get media for tag {} from database: {}", tag, error);
+            }
+        }
         let ret = connection.execute("DELETE FROM tags WHERE tag_id = ?1", params![&tag_id]);
         if ret.is_err() {
             log::error!("Failed to delete candidate {}!", tag_id);
             return;
         }
         let ret = connection.execute(
-            "DELETE FROM tags_media_map WHERE tag_id = ?1",
+            "DELETE FROM tags_media_map WHERE tagmap_id = ?1",
             params![&tag_id],
         );
         if ret.is_err() {
             log::error!("Failed to delete candidate {}!", tag_id);
             return;
         }
+        for media_id in media_ids {
+            fts::refresh_tags(&connection, media_id);
+        }
     }
 }
 
@@ -2199,11 +2329,11 @@ pub fn insert_tag(
         }
     };
     let query = "SELECT tag_id FROM tags WHERE tag = ?1";
-    match connection.prepare(query) {
+    match connection.prepare_cached(query) {
         Ok(mut statement) => match statement.query(params![&tag]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
-                    let s_opt = row.get(1);
+                    let s_opt = row.get(0);
                     if s_opt.is_ok() {
                         tag_id = s_opt.unwrap();
                     }
@@ -2218,7 +2348,7 @@ pub fn insert_tag(
         }
     }
     if tag_id == -1 {
-        match connection.execute("INSERT INTO tags (tag) VALUES (?1)", params![&tag]) {
+        match execute_cached(&connection, "INSERT INTO tags (tag) VALUES (?1)", params![&tag]) {
             Ok(_retval) => {} //log::warn!("Inserted {} video with ID {} and location {} into candidates.", video.id, video.index, candidate_id),
             Err(error) => {
                 log::error!("Failed to insert tag into  database: {}", error);
@@ -2227,7 +2357,7 @@ pub fn insert_tag(
         }
 
         let query = "SELECT last_insert_rowid()";
-        match connection.prepare(query) {
+        match connection.prepare_cached(query) {
             Ok(mut statement) => match statement.query(params![]) {
                 Ok(mut rows) => {
                     while let Ok(Some(row)) = rows.next() {
@@ -2248,7 +2378,8 @@ pub fn insert_tag(
         }
     }
     if media_id > 0 {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO tags_media_map (media_id, tagmap_id) VALUES (?1, ?2)",
             params![&media_id, tag_id],
         ) {
@@ -2261,6 +2392,7 @@ pub fn insert_tag(
                 return tag_id;
             }
         }
+        fts::refresh_tags(&connection, media_id);
     }
 
     tag_id
@@ -2279,7 +2411,8 @@ pub fn insert_media_tag(
         }
     };
     if media_id > 0 {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO tags_media_map (media_id, tagmap_id) VALUES (?1, ?2)",
             params![&media_id, tag_id],
         ) {
@@ -2292,6 +2425,7 @@ pub fn insert_media_tag(
                 return;
             }
         }
+        fts::refresh_tags(&connection, media_id);
     }
 }
 
@@ -2315,7 +2449,7 @@ pub fn delete_video(
         Ok(mut statement) => match statement.query(params![&metadata.path]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
-                    let s_opt = row.get(1);
+                    let s_opt = row.get(0);
                     if s_opt.is_ok() {
                         video_id = s_opt.unwrap();
                     }
@@ -2343,6 +2477,8 @@ pub fn delete_video(
         log::error!("Failed to delete candidate {}!", video_id);
         return;
     }
+    fts::remove(&connection, video_id);
+    nfo::remove(&connection, video_id as i64);
     // clear the entry in the candidates list without deleting it
     let ret = connection.execute(
         "DELETE FROM file_metadata WHERE filepath = ?1",
@@ -2361,8 +2497,10 @@ pub fn update_video(
     statdata: &std::fs::Metadata,
     data: &crate::scanmetadata::ScanMetaData,
 ) {
+    let added = file(sql_connection.clone(), &metadata.path).added;
     delete_video(sql_connection.clone(), metadata, data);
     insert_video(sql_connection.clone(), metadata, statdata, data);
+    restore_added(sql_connection.clone(), &metadata.path, added);
 }
 
 pub fn video_by_id(
@@ -2382,7 +2520,7 @@ pub fn video_by_id(
     };
 
     v.path = filepath.to_string();
-    let query = "SELECT name, title, released, poster, duration, width, height, framerate, description, thumb FROM video_metadata WHERE video_id = ?1";
+    let query = "SELECT name, title, released, poster, duration, width, height, framerate, description, thumb, codec, rating FROM video_metadata WHERE video_id = ?1";
     match connection.prepare(query) {
         Ok(mut statement) => {
             match statement.query(params![&video_id]) {
@@ -2463,6 +2601,20 @@ pub fn video_by_id(
                                         continue;
                                     }
                                 }
+                                match row.get::<_, Option<String>>(10) {
+                                    Ok(val) => v.codec = val.unwrap_or_default(),
+                                    Err(error) => {
+                                        log::error!("Failed to read codec for video: {}", error);
+                                        continue;
+                                    }
+                                }
+                                match row.get(11) {
+                                    Ok(val) => v.rating = val,
+                                    Err(error) => {
+                                        log::error!("Failed to read rating for video: {}", error);
+                                        continue;
+                                    }
+                                }
                             }
                             Ok(None) => {
                                 //log::warn!("No data read from indices.");
@@ -2669,6 +2821,7 @@ pub fn video_by_id(
             log::error!("could not prepare SQL statement: {}", err);
         }
     }
+    nfo::read(&connection, video_id, &mut v);
     let query = "SELECT tag_id, tag FROM tags 
                         INNER JOIN tags_media_map 
                         ON tags_media_map.tagmap_id = tags.tag_id 
@@ -2741,7 +2894,8 @@ pub fn video(
     };
     v.path = filepath.to_string();
     let video_id = filedata.metadata_id;
-    let query = "SELECT name, title, released, poster, duration, width, height, framerate, description, thumb FROM video_metadata WHERE video_id = ?1";
+    v.added = filedata.added;
+    let query = "SELECT name, title, released, poster, duration, width, height, framerate, description, thumb, codec, rating FROM video_metadata WHERE video_id = ?1";
     match connection.prepare(query) {
         Ok(mut statement) => {
             match statement.query(params![&video_id]) {
@@ -2822,6 +2976,20 @@ pub fn video(
                                         continue;
                                     }
                                 }
+                                match row.get::<_, Option<String>>(10) {
+                                    Ok(val) => v.codec = val.unwrap_or_default(),
+                                    Err(error) => {
+                                        log::error!("Failed to read codec for video: {}", error);
+                                        continue;
+                                    }
+                                }
+                                match row.get(11) {
+                                    Ok(val) => v.rating = val,
+                                    Err(error) => {
+                                        log::error!("Failed to read rating for video: {}", error);
+                                        continue;
+                                    }
+                                }
                             }
                             Ok(None) => {
                                 //log::warn!("No data read from indices.");
@@ -3095,6 +3263,7 @@ pub fn video(
             log::error!("could not prepare SQL statement: {}", err);
         }
     }
+    nfo::read(&connection, video_id, &mut v);
     let query = "SELECT tag_id, tag FROM tags 
                         INNER JOIN tags_media_map 
                         ON tags_media_map.tagmap_id = tags.tag_id 
@@ -3152,7 +3321,8 @@ pub fn video(
     v
 }
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
+#[serde(default)]
 pub struct AudioMetadata {
     pub id: u32,
     pub name: String,
@@ -3164,7 +3334,10 @@ pub struct AudioMetadata {
     pub genre: String,
     pub composer: String,
     pub track_id: u32,
+    /// Disc of a multi-disc album, 0 if unknown.
+    pub disc: u32,
     pub duration: u32,
+    /// kbit/s
     pub bitrate: f32,
     pub album: String,
     pub artist: Vec<String>,
@@ -3172,6 +3345,10 @@ pub struct AudioMetadata {
     pub chapters: Vec<Chapter>,
     pub lyrics: Vec<String>,
     pub tags: Vec<Tag>,
+    /// When the file was first scanned, in seconds since the epoch, 0 if unknown. It
+    /// belongs to this library, so it is not exported.
+    #[serde(skip)]
+    pub added: u64,
 }
 
 impl Default for AudioMetadata {
@@ -3187,6 +3364,7 @@ impl Default for AudioMetadata {
             genre: String::new(),
             composer: String::new(),
             track_id: 0,
+            disc: 0,
             duration: 0,
             bitrate: 0.0,
             album: String::new(),
@@ -3195,6 +3373,7 @@ impl Default for AudioMetadata {
             chapters: Vec::new(),
             lyrics: Vec::new(),
             tags: Vec::new(),
+            added: 0,
         }
     }
 }
@@ -3214,9 +3393,10 @@ pub fn insert_audio(
         }
     };
     metadata.id = audio_id;
-    match connection.execute(
-        "INSERT INTO audio_metadata (audio_id, name, title, released, poster, thumb, genre, composer, track_id, duration, bitrate) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-        params![&metadata.id, &metadata.name, &metadata.title, &metadata.date, &metadata.poster, &metadata.thumb, &metadata.genre, &metadata.composer, &metadata.track_id, &metadata.duration, &metadata.bitrate],
+    match execute_cached(
+        &connection,
+        "INSERT INTO audio_metadata (audio_id, name, title, released, poster, thumb, genre, composer, track_id, duration, bitrate, disc) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![&metadata.id, &metadata.name, &metadata.title, &metadata.date, &metadata.poster, &metadata.thumb, &metadata.genre, &metadata.composer, &metadata.track_id, &metadata.duration, &metadata.bitrate, &metadata.disc],
     ) {
         Ok(_retval) => {}, //log::warn!("Inserted {} video with ID {} and location {} into candidates.", video.id, video.index, candidate_id),
         Err(error) => {
@@ -3230,7 +3410,7 @@ pub fn insert_audio(
     // insert album name into table and get the id
     let mut album_id = -1;
     let query = "SELECT album_id FROM albums WHERE album_name = ?1";
-    match connection.prepare(query) {
+    match connection.prepare_cached(query) {
         Ok(mut statement) => match statement.query(params![&metadata.album]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
@@ -3253,7 +3433,8 @@ pub fn insert_audio(
         }
     }
     if album_id == -1 {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO albums (album_name) VALUES (?1)",
             params![&metadata.album],
         ) {
@@ -3264,7 +3445,7 @@ pub fn insert_audio(
         }
 
         let query = "SELECT last_insert_rowid()";
-        match connection.prepare(query) {
+        match connection.prepare_cached(query) {
             Ok(mut statement) => match statement.query(params![]) {
                 Ok(mut rows) => {
                     while let Ok(Some(row)) = rows.next() {
@@ -3285,7 +3466,8 @@ pub fn insert_audio(
     }
     // insert the album id and map it to the audio file
     if album_id != -1 {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO album_audio_map (audio_id, album_id) VALUES (?1, ?2)",
             params![&audio_id, &album_id],
         ) {
@@ -3300,7 +3482,7 @@ pub fn insert_audio(
     for i in 0..metadata.artist.len() {
         let mut artist_id = -1;
         let query = "SELECT artist_id FROM artists WHERE artist_name = ?1";
-        match connection.prepare(query) {
+        match connection.prepare_cached(query) {
             Ok(mut statement) => match statement.query(params![&metadata.artist[i]]) {
                 Ok(mut rows) => {
                     while let Ok(Some(row)) = rows.next() {
@@ -3323,7 +3505,8 @@ pub fn insert_audio(
             }
         }
         if artist_id == -1 {
-            match connection.execute(
+            match execute_cached(
+                &connection,
                 "INSERT INTO artists (artist_name) VALUES (?1)",
                 params![&metadata.artist[i]],
             ) {
@@ -3334,7 +3517,7 @@ pub fn insert_audio(
             }
 
             let query = "SELECT last_insert_rowid()";
-            match connection.prepare(query) {
+            match connection.prepare_cached(query) {
                 Ok(mut statement) => match statement.query(params![]) {
                     Ok(mut rows) => {
                         while let Ok(Some(row)) = rows.next() {
@@ -3357,7 +3540,8 @@ pub fn insert_audio(
             continue;
         }
         // map the artist id to the audio id
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO artist_audio_map (audio_id, artist_id) VALUES (?1, ?2)",
             params![&audio_id, &artist_id],
         ) {
@@ -3372,7 +3556,7 @@ pub fn insert_audio(
     for i in 0..metadata.albumartist.len() {
         let mut albumartist_id = -1;
         let query = "SELECT artist_id FROM artists WHERE artist_name = ?1";
-        match connection.prepare(query) {
+        match connection.prepare_cached(query) {
             Ok(mut statement) => match statement.query(params![&metadata.albumartist[i]]) {
                 Ok(mut rows) => {
                     while let Ok(Some(row)) = rows.next() {
@@ -3395,7 +3579,8 @@ pub fn insert_audio(
             }
         }
         if albumartist_id == -1 {
-            match connection.execute(
+            match execute_cached(
+                &connection,
                 "INSERT INTO artists (artist_name) VALUES (?1)",
                 params![&metadata.albumartist[i]],
             ) {
@@ -3406,7 +3591,7 @@ pub fn insert_audio(
             }
 
             let query = "SELECT last_insert_rowid()";
-            match connection.prepare(query) {
+            match connection.prepare_cached(query) {
                 Ok(mut statement) => match statement.query(params![]) {
                     Ok(mut rows) => {
                         while let Ok(Some(row)) = rows.next() {
@@ -3429,7 +3614,8 @@ pub fn insert_audio(
             continue;
         }
         // map the album artist id to the audio id
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO albumartist_audio_map (audio_id, albumartist_id) VALUES (?1, ?2)",
             params![&audio_id, &albumartist_id],
         ) {
@@ -3442,7 +3628,8 @@ pub fn insert_audio(
     }
     //  insert chapters into table
     for i in 0..metadata.chapters.len() {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO indexes (audio_id, title, start, end) VALUES (?1, ?2, ?3, ?4)",
             params![
                 &audio_id,
@@ -3460,7 +3647,8 @@ pub fn insert_audio(
     }
     // insert external lyrics in to table
     for i in 0..metadata.lyrics.len() {
-        match connection.execute(
+        match execute_cached(
+            &connection,
             "INSERT INTO lyrics (audio_id, lyricsfile) VALUES (?1, ?2)",
             params![&audio_id, &metadata.lyrics[i]],
         ) {
@@ -3471,6 +3659,8 @@ pub fn insert_audio(
             }
         }
     }
+    fts::index_audio(&connection, metadata);
+    drop(connection);
     // insert user defined tags into table
     for i in 0..metadata.tags.len() {
         let tag_id = insert_tag(
@@ -3504,7 +3694,7 @@ pub fn delete_audio(
         Ok(mut statement) => match statement.query(params![&metadata.path]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
-                    let s_opt = row.get(1);
+                    let s_opt = row.get(0);
                     if s_opt.is_ok() {
                         audio_id = s_opt.unwrap();
                     }
@@ -3532,6 +3722,7 @@ pub fn delete_audio(
         log::error!("Failed to delete audio file {}!", audio_id);
         return;
     }
+    fts::remove(&connection, audio_id);
     // clear the entry in the candidates list without deleting it
     let ret = connection.execute(
         "DELETE FROM file_metadata WHERE filepath = ?1",
@@ -3550,8 +3741,10 @@ pub fn update_audio(
     statdata: &std::fs::Metadata,
     data: &crate::scanmetadata::ScanMetaData,
 ) {
+    let added = file(sql_connection.clone(), &metadata.path).added;
     delete_audio(sql_connection.clone(), metadata, data);
     insert_audio(sql_connection.clone(), metadata, statdata, data);
+    restore_added(sql_connection.clone(), &metadata.path, added);
 }
 
 pub fn audio_by_id(
@@ -3572,7 +3765,7 @@ pub fn audio_by_id(
     v.path = filepath.to_string();
     // fill v from all tables
     v.id = audio_id as u32;
-    let query = "SELECT name, title, released, poster, thumb, duration, genre, composer, track_id FROM audio_metadata WHERE audio_id = ?1";
+    let query = "SELECT name, title, released, poster, thumb, duration, genre, composer, track_id, disc FROM audio_metadata WHERE audio_id = ?1";
     match connection.prepare(query) {
         Ok(mut statement) => {
             match statement.query(params![&audio_id]) {
@@ -3643,6 +3836,13 @@ pub fn audio_by_id(
                                         continue;
                                     }
                                 }
+                                match row.get(9) {
+                                    Ok(val) => v.disc = val,
+                                    Err(error) => {
+                                        log::error!("Failed to read disc for audio: {}", error);
+                                        continue;
+                                    }
+                                }
                             }
                             Ok(None) => {
                                 //log::warn!("No data read from indices.");
@@ -3965,8 +4165,9 @@ pub fn audio(
         }
     };
     let audio_id = filedata.metadata_id;
+    v.added = filedata.added;
     v.id = audio_id as u32;
-    let query = "SELECT name, title, released, poster, thumb, duration, genre, composer, track_id FROM audio_metadata WHERE audio_id = ?1";
+    let query = "SELECT name, title, released, poster, thumb, duration, genre, composer, track_id, disc FROM audio_metadata WHERE audio_id = ?1";
     match connection.prepare(query) {
         Ok(mut statement) => {
             match statement.query(params![&audio_id]) {
@@ -4037,6 +4238,13 @@ pub fn audio(
                                         continue;
                                     }
                                 }
+                                match row.get(9) {
+                                    Ok(val) => v.disc = val,
+                                    Err(error) => {
+                                        log::error!("Failed to read disc for audio: {}", error);
+                                        continue;
+                                    }
+                                }
                             }
                             Ok(None) => {
                                 //log::warn!("No data read from indices.");
@@ -4343,7 +4551,8 @@ pub fn audio(
     v
 }
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
+#[serde(default)]
 pub struct ImageMetadata {
     pub id: u32,
     pub name: String,
@@ -4364,6 +4573,12 @@ pub struct ImageMetadata {
     pub gps_longitude: f32,
     pub gps_altitude: f32,
     pub tags: Vec<Tag>,
+    /// EXIF capture time in seconds since the epoch, read as UTC, 0 if unknown.
+    pub taken: i64,
+    /// When the file was first scanned, in seconds since the epoch, 0 if unknown. It
+    /// belongs to this library, so it is not exported.
+    #[serde(skip)]
+    pub added: u64,
 }
 
 impl Default for ImageMetadata {
@@ -4388,6 +4603,8 @@ impl Default for ImageMetadata {
             gps_longitude: 0.0,
             gps_altitude: 0.0,
             tags: Vec::new(),
+            taken: 0,
+            added: 0,
         }
     }
 }
@@ -4407,9 +4624,10 @@ pub fn insert_image(
         }
     };
     metadata.id = image_id;
-    match connection.execute(
-        "INSERT INTO image_metadata (image_id, name, path, created, resized, thumb, width, height, photographer, LenseModel, Focallength, Exposuretime, FNumber, gpsstring, gpslatitude, gpslongitude, gpsaltitude) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
-        params![&metadata.id, &metadata.name, &metadata.path, &metadata.date, &metadata.resized, &metadata.thumb, &metadata.width, &metadata.height, &metadata.photographer, &metadata.lense_model, &metadata.focal_length, &metadata.exposure_time, &metadata.fnumber, &metadata.gps_string, &metadata.gps_latitude, &metadata.gps_longitude, &metadata.gps_altitude],
+    match execute_cached(
+        &connection,
+        "INSERT INTO image_metadata (image_id, name, path, created, resized, thumb, width, height, photographer, LenseModel, Focallength, Exposuretime, FNumber, gpsstring, gpslatitude, gpslongitude, gpsaltitude, taken) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+        params![&metadata.id, &metadata.name, &metadata.path, &metadata.date, &metadata.resized, &metadata.thumb, &metadata.width, &metadata.height, &metadata.photographer, &metadata.lense_model, &metadata.focal_length, &metadata.exposure_time, &metadata.fnumber, &metadata.gps_string, &metadata.gps_latitude, &metadata.gps_longitude, &metadata.gps_altitude, &metadata.taken],
     ) {
         Ok(_retval) => {}, //log::warn!("Inserted {} image with ID {} and location {} into candidates.", video.id, video.index, candidate_id),
         Err(error) => {
@@ -4420,6 +4638,7 @@ pub fn insert_image(
             return;
         }
     }
+    fts::index_image(&connection, metadata);
     drop(connection);
     for i in 0..metadata.tags.len() {
         let tag_id = insert_tag(
@@ -4453,7 +4672,7 @@ pub fn delete_image(
         Ok(mut statement) => match statement.query(params![&metadata.path]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
-                    let s_opt = row.get(1);
+                    let s_opt = row.get(0);
                     if s_opt.is_ok() {
                         image_id = s_opt.unwrap();
                     }
@@ -4481,6 +4700,7 @@ pub fn delete_image(
         log::error!("Failed to delete candidate {}!", image_id);
         return;
     }
+    fts::remove(&connection, image_id);
     data.known_files_remove(PathBuf::from(&metadata.path));
 }
 
@@ -4490,8 +4710,10 @@ pub fn update_image(
     statdata: &std::fs::Metadata,
     data: &crate::scanmetadata::ScanMetaData,
 ) {
+    let added = file(sql_connection.clone(), &metadata.path).added;
     delete_image(sql_connection.clone(), metadata, data);
     insert_image(sql_connection.clone(), metadata, statdata, data);
+    restore_added(sql_connection.clone(), &metadata.path, added);
 }
 
 pub fn image_by_id(
@@ -4512,7 +4734,7 @@ pub fn image_by_id(
     v.path = filepath.to_string();
     v.id = image_id as u32;
     // fill v from all tables
-    let query = "SELECT name, path, created, resized, thumb, width, height, Photographer, LenseModel, Focallength, Exposuretime, FNumber, GPSLatitude, GPSLongitude, GPSAltitude FROM image_metadata WHERE image_id = ?1";
+    let query = "SELECT name, path, created, resized, thumb, width, height, Photographer, LenseModel, Focallength, Exposuretime, FNumber, GPSLatitude, GPSLongitude, GPSAltitude, taken FROM image_metadata WHERE image_id = ?1";
     match connection.prepare(query) {
         Ok(mut statement) => {
             match statement.query(params![&image_id]) {
@@ -4646,6 +4868,13 @@ pub fn image_by_id(
                                         continue;
                                     }
                                 }
+                                match row.get(15) {
+                                    Ok(val) => v.taken = val,
+                                    Err(error) => {
+                                        log::error!("Failed to read taken for image: {}", error);
+                                        continue;
+                                    }
+                                }
                             }
                             Ok(None) => {
                                 //log::warn!("No data read from indices.");
@@ -4743,8 +4972,9 @@ pub fn image(
         }
     };
     let image_id = filedata.metadata_id;
+    v.added = filedata.added;
     v.id = image_id as u32;
-    let query = "SELECT name, path, created, resized, thumb, width, height, Photographer, LenseModel, Focallength, Exposuretime, FNumber, GPSLatitude, GPSLongitude, GPSAltitude, image_id FROM image_metadata WHERE image_id = ?1";
+    let query = "SELECT name, path, created, resized, thumb, width, height, Photographer, LenseModel, Focallength, Exposuretime, FNumber, GPSLatitude, GPSLongitude, GPSAltitude, image_id, taken FROM image_metadata WHERE image_id = ?1";
     match connection.prepare(query) {
         Ok(mut statement) => {
             match statement.query(params![&image_id]) {
@@ -4888,6 +5118,13 @@ pub fn image(
                                         continue;
                                     }
                                 }
+                                match row.get(16) {
+                                    Ok(val) => v.taken = val,
+                                    Err(error) => {
+                                        log::error!("Failed to read taken for image: {}", error);
+                                        continue;
+                                    }
+                                }
                             }
                             Ok(None) => {
                                 //log::warn!("No data read from indices.");
@@ -4975,6 +5212,12 @@ pub struct FileMetadata {
     pub modification_time: u64,
     pub file_type: i32,
     pub metadata_id: i64,
+    /// Size in bytes, 0 if the file was stored before sizes were recorded.
+    pub size: u64,
+    /// Content fingerprint from `crate::fingerprint::of_file()`, empty if unknown.
+    pub fingerprint: String,
+    /// When the file was first scanned, in seconds since the epoch.
+    pub added: u64,
 }
 
 impl Default for FileMetadata {
@@ -4985,7 +5228,24 @@ impl Default for FileMetadata {
             modification_time: 0,
             file_type: 0,
             metadata_id: -1,
+            size: 0,
+            fingerprint: String::new(),
+            added: 0,
+        }
+    }
+}
+
+impl FileMetadata {
+    /// Whether the file on disk differs from the stored one by modification time or size.
+    pub fn changed(&self, statdata: &std::fs::Metadata) -> bool {
+        let mut modification_time: u64 = 0;
+        if let Ok(modified) = statdata.modified() {
+            if let Ok(n) = modified.duration_since(std::time::UNIX_EPOCH) {
+                modification_time = n.as_secs();
+            }
         }
+        modification_time != self.modification_time
+            || (self.size != 0 && statdata.len() != self.size)
     }
 }
 
@@ -5052,6 +5312,27 @@ pub fn file_by_id(
                                         continue;
                                     }
                                 }
+                                match row.get(5) {
+                                    Ok(val) => v.size = val,
+                                    Err(error) => {
+                                        log::error!("Failed to read size for file: {}", error);
+                                        continue;
+                                    }
+                                }
+                                match row.get::<_, Option<String>>(6) {
+                                    Ok(val) => v.fingerprint = val.unwrap_or_default(),
+                                    Err(error) => {
+                                        log::error!("Failed to read fingerprint for file: {}", error);
+                                        continue;
+                                    }
+                                }
+                                match row.get::<_, Option<u64>>(7) {
+                                    Ok(val) => v.added = val.unwrap_or_default(),
+                                    Err(error) => {
+                                        log::error!("Failed to read added for file: {}", error);
+                                        continue;
+                                    }
+                                }
                             }
                             Ok(None) => {
                                 //log::warn!("No data read from indices.");
@@ -5143,6 +5424,27 @@ pub fn file(
                                         continue;
                                     }
                                 }
+                                match row.get(5) {
+                                    Ok(val) => v.size = val,
+                                    Err(error) => {
+                                        log::error!("Failed to read size for file: {}", error);
+                                        continue;
+                                    }
+                                }
+                                match row.get::<_, Option<String>>(6) {
+                                    Ok(val) => v.fingerprint = val.unwrap_or_default(),
+                                    Err(error) => {
+                                        log::error!("Failed to read fingerprint for file: {}", error);
+                                        continue;
+                                    }
+                                }
+                                match row.get::<_, Option<u64>>(7) {
+                                    Ok(val) => v.added = val.unwrap_or_default(),
+                                    Err(error) => {
+                                        log::error!("Failed to read added for file: {}", error);
+                                        continue;
+                                    }
+                                }
                             }
                             Ok(None) => {
                                 //log::warn!("No data read from indices.");
@@ -5234,6 +5536,27 @@ pub fn files(
                                         continue;
                                     }
                                 }
+                                match row.get(5) {
+                                    Ok(val) => s.size = val,
+                                    Err(error) => {
+                                        log::error!("Failed to read size for file: {}", error);
+                                        continue;
+                                    }
+                                }
+                                match row.get::<_, Option<String>>(6) {
+                                    Ok(val) => s.fingerprint = val.unwrap_or_default(),
+                                    Err(error) => {
+                                        log::error!("Failed to read fingerprint for file: {}", error);
+                                        continue;
+                                    }
+                                }
+                                match row.get::<_, Option<u64>>(7) {
+                                    Ok(val) => s.added = val.unwrap_or_default(),
+                                    Err(error) => {
+                                        log::error!("Failed to read added for file: {}", error);
+                                        continue;
+                                    }
+                                }
                                 known_files.insert(s.filepath.clone(), s.clone());
                                 if s.file_type == 3 {
                                     let mut thumbstring = crate::parsers::osstr_to_string(
@@ -5290,6 +5613,16 @@ pub fn files(
     known_files
 }
 
+/// Run a statement through the connection's statement cache, so inserts repeated for every
+/// file of a scan are only prepared once.
+fn execute_cached<P: rusqlite::Params>(
+    connection: &Connection,
+    sql: &str,
+    params: P,
+) -> Result<usize> {
+    connection.prepare_cached(sql)?.execute(params)
+}
+
 pub fn insert_file(
     sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
     path: &str,
@@ -5311,6 +5644,13 @@ pub fn insert_file(
             Err(_) => panic!("SystemTime before UNIX EPOCH!"),
         }
     }
+    let mut added: u64 = 0;
+    if let Ok(n) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        added = n.as_secs();
+    }
+    // fingerprinted by the scanner while looking for moved files, reading the file here
+    // would hold up every write queued behind this one
+    let fingerprint = data.fingerprints_get(PathBuf::from(path));
     let connection = match sql_connection.lock() {
         Ok(conn) => conn,
         Err(error) => {
@@ -5318,23 +5658,31 @@ pub fn insert_file(
             return 0;
         }
     };
-    match connection.execute(
+    match execute_cached(
+        &connection,
         "INSERT INTO file_metadata (filepath, creation_time, modification_time, 
-            file_type) VALUES (?1, ?2, ?3, ?4)",
-        params![&path, &creation_time, &modification_time, &file_type],
+            file_type, size, fingerprint, added) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            &path,
+            &creation_time,
+            &modification_time,
+            &file_type,
+            &metadata.len(),
+            &fingerprint,
+            &added
+        ],
     ) {
         Ok(_retval) => {} //log::warn!("Inserted {} video with ID {} and location {} into candidates.", video.id, video.index, candidate_id),
         Err(error) => {
             log::error!("Failed to insert file into  database: {}", error);
             drop(connection);
             delete_file(sql_connection.clone(), path, data);
-            insert_file(sql_connection.clone(), path, metadata, file_type, data);
-            return 0;
+            return insert_file(sql_connection.clone(), path, metadata, file_type, data);
         }
     }
     let mut metadata_id = 0;
     let query = "SELECT last_insert_rowid()";
-    match connection.prepare(query) {
+    match connection.prepare_cached(query) {
         Ok(mut statement) => match statement.query(params![]) {
             Ok(mut rows) => {
                 while let Ok(Some(row)) = rows.next() {
@@ -5360,7 +5708,9 @@ pub fn insert_file(
         modification_time,
         file_type,
         metadata_id,
-        ..Default::default()
+        size: metadata.len(),
+        fingerprint: fingerprint.unwrap_or_default(),
+        added,
     };
     data.known_files_insert(meta.filepath.clone(), meta.clone());
 
@@ -5407,6 +5757,178 @@ pub fn delete_file(
     data.known_files_remove(PathBuf::from(path));
 }
 
+/// Statements removing a file and everything stored for it, `?1` is its `metadata_id`.
+const REMOVE_FILE: [&str; 26] = [
+    "DELETE FROM collections_map WHERE collection_id IN
+        (SELECT collection_id FROM collections WHERE file_id = ?1)",
+    "DELETE FROM collections WHERE file_id = ?1",
+    "DELETE FROM collections_map WHERE episode_id = ?1",
+    "DELETE FROM video_metadata WHERE video_id = ?1",
+    "DELETE FROM subtitles WHERE video_id = ?1",
+    "DELETE FROM audiolangs WHERE video_id = ?1",
+    "DELETE FROM sublangs WHERE video_id = ?1",
+    "DELETE FROM chapters WHERE video_id = ?1",
+    "DELETE FROM directors WHERE video_id = ?1",
+    "DELETE FROM actors WHERE video_id = ?1",
+    "DELETE FROM genres WHERE video_id = ?1",
+    "DELETE FROM studios WHERE video_id = ?1",
+    "DELETE FROM countries WHERE video_id = ?1",
+    "DELETE FROM writers WHERE video_id = ?1",
+    "DELETE FROM ratings WHERE video_id = ?1",
+    "DELETE FROM unique_ids WHERE video_id = ?1",
+    "DELETE FROM movie_sets_map WHERE video_id = ?1",
+    "DELETE FROM audio_metadata WHERE audio_id = ?1",
+    "DELETE FROM album_audio_map WHERE audio_id = ?1",
+    "DELETE FROM artist_audio_map WHERE audio_id = ?1",
+    "DELETE FROM albumartist_audio_map WHERE audio_id = ?1",
+    "DELETE FROM indexes WHERE audio_id = ?1",
+    "DELETE FROM lyrics WHERE audio_id = ?1",
+    "DELETE FROM image_metadata WHERE image_id = ?1",
+    "DELETE FROM tags_media_map WHERE media_id = ?1",
+    "DELETE FROM file_metadata WHERE metadata_id = ?1",
+];
+
+/// Remove a file that no longer exists together with its metadata, people, album and tag
+/// mappings.
+pub fn remove_file(
+    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    file: &FileMetadata,
+    data: &crate::scanmetadata::ScanMetaData,
+) {
+    let connection = match sql_connection.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return;
+        }
+    };
+    for statement in REMOVE_FILE {
+        if let Err(error) = execute_cached(&connection, statement, params![&file.metadata_id]) {
+            log::error!(
+                "Failed to remove {} from the database: {}",
+                file.filepath.display(),
+                error
+            );
+            return;
+        }
+    }
+    fts::remove(&connection, file.metadata_id as u32);
+    data.known_files_remove(file.filepath.clone());
+}
+
+/// Stored files with the given content fingerprint.
+pub fn files_by_fingerprint(
+    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    fingerprint: &str,
+) -> Vec<FileMetadata> {
+    let mut ids: Vec<i64> = Vec::new();
+    {
+        let connection = match sql_connection.lock() {
+            Ok(conn) => conn,
+            Err(error) => {
+                log::error!("Failed to lock sql connection for use! {}", error);
+                return Vec::new();
+            }
+        };
+        match connection
+            .prepare_cached("SELECT metadata_id FROM file_metadata WHERE fingerprint = ?1")
+        {
+            Ok(mut statement) => {
+                match statement.query_map(params![&fingerprint], |row| row.get::<_, i64>(0)) {
+                    Ok(rows) => ids.extend(rows.flatten()),
+                    Err(error) => {
+                        log::error!("Failed to look up fingerprint {}: {}", fingerprint, error);
+                    }
+                }
+            }
+            Err(error) => {
+                log::error!("Failed to look up fingerprint {}: {}", fingerprint, error);
+            }
+        };
+    }
+    ids.into_iter()
+        .map(|metadata_id| file_by_id(sql_connection.clone(), metadata_id))
+        .collect()
+}
+
+/// Point a stored file at the path it was moved to. The metadata id stays the same, so the
+/// metadata, tags and thumbnails stay linked. Names that were taken from the old file name
+/// follow the new one.
+pub fn move_file(
+    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    file: &FileMetadata,
+    path: &Path,
+    statdata: &std::fs::Metadata,
+    data: &crate::scanmetadata::ScanMetaData,
+) -> FileMetadata {
+    let mut modification_time: u64 = 0;
+    if let Ok(modified) = statdata.modified() {
+        if let Ok(n) = modified.duration_since(std::time::UNIX_EPOCH) {
+            modification_time = n.as_secs();
+        }
+    }
+    let moved = FileMetadata {
+        filepath: path.to_path_buf(),
+        modification_time,
+        size: statdata.len(),
+        ..file.clone()
+    };
+    let new_path = crate::parsers::osstr_to_string(path.as_os_str().to_os_string());
+    let stem = |p: &Path| match p.file_stem() {
+        Some(stem) => crate::parsers::osstr_to_string(stem.to_os_string()),
+        None => String::new(),
+    };
+    let (old_name, new_name) = (stem(&file.filepath), stem(path));
+    let connection = match sql_connection.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return file.clone();
+        }
+    };
+    let updates: [(&str, &[&dyn rusqlite::ToSql]); 6] = [
+        (
+            "UPDATE file_metadata SET filepath = ?2, modification_time = ?3, size = ?4
+                WHERE metadata_id = ?1",
+            &[&file.metadata_id, &new_path, &modification_time, &moved.size],
+        ),
+        (
+            "UPDATE image_metadata SET path = ?2 WHERE image_id = ?1",
+            &[&file.metadata_id, &new_path],
+        ),
+        (
+            "UPDATE collections_map SET path = ?2 WHERE episode_id = ?1",
+            &[&file.metadata_id, &new_path],
+        ),
+        (
+            "UPDATE video_metadata SET name = ?3 WHERE video_id = ?1 AND name = ?2",
+            &[&file.metadata_id, &old_name, &new_name],
+        ),
+        (
+            "UPDATE audio_metadata SET name = ?3 WHERE audio_id = ?1 AND name = ?2",
+            &[&file.metadata_id, &old_name, &new_name],
+        ),
+        (
+            "UPDATE image_metadata SET name = ?3 WHERE image_id = ?1 AND name = ?2",
+            &[&file.metadata_id, &old_name, &new_name],
+        ),
+    ];
+    for (statement, values) in updates {
+        if let Err(error) = execute_cached(&connection, statement, values) {
+            log::error!(
+                "Failed to move {} to {} in the database: {}",
+                file.filepath.display(),
+                path.display(),
+                error
+            );
+            return file.clone();
+        }
+    }
+    data.known_files_remove(file.filepath.clone());
+    data.known_files_insert(moved.filepath.clone(), moved.clone());
+    moved
+}
+
 pub fn update_file(
     sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
     path: &str,
@@ -5414,8 +5936,35 @@ pub fn update_file(
     file_type: i32,
     data: &crate::scanmetadata::ScanMetaData,
 ) {
+    let added = file(sql_connection.clone(), path).added;
     delete_file(sql_connection.clone(), path, data);
     insert_file(sql_connection.clone(), path, metadata, file_type, data);
+    restore_added(sql_connection.clone(), path, added);
+}
+
+/// A file stored again after it changed keeps the time it was first added.
+fn restore_added(
+    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    path: &str,
+    added: u64,
+) {
+    if added == 0 {
+        return;
+    }
+    let connection = match sql_connection.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return;
+        }
+    };
+    if let Err(error) = execute_cached(
+        &connection,
+        "UPDATE file_metadata SET added = ?2 WHERE filepath = ?1",
+        params![&path, &added],
+    ) {
+        log::error!("Failed to keep the added time of {}: {}", path, error);
+    }
 }
 
 pub fn insert_search(
@@ -5439,14 +5988,21 @@ pub fn insert_search(
                 duration, creation_date, modification_date, release_date, 
                 lense_model, focal_length, exposure_time, fnumber,
                 gps_latitude, gps_longitude, gps_altitude, 
-                album, composer, genre, tags) VALUES 
+                album, composer, genre, tags, query,
+                resolution, frame_rate, bitrate, codec, aspect_ratio, fuzzy,
+                search_id, name, folder, position, last_run, result_count) VALUES 
                 (?1, ?2, ?3, ?4, ?5, ?6, 
                 ?7, ?8, ?9, ?10, ?11, ?12, 
                 ?13, ?14, ?15, ?16, 
                 ?17, ?18, ?19, ?20, 
                 ?21, ?22, ?23, ?24, 
                 ?25, ?26, ?27, 
-                ?28, ?29, ?30, ?31)",
+                ?28, ?29, ?30, ?31, ?32,
+                ?33, ?34, ?35, ?36, ?37, ?38,
+                ?39, ?40, ?41,
+                CASE WHEN ?42 > 0 THEN ?42
+                    ELSE (SELECT COALESCE(MAX(position), 0) + 1 FROM searches) END,
+                ?43, ?44)",
         params![
             &s.from_string.to_ascii_lowercase(),
             &fromvalue,
@@ -5478,7 +6034,21 @@ pub fn insert_search(
             &s.album,
             &s.composer,
             &s.genre,
-            &s.tags
+            &s.tags,
+            &s.search_string,
+            &s.resolution,
+            &s.frame_rate,
+            &s.bitrate,
+            &s.codec,
+            &s.aspect_ratio,
+            &s.fuzzy,
+            // a search saved again keeps its id, new ones get the next free one
+            &(s.search_id > 0).then_some(s.search_id),
+            &s.name,
+            &s.folder,
+            &s.position,
+            &s.last_run,
+            &s.result_count
         ],
     ) {
         Ok(_retval) => {} //log::warn!("Inserted {} video with ID {} and location {} into candidates.", video.id, video.index, candidate_id),
@@ -5535,26 +6105,72 @@ pub fn update_search(
     insert_search(sql_connection.clone(), s);
 }
 
-pub fn searches(
+/// Remember when the saved search `search_id` was run and how many files it found.
+pub fn record_search_run(
     sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
-) -> Vec<SearchData> {
-    let mut searches = Vec::new();
+    search_id: u32,
+    last_run: i64,
+    result_count: u32,
+) {
     let connection = match sql_connection.lock() {
         Ok(conn) => conn,
         Err(error) => {
             log::error!("Failed to lock sql connection for use! {}", error);
-            return searches;
+            return;
         }
     };
-    let query = "SELECT * FROM searches";
-    match connection.prepare(query) {
-        Ok(mut statement) => {
-            match statement.query(params![]) {
-                Ok(mut rows) => {
-                    loop {
-                        match rows.next() {
-                            Ok(Some(row)) => {
-                                let mut v = SearchData {
+    if let Err(error) = execute_cached(
+        &connection,
+        "UPDATE searches SET last_run = ?2, result_count = ?3 WHERE search_id = ?1",
+        params![&search_id, &last_run, &result_count],
+    ) {
+        log::error!("Failed to record run of search {}: {}", search_id, error);
+    }
+}
+
+/// Number the saved searches in the order of `search_ids`.
+pub fn reorder_searches(
+    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    search_ids: &[u32],
+) {
+    let connection = match sql_connection.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return;
+        }
+    };
+    for (position, search_id) in search_ids.iter().enumerate() {
+        if let Err(error) = execute_cached(
+            &connection,
+            "UPDATE searches SET position = ?2 WHERE search_id = ?1",
+            params![search_id, &(position as u32 + 1)],
+        ) {
+            log::error!("Failed to move search {}: {}", search_id, error);
+        }
+    }
+}
+
+pub fn searches(
+    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+) -> Vec<SearchData> {
+    let mut searches = Vec::new();
+    let connection = match sql_connection.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return searches;
+        }
+    };
+    let query = "SELECT * FROM searches ORDER BY folder, position, search_id";
+    match connection.prepare(query) {
+        Ok(mut statement) => {
+            match statement.query(params![]) {
+                Ok(mut rows) => {
+                    loop {
+                        match rows.next() {
+                            Ok(Some(row)) => {
+                                let mut v = SearchData {
                                     ..Default::default()
                                 };
                                 match row.get(0) {
@@ -5861,6 +6477,59 @@ pub fn searches(
                                         continue;
                                     }
                                 }
+                                match row.get::<_, Option<String>>(32) {
+                                    Ok(val) => v.search_string = val.unwrap_or_default(),
+                                    Err(error) => {
+                                        log::error!("Failed to read query for searches: {}", error);
+                                        continue;
+                                    }
+                                }
+                                // NULL for searches saved before these fields existed
+                                match (33..39)
+                                    .map(|i| row.get::<_, Option<bool>>(i))
+                                    .collect::<Result<Vec<_>, _>>()
+                                {
+                                    Ok(val) => {
+                                        let flag = |i: usize| val[i].unwrap_or_default();
+                                        v.resolution = flag(0);
+                                        v.frame_rate = flag(1);
+                                        v.bitrate = flag(2);
+                                        v.codec = flag(3);
+                                        v.aspect_ratio = flag(4);
+                                        v.fuzzy = flag(5);
+                                    }
+                                    Err(error) => {
+                                        log::error!(
+                                            "Failed to read technical fields for searches: {}",
+                                            error
+                                        );
+                                        continue;
+                                    }
+                                }
+                                match (|| {
+                                    Ok::<_, rusqlite::Error>((
+                                        row.get(39)?,
+                                        row.get(40)?,
+                                        row.get(41)?,
+                                        row.get(42)?,
+                                        row.get(43)?,
+                                    ))
+                                })() {
+                                    Ok((name, folder, position, last_run, result_count)) => {
+                                        v.name = name;
+                                        v.folder = folder;
+                                        v.position = position;
+                                        v.last_run = last_run;
+                                        v.result_count = result_count;
+                                    }
+                                    Err(error) => {
+                                        log::error!(
+                                            "Failed to read name and folder for searches: {}",
+                                            error
+                                        );
+                                        continue;
+                                    }
+                                }
 
                                 searches.push(v);
                             }
@@ -5888,9 +6557,21 @@ pub fn searches(
     searches
 }
 
-pub fn connect() -> Result<rusqlite::Connection, rusqlite::Error> {
-    let sqlite_file;
-    let connection;
+static DATABASE_FILE: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+/// Use `sqlite_file` instead of the default database for every later `connect()` and
+/// `Database::open()`. Only the first call has an effect.
+pub fn set_database_file(sqlite_file: PathBuf) {
+    if DATABASE_FILE.set(sqlite_file).is_err() {
+        log::warn!("Database file was already selected, ignoring the new one");
+    }
+}
+
+/// The database file `connect()` and `Database::open()` open.
+pub fn database_file() -> PathBuf {
+    if let Some(sqlite_file) = DATABASE_FILE.get() {
+        return sqlite_file.clone();
+    }
     match dirs::data_local_dir() {
         Some(pb) => {
             let mut dir = pb.join("media-browser");
@@ -5901,689 +6582,480 @@ pub fn connect() -> Result<rusqlite::Connection, rusqlite::Error> {
                     dir = dirs::home_dir().unwrap();
                 }
             }
-            sqlite_file = dir.join("metadata.sqlite");
+            dir.join("metadata.sqlite")
         }
         None => {
             let dir = dirs::home_dir().unwrap();
-            sqlite_file = dir.join("metadata.sqlite");
-        }
-    }
-    if !sqlite_file.is_file() {
-        connection = Connection::open(sqlite_file)?;
-        println!("{}", connection.is_autocommit());
-        // file_type: 0 other file, 1 image, 2, video, 3 audio
-        match connection.execute(
-            "CREATE TABLE file_metadata (
-                filepath TEXT NOT NULL unique NOT NULL, 
-                creation_time UNSIGNED BIG INT, 
-                modification_time UNSIGNED BIG INT, 
-                file_type INT,   
-                metadata_id INTEGER,
-                PRIMARY KEY(metadata_id AUTOINCREMENT)
-            )",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table file_metadata: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_file_metadata_filename ON file_metadata (filepath)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on file_metadata: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE video_metadata (
-                video_id INTEGER PRIMARY KEY,
-                name  TEXT NOT NULL, 
-                title  TEXT NOT NULL, 
-                released UNSIGNED BIG INT NOT NULL, 
-                poster  TEXT, 
-                thumb   TEXT,
-                subtitles BIG INT,
-                duration INT,
-                width INT,
-                height INT,
-                framerate FLOAT,
-                description TEXT
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table indices: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_video_metadata_title ON video_metadata (title)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on file_metadata: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE subtitles (
-                subtitle_id INTEGER, 
-                video_id INTEGER, 
-                subpath TEXT, 
-                PRIMARY KEY(subtitle_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_subtitles_video_id ON subtitles (video_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on subtitles: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE audiolangs (
-                audiolang_id INTEGER, 
-                video_id INTEGER, 
-                audiolang TEXT, 
-                PRIMARY KEY(audiolang_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_audiolangs_video_id ON audiolangs (video_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on audiolangs: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE sublangs (
-                sublang_id INTEGER, 
-                video_id INTEGER, 
-                sublang TEXT, 
-                PRIMARY KEY(sublang_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_sublangs_video_id ON sublangs (video_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on sublangs: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE chapters (
-                chapter_id INTEGER, 
-                video_id INTEGER, 
-                title TEXT, 
-                start DOUBLE,
-                end DOUBLE,
-                PRIMARY KEY(chapter_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_chapters_video_id ON chapters (video_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on sublangs: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-        CREATE TABLE people (
-            person_id INTEGER, 
-            person_name TEXT, 
-            PRIMARY KEY(person_id AUTOINCREMENT)
-        )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table people: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute("CREATE INDEX index_people_name ON people (person_name)", ()) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on directors: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE directors (
-                entry_id INTEGER,
-                director_id INTEGER, 
-                video_id INTEGER, 
-                PRIMARY KEY(entry_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table directors: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_directors_video_id ON directors (video_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on directors: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE actors (
-                entry_id INTEGER,
-                actor_id INTEGER, 
-                video_id INTEGER, 
-                PRIMARY KEY(entry_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_actors_video_id ON actors (video_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on actors: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-        CREATE TABLE collections (
-            collection_id INTEGER,
-            file_id INTEGER,
-            collection_name TEXT,
-            poster TEXT,
-            thumb TEXT,
-            description TEXT,
-            path TEXT,
-            PRIMARY KEY(collection_id AUTOINCREMENT)
-        )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table collections: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX collection_file_id ON collections (file_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on collections_file_id: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX collection_collection_name ON collections (collection_name)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on collection_name: {}", error);
-                return Err(error);
-            }
+            dir.join("metadata.sqlite")
         }
+    }
+}
 
-        match connection.execute(
-            "
-            CREATE TABLE collections_map (
-                entry_id INTEGER,
-                collection_id INTEGER, 
-                episode_id INTEGER, 
-                series INTEGER,
-                episode INTEGER,
-                title TEXT,
-                path TEXT,
-                poster TEXT,
-                thumb TEXT,
-                PRIMARY KEY(entry_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table collections_map: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX collection_map_episode_id ON collections_map (episode_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on collection_name: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE audio_metadata (
-                audio_id INTEGER PRIMARY KEY,
-                name  TEXT NOT NULL, 
-                title  TEXT NOT NULL, 
-                released UNSIGNED BIG INT NOT NULL, 
-                poster  TEXT, 
-                thumb   TEXT,
-                genre   TEXT,
-                composer TEXT,
-                track_id INT,
-                duration INT,
-                bitrate FLOAT
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table indices: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE albums (
-                album_id INTEGER, 
-                album_name TEXT, 
-                PRIMARY KEY(album_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute("CREATE INDEX index_albums_name ON albums (album_name)", ()) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on albums: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-        CREATE TABLE album_audio_map (
-            entry_id INTEGER,
-            audio_id INTEGER,
-            album_id INTEGER,
-            PRIMARY KEY(entry_id AUTOINCREMENT) 
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table albums: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_album_audio_audio_id ON album_audio_map (audio_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on albums: {}", error);
-                return Err(error);
-            }
-        }
+pub fn connect() -> Result<rusqlite::Connection, migrations::Error> {
+    connect_at(&database_file())
+}
 
-        match connection.execute(
-            "
-            CREATE TABLE artists (
-                artist_id INTEGER, 
-                artist_name TEXT, 
-                PRIMARY KEY(artist_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table artists: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_artists_name_id ON artists (artist_name)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on artists: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-        CREATE TABLE artist_audio_map (
-            entry_id INTEGER,
-            audio_id INTEGER,
-            artist_id INTEGER, 
-            PRIMARY KEY(entry_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table artist_audio_map: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_artist_audio_map_audio_id ON artist_audio_map (audio_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on artist_audio_map: {}", error);
-                return Err(error);
-            }
+/// Open or create the database at `sqlite_file` and upgrade it to the current schema.
+pub fn connect_at(sqlite_file: &Path) -> Result<rusqlite::Connection, migrations::Error> {
+    if let Some(dir) = sqlite_file.parent() {
+        if !dir.as_os_str().is_empty() && !dir.exists() {
+            std::fs::create_dir_all(dir)?;
         }
+    }
+    let mut connection = Connection::open(sqlite_file)?;
+    migrations::migrate(&mut connection, Some(sqlite_file))?;
+    geo::register(&connection)?;
+    fold::register(&connection)?;
+    Ok(connection)
+}
 
-        match connection.execute(
-            "
-        CREATE TABLE albumartist_audio_map (
-            entry_id INTEGER,
-            audio_id INTEGER,
-            albumartist_id INTEGER, 
-            PRIMARY KEY(entry_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table albumartist_audio_map: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_albumartist_audio_map_audio_id ON artist_audio_map (audio_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on albumartist_audio_map: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE indexes (
-                index_id INTEGER, 
-                audio_id INTEGER, 
-                title TEXT, 
-                start DOUBLE,
-                end DOUBLE,
-                PRIMARY KEY(index_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_indexes_audio_id ON indexes (audio_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on artist_audio_map: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "
-            CREATE TABLE lyrics (
-                lyrics_id INTEGER, 
-                audio_id INTEGER, 
-                lyricsfile TEXT, 
-                PRIMARY KEY(lyrics_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_lyrics_audio_id ON lyrics (audio_id)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on lyrics: {}", error);
-                return Err(error);
-            }
-        }
+/// Throwaway database with the current schema, used by tests.
+pub fn connect_in_memory() -> Result<rusqlite::Connection, migrations::Error> {
+    let mut connection = Connection::open_in_memory()?;
+    migrations::migrate(&mut connection, None)?;
+    geo::register(&connection)?;
+    fold::register(&connection)?;
+    Ok(connection)
+}
 
-        match connection.execute(
-            "
-            CREATE TABLE image_metadata (
-                image_id INTEGER PRIMARY KEY,
-                name     TEXT NOT NULL, 
-                path     TEXT NOT NULL, 
-                created  UNSIGNED BIG INT NOT NULL, 
-                resized  TEXT, 
-                thumb    TEXT,
-                width    INTEGER,
-                height   INTEGER,
-                Photographer TEXT,
-                LenseModel TEXT,
-                Focallength TEXT,
-                Exposuretime TEXT,
-                FNumber  TEXT,
-                GPSString  TEXT,
-                GPSLatitude DOUBLE,
-                GPSLongitude DOUBLE,
-                GPSAltitude DOUBLE
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table image_metadata: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute(
-            "CREATE INDEX index_image_photographer ON image_metadata (Photographer)",
-            (),
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on image_metadata: {}", error);
-                return Err(error);
-            }
-        }
-        match connection.execute("CREATE INDEX index_image_name ON image_metadata (name)", ()) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on image_metadata: {}", error);
-                return Err(error);
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStrExt;
 
-        match connection.execute(
-            "
-            CREATE TABLE tags (
-                tag_id INTEGER, 
-                tag TEXT, 
-                PRIMARY KEY(tag_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
+    const TRICKY_PATHS: [&str; 4] = [
+        "/media/O'Brien's Movies/heat.mkv",
+        "/media/100% Pure/track_01.flac",
+        "/media/back\\slash/photo.jpeg",
+        "/media/'; DROP TABLE file_metadata; --/clip.mkv",
+    ];
+
+    fn connection() -> std::sync::Arc<std::sync::Mutex<Connection>> {
+        std::sync::Arc::new(std::sync::Mutex::new(connect_in_memory().unwrap()))
+    }
+
+    fn stat() -> std::fs::Metadata {
+        std::fs::metadata(std::env::temp_dir()).unwrap()
+    }
+
+    fn paths_like(
+        sql_connection: std::sync::Arc<std::sync::Mutex<Connection>>,
+        text: &str,
+    ) -> Vec<PathBuf> {
+        let query = Query::select("SELECT metadata_id FROM file_metadata").like("filepath", text);
+        search_file_metadata(sql_connection, query)
+            .into_iter()
+            .map(|f| f.filepath)
+            .collect()
+    }
+
+    #[test]
+    fn file_paths_with_special_characters() {
+        let sql_connection = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        for path in TRICKY_PATHS {
+            insert_file(sql_connection.clone(), path, &stat(), 2, &data);
+        }
+        assert_eq!(
+            paths_like(sql_connection.clone(), "O'Brien"),
+            vec![PathBuf::from(TRICKY_PATHS[0])]
+        );
+        assert_eq!(
+            paths_like(sql_connection.clone(), "100%"),
+            vec![PathBuf::from(TRICKY_PATHS[1])]
+        );
+        assert!(paths_like(sql_connection.clone(), "trac_").is_empty());
+        assert_eq!(
+            paths_like(sql_connection.clone(), "back\\slash"),
+            vec![PathBuf::from(TRICKY_PATHS[2])]
+        );
+        assert_eq!(
+            paths_like(sql_connection.clone(), "DROP TABLE"),
+            vec![PathBuf::from(TRICKY_PATHS[3])]
+        );
+        let known_files = files(sql_connection);
+        for path in TRICKY_PATHS {
+            assert!(known_files.contains_key(Path::new(path)));
         }
-        match connection.execute("CREATE INDEX index_tags_tag ON tags (tag)", ()) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create index on tags: {}", error);
-                return Err(error);
-            }
+    }
+
+    #[test]
+    fn non_utf8_file_paths() {
+        let sql_connection = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let names: [&[u8]; 2] = [b"/media/caf\xe9 100%.mkv", b"/media/na\xefve.mkv"];
+        for name in names {
+            let path = crate::parsers::osstr_to_string(
+                std::ffi::OsStr::from_bytes(name).to_os_string(),
+            );
+            assert!(!path.is_empty());
+            assert!(insert_file(sql_connection.clone(), &path, &stat(), 2, &data) > 0);
         }
+        assert_eq!(paths_like(sql_connection.clone(), "caf").len(), 1);
+        assert_eq!(paths_like(sql_connection.clone(), "100%").len(), 1);
+        assert_eq!(paths_like(sql_connection, "/media/").len(), 2);
+    }
 
-        match connection.execute(
-            "
-            CREATE TABLE tags_media_map (
-                element_id INTEGER,
-                media_id INTEGER, 
-                tagmap_id INTEGER,
-                PRIMARY KEY(element_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table parameters: {}", error);
-                return Err(error);
-            }
+    #[test]
+    fn search_text_is_not_sql() {
+        let sql_connection = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let mut image = ImageMetadata {
+            name: "photo.jpeg".to_string(),
+            path: TRICKY_PATHS[2].to_string(),
+            lense_model: "Nikkor 'Z' 24-70mm f/4".to_string(),
+            ..Default::default()
+        };
+        insert_image(sql_connection.clone(), &mut image, &stat(), &data);
+        for (text, found) in [("'Z'", 1), ("%", 0), ("' OR '1'='1", 0), ("\\", 0)] {
+            let search = SearchData {
+                from_string: text.to_string(),
+                image: true,
+                lense_model: true,
+                ..Default::default()
+            };
+            let (images, _files) = search_image(sql_connection.clone(), &search);
+            assert_eq!(images.len(), found, "searching for {}", text);
         }
-        /*
-                match connection.execute(
-                    "CREATE INDEX index_tagsmediamap_media_id ON tags_media_map (media_id)", (),
-                ) {
-                    Ok(_ret) => {},
-                    Err(error) => {
-                        log::error!("Failed to create index on tags_media_map: {}", error);
-                        return Err(error);
-                    }
-                }
-                match connection.execute(
-                    "CREATE INDEX index_tagsmediamap_tag_id ON tags_media_map (tagmap_id)", (),
-                ) {
-                    Ok(_ret) => {},
-                    Err(error) => {
-                        log::error!("Failed to create index on tags_media_map: {}", error);
-                        return Err(error);
-                    }
-                }
-        */
-        match connection.execute(
-            "
-            CREATE TABLE searches (
-                search_id INTEGER,
-                from_string  TEXT, 
-                from_value DOUBLE, 
-                from_date INTEGER,
-                to_string  TEXT, 
-                to_value DOUBLE, 
-                to_date INTEGER,
-                image  INTEGER, 
-                video  INTEGER, 
-                audio  INTEGER, 
-                filepath  INTEGER, 
-                title  INTEGER, 
-                description  INTEGER, 
-                actor  INTEGER, 
-                director  INTEGER, 
-                artist  INTEGER, 
-                album_artist  INTEGER, 
-                duration  INTEGER, 
-                creation_date  INTEGER, 
-                modification_date  INTEGER, 
-                release_date  INTEGER, 
-                lense_model  INTEGER, 
-                focal_length  INTEGER, 
-                exposure_time  INTEGER, 
-                fnumber  INTEGER, 
-                gps_latitude  INTEGER, 
-                gps_longitude  INTEGER, 
-                gps_altitude  INTEGER,
-                album  INTEGER, 
-                composer  INTEGER, 
-                genre  INTEGER,  
-                tags  INTEGER, 
-                PRIMARY KEY(search_id AUTOINCREMENT)
-            )",
-            [],
-        ) {
-            Ok(_ret) => {}
-            Err(error) => {
-                log::error!("Failed to create table image_metadata: {}", error);
-                return Err(error);
-            }
+    }
+
+    #[test]
+    fn search_files_are_found_once_per_media_type() {
+        let sql_connection = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        for (path, file_type) in TRICKY_PATHS.into_iter().zip([2, 3, 1, 2]) {
+            insert_file(sql_connection.clone(), path, &stat(), file_type, &data);
+        }
+        let query = SearchData {
+            search_string: "path:media".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(search_files(sql_connection.clone(), &query).len(), 4);
+        let videos = SearchData {
+            video: true,
+            ..query
+        };
+        let found = search_files(sql_connection.clone(), &videos);
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|found| found.file.file_type == 2));
+        let fields = SearchData {
+            from_string: "media".to_string(),
+            filepath: true,
+            title: true,
+            video: true,
+            audio: true,
+            ..Default::default()
+        };
+        let found = search_files(sql_connection.clone(), &fields);
+        let ids: HashSet<i64> = found.iter().map(|found| found.file.metadata_id).collect();
+        assert_eq!((found.len(), ids.len()), (3, 3));
+        // a stopped search builds no more items
+        let database = Database::in_memory().unwrap();
+        let controller = Controller::new();
+        controller.cancel();
+        assert!(found_items(sql_connection, &database, &found, &controller).is_empty());
+    }
+
+    #[test]
+    fn changed_compares_time_and_size() {
+        let statdata = stat();
+        let mut stored = FileMetadata {
+            modification_time: statdata
+                .modified()
+                .unwrap()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            size: statdata.len(),
+            ..Default::default()
+        };
+        assert!(!stored.changed(&statdata));
+        stored.size += 1;
+        assert!(stored.changed(&statdata));
+        // rows stored before sizes were recorded
+        stored.size = 0;
+        assert!(!stored.changed(&statdata));
+        stored.modification_time -= 1;
+        assert!(stored.changed(&statdata));
+    }
+
+    #[test]
+    fn remove_file_drops_metadata_and_tags() {
+        let sql_connection = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let mut image = ImageMetadata {
+            name: "gone.jpeg".to_string(),
+            path: "/media/gone.jpeg".to_string(),
+            ..Default::default()
+        };
+        insert_image(sql_connection.clone(), &mut image, &stat(), &data);
+        insert_tag(sql_connection.clone(), image.id, "holiday".to_string());
+        let stored = file(sql_connection.clone(), "/media/gone.jpeg");
+        assert_eq!(stored.size, stat().len());
+        remove_file(sql_connection.clone(), &stored, &data);
+        assert!(files(sql_connection.clone()).is_empty());
+        let connection = sql_connection.lock().unwrap();
+        for table in ["image_metadata", "tags_media_map"] {
+            let rows: i64 = connection
+                .query_row(&format!("SELECT count(*) FROM {}", table), [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(rows, 0, "rows left in {}", table);
         }
-    } else {
-        connection = Connection::open(sqlite_file)?;
     }
-    Ok(connection)
+
+    #[test]
+    fn sort_keys_are_stored() {
+        let sql_connection = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let mut movie = VideoMetadata {
+            path: "/media/movie.mkv".to_string(),
+            rating: 7.5,
+            ..Default::default()
+        };
+        insert_video(sql_connection.clone(), &mut movie, &stat(), &data);
+        let mut song = AudioMetadata {
+            path: "/media/song.flac".to_string(),
+            track_id: 3,
+            disc: 2,
+            ..Default::default()
+        };
+        insert_audio(sql_connection.clone(), &mut song, &stat(), &data);
+        let mut photo = ImageMetadata {
+            path: "/media/photo.jpeg".to_string(),
+            taken: 1_719_858_600,
+            ..Default::default()
+        };
+        insert_image(sql_connection.clone(), &mut photo, &stat(), &data);
+
+        let movie = video(sql_connection.clone(), "/media/movie.mkv", &data);
+        assert_eq!(movie.rating, 7.5);
+        assert!(movie.added > 0);
+        let song = audio(sql_connection.clone(), "/media/song.flac", &data);
+        assert_eq!((song.disc, song.track_id), (2, 3));
+        assert!(song.added > 0);
+        let photo = image(sql_connection.clone(), "/media/photo.jpeg", &data);
+        assert_eq!(photo.taken, 1_719_858_600);
+        assert!(photo.added > 0);
+        assert_eq!(
+            image_by_id(sql_connection.clone(), "/media/photo.jpeg", photo.id as i64).taken,
+            photo.taken
+        );
+
+        // a file scanned again keeps the time it was added
+        let connection = sql_connection.lock().unwrap();
+        connection
+            .execute("UPDATE file_metadata SET added = 1000", [])
+            .unwrap();
+        drop(connection);
+        let mut movie = VideoMetadata {
+            path: "/media/movie.mkv".to_string(),
+            rating: 8.0,
+            ..Default::default()
+        };
+        update_video(sql_connection.clone(), &mut movie, &stat(), &data);
+        let movie = video(sql_connection.clone(), "/media/movie.mkv", &data);
+        assert_eq!((movie.rating, movie.added), (8.0, 1000));
+    }
+
+    #[test]
+    fn move_file_keeps_metadata_and_tags() {
+        let sql_connection = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        data.fingerprints_insert(PathBuf::from("/media/old.jpeg"), "4-0123".to_string());
+        let mut image = ImageMetadata {
+            name: "old".to_string(),
+            path: "/media/old.jpeg".to_string(),
+            ..Default::default()
+        };
+        insert_image(sql_connection.clone(), &mut image, &stat(), &data);
+        insert_tag(sql_connection.clone(), image.id, "holiday".to_string());
+        let found = files_by_fingerprint(sql_connection.clone(), "4-0123");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].filepath, PathBuf::from("/media/old.jpeg"));
+
+        let moved = move_file(
+            sql_connection.clone(),
+            &found[0],
+            Path::new("/media/sorted/new.jpeg"),
+            &stat(),
+            &data,
+        );
+        assert_eq!(moved.metadata_id, found[0].metadata_id);
+        assert!(!data.known_files_contains(PathBuf::from("/media/old.jpeg")));
+        assert!(data.known_files_contains(PathBuf::from("/media/sorted/new.jpeg")));
+        let stored = file(sql_connection.clone(), "/media/sorted/new.jpeg");
+        assert_eq!(stored.metadata_id, found[0].metadata_id);
+        assert_eq!(stored.fingerprint, "4-0123");
+        let connection = sql_connection.lock().unwrap();
+        let (name, path): (String, String) = connection
+            .query_row(
+                "SELECT name, path FROM image_metadata WHERE image_id = ?1",
+                [image.id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(name, "new");
+        assert_eq!(path, "/media/sorted/new.jpeg");
+        let tags: i64 = connection
+            .query_row(
+                "SELECT count(*) FROM tags_media_map WHERE media_id = ?1",
+                [image.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tags, 1);
+    }
+
+    #[test]
+    fn saved_search_with_special_characters() {
+        let sql_connection = connection();
+        let search = SearchData {
+            from_string: "o'brien 100% c:\\movies".to_string(),
+            video: true,
+            actor: true,
+            ..Default::default()
+        };
+        let search_id = insert_search(sql_connection.clone(), search.clone());
+        assert!(search_id > 0);
+        let stored = searches(sql_connection);
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].from_string, search.from_string);
+        assert_eq!(stored[0].search_string, "");
+    }
+
+    #[test]
+    fn saved_search_keeps_query_text() {
+        let sql_connection = connection();
+        let search = SearchData {
+            search_string: "actor:\"O'Brien\" OR Title:100%".to_string(),
+            video: true,
+            ..Default::default()
+        };
+        insert_search(sql_connection.clone(), search.clone());
+        let stored = searches(sql_connection);
+        assert_eq!(stored[0].search_string, search.search_string);
+        assert_eq!(stored[0], search);
+    }
+
+    #[test]
+    fn saved_search_keeps_technical_fields() {
+        let sql_connection = connection();
+        let search = SearchData {
+            from_string: "720p".to_string(),
+            video: true,
+            resolution: true,
+            aspect_ratio: true,
+            ..Default::default()
+        };
+        insert_search(sql_connection.clone(), search.clone());
+        let stored = searches(sql_connection);
+        assert!(stored[0].resolution && stored[0].aspect_ratio);
+        assert!(!stored[0].frame_rate && !stored[0].bitrate && !stored[0].codec);
+        assert_eq!(stored[0], search);
+    }
+
+    #[test]
+    fn saved_search_keeps_typo_tolerance() {
+        let sql_connection = connection();
+        let search = SearchData {
+            from_string: "tarentino".to_string(),
+            video: true,
+            director: true,
+            fuzzy: true,
+            ..Default::default()
+        };
+        insert_search(sql_connection.clone(), search.clone());
+        let stored = searches(sql_connection);
+        assert!(stored[0].fuzzy);
+        assert_eq!(stored[0], search);
+        assert!(similar_expression(&stored[0]).is_some());
+        assert!(similar_expression(&SearchData {
+            fuzzy: false,
+            ..stored[0].clone()
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn saved_searches_are_named_ordered_and_edited() {
+        let sql_connection = connection();
+        let nineties = SearchData {
+            search_string: "released:1990..1999".to_string(),
+            video: true,
+            name: "Nineties".to_string(),
+            folder: "Movies".to_string(),
+            ..Default::default()
+        };
+        let nineties_id = insert_search(sql_connection.clone(), nineties.clone());
+        let photos_id = insert_search(
+            sql_connection.clone(),
+            SearchData {
+                image: true,
+                name: "Holiday".to_string(),
+                folder: "Photos".to_string(),
+                ..Default::default()
+            },
+        );
+        let eighties_id = insert_search(
+            sql_connection.clone(),
+            SearchData {
+                search_string: "released:1980..1989".to_string(),
+                video: true,
+                name: "Eighties".to_string(),
+                folder: "Movies".to_string(),
+                ..Default::default()
+            },
+        );
+        let stored = searches(sql_connection.clone());
+        let ids: Vec<u32> = stored.iter().map(|s| s.search_id).collect();
+        assert_eq!(ids, [nineties_id, eighties_id, photos_id]);
+        assert_eq!(stored[0].display(), "Movies / Nineties");
+        assert_eq!(stored[0], nineties);
+
+        reorder_searches(
+            sql_connection.clone(),
+            &[eighties_id, nineties_id, photos_id],
+        );
+        record_search_run(sql_connection.clone(), nineties_id, 1_700_000_000, 12);
+        let stored = searches(sql_connection.clone());
+        let ids: Vec<u32> = stored.iter().map(|s| s.search_id).collect();
+        assert_eq!(ids, [eighties_id, nineties_id, photos_id]);
+        assert_eq!(
+            (stored[1].last_run, stored[1].result_count),
+            (1_700_000_000, 12)
+        );
+
+        // saved again with other conditions, it keeps its id and place
+        let mut edited = stored[1].clone();
+        edited.search_string = "released:1990..1995".to_string();
+        edited.name = "Early nineties".to_string();
+        update_search(sql_connection.clone(), edited);
+        let stored = searches(sql_connection);
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stored[1].search_id, nineties_id);
+        assert_eq!(stored[1].name, "Early nineties");
+        assert_eq!(stored[1].search_string, "released:1990..1995");
+    }
+
+    #[test]
+    fn smart_collection_survives_the_config() {
+        let collection = SmartCollection {
+            name: "Nineties".to_string(),
+            search: SearchData {
+                search_string: "released:1990..1999".to_string(),
+                video: true,
+                ..Default::default()
+            },
+        };
+        let text = serde_json::to_string(&collection).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SmartCollection>(&text).unwrap(),
+            collection
+        );
+        // entries written by older versions miss fields added since
+        let old: SmartCollection =
+            serde_json::from_str("{\"name\":\"Old\",\"search\":{\"video\":true}}").unwrap();
+        assert_eq!(old.search.title, SearchData::default().title);
+        assert!(old.search.video);
+    }
 }
@@ -0,0 +1,797 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Export and import of library entries as JSON Lines or CSV, so curated tags and metadata
+//! can be shared between machines that keep their media under different paths.
+
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use super::service::Handle;
+use super::{AudioMetadata, CollectionMetadata, ImageMetadata, Tag, VideoMetadata};
+
+/// One exported library entry, written as a JSON object with a `type` field.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Record {
+    // the NFO details make videos much larger than the other kinds
+    Video(Box<VideoMetadata>),
+    Audio(AudioMetadata),
+    Image(ImageMetadata),
+    Collection(CollectionMetadata),
+}
+
+impl Record {
+    pub fn path(&self) -> String {
+        match self {
+            Record::Video(video) => video.path.clone(),
+            Record::Audio(audio) => audio.path.clone(),
+            Record::Image(image) => image.path.clone(),
+            Record::Collection(collection) => {
+                crate::parsers::osstr_to_string(collection.path.clone().into_os_string())
+            }
+        }
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        match self {
+            Record::Video(video) => &video.tags,
+            Record::Audio(audio) => &audio.tags,
+            Record::Image(image) => &image.tags,
+            Record::Collection(collection) => &collection.tags,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    JsonLines,
+    Csv,
+}
+
+impl Format {
+    /// CSV for files ending in `.csv`, JSON Lines for everything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::JsonLines,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Csv(#[from] csv::Error),
+    #[error("line {line}: {message}")]
+    Parse { line: u64, message: String },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Kind {
+    Text,
+    Number,
+    /// A list of strings, written as one cell separated by `LIST_SEPARATOR`.
+    List,
+    /// Tag names separated by `LIST_SEPARATOR`.
+    Tags,
+    /// Nested structures (chapters, episodes) that are kept as JSON inside the cell.
+    Json,
+}
+
+const LIST_SEPARATOR: &str = "; ";
+
+/// CSV columns, the union of the fields of all record types. Database ids are left out,
+/// they mean nothing on another machine.
+const COLUMNS: [(&str, Kind); 60] = [
+    ("type", Kind::Text),
+    ("path", Kind::Text),
+    ("name", Kind::Text),
+    ("title", Kind::Text),
+    ("date", Kind::Text),
+    ("poster", Kind::Text),
+    ("thumb", Kind::Text),
+    ("resized", Kind::Text),
+    ("description", Kind::Text),
+    ("tags", Kind::Tags),
+    ("duration", Kind::Number),
+    ("width", Kind::Number),
+    ("height", Kind::Number),
+    ("framerate", Kind::Number),
+    ("codec", Kind::Text),
+    ("season", Kind::Number),
+    ("episode", Kind::Number),
+    ("last_episode", Kind::Number),
+    ("rating", Kind::Number),
+    ("original_title", Kind::Text),
+    ("sort_title", Kind::Text),
+    ("tagline", Kind::Text),
+    ("mpaa", Kind::Text),
+    ("director", Kind::List),
+    ("actors", Kind::List),
+    ("subtitles", Kind::List),
+    ("audiolangs", Kind::List),
+    ("sublangs", Kind::List),
+    ("chapters", Kind::Json),
+    ("genres", Kind::List),
+    ("studios", Kind::List),
+    ("countries", Kind::List),
+    ("writers", Kind::List),
+    ("roles", Kind::Json),
+    ("ratings", Kind::Json),
+    ("unique_ids", Kind::Json),
+    ("movie_set", Kind::Text),
+    ("movie_set_overview", Kind::Text),
+    ("edition", Kind::Text),
+    ("source", Kind::Text),
+    ("genre", Kind::Text),
+    ("composer", Kind::Text),
+    ("track_id", Kind::Number),
+    ("disc", Kind::Number),
+    ("bitrate", Kind::Number),
+    ("album", Kind::Text),
+    ("artist", Kind::List),
+    ("albumartist", Kind::List),
+    ("lyrics", Kind::List),
+    ("photographer", Kind::Text),
+    ("lense_model", Kind::Text),
+    ("focal_length", Kind::Text),
+    ("exposure_time", Kind::Text),
+    ("fnumber", Kind::Text),
+    ("gps_string", Kind::Text),
+    ("gps_latitude", Kind::Number),
+    ("gps_longitude", Kind::Number),
+    ("gps_altitude", Kind::Number),
+    ("taken", Kind::Number),
+    ("episodes", Kind::Json),
+];
+
+fn media_tags(handle: &Handle, media_id: i64) -> BTreeSet<String> {
+    let mut tags = BTreeSet::new();
+    let connection = match handle.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return tags;
+        }
+    };
+    let query = "SELECT tag FROM tags
+        INNER JOIN tags_media_map ON tags_media_map.tagmap_id = tags.tag_id
+        WHERE tags_media_map.media_id = ?1";
+    match connection.prepare_cached(query) {
+        Ok(mut statement) => {
+            match statement.query_map(params![media_id], |row| row.get::<_, String>(0)) {
+                Ok(rows) => tags.extend(rows.flatten()),
+                Err(error) => log::error!("Failed to read tags of {}: {}", media_id, error),
+            }
+        }
+        Err(error) => log::error!("Failed to prepare tag query: {}", error),
+    }
+    tags
+}
+
+fn collection_ids(handle: &Handle) -> BTreeSet<i64> {
+    let mut ids = BTreeSet::new();
+    let connection = match handle.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return ids;
+        }
+    };
+    match connection.prepare("SELECT file_id FROM collections") {
+        Ok(mut statement) => match statement.query_map([], |row| row.get::<_, i64>(0)) {
+            Ok(rows) => ids.extend(rows.flatten()),
+            Err(error) => log::error!("Failed to read collections: {}", error),
+        },
+        Err(error) => log::error!("Failed to prepare collection query: {}", error),
+    }
+    ids
+}
+
+/// Read the entries for `paths` from the library, or the whole library if `paths` is `None`.
+/// Collections come last, so an import finds their episodes already in place.
+pub fn records(handle: Handle, paths: Option<&[PathBuf]>) -> Vec<Record> {
+    let files = match paths {
+        Some(paths) => paths
+            .iter()
+            .map(|path| {
+                super::file(
+                    handle.clone(),
+                    &crate::parsers::osstr_to_string(path.clone().into_os_string()),
+                )
+            })
+            .filter(|file| file.metadata_id >= 0)
+            .collect::<Vec<_>>(),
+        // files() also lists the generated thumbnail and lyrics paths of every file
+        None => super::files(handle.clone())
+            .into_iter()
+            .filter(|(path, file)| path == &file.filepath)
+            .map(|(_, file)| file)
+            .collect(),
+    };
+    let collections = collection_ids(&handle);
+    let data = crate::scanmetadata::ScanMetaData::new();
+    let mut records = Vec::with_capacity(files.len());
+    let mut collection_records = Vec::new();
+    for file in files {
+        let filepath = crate::parsers::osstr_to_string(file.filepath.clone().into_os_string());
+        match file.file_type {
+            1 => records.push(Record::Image(super::image_by_id(
+                handle.clone(),
+                &filepath,
+                file.metadata_id,
+            ))),
+            2 if collections.contains(&file.metadata_id) => collection_records.push(
+                Record::Collection(super::collection(handle.clone(), &filepath, &data)),
+            ),
+            2 => records.push(Record::Video(Box::new(super::video_by_id(
+                handle.clone(),
+                &filepath,
+                file.metadata_id,
+            )))),
+            3 => records.push(Record::Audio(super::audio_by_id(
+                handle.clone(),
+                &filepath,
+                file.metadata_id,
+            ))),
+            _ => {}
+        }
+    }
+    records.extend(collection_records);
+    records
+}
+
+fn cell(value: Option<&Value>, kind: Kind) -> String {
+    let strings = |values: &Vec<Value>, key: Option<&str>| {
+        values
+            .iter()
+            .filter_map(|value| match key {
+                Some(key) => value.get(key)?.as_str(),
+                None => value.as_str(),
+            })
+            .collect::<Vec<_>>()
+            .join(LIST_SEPARATOR)
+    };
+    match (value, kind) {
+        (Some(Value::String(text)), Kind::Text) => text.clone(),
+        (Some(Value::Number(number)), Kind::Number) => number.to_string(),
+        (Some(Value::Array(values)), Kind::List) => strings(values, None),
+        (Some(Value::Array(values)), Kind::Tags) => strings(values, Some("tag")),
+        (Some(Value::Array(values)), Kind::Json) if !values.is_empty() => {
+            Value::Array(values.clone()).to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+fn parse_cell(text: &str, kind: Kind) -> Result<Value, String> {
+    let list = || {
+        text.split(LIST_SEPARATOR.trim())
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+    };
+    match kind {
+        Kind::Text => Ok(Value::String(text.to_string())),
+        Kind::Number => {
+            if let Ok(number) = text.parse::<i64>() {
+                return Ok(Value::Number(number.into()));
+            }
+            text.parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| format!("{:?} is not a number", text))
+        }
+        Kind::List => Ok(Value::Array(
+            list().map(|item| Value::String(item.to_string())).collect(),
+        )),
+        Kind::Tags => Ok(Value::Array(
+            list()
+                .map(|tag| serde_json::json!({ "tag_id": 0, "tag": tag }))
+                .collect(),
+        )),
+        Kind::Json => serde_json::from_str(text).map_err(|error| error.to_string()),
+    }
+}
+
+/// Write `records` to `writer`, one per line.
+pub fn write<W: Write>(records: &[Record], format: Format, mut writer: W) -> Result<(), Error> {
+    match format {
+        Format::JsonLines => {
+            for record in records {
+                serde_json::to_writer(&mut writer, record)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+        Format::Csv => {
+            let mut csv = csv::Writer::from_writer(writer);
+            csv.write_record(COLUMNS.iter().map(|(name, _)| *name))?;
+            for record in records {
+                let value = serde_json::to_value(record)?;
+                csv.write_record(
+                    COLUMNS
+                        .iter()
+                        .map(|(name, kind)| cell(value.get(*name), *kind)),
+                )?;
+            }
+            csv.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Read records written by `write()`. CSV columns may come in any order and unknown columns
+/// are ignored, so a spreadsheet edit of an export can be imported again.
+pub fn read<R: Read>(reader: R, format: Format) -> Result<Vec<Record>, Error> {
+    let mut records = Vec::new();
+    match format {
+        Format::JsonLines => {
+            for (index, line) in BufReader::new(reader).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record = serde_json::from_str(&line).map_err(|error| Error::Parse {
+                    line: index as u64 + 1,
+                    message: error.to_string(),
+                })?;
+                records.push(record);
+            }
+        }
+        Format::Csv => {
+            let mut csv = csv::Reader::from_reader(reader);
+            let columns = csv
+                .headers()?
+                .iter()
+                .map(|header| COLUMNS.iter().find(|(name, _)| *name == header.trim()))
+                .collect::<Vec<_>>();
+            for row in csv.records() {
+                let row = row?;
+                let line = row.position().map_or(0, |position| position.line());
+                let mut object = Map::new();
+                for (column, text) in columns.iter().zip(row.iter()) {
+                    if let Some((name, kind)) = column {
+                        if text.is_empty() {
+                            continue;
+                        }
+                        let value = parse_cell(text, *kind).map_err(|message| Error::Parse {
+                            line,
+                            message: format!("{}: {}", name, message),
+                        })?;
+                        object.insert(name.to_string(), value);
+                    }
+                }
+                let record = serde_json::from_value(Value::Object(object)).map_err(|error| {
+                    Error::Parse {
+                        line,
+                        message: error.to_string(),
+                    }
+                })?;
+                records.push(record);
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Replaces the `from` prefix of imported paths with `to`, e.g. `/home/alice/Videos` with
+/// `/mnt/media/Videos`. An empty `from` leaves all paths alone.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Rewrite {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl Rewrite {
+    pub fn new(from: impl Into<PathBuf>, to: impl Into<PathBuf>) -> Self {
+        Self {
+            from: from.into(),
+            to: to.into(),
+        }
+    }
+
+    pub fn path(&self, path: &str) -> String {
+        if self.from.as_os_str().is_empty() || path.is_empty() {
+            return path.to_string();
+        }
+        match Path::new(path).strip_prefix(&self.from) {
+            Ok(rest) if rest.as_os_str().is_empty() => {
+                crate::parsers::osstr_to_string(self.to.clone().into_os_string())
+            }
+            Ok(rest) => crate::parsers::osstr_to_string(self.to.join(rest).into_os_string()),
+            Err(_) => path.to_string(),
+        }
+    }
+
+    /// Rewrite a generated file like a poster or thumbnail and drop it if it does not exist
+    /// here, the scanner then creates it again.
+    fn cached(&self, path: &mut String) {
+        *path = self.path(path);
+        if !path.is_empty() && !Path::new(path.as_str()).exists() {
+            path.clear();
+        }
+    }
+
+    fn apply(&self, record: &mut Record) {
+        let paths = |paths: &mut Vec<String>| {
+            for path in paths.iter_mut() {
+                *path = self.path(path);
+            }
+        };
+        match record {
+            Record::Video(video) => {
+                video.path = self.path(&video.path);
+                self.cached(&mut video.poster);
+                self.cached(&mut video.thumb);
+                paths(&mut video.subtitles);
+            }
+            Record::Audio(audio) => {
+                audio.path = self.path(&audio.path);
+                self.cached(&mut audio.poster);
+                self.cached(&mut audio.thumb);
+                paths(&mut audio.lyrics);
+            }
+            Record::Image(image) => {
+                image.path = self.path(&image.path);
+                self.cached(&mut image.resized);
+                self.cached(&mut image.thumb);
+            }
+            Record::Collection(collection) => {
+                collection.path = PathBuf::from(self.path(&crate::parsers::osstr_to_string(
+                    collection.path.clone().into_os_string(),
+                )));
+                self.cached(&mut collection.poster);
+                self.cached(&mut collection.thumb);
+                for episode in collection.episodes.iter_mut() {
+                    episode.path = PathBuf::from(self.path(&crate::parsers::osstr_to_string(
+                        episode.path.clone().into_os_string(),
+                    )));
+                    self.cached(&mut episode.poster);
+                    self.cached(&mut episode.thumb);
+                }
+            }
+        }
+    }
+}
+
+/// What to do with an imported entry whose file is already in the library.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Duplicates {
+    /// Keep the library entry as it is.
+    #[default]
+    Skip,
+    /// Keep the library entry and add the imported tags it is missing. Everything else is
+    /// read from the file itself by the scanner, so the local values are kept.
+    Merge,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+    pub imported: usize,
+    pub merged: usize,
+    pub skipped: usize,
+    /// Entries whose file does not exist on this machine, even after the path rewrite.
+    pub missing: usize,
+}
+
+fn add_tags(handle: &Handle, media_id: i64, tags: &[Tag]) {
+    let known = media_tags(handle, media_id);
+    let mut added = BTreeSet::new();
+    for tag in tags {
+        if !known.contains(&tag.tag) && added.insert(tag.tag.clone()) {
+            super::insert_tag(handle.clone(), media_id as u32, tag.tag.clone());
+        }
+    }
+}
+
+/// Add `records` to the library. Entries are stored under their rewritten path and only if
+/// that file exists, the file stat and fingerprint are taken from the local copy.
+pub fn import(
+    handle: Handle,
+    records: Vec<Record>,
+    rewrite: &Rewrite,
+    duplicates: Duplicates,
+) -> Summary {
+    let data = crate::scanmetadata::ScanMetaData::new();
+    let mut summary = Summary::default();
+    for mut record in records {
+        rewrite.apply(&mut record);
+        let path = record.path();
+        let statdata = match std::fs::metadata(&path) {
+            Ok(statdata) => statdata,
+            Err(error) => {
+                log::warn!("Not importing {}: {}", path, error);
+                summary.missing += 1;
+                continue;
+            }
+        };
+        let existing = super::file(handle.clone(), &path);
+        if existing.metadata_id >= 0 {
+            match duplicates {
+                Duplicates::Skip => summary.skipped += 1,
+                Duplicates::Merge => {
+                    add_tags(&handle, existing.metadata_id, record.tags());
+                    summary.merged += 1;
+                }
+            }
+            continue;
+        }
+        match record {
+            Record::Video(mut video) => {
+                super::insert_video(handle.clone(), &mut video, &statdata, &data)
+            }
+            Record::Audio(mut audio) => {
+                super::insert_audio(handle.clone(), &mut audio, &statdata, &data)
+            }
+            Record::Image(mut image) => {
+                super::insert_image(handle.clone(), &mut image, &statdata, &data)
+            }
+            Record::Collection(mut collection) => {
+                // episodes point at the local ids of their video files
+                collection.episodes.retain_mut(|episode| {
+                    let file = super::file(
+                        handle.clone(),
+                        &crate::parsers::osstr_to_string(episode.path.clone().into_os_string()),
+                    );
+                    episode.file_id = file.metadata_id.max(0) as u32;
+                    file.metadata_id >= 0
+                });
+                let tags = std::mem::take(&mut collection.tags);
+                super::insert_collection(handle.clone(), &mut collection, &statdata, &data);
+                add_tags(&handle, collection.id as i64, &tags);
+            }
+        }
+        summary.imported += 1;
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn connection() -> Handle {
+        std::sync::Arc::new(std::sync::Mutex::new(
+            crate::sql::connect_in_memory().unwrap(),
+        ))
+    }
+
+    fn tag(name: &str) -> Tag {
+        Tag {
+            tag_id: 0,
+            tag: name.to_string(),
+        }
+    }
+
+    fn library(dir: &Path) -> Handle {
+        let handle = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        std::fs::create_dir_all(dir.join("Heat")).unwrap();
+        let video_path = dir.join("Heat").join("heat.mkv");
+        let image_path = dir.join("beach.jpeg");
+        std::fs::write(&video_path, b"video").unwrap();
+        std::fs::write(&image_path, b"image").unwrap();
+        let mut video = VideoMetadata {
+            name: "heat".to_string(),
+            title: "Heat, \"the\" movie".to_string(),
+            date: NaiveDate::from_ymd_opt(1995, 12, 15).unwrap(),
+            path: video_path.to_string_lossy().to_string(),
+            duration: 10_200,
+            framerate: 23.976,
+            codec: "h264".to_string(),
+            rating: 7.6,
+            description: "A crew of thieves;\nand a detective.".to_string(),
+            director: vec!["Michael Mann".to_string()],
+            actors: vec!["Al Pacino".to_string(), "Robert De Niro".to_string()],
+            chapters: vec![crate::sql::Chapter {
+                title: "Opening".to_string(),
+                start: 0.0,
+                end: 300.0,
+            }],
+            tags: vec![tag("crime"), tag("favourite")],
+            genres: vec!["Crime".to_string(), "Drama".to_string()],
+            roles: vec![crate::sql::Role {
+                actor: "Al Pacino".to_string(),
+                role: "Lt. Vincent Hanna".to_string(),
+                thumb: String::new(),
+            }],
+            ratings: vec![crate::sql::Rating {
+                source: "imdb".to_string(),
+                value: 8.3,
+                max: 10.0,
+                votes: 750_000,
+                default: true,
+            }],
+            movie_set: "Michael Mann Crime".to_string(),
+            ..Default::default()
+        };
+        crate::sql::insert_video(
+            handle.clone(),
+            &mut video,
+            &std::fs::metadata(&video_path).unwrap(),
+            &data,
+        );
+        let mut image = ImageMetadata {
+            name: "beach".to_string(),
+            path: image_path.to_string_lossy().to_string(),
+            width: 640,
+            height: 480,
+            gps_latitude: 52.5,
+            tags: vec![tag("holiday")],
+            ..Default::default()
+        };
+        crate::sql::insert_image(
+            handle.clone(),
+            &mut image,
+            &std::fs::metadata(&image_path).unwrap(),
+            &data,
+        );
+        handle
+    }
+
+    fn round_trip(records: &[Record], format: Format) -> Vec<Record> {
+        let mut buffer = Vec::new();
+        write(records, format, &mut buffer).unwrap();
+        read(buffer.as_slice(), format).unwrap()
+    }
+
+    fn names(mut tags: Vec<Tag>) -> Vec<String> {
+        tags.sort_by(|a, b| a.tag.cmp(&b.tag));
+        tags.into_iter().map(|tag| tag.tag).collect()
+    }
+
+    #[test]
+    fn records_round_trip_as_json_lines_and_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let records = records(library(dir.path()), None);
+        assert_eq!(records.len(), 2);
+        assert_eq!(round_trip(&records, Format::JsonLines), records);
+
+        let csv = round_trip(&records, Format::Csv);
+        assert_eq!(csv.len(), 2);
+        for (read, written) in csv.iter().zip(&records) {
+            match (read, written) {
+                (Record::Video(read), Record::Video(written)) => {
+                    assert_eq!(read.title, written.title);
+                    assert_eq!(read.description, written.description);
+                    assert_eq!(read.date, written.date);
+                    assert_eq!(read.framerate, written.framerate);
+                    assert_eq!(read.codec, written.codec);
+                    assert_eq!(read.rating, written.rating);
+                    assert_eq!(read.actors, written.actors);
+                    assert_eq!(read.chapters, written.chapters);
+                    assert_eq!(read.genres, written.genres);
+                    assert_eq!(read.roles, written.roles);
+                    assert_eq!(read.ratings, written.ratings);
+                    assert_eq!(read.movie_set, written.movie_set);
+                    assert_eq!(names(read.tags.clone()), names(written.tags.clone()));
+                }
+                (Record::Image(read), Record::Image(written)) => {
+                    assert_eq!(read.path, written.path);
+                    assert_eq!(read.gps_latitude, written.gps_latitude);
+                    assert_eq!(names(read.tags.clone()), names(written.tags.clone()));
+                }
+                _ => panic!("records changed type: {:?} {:?}", read, written),
+            }
+        }
+    }
+
+    #[test]
+    fn read_reports_the_broken_line() {
+        let text = "{\"type\":\"image\",\"path\":\"/a.jpeg\"}\n\n{\"type\":\"book\"}\n";
+        match read(text.as_bytes(), Format::JsonLines) {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let text = "path,type,width\n/a.jpeg,image,wide\n";
+        match read(text.as_bytes(), Format::Csv) {
+            Err(Error::Parse { line, message }) => {
+                assert_eq!(line, 2);
+                assert!(message.starts_with("width"));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rewrite_replaces_only_whole_path_prefixes() {
+        let rewrite = Rewrite::new("/home/alice/Videos", "/mnt/media/Videos");
+        assert_eq!(
+            rewrite.path("/home/alice/Videos/Heat/heat.mkv"),
+            "/mnt/media/Videos/Heat/heat.mkv"
+        );
+        assert_eq!(rewrite.path("/home/alice/Videos"), "/mnt/media/Videos");
+        assert_eq!(
+            rewrite.path("/home/alice/VideosOld/a.mkv"),
+            "/home/alice/VideosOld/a.mkv"
+        );
+        assert_eq!(Rewrite::default().path("/a/b.mkv"), "/a/b.mkv");
+    }
+
+    #[test]
+    fn import_rewrites_paths_and_skips_or_merges_duplicates() {
+        let source = tempfile::tempdir().unwrap();
+        let mut exported = records(library(source.path()), None);
+
+        // the same files on another machine, one of them already scanned there with a tag
+        let target = tempfile::tempdir().unwrap();
+        let handle = library(target.path());
+        let image_path = target
+            .path()
+            .join("beach.jpeg")
+            .to_string_lossy()
+            .to_string();
+        let image = crate::sql::file(handle.clone(), &image_path);
+        crate::sql::execute_cached(
+            &handle.lock().unwrap(),
+            "DELETE FROM tags_media_map WHERE media_id = ?1",
+            params![image.metadata_id],
+        )
+        .unwrap();
+        crate::sql::insert_tag(handle.clone(), image.metadata_id as u32, "local".into());
+        let video_path = target.path().join("Heat").join("heat.mkv");
+        crate::sql::delete_file(
+            handle.clone(),
+            &video_path.to_string_lossy(),
+            &crate::scanmetadata::ScanMetaData::new(),
+        );
+        exported.push(Record::Image(ImageMetadata {
+            path: source
+                .path()
+                .join("elsewhere.jpeg")
+                .to_string_lossy()
+                .to_string(),
+            ..Default::default()
+        }));
+
+        let rewrite = Rewrite::new(source.path(), target.path());
+        let summary = import(handle.clone(), exported.clone(), &rewrite, Duplicates::Skip);
+        assert_eq!(
+            summary,
+            Summary {
+                imported: 1,
+                merged: 0,
+                skipped: 1,
+                missing: 1,
+            }
+        );
+        assert_eq!(
+            media_tags(&handle, image.metadata_id),
+            BTreeSet::from(["local".to_string()])
+        );
+        let file = crate::sql::file(handle.clone(), &video_path.to_string_lossy());
+        let video = crate::sql::video_by_id(
+            handle.clone(),
+            &video_path.to_string_lossy(),
+            file.metadata_id,
+        );
+        assert_eq!(video.title, "Heat, \"the\" movie");
+        assert_eq!(names(video.tags), vec!["crime", "favourite"]);
+
+        let summary = import(handle.clone(), exported, &rewrite, Duplicates::Merge);
+        assert_eq!(summary.merged, 2);
+        assert_eq!(summary.imported, 0);
+        assert_eq!(
+            media_tags(&handle, image.metadata_id),
+            BTreeSet::from(["holiday".to_string(), "local".to_string()])
+        );
+        assert_eq!(
+            media_tags(&handle, file.metadata_id),
+            BTreeSet::from(["crime".to_string(), "favourite".to_string()])
+        );
+    }
+}
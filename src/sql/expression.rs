@@ -0,0 +1,1589 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Text query language of the database search, e.g.
+//! `actor:"Al Pacino" AND (released:1990..1999 OR tag:favourite) -genre:horror`.
+//!
+//! Words with a known field prefix match that field, other words are looked up in the
+//! full-text index. Terms next to each other have to match all, `OR` matches either side,
+//! `NOT` or a leading `-` excludes, and parentheses group. NOT binds tighter than AND,
+//! AND tighter than OR. Durations, GPS values and dates also take `>`, `>=`, `<`, `<=`,
+//! `=` and ranges like `2010..2015`, where either end may be left open.
+//!
+//! Technical properties take the same comparisons: `resolution:>=4k` or `resolution:<720p`
+//! compare the height a picture would have at 16:9, `fps:23.976`, `bitrate:<192k` (audio,
+//! kbit/s), `aspect:16:9` and `orientation:portrait`, `landscape` or `square`.
+//! `codec:hevc` matches the video codec.
+//!
+//! Videos with an NFO file are also found by `writer:`, `studio:`, `country:`, `set:` for
+//! the movie set and `mpaa:` for the age rating. `id:tt0111161` or `id:tmdb:278` match an
+//! IMDb, TMDB or TVDB id exactly, with or without its site.
+//!
+//! Photos are found by place with `near:latitude,longitude[,radius]`, the radius in km
+//! unless it ends in `m`, and with `area:south,west,north,east`.
+//!
+//! Text ignores case and accents, `album:amelie` finds "Amélie". A leading `~` also
+//! accepts typos, as in `~tarntino`, `director:~tarentino` or `~"quentin tarentino"`.
+
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use rusqlite::types::Value;
+use thiserror::Error;
+
+use super::fold::{self, SIMILAR};
+use super::fts::{self, Column};
+use super::geo::{self, Area};
+use super::query::{like_pattern, Query};
+use super::SearchType;
+
+/// Field prefixes of the query language, the first name of a field is the one shown to users.
+pub const FIELDS: &[(&str, SearchType)] = &[
+    ("path", SearchType::FilePath),
+    ("filepath", SearchType::FilePath),
+    ("title", SearchType::Title),
+    ("description", SearchType::Description),
+    ("actor", SearchType::Actor),
+    ("director", SearchType::Director),
+    ("artist", SearchType::Artist),
+    ("albumartist", SearchType::AlbumArtist),
+    ("album", SearchType::Album),
+    ("composer", SearchType::Composer),
+    ("genre", SearchType::Genre),
+    ("duration", SearchType::Duration),
+    ("created", SearchType::CreationDate),
+    ("modified", SearchType::ModificationDate),
+    ("released", SearchType::ReleaseDate),
+    ("lens", SearchType::LenseModel),
+    ("focal", SearchType::FocalLength),
+    ("exposure", SearchType::ExposureTime),
+    ("fnumber", SearchType::FNumber),
+    ("lat", SearchType::GPSLatitude),
+    ("latitude", SearchType::GPSLatitude),
+    ("lon", SearchType::GPSLongitude),
+    ("longitude", SearchType::GPSLongitude),
+    ("alt", SearchType::GPSAltitude),
+    ("altitude", SearchType::GPSAltitude),
+    ("near", SearchType::GPSLocation),
+    ("area", SearchType::GPSLocation),
+    ("resolution", SearchType::Resolution),
+    ("fps", SearchType::FrameRate),
+    ("framerate", SearchType::FrameRate),
+    ("bitrate", SearchType::Bitrate),
+    ("codec", SearchType::Codec),
+    ("aspect", SearchType::AspectRatio),
+    ("orientation", SearchType::AspectRatio),
+    ("tag", SearchType::Tag),
+    ("writer", SearchType::Writer),
+    ("studio", SearchType::Studio),
+    ("country", SearchType::Country),
+    ("set", SearchType::MovieSet),
+    ("mpaa", SearchType::Certification),
+    ("certification", SearchType::Certification),
+    ("id", SearchType::ExternalId),
+];
+
+/// Height of a picture scaled to 16:9 at the same width, so 3840x1600 counts as 2160 like
+/// 3840x2160, and portrait pictures are measured along their short side. Unknown sizes are NULL.
+const RESOLUTION: &str = "NULLIF(MAX(MIN(width, height), MAX(width, height) * 9 / 16.0), 0)";
+
+const ASPECT_RATIO: &str = "NULLIF(width, 0) * 1.0 / NULLIF(height, 0)";
+
+/// How far a frame rate, bitrate or aspect ratio may be off to count as equal, so that
+/// `fps:23.976` matches 24000/1001 and `aspect:16:9` matches 1.78.
+const TOLERANCE: f64 = 0.01;
+
+const TEXT_COLUMNS: [Column; 8] = [
+    Column::Title,
+    Column::Description,
+    Column::People,
+    Column::Album,
+    Column::Genre,
+    Column::Tags,
+    Column::Lyrics,
+    Column::Subtitles,
+];
+
+/// Fields searched with typos for words without a field.
+pub const SIMILAR_FIELDS: [SearchType; 7] = [
+    SearchType::Title,
+    SearchType::Actor,
+    SearchType::Director,
+    SearchType::Artist,
+    SearchType::AlbumArtist,
+    SearchType::Composer,
+    SearchType::Album,
+];
+
+/// Field for a prefix, ignoring case.
+pub fn field(name: &str) -> Option<SearchType> {
+    FIELDS
+        .iter()
+        .find(|(prefix, _)| prefix.eq_ignore_ascii_case(name))
+        .map(|(_, field)| *field)
+}
+
+/// Prefix shown for a field, None for fields the query language can't search.
+pub fn field_name(field: SearchType) -> Option<&'static str> {
+    FIELDS
+        .iter()
+        .find(|(_, f)| *f == field)
+        .map(|(prefix, _)| *prefix)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    /// Words without a field, looked up in the full-text index.
+    Text(String),
+    /// Words without a field, also matched with typos in titles, people and albums.
+    Similar(String),
+    Field(SearchType, Condition),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    /// Part of a text field, or the whole tag or id for `tag:` and `id:`.
+    Text(String),
+    /// Like `Text`, or close enough to it by `fold::similarity()`.
+    Similar(String),
+    /// Durations in seconds and GPS values.
+    Number {
+        min: Option<Limit>,
+        max: Option<Limit>,
+    },
+    /// Days from `from` up to but not including `until`.
+    Dates {
+        from: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+    },
+    /// Photos taken within `kilometres` of a position.
+    Near {
+        latitude: f64,
+        longitude: f64,
+        kilometres: f64,
+    },
+    /// Photos taken inside a box.
+    Area(Area),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limit {
+    pub value: f64,
+    pub inclusive: bool,
+}
+
+/// Positions count characters from 1.
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ParseError {
+    #[error("the query is empty")]
+    Empty,
+    #[error("unknown field \"{field}\" at character {position}")]
+    UnknownField { field: String, position: usize },
+    #[error("missing value after \"{field}:\" at character {position}")]
+    MissingValue { field: String, position: usize },
+    #[error("\"{value}\" is not a valid {expected} at character {position}")]
+    InvalidValue {
+        value: String,
+        expected: &'static str,
+        position: usize,
+    },
+    #[error("{operator} at character {position} needs a term on both sides")]
+    MissingOperand {
+        operator: &'static str,
+        position: usize,
+    },
+    #[error("the parenthesis at character {position} is never closed")]
+    UnclosedParenthesis { position: usize },
+    #[error("unexpected \")\" at character {position}")]
+    UnexpectedParenthesis { position: usize },
+    #[error("the quote at character {position} is never closed")]
+    UnclosedQuote { position: usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Quoted(String),
+}
+
+fn tokens(text: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let position = i + 1;
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push((Token::Open, position));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::Close, position));
+                i += 1;
+            }
+            '"' => {
+                let end = match chars[i + 1..].iter().position(|c| *c == '"') {
+                    Some(length) => i + 1 + length,
+                    None => return Err(ParseError::UnclosedQuote { position }),
+                };
+                tokens.push((Token::Quoted(chars[i + 1..end].iter().collect()), position));
+                i = end + 1;
+            }
+            '-' if chars
+                .get(i + 1)
+                .is_some_and(|c| !c.is_whitespace() && *c != ')') =>
+            {
+                tokens.push((Token::Not, position));
+                i += 1;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !matches!(chars[i], '(' | ')' | '"')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let token = match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                };
+                tokens.push((token, position));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(token, _)| token)
+    }
+
+    fn take(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    /// Skip the peeked operator and return its position.
+    fn skip(&mut self) -> usize {
+        let position = self
+            .tokens
+            .get(self.next)
+            .map_or(0, |(_, position)| *position);
+        self.next += 1;
+        position
+    }
+
+    /// A term has to follow the operator at `position`.
+    fn operand(&self, operator: &'static str, position: usize) -> Result<(), ParseError> {
+        match self.peek() {
+            None | Some(Token::Close) | Some(Token::And) | Some(Token::Or) => {
+                Err(ParseError::MissingOperand { operator, position })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.and()?;
+        while let Some(Token::Or) = self.peek() {
+            let position = self.skip();
+            self.operand("OR", position)?;
+            left = Expression::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.not()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    let position = self.skip();
+                    self.operand("AND", position)?;
+                }
+                Some(Token::Open | Token::Not | Token::Word(_) | Token::Quoted(_)) => {}
+                _ => return Ok(left),
+            }
+            left = Expression::And(Box::new(left), Box::new(self.not()?));
+        }
+    }
+
+    fn not(&mut self) -> Result<Expression, ParseError> {
+        if let Some(Token::Not) = self.peek() {
+            let position = self.skip();
+            self.operand("NOT", position)?;
+            return Ok(Expression::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression, ParseError> {
+        match self.take() {
+            Some((Token::Open, position)) => {
+                self.operand("(", position)?;
+                let expression = self.or()?;
+                match self.take() {
+                    Some((Token::Close, _)) => Ok(expression),
+                    _ => Err(ParseError::UnclosedParenthesis { position }),
+                }
+            }
+            Some((Token::Quoted(text), position)) => text_term(text, position),
+            Some((Token::Word(word), position)) => match word.split_once(':') {
+                Some((name, value)) if !name.is_empty() => {
+                    let field = match field(name) {
+                        Some(field) => field,
+                        None => {
+                            return Err(ParseError::UnknownField {
+                                field: name.to_string(),
+                                position,
+                            })
+                        }
+                    };
+                    // a quoted value, or one separated by a space, is the next token
+                    let (value, value_position) = if !value.is_empty() && value != "~" {
+                        (value.to_string(), position + name.chars().count() + 1)
+                    } else {
+                        match self.tokens.get(self.next).cloned() {
+                            Some((Token::Word(next) | Token::Quoted(next), position)) => {
+                                self.next += 1;
+                                (format!("{}{}", value, next), position)
+                            }
+                            _ => (String::new(), position),
+                        }
+                    };
+                    if value.trim().is_empty() {
+                        return Err(ParseError::MissingValue {
+                            field: name.to_string(),
+                            position,
+                        });
+                    }
+                    Ok(Expression::Field(
+                        field,
+                        condition(field, name, value.trim(), value_position)?,
+                    ))
+                }
+                _ => match word.strip_prefix('~') {
+                    Some("") => match self.take() {
+                        Some((Token::Quoted(text), _)) => similar_term(text, position),
+                        _ => similar_term(String::new(), position),
+                    },
+                    Some(text) => similar_term(text.to_string(), position),
+                    None => text_term(word, position),
+                },
+            },
+            Some((Token::Close, position)) => Err(ParseError::UnexpectedParenthesis { position }),
+            Some((Token::And, position)) => Err(ParseError::MissingOperand {
+                operator: "AND",
+                position,
+            }),
+            Some((Token::Or, position)) => Err(ParseError::MissingOperand {
+                operator: "OR",
+                position,
+            }),
+            // not() takes NOT, and operand() makes sure a term follows every operator
+            Some((Token::Not, _)) | None => Err(ParseError::Empty),
+        }
+    }
+}
+
+fn text_term(text: String, position: usize) -> Result<Expression, ParseError> {
+    if fts::match_expression(&text, &TEXT_COLUMNS).is_none() {
+        return Err(ParseError::InvalidValue {
+            value: text,
+            expected: "search word",
+            position,
+        });
+    }
+    Ok(Expression::Text(text))
+}
+
+fn similar_term(text: String, position: usize) -> Result<Expression, ParseError> {
+    match text_term(text, position)? {
+        Expression::Text(text) => Ok(Expression::Similar(text)),
+        expression => Ok(expression),
+    }
+}
+
+/// Parse a query, see the module documentation for the syntax.
+pub fn parse(text: &str) -> Result<Expression, ParseError> {
+    let mut parser = Parser {
+        tokens: tokens(text)?,
+        next: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    let expression = parser.or()?;
+    match parser.take() {
+        None => Ok(expression),
+        Some((_, position)) => Err(ParseError::UnexpectedParenthesis { position }),
+    }
+}
+
+/// Query text for the photos taken within `kilometres` of a position.
+pub fn near(latitude: f64, longitude: f64, kilometres: f64) -> String {
+    format!("near:{},{},{}km", latitude, longitude, kilometres)
+}
+
+fn condition(
+    field: SearchType,
+    name: &str,
+    value: &str,
+    position: usize,
+) -> Result<Condition, ParseError> {
+    match field {
+        SearchType::GPSLocation => place(name, value, position),
+        SearchType::Resolution => numbers(value, position, "resolution", resolution_lines),
+        SearchType::FrameRate => numbers(value, position, "frame rate", |text| {
+            text.trim_end_matches(|c: char| c.is_ascii_alphabetic())
+                .trim()
+                .parse::<f64>()
+                .ok()
+        })
+        .map(approximate),
+        SearchType::Bitrate => {
+            numbers(value, position, "bitrate (kbit/s)", kilobits).map(approximate)
+        }
+        // `aspect:portrait` works as well
+        SearchType::AspectRatio
+            if name.eq_ignore_ascii_case("orientation")
+                || value.starts_with(|c: char| c.is_ascii_alphabetic()) =>
+        {
+            orientation(value, position)
+        }
+        SearchType::AspectRatio => {
+            numbers(value, position, "aspect ratio", aspect_ratio).map(approximate)
+        }
+        SearchType::Duration => numbers(value, position, "duration", duration_seconds),
+        SearchType::GPSLatitude | SearchType::GPSLongitude | SearchType::GPSAltitude => {
+            numbers(value, position, "number", |text| text.parse::<f64>().ok())
+        }
+        SearchType::CreationDate | SearchType::ModificationDate | SearchType::ReleaseDate => {
+            dates(value, position)
+        }
+        _ => match value.strip_prefix('~').map(str::trim) {
+            Some("") => Err(ParseError::MissingValue {
+                field: name.to_string(),
+                position,
+            }),
+            Some(value) => Ok(Condition::Similar(value.to_string())),
+            None => Ok(Condition::Text(value.to_string())),
+        },
+    }
+}
+
+/// `near:` takes latitude, longitude and an optional radius, `area:` the south, west,
+/// north and east limits.
+fn place(name: &str, value: &str, position: usize) -> Result<Condition, ParseError> {
+    let area = name.eq_ignore_ascii_case("area");
+    let invalid = || ParseError::InvalidValue {
+        value: value.to_string(),
+        expected: if area {
+            "area (south,west,north,east)"
+        } else {
+            "place (latitude,longitude,radius)"
+        },
+        position,
+    };
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    let degrees = |text: &str, limit: f64| match text.parse::<f64>() {
+        Ok(value) if value.abs() <= limit => Ok(value),
+        _ => Err(invalid()),
+    };
+    if area {
+        if parts.len() != 4 {
+            return Err(invalid());
+        }
+        let area = Area {
+            south: degrees(parts[0], 90.0)?,
+            west: degrees(parts[1], 180.0)?,
+            north: degrees(parts[2], 90.0)?,
+            east: degrees(parts[3], 180.0)?,
+        };
+        if area.south > area.north {
+            return Err(invalid());
+        }
+        return Ok(Condition::Area(area));
+    }
+    if parts.len() != 2 && parts.len() != 3 {
+        return Err(invalid());
+    }
+    let kilometres = match parts.get(2) {
+        None => geo::DEFAULT_RADIUS_KM,
+        Some(radius) => {
+            let radius = radius.to_ascii_lowercase();
+            let parsed = match radius.strip_suffix("km") {
+                Some(km) => km.trim().parse::<f64>().ok(),
+                None => match radius.strip_suffix('m') {
+                    Some(m) => m.trim().parse::<f64>().ok().map(|m| m / 1000.0),
+                    None => radius.parse::<f64>().ok(),
+                },
+            };
+            match parsed {
+                Some(kilometres) if kilometres > 0.0 && kilometres.is_finite() => kilometres,
+                _ => return Err(invalid()),
+            }
+        }
+    };
+    Ok(Condition::Near {
+        latitude: degrees(parts[0], 90.0)?,
+        longitude: degrees(parts[1], 180.0)?,
+        kilometres,
+    })
+}
+
+/// Lines of a resolution like `720p`, `1080` or `4k`.
+fn resolution_lines(text: &str) -> Option<f64> {
+    let text = text.to_ascii_lowercase();
+    match text.as_str() {
+        "2k" => Some(1440.0),
+        "4k" | "uhd" => Some(2160.0),
+        "8k" => Some(4320.0),
+        _ => text.strip_suffix('p').unwrap_or(&text).parse::<f64>().ok(),
+    }
+}
+
+/// kbit/s from `192`, `192k`, `192kbps`, `192kbit/s` or `1.4mbps`.
+fn kilobits(text: &str) -> Option<f64> {
+    let text = text.to_ascii_lowercase();
+    let (number, factor) = match text.find(|c: char| c.is_ascii_alphabetic()) {
+        None => (text.as_str(), 1.0),
+        Some(unit) => match &text[unit..] {
+            "k" | "kb/s" | "kbps" | "kbit/s" => (&text[..unit], 1.0),
+            "m" | "mb/s" | "mbps" | "mbit/s" => (&text[..unit], 1000.0),
+            _ => return None,
+        },
+    };
+    number.trim().parse::<f64>().ok().map(|n| n * factor)
+}
+
+/// Width divided by height, from `1.78`, `16:9` or `16/9`.
+fn aspect_ratio(text: &str) -> Option<f64> {
+    match text.split_once([':', '/']) {
+        Some((width, height)) => {
+            let width = width.trim().parse::<f64>().ok()?;
+            let height = height.trim().parse::<f64>().ok()?;
+            if height == 0.0 {
+                return None;
+            }
+            Some(width / height)
+        }
+        None => text.parse::<f64>().ok(),
+    }
+}
+
+fn orientation(value: &str, position: usize) -> Result<Condition, ParseError> {
+    let limit = |value: f64, inclusive: bool| Some(Limit { value, inclusive });
+    match value.to_ascii_lowercase().as_str() {
+        "portrait" => Ok(Condition::Number {
+            min: None,
+            max: limit(1.0 - TOLERANCE, false),
+        }),
+        "landscape" => Ok(Condition::Number {
+            min: limit(1.0 + TOLERANCE, false),
+            max: None,
+        }),
+        "square" => Ok(approximate(Condition::Number {
+            min: limit(1.0, true),
+            max: limit(1.0, true),
+        })),
+        _ => Err(ParseError::InvalidValue {
+            value: value.to_string(),
+            expected: "orientation (portrait, landscape or square)",
+            position,
+        }),
+    }
+}
+
+/// Widen an exact number by `TOLERANCE`, ranges and comparisons are kept.
+fn approximate(condition: Condition) -> Condition {
+    match condition {
+        Condition::Number {
+            min: Some(min),
+            max: Some(max),
+        } if min.value == max.value => Condition::Number {
+            min: Some(Limit {
+                value: min.value - TOLERANCE,
+                inclusive: true,
+            }),
+            max: Some(Limit {
+                value: max.value + TOLERANCE,
+                inclusive: true,
+            }),
+        },
+        condition => condition,
+    }
+}
+
+/// Split off a comparison operator, `=` if there is none.
+fn comparison(value: &str) -> (&str, &str) {
+    for operator in [">=", "<=", ">", "<", "="] {
+        if let Some(rest) = value.strip_prefix(operator) {
+            return (operator, rest.trim());
+        }
+    }
+    ("=", value)
+}
+
+fn numbers(
+    value: &str,
+    position: usize,
+    expected: &'static str,
+    parse: impl Fn(&str) -> Option<f64>,
+) -> Result<Condition, ParseError> {
+    let invalid = || ParseError::InvalidValue {
+        value: value.to_string(),
+        expected,
+        position,
+    };
+    let limit = |text: &str, inclusive: bool| match text {
+        "" => Ok(None),
+        _ => match parse(text) {
+            Some(value) => Ok(Some(Limit { value, inclusive })),
+            None => Err(invalid()),
+        },
+    };
+    let (min, max) = match value.split_once("..") {
+        Some((from, to)) => (limit(from, true)?, limit(to, true)?),
+        None => match comparison(value) {
+            (_, "") => return Err(invalid()),
+            (">=", text) => (limit(text, true)?, None),
+            (">", text) => (limit(text, false)?, None),
+            ("<=", text) => (None, limit(text, true)?),
+            ("<", text) => (None, limit(text, false)?),
+            (_, text) => (limit(text, true)?, limit(text, true)?),
+        },
+    };
+    if min.is_none() && max.is_none() {
+        return Err(invalid());
+    }
+    Ok(Condition::Number { min, max })
+}
+
+/// Seconds in e.g. `90`, `90s`, `90m`, `1.5h` or `1h30m`. Numbers without a unit are seconds.
+fn duration_seconds(text: &str) -> Option<f64> {
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    let mut seconds = 0.0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let factor = match rest[..unit].to_ascii_lowercase().as_str() {
+            "" | "s" | "sec" => 1.0,
+            "m" | "min" => 60.0,
+            "h" => 3600.0,
+            _ => return None,
+        };
+        seconds += number * factor;
+        rest = &rest[unit..];
+    }
+    Some(seconds)
+}
+
+/// The days covered by `2010`, `2010-05` or `2010-05-03`, end excluded.
+fn date_interval(text: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let parts: Vec<&str> = text.split('-').collect();
+    let number = |index: usize| parts[index].parse::<u32>().ok();
+    match parts.len() {
+        1 if parts[0].len() == 4 => {
+            let year = number(0)? as i32;
+            Some((
+                NaiveDate::from_ymd_opt(year, 1, 1)?,
+                NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+            ))
+        }
+        2 => {
+            let start = NaiveDate::from_ymd_opt(number(0)? as i32, number(1)?, 1)?;
+            let end = match start.month() {
+                12 => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)?,
+                month => NaiveDate::from_ymd_opt(start.year(), month + 1, 1)?,
+            };
+            Some((start, end))
+        }
+        3 => {
+            let day = NaiveDate::from_ymd_opt(number(0)? as i32, number(1)?, number(2)?)?;
+            Some((day, day.succ_opt()?))
+        }
+        _ => None,
+    }
+}
+
+fn dates(value: &str, position: usize) -> Result<Condition, ParseError> {
+    let invalid = || ParseError::InvalidValue {
+        value: value.to_string(),
+        expected: "date",
+        position,
+    };
+    let interval = |text: &str| match text {
+        "" => Ok(None),
+        _ => date_interval(text).map(Some).ok_or_else(invalid),
+    };
+    let (from, until) = match value.split_once("..") {
+        Some((from, to)) => (
+            interval(from)?.map(|(start, _)| start),
+            interval(to)?.map(|(_, end)| end),
+        ),
+        None => {
+            let (operator, text) = comparison(value);
+            let (start, end) = match interval(text)? {
+                Some(interval) => interval,
+                None => return Err(invalid()),
+            };
+            match operator {
+                ">=" => (Some(start), None),
+                ">" => (Some(end), None),
+                "<=" => (None, Some(end)),
+                "<" => (None, Some(start)),
+                _ => (Some(start), Some(end)),
+            }
+        }
+    };
+    if from.is_none() && until.is_none() {
+        return Err(invalid());
+    }
+    Ok(Condition::Dates { from, until })
+}
+
+/// Where a field is stored: queries for the metadata_ids of matching rows and the column
+/// they test, or no query for columns of file_metadata itself.
+fn sources(field: SearchType) -> &'static [(&'static str, &'static str)] {
+    match field {
+        SearchType::FilePath => &[("", "filepath")],
+        SearchType::Title => &[
+            ("SELECT video_id FROM video_metadata", "title"),
+            ("SELECT video_id FROM video_metadata", "original_title"),
+            ("SELECT audio_id FROM audio_metadata", "title"),
+            ("SELECT image_id FROM image_metadata", "name"),
+        ],
+        SearchType::Description => &[
+            ("SELECT video_id FROM video_metadata", "description"),
+            ("SELECT video_id FROM video_metadata", "tagline"),
+        ],
+        SearchType::Actor => &[(
+            "SELECT actors.video_id FROM actors
+                INNER JOIN people ON people.person_id = actors.actor_id",
+            "people.person_name",
+        )],
+        SearchType::Director => &[(
+            "SELECT directors.video_id FROM directors
+                INNER JOIN people ON people.person_id = directors.director_id",
+            "people.person_name",
+        )],
+        SearchType::Artist => &[(
+            "SELECT artist_audio_map.audio_id FROM artist_audio_map
+                INNER JOIN artists ON artists.artist_id = artist_audio_map.artist_id",
+            "artists.artist_name",
+        )],
+        SearchType::AlbumArtist => &[(
+            "SELECT albumartist_audio_map.audio_id FROM albumartist_audio_map
+                INNER JOIN artists ON artists.artist_id = albumartist_audio_map.albumartist_id",
+            "artists.artist_name",
+        )],
+        SearchType::Album => &[(
+            "SELECT album_audio_map.audio_id FROM album_audio_map
+                INNER JOIN albums ON albums.album_id = album_audio_map.album_id",
+            "albums.album_name",
+        )],
+        SearchType::Composer => &[("SELECT audio_id FROM audio_metadata", "composer")],
+        SearchType::Genre => &[
+            ("SELECT audio_id FROM audio_metadata", "genre"),
+            ("SELECT video_id FROM genres", "genre"),
+        ],
+        SearchType::Duration => &[
+            ("SELECT video_id FROM video_metadata", "duration"),
+            ("SELECT audio_id FROM audio_metadata", "duration"),
+        ],
+        SearchType::CreationDate => &[("", "creation_time")],
+        SearchType::ModificationDate => &[("", "modification_time")],
+        SearchType::ReleaseDate => &[
+            ("SELECT video_id FROM video_metadata", "released"),
+            ("SELECT audio_id FROM audio_metadata", "released"),
+            ("SELECT image_id FROM image_metadata", "created"),
+        ],
+        SearchType::LenseModel => &[("SELECT image_id FROM image_metadata", "LenseModel")],
+        SearchType::FocalLength => &[("SELECT image_id FROM image_metadata", "Focallength")],
+        SearchType::ExposureTime => &[("SELECT image_id FROM image_metadata", "Exposuretime")],
+        SearchType::FNumber => &[("SELECT image_id FROM image_metadata", "FNumber")],
+        SearchType::GPSLatitude => &[("SELECT image_id FROM image_metadata", "GPSLatitude")],
+        SearchType::GPSLongitude => &[("SELECT image_id FROM image_metadata", "GPSLongitude")],
+        SearchType::GPSAltitude => &[("SELECT image_id FROM image_metadata", "GPSAltitude")],
+        // the place conditions test both columns
+        SearchType::GPSLocation => &[("SELECT image_id FROM image_metadata", "GPSLatitude")],
+        SearchType::Resolution => &[
+            ("SELECT video_id FROM video_metadata", RESOLUTION),
+            ("SELECT image_id FROM image_metadata", RESOLUTION),
+        ],
+        SearchType::FrameRate => &[(
+            "SELECT video_id FROM video_metadata",
+            "NULLIF(framerate, 0)",
+        )],
+        SearchType::Bitrate => &[("SELECT audio_id FROM audio_metadata", "NULLIF(bitrate, 0)")],
+        SearchType::Codec => &[("SELECT video_id FROM video_metadata", "codec")],
+        SearchType::AspectRatio => &[
+            ("SELECT video_id FROM video_metadata", ASPECT_RATIO),
+            ("SELECT image_id FROM image_metadata", ASPECT_RATIO),
+        ],
+        SearchType::Tag => &[(
+            "SELECT tags_media_map.media_id FROM tags_media_map
+                INNER JOIN tags ON tags.tag_id = tags_media_map.tagmap_id",
+            "tags.tag",
+        )],
+        SearchType::Writer => &[(
+            "SELECT writers.video_id FROM writers
+                INNER JOIN people ON people.person_id = writers.writer_id",
+            "people.person_name",
+        )],
+        SearchType::Studio => &[("SELECT video_id FROM studios", "studio")],
+        SearchType::Country => &[("SELECT video_id FROM countries", "country")],
+        SearchType::MovieSet => &[(
+            "SELECT movie_sets_map.video_id FROM movie_sets_map
+                INNER JOIN movie_sets ON movie_sets.set_id = movie_sets_map.set_id",
+            "movie_sets.set_name",
+        )],
+        SearchType::Certification => &[("SELECT video_id FROM video_metadata", "mpaa")],
+        SearchType::ExternalId => &[
+            ("SELECT video_id FROM unique_ids", "value"),
+            ("SELECT video_id FROM unique_ids", "kind || ':' || value"),
+        ],
+        SearchType::Producer => &[],
+    }
+}
+
+/// Seconds since the epoch at local midnight, like creation_time and modification_time.
+fn timestamp(date: NaiveDate) -> i64 {
+    let midnight = date.and_time(NaiveTime::MIN);
+    match midnight.and_local_timezone(chrono::Local).earliest() {
+        Some(time) => time.timestamp(),
+        None => midnight.and_utc().timestamp(),
+    }
+}
+
+fn predicate(
+    field: SearchType,
+    condition: &Condition,
+    column: &str,
+    values: &mut Vec<Value>,
+) -> String {
+    let mut tests = Vec::new();
+    match condition {
+        Condition::Text(text) if matches!(field, SearchType::Tag | SearchType::ExternalId) => {
+            tests.push(format!("fold({}) = ?", column));
+            values.push(Value::Text(fold::fold(text)));
+        }
+        Condition::Text(text) => {
+            tests.push(format!("fold({}) LIKE ? ESCAPE '\\'", column));
+            values.push(Value::Text(like_pattern(&fold::fold(text))));
+        }
+        Condition::Similar(text) => {
+            let exact = predicate(field, &Condition::Text(text.clone()), column, values);
+            tests.push(format!("({} OR similarity({}, ?) >= ?)", exact, column));
+            values.extend([Value::Text(text.clone()), Value::Real(SIMILAR)]);
+        }
+        Condition::Number { min, max } => {
+            if let Some(min) = min {
+                let operator = if min.inclusive { ">=" } else { ">" };
+                tests.push(format!("{} {} ?", column, operator));
+                values.push(Value::Real(min.value));
+            }
+            if let Some(max) = max {
+                let operator = if max.inclusive { "<=" } else { "<" };
+                tests.push(format!("{} {} ?", column, operator));
+                values.push(Value::Real(max.value));
+            }
+        }
+        Condition::Dates { from, until } => {
+            // release dates are stored as text, file times as seconds
+            let value = |date: &NaiveDate| match field {
+                SearchType::ReleaseDate => Value::Text(date.format("%Y-%m-%d").to_string()),
+                _ => Value::Integer(timestamp(*date)),
+            };
+            if let Some(from) = from {
+                tests.push(format!("{} >= ?", column));
+                values.push(value(from));
+            }
+            if let Some(until) = until {
+                tests.push(format!("{} < ?", column));
+                values.push(value(until));
+            }
+        }
+        Condition::Near {
+            latitude,
+            longitude,
+            kilometres,
+        } => {
+            // the box uses the GPS index, the distance is only computed inside it
+            let (test, limits) =
+                Area::around(*latitude, *longitude, *kilometres).sql("GPSLatitude", "GPSLongitude");
+            tests.push(test);
+            values.extend(limits.into_iter().map(Value::Real));
+            tests.push("distance_km(GPSLatitude, GPSLongitude, ?, ?) <= ?".to_string());
+            values.extend([
+                Value::Real(*latitude),
+                Value::Real(*longitude),
+                Value::Real(*kilometres),
+            ]);
+        }
+        Condition::Area(area) => {
+            let (test, limits) = area.sql("GPSLatitude", "GPSLongitude");
+            tests.push(test);
+            values.extend(limits.into_iter().map(Value::Real));
+        }
+    }
+    if matches!(condition, Condition::Near { .. } | Condition::Area(_)) {
+        // photos without a position store 0, 0
+        tests.push("NOT (GPSLatitude = 0 AND GPSLongitude = 0)".to_string());
+    }
+    tests.join(" AND ")
+}
+
+impl Expression {
+    fn compile(&self, values: &mut Vec<Value>) -> String {
+        match self {
+            Expression::And(left, right) => {
+                format!("({} AND {})", left.compile(values), right.compile(values))
+            }
+            Expression::Or(left, right) => {
+                format!("({} OR {})", left.compile(values), right.compile(values))
+            }
+            Expression::Not(expression) => format!("NOT {}", expression.compile(values)),
+            Expression::Text(text) => match fts::match_expression(text, &TEXT_COLUMNS) {
+                Some(expression) => {
+                    values.push(Value::Text(expression));
+                    "metadata_id IN (SELECT rowid FROM media_fts WHERE media_fts MATCH ?)"
+                        .to_string()
+                }
+                None => "0".to_string(),
+            },
+            Expression::Similar(text) => {
+                let mut expression = Expression::Text(text.clone());
+                for field in SIMILAR_FIELDS {
+                    expression = Expression::Or(
+                        Box::new(expression),
+                        Box::new(Expression::Field(field, Condition::Similar(text.clone()))),
+                    );
+                }
+                expression.compile(values)
+            }
+            Expression::Field(field, condition) => {
+                let sources = sources(*field);
+                match sources {
+                    [] => "0".to_string(),
+                    [("", column)] => format!("({})", predicate(*field, condition, column, values)),
+                    _ => {
+                        let queries: Vec<String> = sources
+                            .iter()
+                            .map(|(query, column)| {
+                                format!(
+                                    "{} WHERE {}",
+                                    query,
+                                    predicate(*field, condition, column, values)
+                                )
+                            })
+                            .collect();
+                        format!("metadata_id IN ({})", queries.join(" UNION "))
+                    }
+                }
+            }
+        }
+    }
+
+    /// SQL condition on file_metadata with a `?` for each of the returned values.
+    pub fn sql(&self) -> (String, Vec<Value>) {
+        let mut values = Vec::new();
+        let sql = self.compile(&mut values);
+        (sql, values)
+    }
+
+    /// The same expression with typos accepted in every text term.
+    pub fn fuzzy(self) -> Expression {
+        match self {
+            Expression::And(left, right) => {
+                Expression::And(Box::new(left.fuzzy()), Box::new(right.fuzzy()))
+            }
+            Expression::Or(left, right) => {
+                Expression::Or(Box::new(left.fuzzy()), Box::new(right.fuzzy()))
+            }
+            Expression::Not(expression) => Expression::Not(Box::new(expression.fuzzy())),
+            Expression::Text(text) => Expression::Similar(text),
+            Expression::Field(field, Condition::Text(text)) => {
+                Expression::Field(field, Condition::Similar(text))
+            }
+            expression => expression,
+        }
+    }
+
+    /// Query for the metadata_ids of all matching files.
+    pub fn query(&self) -> Query {
+        let (sql, values) = self.sql();
+        Query::select("SELECT metadata_id FROM file_metadata").condition(&sql, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::{AudioMetadata, ImageMetadata, Tag, UniqueId, VideoMetadata};
+
+    fn text(text: &str) -> Box<Expression> {
+        Box::new(Expression::Text(text.to_string()))
+    }
+
+    fn at_least(value: f64, inclusive: bool) -> Condition {
+        Condition::Number {
+            min: Some(Limit { value, inclusive }),
+            max: None,
+        }
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn operators_bind_not_and_or() {
+        assert_eq!(
+            parse("heat OR crime drama").unwrap(),
+            Expression::Or(
+                text("heat"),
+                Box::new(Expression::And(text("crime"), text("drama")))
+            )
+        );
+        assert_eq!(
+            parse("(heat OR crime) AND NOT drama").unwrap(),
+            Expression::And(
+                Box::new(Expression::Or(text("heat"), text("crime"))),
+                Box::new(Expression::Not(text("drama")))
+            )
+        );
+        assert_eq!(parse("-drama").unwrap(), Expression::Not(text("drama")));
+        assert_eq!(
+            parse("actor:\"Al Pacino\" -tag: favourite").unwrap(),
+            Expression::And(
+                Box::new(Expression::Field(
+                    SearchType::Actor,
+                    Condition::Text("Al Pacino".to_string())
+                )),
+                Box::new(Expression::Not(Box::new(Expression::Field(
+                    SearchType::Tag,
+                    Condition::Text("favourite".to_string())
+                ))))
+            )
+        );
+    }
+
+    #[test]
+    fn numbers_and_dates_take_comparisons_and_ranges() {
+        let field = |query: &str| match parse(query).unwrap() {
+            Expression::Field(_, condition) => condition,
+            other => panic!("not a field: {:?}", other),
+        };
+        assert_eq!(field("duration:>90m"), at_least(5400.0, false));
+        assert_eq!(field("duration:>=1h30m"), at_least(5400.0, true));
+        assert_eq!(field("Duration:>=5400"), at_least(5400.0, true));
+        assert_eq!(
+            field("lat:-34..-33.5"),
+            Condition::Number {
+                min: Some(Limit {
+                    value: -34.0,
+                    inclusive: true
+                }),
+                max: Some(Limit {
+                    value: -33.5,
+                    inclusive: true
+                }),
+            }
+        );
+        assert_eq!(
+            field("released:2010..2015"),
+            Condition::Dates {
+                from: day(2010, 1, 1),
+                until: day(2016, 1, 1),
+            }
+        );
+        assert_eq!(
+            field("released:2010-12"),
+            Condition::Dates {
+                from: day(2010, 12, 1),
+                until: day(2011, 1, 1),
+            }
+        );
+        assert_eq!(
+            field("created:>2010-05-03"),
+            Condition::Dates {
+                from: day(2010, 5, 4),
+                until: None,
+            }
+        );
+        assert_eq!(
+            field("modified:..2010"),
+            Condition::Dates {
+                from: None,
+                until: day(2011, 1, 1),
+            }
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        assert_eq!(parse("  "), Err(ParseError::Empty));
+        assert_eq!(
+            parse("heat producer:ghibli"),
+            Err(ParseError::UnknownField {
+                field: "producer".to_string(),
+                position: 6
+            })
+        );
+        assert_eq!(
+            parse("heat OR"),
+            Err(ParseError::MissingOperand {
+                operator: "OR",
+                position: 6
+            })
+        );
+        assert_eq!(
+            parse("(heat OR crime"),
+            Err(ParseError::UnclosedParenthesis { position: 1 })
+        );
+        assert_eq!(
+            parse("heat)"),
+            Err(ParseError::UnexpectedParenthesis { position: 5 })
+        );
+        assert_eq!(
+            parse("actor:\"Al"),
+            Err(ParseError::UnclosedQuote { position: 7 })
+        );
+        assert_eq!(
+            parse("actor:"),
+            Err(ParseError::MissingValue {
+                field: "actor".to_string(),
+                position: 1
+            })
+        );
+        assert_eq!(
+            parse("duration:>90x"),
+            Err(ParseError::InvalidValue {
+                value: ">90x".to_string(),
+                expected: "duration",
+                position: 10
+            })
+        );
+        assert_eq!(
+            parse("released:2010-13"),
+            Err(ParseError::InvalidValue {
+                value: "2010-13".to_string(),
+                expected: "date",
+                position: 10
+            })
+        );
+    }
+
+    fn tag(name: &str) -> Tag {
+        Tag {
+            tag_id: 0,
+            tag: name.to_string(),
+        }
+    }
+
+    fn library(dir: &std::path::Path) -> super::super::service::Handle {
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(
+            crate::sql::connect_in_memory().unwrap(),
+        ));
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let file = |name: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, name).unwrap();
+            (
+                path.to_string_lossy().to_string(),
+                std::fs::metadata(&path).unwrap(),
+            )
+        };
+        let (path, metadata) = file("heat.mkv");
+        let mut heat = VideoMetadata {
+            title: "Heat".to_string(),
+            date: NaiveDate::from_ymd_opt(1995, 12, 15).unwrap(),
+            path,
+            duration: 10_200,
+            width: 1920,
+            height: 800,
+            framerate: 23.976_025,
+            codec: "h264".to_string(),
+            actors: vec!["Al Pacino".to_string(), "Robert De Niro".to_string()],
+            director: vec!["Michael Mann".to_string()],
+            tags: vec![tag("Favourite")],
+            original_title: "Heat".to_string(),
+            tagline: "A Los Angeles crime saga".to_string(),
+            mpaa: "Rated R".to_string(),
+            genres: vec!["Crime".to_string(), "Thriller".to_string()],
+            studios: vec!["Warner Bros.".to_string()],
+            countries: vec!["United States".to_string()],
+            writers: vec!["Michael Mann".to_string()],
+            unique_ids: vec![
+                UniqueId {
+                    kind: "imdb".to_string(),
+                    value: "tt0113277".to_string(),
+                    default: true,
+                },
+                UniqueId {
+                    kind: "tmdb".to_string(),
+                    value: "949".to_string(),
+                    default: false,
+                },
+            ],
+            movie_set: "Michael Mann Crime".to_string(),
+            ..Default::default()
+        };
+        crate::sql::insert_video(handle.clone(), &mut heat, &metadata, &data);
+        let (path, metadata) = file("insider.mkv");
+        let mut insider = VideoMetadata {
+            title: "The Insider".to_string(),
+            date: NaiveDate::from_ymd_opt(1999, 11, 5).unwrap(),
+            path,
+            duration: 9_420,
+            width: 1280,
+            height: 720,
+            framerate: 25.0,
+            codec: "hevc".to_string(),
+            actors: vec!["Al Pacino".to_string(), "Russell Crowe".to_string()],
+            ..Default::default()
+        };
+        crate::sql::insert_video(handle.clone(), &mut insider, &metadata, &data);
+        let (path, metadata) = file("theme.flac");
+        let mut theme = AudioMetadata {
+            title: "Heat Theme".to_string(),
+            date: NaiveDate::from_ymd_opt(1995, 1, 1).unwrap(),
+            path,
+            duration: 240,
+            bitrate: 128.0,
+            genre: "Soundtrack".to_string(),
+            album: "Heat".to_string(),
+            artist: vec!["Moby".to_string()],
+            ..Default::default()
+        };
+        crate::sql::insert_audio(handle.clone(), &mut theme, &metadata, &data);
+        let (path, metadata) = file("beach.jpeg");
+        let mut beach = ImageMetadata {
+            name: "beach".to_string(),
+            path,
+            lense_model: "EF 50mm f/1.8".to_string(),
+            width: 4000,
+            height: 3000,
+            gps_latitude: -33.8908,
+            gps_longitude: 151.2743,
+            tags: vec![tag("holiday")],
+            ..Default::default()
+        };
+        crate::sql::insert_image(handle.clone(), &mut beach, &metadata, &data);
+        let (path, metadata) = file("harbour.jpeg");
+        let mut harbour = ImageMetadata {
+            name: "harbour".to_string(),
+            path,
+            width: 3000,
+            height: 4000,
+            gps_latitude: -33.8568,
+            gps_longitude: 151.2153,
+            ..Default::default()
+        };
+        crate::sql::insert_image(handle.clone(), &mut harbour, &metadata, &data);
+        let (path, metadata) = file("scan.jpeg");
+        let mut scan = ImageMetadata {
+            name: "scan".to_string(),
+            path,
+            ..Default::default()
+        };
+        crate::sql::insert_image(handle.clone(), &mut scan, &metadata, &data);
+        handle
+    }
+
+    fn names(handle: &super::super::service::Handle, query: &str) -> Vec<String> {
+        let query = parse(query).unwrap().query();
+        let connection = handle.lock().unwrap();
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT filepath FROM file_metadata WHERE metadata_id IN ({})",
+                query.sql()
+            ))
+            .unwrap();
+        let mut names: Vec<String> = statement
+            .query_map(query.params(), |row| row.get::<_, String>(0))
+            .unwrap()
+            .map(|path| {
+                std::path::Path::new(&path.unwrap())
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn queries_select_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = library(dir.path());
+        assert_eq!(names(&handle, "actor:pacino"), ["heat.mkv", "insider.mkv"]);
+        assert_eq!(
+            names(&handle, "actor:pacino -director:mann"),
+            ["insider.mkv"]
+        );
+        assert_eq!(
+            names(&handle, "released:1990..1996 duration:>2h"),
+            ["heat.mkv"]
+        );
+        assert_eq!(names(&handle, "released:1995"), ["heat.mkv", "theme.flac"]);
+        assert_eq!(
+            names(&handle, "tag:favourite OR (lens:50mm lat:<-30)"),
+            ["beach.jpeg", "heat.mkv"]
+        );
+        assert_eq!(names(&handle, "album:heat OR artist:moby"), ["theme.flac"]);
+        assert_eq!(names(&handle, "heat -genre:soundtrack"), ["heat.mkv"]);
+        assert_eq!(names(&handle, "title:\"100%\""), Vec::<String>::new());
+        assert_eq!(names(&handle, "path:beach"), ["beach.jpeg"]);
+    }
+
+    #[test]
+    fn nfo_details_select_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = library(dir.path());
+        assert_eq!(names(&handle, "genre:thriller"), ["heat.mkv"]);
+        assert_eq!(names(&handle, "genre:soundtrack"), ["theme.flac"]);
+        assert_eq!(names(&handle, "writer:mann studio:warner"), ["heat.mkv"]);
+        assert_eq!(names(&handle, "country:\"united states\""), ["heat.mkv"]);
+        assert_eq!(names(&handle, "set:\"mann crime\" mpaa:r"), ["heat.mkv"]);
+        assert_eq!(names(&handle, "description:\"crime saga\""), ["heat.mkv"]);
+        assert_eq!(names(&handle, "id:tt0113277"), ["heat.mkv"]);
+        assert_eq!(names(&handle, "id:tmdb:949"), ["heat.mkv"]);
+        assert_eq!(names(&handle, "id:\"tmdb:949\""), ["heat.mkv"]);
+        assert_eq!(names(&handle, "id:tvdb:949"), Vec::<String>::new());
+        assert_eq!(names(&handle, "id:tt011"), Vec::<String>::new());
+        assert_eq!(names(&handle, "saga"), ["heat.mkv"]);
+    }
+
+    #[test]
+    fn accents_and_typos_are_tolerated() {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = library(dir.path());
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let path = dir.path().join("Amélie.mkv");
+        std::fs::write(&path, "amelie").unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        let mut amelie = VideoMetadata {
+            title: "Le Fabuleux Destin d'Amélie Poulain".to_string(),
+            path: path.to_string_lossy().to_string(),
+            director: vec!["Jean-Pierre Jeunet".to_string()],
+            tags: vec![tag("Préféré")],
+            ..Default::default()
+        };
+        crate::sql::insert_video(handle.clone(), &mut amelie, &metadata, &data);
+        let path = dir.path().join("joga.flac");
+        std::fs::write(&path, "joga").unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        let mut joga = AudioMetadata {
+            title: "Jóga".to_string(),
+            path: path.to_string_lossy().to_string(),
+            album: "Homogenic".to_string(),
+            artist: vec!["Björk".to_string()],
+            ..Default::default()
+        };
+        crate::sql::insert_audio(handle.clone(), &mut joga, &metadata, &data);
+
+        assert_eq!(names(&handle, "artist:bjork"), ["joga.flac"]);
+        assert_eq!(names(&handle, "title:JOGA"), ["joga.flac"]);
+        assert_eq!(names(&handle, "title:amelie"), ["Amélie.mkv"]);
+        assert_eq!(names(&handle, "path:AMELIE"), ["Amélie.mkv"]);
+        assert_eq!(names(&handle, "tag:prefere"), ["Amélie.mkv"]);
+        assert_eq!(names(&handle, "director:jeunnet"), Vec::<String>::new());
+        assert_eq!(names(&handle, "director:~jeunnet"), ["Amélie.mkv"]);
+        assert_eq!(names(&handle, "~jeunnet"), ["Amélie.mkv"]);
+        assert_eq!(names(&handle, "~homogenik"), ["joga.flac"]);
+        // typos don't turn every word into a match
+        assert_eq!(names(&handle, "~insomnia"), Vec::<String>::new());
+
+        assert_eq!(
+            parse("~\"quentin tarentino\""),
+            Ok(Expression::Similar("quentin tarentino".to_string()))
+        );
+        assert_eq!(parse("director:~ tarentino"), parse("director:~tarentino"));
+        assert_eq!(
+            parse("jeunnet -title:heat").unwrap().fuzzy(),
+            Expression::And(
+                Box::new(Expression::Similar("jeunnet".to_string())),
+                Box::new(Expression::Not(Box::new(Expression::Field(
+                    SearchType::Title,
+                    Condition::Similar("heat".to_string())
+                ))))
+            )
+        );
+        assert!(matches!(
+            parse("title:~"),
+            Err(ParseError::MissingValue { .. })
+        ));
+        assert!(matches!(parse("~"), Err(ParseError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn technical_values_take_units() {
+        let field = |query: &str| match parse(query).unwrap() {
+            Expression::Field(_, condition) => condition,
+            other => panic!("not a field: {:?}", other),
+        };
+        assert_eq!(field("resolution:>=4k"), at_least(2160.0, true));
+        assert_eq!(field("resolution:>720p"), at_least(720.0, false));
+        assert_eq!(field("bitrate:>=1.4mbps"), at_least(1400.0, true));
+        assert_eq!(field("bitrate:>192kbit/s"), at_least(192.0, false));
+        assert_eq!(field("fps:>=50fps"), at_least(50.0, true));
+        assert_eq!(
+            field("aspect:16:9"),
+            Condition::Number {
+                min: Some(Limit {
+                    value: 16.0 / 9.0 - TOLERANCE,
+                    inclusive: true
+                }),
+                max: Some(Limit {
+                    value: 16.0 / 9.0 + TOLERANCE,
+                    inclusive: true
+                }),
+            }
+        );
+        assert_eq!(field("aspect:landscape"), field("orientation:Landscape"));
+        assert_eq!(
+            parse("orientation:diagonal"),
+            Err(ParseError::InvalidValue {
+                value: "diagonal".to_string(),
+                expected: "orientation (portrait, landscape or square)",
+                position: 13
+            })
+        );
+        assert!(parse("bitrate:<192 bananas").is_ok());
+        assert!(parse("bitrate:<192bananas").is_err());
+    }
+
+    #[test]
+    fn technical_values_select_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = library(dir.path());
+        // 1920x800 is scope 1080p, 4000x3000 and 3000x4000 are photos
+        assert_eq!(names(&handle, "resolution:1080p"), ["heat.mkv"]);
+        assert_eq!(names(&handle, "resolution:<=720p"), ["insider.mkv"]);
+        assert_eq!(
+            names(&handle, "resolution:>=4k"),
+            ["beach.jpeg", "harbour.jpeg"]
+        );
+        assert_eq!(names(&handle, "fps:23.976"), ["heat.mkv"]);
+        assert_eq!(names(&handle, "fps:>24"), ["insider.mkv"]);
+        assert_eq!(names(&handle, "codec:HEVC"), ["insider.mkv"]);
+        assert_eq!(names(&handle, "bitrate:<192k"), ["theme.flac"]);
+        assert_eq!(names(&handle, "bitrate:>=192k"), Vec::<String>::new());
+        assert_eq!(names(&handle, "orientation:portrait"), ["harbour.jpeg"]);
+        assert_eq!(names(&handle, "aspect:>2"), ["heat.mkv"]);
+        // files without a known size are neither small nor square
+        assert_eq!(
+            names(&handle, "resolution:<100 OR aspect:square"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn places_take_a_radius_or_a_box() {
+        let field = |query: &str| match parse(query).unwrap() {
+            Expression::Field(SearchType::GPSLocation, condition) => condition,
+            other => panic!("not a place: {:?}", other),
+        };
+        assert_eq!(
+            field("near:-33.85,151.2"),
+            Condition::Near {
+                latitude: -33.85,
+                longitude: 151.2,
+                kilometres: geo::DEFAULT_RADIUS_KM,
+            }
+        );
+        assert_eq!(
+            field("near:\"-33.85, 151.2, 500m\""),
+            Condition::Near {
+                latitude: -33.85,
+                longitude: 151.2,
+                kilometres: 0.5,
+            }
+        );
+        assert_eq!(
+            field(&near(-33.85, 151.2, 10.0)),
+            Condition::Near {
+                latitude: -33.85,
+                longitude: 151.2,
+                kilometres: 10.0,
+            }
+        );
+        assert_eq!(
+            field("area:-34,151,-33.5,151.5"),
+            Condition::Area(Area {
+                south: -34.0,
+                west: 151.0,
+                north: -33.5,
+                east: 151.5,
+            })
+        );
+        for invalid in [
+            "near:95,10",
+            "near:10",
+            "near:10,20,-1km",
+            "area:-33,151,-34,152",
+        ] {
+            assert!(
+                matches!(parse(invalid), Err(ParseError::InvalidValue { .. })),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn places_select_photos_by_distance() {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = library(dir.path());
+        // the beach is about 6.6 km from the harbour
+        assert_eq!(names(&handle, "near:-33.8568,151.2153"), ["harbour.jpeg"]);
+        assert_eq!(
+            names(&handle, "near:-33.8568,151.2153,7km"),
+            ["beach.jpeg", "harbour.jpeg"]
+        );
+        assert_eq!(
+            names(&handle, "near:-33.8568,151.2153,6km"),
+            ["harbour.jpeg"]
+        );
+        assert_eq!(names(&handle, "area:-34,151.25,-33,152"), ["beach.jpeg"]);
+        // photos without a position are never near anything
+        assert_eq!(
+            names(&handle, "area:-1,-1,1,1 OR near:0,0,100"),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Accent- and case-insensitive text matching, and typo tolerance by trigram similarity.
+//!
+//! `fold()` takes accents off the letters and lower-cases them, so "Björk", "BJORK" and
+//! "bjork" are the same text. The full-text index already folds words like this with
+//! `remove_diacritics 2`, `register()` adds `fold(text)` and `similarity(text, query)` to
+//! the SQL of a connection for the fields and paths searched with LIKE.
+
+use std::collections::HashSet;
+
+use icu_normalizer::DecomposingNormalizer;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+/// Similarity from which a typo-tolerant search counts a text as matching.
+pub const SIMILAR: f64 = 0.5;
+
+/// Accents, umlaut dots and the like, which follow their letter after decomposition.
+fn is_combining(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// `text` in lower case without accents, for comparisons only.
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in DecomposingNormalizer::new_nfd().normalize(text).chars() {
+        match c {
+            c if is_combining(c) => {}
+            // letters without a decomposition
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'æ' | 'Æ' => folded.push_str("ae"),
+            'œ' | 'Œ' => folded.push_str("oe"),
+            'þ' | 'Þ' => folded.push_str("th"),
+            'ø' | 'Ø' => folded.push('o'),
+            'ł' | 'Ł' => folded.push('l'),
+            'đ' | 'Đ' | 'ð' | 'Ð' => folded.push('d'),
+            'ı' => folded.push('i'),
+            c => folded.extend(c.to_lowercase().filter(|c| !is_combining(*c))),
+        }
+    }
+    folded
+}
+
+fn words(text: &str) -> Vec<Vec<char>> {
+    fold(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.chars().collect())
+        .collect()
+}
+
+/// Groups of three letters of the words, with two spaces in front of every word and
+/// one after it, so the start of a word counts more than its end.
+fn trigrams(words: &[Vec<char>]) -> HashSet<[char; 3]> {
+    let mut trigrams = HashSet::new();
+    for word in words {
+        let mut padded = vec![' ', ' '];
+        padded.extend(word);
+        padded.push(' ');
+        for window in padded.windows(3) {
+            trigrams.insert([window[0], window[1], window[2]]);
+        }
+    }
+    trigrams
+}
+
+/// How close `query` comes to the words of `text`, from 0 to 1. The query is compared
+/// with every run of as many words of the text, the closest one counts.
+pub fn similarity(text: &str, query: &str) -> f64 {
+    let query = words(query);
+    let text = words(text);
+    if query.is_empty() || text.is_empty() {
+        return 0.0;
+    }
+    let wanted = trigrams(&query);
+    text.windows(query.len().min(text.len()))
+        .map(|window| {
+            let found = trigrams(window);
+            let shared = wanted.intersection(&found).count();
+            shared as f64 / (wanted.len() + found.len() - shared) as f64
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Make `fold()` and `similarity()` available to the SQL of `connection`.
+pub fn register(connection: &Connection) -> Result<(), rusqlite::Error> {
+    let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+    connection.create_scalar_function("fold", 1, flags, |context| {
+        Ok(context.get::<Option<String>>(0)?.map(|text| fold(&text)))
+    })?;
+    connection.create_scalar_function("similarity", 2, flags, |context| {
+        match (
+            context.get::<Option<String>>(0)?,
+            context.get::<Option<String>>(1)?,
+        ) {
+            (Some(text), Some(query)) => Ok(Some(similarity(&text, &query))),
+            _ => Ok(None),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accents_and_case_are_folded() {
+        assert_eq!(fold("Björk"), "bjork");
+        assert_eq!(fold("AMÉLIE"), "amelie");
+        assert_eq!(fold("Amélie"), fold("Ame\u{301}lie"));
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("Søren Łukasz"), "soren lukasz");
+        assert_eq!(fold("İstanbul"), "istanbul");
+        assert_eq!(fold("東京 2020"), "東京 2020");
+    }
+
+    #[test]
+    fn typos_are_similar() {
+        assert!(similarity("Quentin Tarantino", "tarntino") >= SIMILAR);
+        assert!(similarity("Quentin Tarantino", "tarentino") >= SIMILAR);
+        assert!(similarity("Quentin Tarantino", "quentin tarentino") >= SIMILAR);
+        assert!(similarity("Le Fabuleux Destin d'Amélie Poulain", "amelie") >= SIMILAR);
+        assert!(similarity("Quentin Tarantino", "tornado") < SIMILAR);
+        assert!(similarity("Quentin Tarantino", "") == 0.0);
+        assert_eq!(similarity("Björk", "bjork"), 1.0);
+    }
+
+    #[test]
+    fn sql_functions_fold_and_compare() {
+        let connection = Connection::open_in_memory().unwrap();
+        register(&connection).unwrap();
+        let (folded, close): (String, f64) = connection
+            .query_row(
+                "SELECT fold('Björk'), similarity('Quentin Tarantino', 'tarntino')",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(folded, "bjork");
+        assert_eq!(close, similarity("Quentin Tarantino", "tarntino"));
+        let missing: Option<f64> = connection
+            .query_row("SELECT similarity(NULL, 'x')", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(missing, None);
+    }
+}
@@ -0,0 +1,463 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+use rusqlite::{params, Connection};
+
+/// Marks placed around matching words in search snippets.
+pub const HIGHLIGHT_START: &str = "«";
+pub const HIGHLIGHT_END: &str = "»";
+
+/// Columns of the media_fts full-text index. The rowid of a row is the metadata_id of
+/// the file in file_metadata, which is also the video_id, audio_id or image_id.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Column {
+    Title,
+    Description,
+    People,
+    Album,
+    Genre,
+    Tags,
+    Lyrics,
+    Subtitles,
+}
+
+impl Column {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Title => "title",
+            Column::Description => "description",
+            Column::People => "people",
+            Column::Album => "album",
+            Column::Genre => "genre",
+            Column::Tags => "tags",
+            Column::Lyrics => "lyrics",
+            Column::Subtitles => "subtitles",
+        }
+    }
+
+    /// Columns covered by the text fields enabled in a search.
+    pub fn from_search(search: &crate::sql::SearchData) -> Vec<Column> {
+        let mut columns = Vec::new();
+        if search.title {
+            columns.push(Column::Title);
+        }
+        if search.description {
+            columns.push(Column::Description);
+            columns.push(Column::Lyrics);
+            columns.push(Column::Subtitles);
+        }
+        if search.actor
+            || search.director
+            || search.artist
+            || search.album_artist
+            || search.composer
+        {
+            columns.push(Column::People);
+        }
+        if search.album {
+            columns.push(Column::Album);
+        }
+        if search.genre {
+            columns.push(Column::Genre);
+        }
+        if search.tags {
+            columns.push(Column::Tags);
+        }
+        columns
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Entry {
+    title: String,
+    description: String,
+    people: String,
+    album: String,
+    genre: String,
+    tags: String,
+    lyrics: String,
+    subtitles: String,
+}
+
+/// A search result with its bm25 rank (lower is better) and a highlighted snippet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Hit {
+    pub id: u32,
+    pub rank: f64,
+    pub snippet: String,
+}
+
+pub(crate) fn create(connection: &Connection) -> Result<(), rusqlite::Error> {
+    match connection.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS media_fts USING fts5(
+            title,
+            description,
+            people,
+            album,
+            genre,
+            tags,
+            lyrics,
+            subtitles,
+            tokenize = 'unicode61 remove_diacritics 2'
+        )",
+        (),
+    ) {
+        Ok(_ret) => Ok(()),
+        Err(error) => {
+            log::error!("Failed to create table media_fts: {}", error);
+            Err(error)
+        }
+    }
+}
+
+fn write(connection: &Connection, id: u32, entry: &Entry) {
+    remove(connection, id);
+    match connection.execute(
+        "INSERT INTO media_fts (rowid, title, description, people, album, genre, tags, lyrics, subtitles) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            &id,
+            &entry.title,
+            &entry.description,
+            &entry.people,
+            &entry.album,
+            &entry.genre,
+            &entry.tags,
+            &entry.lyrics,
+            &entry.subtitles
+        ],
+    ) {
+        Ok(_retval) => {}
+        Err(error) => {
+            log::error!("Failed to update full-text index for {}: {}", id, error);
+        }
+    }
+}
+
+fn timed_texts(files: &[String]) -> String {
+    files
+        .iter()
+        .filter(|f| f.ends_with(".srt") || f.ends_with(".lrc"))
+        .map(|f| crate::parsers::timed_text(f))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn tag_text(tags: &[crate::sql::Tag]) -> String {
+    tags.iter()
+        .map(|t| t.tag.clone())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn index_video(connection: &Connection, metadata: &crate::sql::VideoMetadata) {
+    let mut people = metadata.director.clone();
+    people.extend(metadata.actors.iter().cloned());
+    people.extend(metadata.writers.iter().cloned());
+    let mut titles = vec![if metadata.title.is_empty() {
+        metadata.name.clone()
+    } else {
+        metadata.title.clone()
+    }];
+    for title in [&metadata.original_title, &metadata.sort_title] {
+        if !title.is_empty() && !titles.contains(title) {
+            titles.push(title.clone());
+        }
+    }
+    let mut description = vec![metadata.description.clone()];
+    if !metadata.tagline.is_empty() {
+        description.push(metadata.tagline.clone());
+    }
+    // the movie set is to a movie what the album is to a song
+    let entry = Entry {
+        title: titles.join("\n"),
+        description: description.join("\n"),
+        people: people.join("\n"),
+        album: metadata.movie_set.clone(),
+        genre: metadata.genres.join("\n"),
+        tags: tag_text(&metadata.tags),
+        subtitles: timed_texts(&metadata.subtitles),
+        ..Default::default()
+    };
+    write(connection, metadata.id, &entry);
+}
+
+pub fn index_audio(connection: &Connection, metadata: &crate::sql::AudioMetadata) {
+    let mut people = metadata.artist.clone();
+    people.extend(metadata.albumartist.iter().cloned());
+    if !metadata.composer.is_empty() {
+        people.push(metadata.composer.clone());
+    }
+    let entry = Entry {
+        title: if metadata.title.is_empty() {
+            metadata.name.clone()
+        } else {
+            metadata.title.clone()
+        },
+        people: people.join("\n"),
+        album: metadata.album.clone(),
+        genre: metadata.genre.clone(),
+        tags: tag_text(&metadata.tags),
+        lyrics: timed_texts(&metadata.lyrics),
+        ..Default::default()
+    };
+    write(connection, metadata.id, &entry);
+}
+
+pub fn index_image(connection: &Connection, metadata: &crate::sql::ImageMetadata) {
+    let entry = Entry {
+        title: metadata.name.clone(),
+        people: metadata.photographer.clone(),
+        tags: tag_text(&metadata.tags),
+        ..Default::default()
+    };
+    write(connection, metadata.id, &entry);
+}
+
+pub fn remove(connection: &Connection, id: u32) {
+    if let Err(error) = connection.execute("DELETE FROM media_fts WHERE rowid = ?1", params![&id]) {
+        log::error!("Failed to remove {} from full-text index: {}", id, error);
+    }
+}
+
+/// Re-read the user defined tags of one media file into the index.
+pub fn refresh_tags(connection: &Connection, media_id: u32) {
+    match connection.execute(
+        "UPDATE media_fts SET tags = (SELECT ifnull(group_concat(tags.tag, char(10)), '') FROM tags_media_map
+            INNER JOIN tags ON tags.tag_id = tags_media_map.tagmap_id
+            WHERE tags_media_map.media_id = ?1) WHERE rowid = ?1",
+        params![&media_id],
+    ) {
+        Ok(_retval) => {}
+        Err(error) => {
+            log::error!("Failed to update tags in full-text index for {}: {}", media_id, error);
+        }
+    }
+}
+
+/// Fill the index from the metadata tables, reading lyrics and subtitle files from disk.
+pub(crate) fn rebuild(connection: &Connection) -> Result<(), rusqlite::Error> {
+    let tags = "(SELECT ifnull(group_concat(tags.tag, char(10)), '') FROM tags_media_map
+        INNER JOIN tags ON tags.tag_id = tags_media_map.tagmap_id
+        WHERE tags_media_map.media_id = m.id)";
+    connection.execute("DELETE FROM media_fts", ())?;
+    connection.execute(
+        &format!(
+            "INSERT INTO media_fts (rowid, title, description, people, album, genre, tags, lyrics, subtitles)
+            SELECT m.id, m.title, m.description, m.people, '', '', {}, '', '' FROM (
+                SELECT video_id AS id,
+                CASE WHEN ifnull(title, '') = '' THEN name ELSE title END AS title,
+                ifnull(description, '') AS description,
+                (SELECT ifnull(group_concat(person_name, char(10)), '') FROM people WHERE person_id IN (
+                    SELECT director_id FROM directors WHERE directors.video_id = video_metadata.video_id
+                    UNION SELECT actor_id FROM actors WHERE actors.video_id = video_metadata.video_id)) AS people
+                FROM video_metadata) AS m",
+            tags
+        ),
+        (),
+    )?;
+    connection.execute(
+        &format!(
+            "INSERT INTO media_fts (rowid, title, description, people, album, genre, tags, lyrics, subtitles)
+            SELECT m.id, m.title, '', m.people, m.album, m.genre, {}, '', '' FROM (
+                SELECT audio_id AS id,
+                CASE WHEN ifnull(title, '') = '' THEN name ELSE title END AS title,
+                (SELECT ifnull(group_concat(artist_name, char(10)), '') FROM artists WHERE artist_id IN (
+                    SELECT artist_id FROM artist_audio_map WHERE artist_audio_map.audio_id = audio_metadata.audio_id
+                    UNION SELECT albumartist_id FROM albumartist_audio_map WHERE albumartist_audio_map.audio_id = audio_metadata.audio_id))
+                    || char(10) || ifnull(composer, '') AS people,
+                (SELECT ifnull(group_concat(album_name, char(10)), '') FROM albums INNER JOIN album_audio_map
+                    ON albums.album_id = album_audio_map.album_id WHERE album_audio_map.audio_id = audio_metadata.audio_id) AS album,
+                ifnull(genre, '') AS genre
+                FROM audio_metadata) AS m",
+            tags
+        ),
+        (),
+    )?;
+    connection.execute(
+        &format!(
+            "INSERT INTO media_fts (rowid, title, description, people, album, genre, tags, lyrics, subtitles)
+            SELECT m.id, m.title, '', m.people, '', '', {}, '', '' FROM (
+                SELECT image_id AS id, ifnull(name, '') AS title, ifnull(Photographer, '') AS people
+                FROM image_metadata) AS m",
+            tags
+        ),
+        (),
+    )?;
+    for (table, id_column, file_column, fts_column) in [
+        ("subtitles", "video_id", "subpath", "subtitles"),
+        ("lyrics", "audio_id", "lyricsfile", "lyrics"),
+    ] {
+        let mut files: std::collections::BTreeMap<u32, Vec<String>> =
+            std::collections::BTreeMap::new();
+        let mut statement = connection.prepare(&format!(
+            "SELECT {}, {} FROM {}",
+            id_column, file_column, table
+        ))?;
+        let mut rows = statement.query(params![])?;
+        while let Some(row) = rows.next()? {
+            let id: u32 = row.get(0)?;
+            let file: String = row.get(1)?;
+            files.entry(id).or_default().push(file);
+        }
+        for (id, files) in files {
+            connection.execute(
+                &format!("UPDATE media_fts SET {} = ?1 WHERE rowid = ?2", fts_column),
+                params![&timed_texts(&files), &id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Turn free text into an FTS5 query: every word is matched as a prefix, all words have
+/// to appear, and only the given columns are searched. Returns None if nothing is left.
+pub fn match_expression(text: &str, columns: &[Column]) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .filter(|t| t.chars().any(|c| c.is_alphanumeric()))
+        .map(|t| format!("\"{}\"*", t.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() || columns.is_empty() {
+        return None;
+    }
+    let names: Vec<&str> = columns.iter().map(|c| c.name()).collect();
+    Some(format!("{{{}}} : ({})", names.join(" "), terms.join(" ")))
+}
+
+/// Search the index, best matches first.
+pub fn search(connection: &Connection, text: &str, columns: &[Column]) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    let expression = match match_expression(text, columns) {
+        Some(expression) => expression,
+        None => return hits,
+    };
+    let query = "SELECT rowid, rank, snippet(media_fts, -1, ?2, ?3, '…', 12) FROM media_fts
+        WHERE media_fts MATCH ?1 ORDER BY rank";
+    match connection.prepare(query) {
+        Ok(mut statement) => {
+            match statement.query(params![&expression, HIGHLIGHT_START, HIGHLIGHT_END]) {
+                Ok(mut rows) => loop {
+                    match rows.next() {
+                        Ok(Some(row)) => {
+                            let id = match row.get(0) {
+                                Ok(val) => val,
+                                Err(error) => {
+                                    log::error!("Failed to read id for search hit: {}", error);
+                                    continue;
+                                }
+                            };
+                            hits.push(Hit {
+                                id,
+                                rank: row.get(1).unwrap_or_default(),
+                                snippet: row.get(2).unwrap_or_default(),
+                            });
+                        }
+                        Ok(None) => break,
+                        Err(error) => {
+                            log::error!("Failed to read a row from media_fts: {}", error);
+                            break;
+                        }
+                    }
+                },
+                Err(err) => {
+                    log::error!("could not search media_fts: {}", err);
+                }
+            }
+        }
+        Err(err) => {
+            log::error!("could not prepare SQL statement: {}", err);
+        }
+    }
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        crate::sql::connect_in_memory().unwrap()
+    }
+
+    #[test]
+    fn match_expression_quotes_words() {
+        assert_eq!(
+            match_expression("O'Brien \"quoted\"", &[Column::People]),
+            Some("{people} : (\"O'Brien\"* \"\"\"quoted\"\"\"*)".to_string())
+        );
+        assert_eq!(match_expression("  - ", &[Column::Title]), None);
+        assert_eq!(match_expression("word", &[]), None);
+    }
+
+    #[test]
+    fn index_search_and_remove() {
+        let connection = connection();
+        let video = crate::sql::VideoMetadata {
+            id: 7,
+            name: "heat.mkv".to_string(),
+            title: "Heat".to_string(),
+            description: "A group of professional bank robbers".to_string(),
+            director: vec!["Michael Mann".to_string()],
+            actors: vec!["Al Pacino".to_string(), "Robert De Niro".to_string()],
+            ..Default::default()
+        };
+        index_video(&connection, &video);
+        let hits = search(&connection, "pacin", &[Column::People]);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, 7);
+        assert!(hits[0].snippet.contains("«Pacino»"));
+        assert!(search(&connection, "pacino", &[Column::Title]).is_empty());
+
+        // re-indexing replaces the previous row
+        index_video(&connection, &video);
+        assert_eq!(search(&connection, "heat", &[Column::Title]).len(), 1);
+
+        remove(&connection, 7);
+        assert!(search(&connection, "heat", &[Column::Title]).is_empty());
+    }
+
+    #[test]
+    fn results_are_ranked() {
+        let connection = connection();
+        for (id, title, description) in [
+            (1, "Other", "nothing about boats here, only one boat"),
+            (2, "Boat", "boat boat boat"),
+        ] {
+            let video = crate::sql::VideoMetadata {
+                id,
+                title: title.to_string(),
+                description: description.to_string(),
+                ..Default::default()
+            };
+            index_video(&connection, &video);
+        }
+        let hits = search(&connection, "boat", &[Column::Title, Column::Description]);
+        assert_eq!(hits.iter().map(|h| h.id).collect::<Vec<u32>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn rebuild_reads_metadata_tables() {
+        let connection = connection();
+        connection
+            .execute_batch(
+                "INSERT INTO video_metadata (video_id, name, title, released, description) VALUES (1, 'alien.mkv', '', 0, 'In space');
+                INSERT INTO people (person_id, person_name) VALUES (1, 'Ridley Scott');
+                INSERT INTO directors (director_id, video_id) VALUES (1, 1);
+                INSERT INTO audio_metadata (audio_id, name, title, released, genre, composer) VALUES (2, 'song.flac', 'Song', 0, 'Jazz', 'Somebody');
+                INSERT INTO albums (album_id, album_name) VALUES (1, 'Kind of Blue');
+                INSERT INTO album_audio_map (audio_id, album_id) VALUES (2, 1);
+                INSERT INTO image_metadata (image_id, name, path, created, Photographer) VALUES (3, 'beach.jpeg', '/beach.jpeg', 0, 'Ansel');
+                INSERT INTO tags (tag_id, tag) VALUES (1, 'holiday');
+                INSERT INTO tags_media_map (media_id, tagmap_id) VALUES (3, 1);",
+            )
+            .unwrap();
+        rebuild(&connection).unwrap();
+        assert_eq!(search(&connection, "alien", &[Column::Title])[0].id, 1);
+        assert_eq!(search(&connection, "ridley", &[Column::People])[0].id, 1);
+        assert_eq!(search(&connection, "blue", &[Column::Album])[0].id, 2);
+        assert_eq!(search(&connection, "jazz", &[Column::Genre])[0].id, 2);
+        assert_eq!(search(&connection, "holiday", &[Column::Tags])[0].id, 3);
+    }
+}
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Distances between GPS positions, for the `near:` search.
+//!
+//! SQLite has no trigonometric functions, so `register()` adds
+//! `distance_km(lat1, lon1, lat2, lon2)` to every connection. Queries narrow the rows down
+//! with `Area::around()` first, which the GPS index answers, and only compute the distance
+//! for the rows inside that box.
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+/// Mean earth radius used by the haversine formula.
+pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// Radius of a `near:` search without one.
+pub const DEFAULT_RADIUS_KM: f64 = 1.0;
+
+/// Great-circle distance between two positions in degrees.
+pub fn distance_km(latitude1: f64, longitude1: f64, latitude2: f64, longitude2: f64) -> f64 {
+    let (phi1, phi2) = (latitude1.to_radians(), latitude2.to_radians());
+    let delta_phi = (latitude2 - latitude1).to_radians();
+    let delta_lambda = (longitude2 - longitude1).to_radians();
+    let a = (delta_phi / 2.0).sin().powi(2)
+        + phi1.cos() * phi2.cos() * (delta_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+/// Make `distance_km()` available to the SQL of `connection`.
+pub fn register(connection: &Connection) -> Result<(), rusqlite::Error> {
+    connection.create_scalar_function(
+        "distance_km",
+        4,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |context| {
+            let mut degrees = [0.0; 4];
+            for (i, value) in degrees.iter_mut().enumerate() {
+                match context.get::<Option<f64>>(i)? {
+                    Some(v) => *value = v,
+                    None => return Ok(None),
+                }
+            }
+            Ok(Some(distance_km(
+                degrees[0], degrees[1], degrees[2], degrees[3],
+            )))
+        },
+    )
+}
+
+/// Latitude and longitude limits in degrees. `west` is greater than `east` for boxes
+/// crossing the 180th meridian.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Area {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl Area {
+    /// Smallest box holding every position within `kilometres` of the given one.
+    pub fn around(latitude: f64, longitude: f64, kilometres: f64) -> Area {
+        let degrees = (kilometres / EARTH_RADIUS_KM).to_degrees();
+        let south = latitude - degrees;
+        let north = latitude + degrees;
+        if south <= -90.0 || north >= 90.0 {
+            // the circle holds a pole, so every longitude is in range
+            return Area {
+                south: south.max(-90.0),
+                west: -180.0,
+                north: north.min(90.0),
+                east: 180.0,
+            };
+        }
+        let ratio = (kilometres / EARTH_RADIUS_KM).sin() / latitude.to_radians().cos();
+        if ratio >= 1.0 {
+            return Area {
+                south,
+                west: -180.0,
+                north,
+                east: 180.0,
+            };
+        }
+        let spread = ratio.asin().to_degrees();
+        Area {
+            south,
+            west: wrap(longitude - spread),
+            north,
+            east: wrap(longitude + spread),
+        }
+    }
+
+    /// SQL condition on the `latitude` and `longitude` columns, with a `?` for each of
+    /// the returned values.
+    pub fn sql(&self, latitude: &str, longitude: &str) -> (String, Vec<f64>) {
+        let longitudes = if self.west <= self.east {
+            format!("{} BETWEEN ? AND ?", longitude)
+        } else {
+            format!("({} >= ? OR {} <= ?)", longitude, longitude)
+        };
+        (
+            format!("{} BETWEEN ? AND ? AND {}", latitude, longitudes),
+            vec![self.south, self.north, self.west, self.east],
+        )
+    }
+}
+
+/// Longitude moved into -180..=180.
+fn wrap(longitude: f64) -> f64 {
+    if longitude > 180.0 {
+        longitude - 360.0
+    } else if longitude < -180.0 {
+        longitude + 360.0
+    } else {
+        longitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances_follow_the_great_circle() {
+        // Sydney Opera House to the Harbour Bridge, and Paris to London
+        let harbour = distance_km(-33.8568, 151.2153, -33.8523, 151.2108);
+        assert!((harbour - 0.65).abs() < 0.02, "{}", harbour);
+        let channel = distance_km(48.8566, 2.3522, 51.5074, -0.1278);
+        assert!((channel - 343.5).abs() < 1.0, "{}", channel);
+        assert_eq!(distance_km(10.0, 20.0, 10.0, 20.0), 0.0);
+    }
+
+    #[test]
+    fn sql_function_matches_rust() {
+        let connection = Connection::open_in_memory().unwrap();
+        register(&connection).unwrap();
+        let distance: f64 = connection
+            .query_row(
+                "SELECT distance_km(48.8566, 2.3522, 51.5074, -0.1278)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(distance, distance_km(48.8566, 2.3522, 51.5074, -0.1278));
+        let missing: Option<f64> = connection
+            .query_row("SELECT distance_km(NULL, 2.0, 51.0, 0.0)", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn boxes_hold_the_whole_circle() {
+        let area = Area::around(60.0, 10.0, 100.0);
+        for bearing in 0..360 {
+            // walk 99.9 km along the bearing and check the end point is inside
+            let (phi, lambda) = (60f64.to_radians(), 10f64.to_radians());
+            let delta = 99.9 / EARTH_RADIUS_KM;
+            let theta = (bearing as f64).to_radians();
+            let phi2 = (phi.sin() * delta.cos() + phi.cos() * delta.sin() * theta.cos()).asin();
+            let lambda2 = lambda
+                + (theta.sin() * delta.sin() * phi.cos())
+                    .atan2(delta.cos() - phi.sin() * phi2.sin());
+            let (latitude, longitude) = (phi2.to_degrees(), lambda2.to_degrees());
+            assert!(latitude >= area.south && latitude <= area.north);
+            assert!(longitude >= area.west && longitude <= area.east);
+        }
+        let pole = Area::around(89.9, 0.0, 50.0);
+        assert_eq!((pole.north, pole.west, pole.east), (90.0, -180.0, 180.0));
+        let date_line = Area::around(0.0, 179.9, 50.0);
+        assert!(date_line.west > date_line.east);
+    }
+}
@@ -0,0 +1,446 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+use rusqlite::{params, Connection, DropBehavior};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::MutexGuard;
+use std::time::SystemTime;
+
+use super::service::Handle;
+use super::Database;
+use crate::operation::Controller;
+
+/// Tables and the column that holds the metadata_id of the media file a row belongs to.
+const MEDIA_ROWS: [(&str, &str); 25] = [
+    ("video_metadata", "video_id"),
+    ("subtitles", "video_id"),
+    ("audiolangs", "video_id"),
+    ("sublangs", "video_id"),
+    ("chapters", "video_id"),
+    ("directors", "video_id"),
+    ("actors", "video_id"),
+    ("genres", "video_id"),
+    ("studios", "video_id"),
+    ("countries", "video_id"),
+    ("writers", "video_id"),
+    ("ratings", "video_id"),
+    ("unique_ids", "video_id"),
+    ("movie_sets_map", "video_id"),
+    ("audio_metadata", "audio_id"),
+    ("album_audio_map", "audio_id"),
+    ("artist_audio_map", "audio_id"),
+    ("albumartist_audio_map", "audio_id"),
+    ("indexes", "audio_id"),
+    ("lyrics", "audio_id"),
+    ("image_metadata", "image_id"),
+    ("tags_media_map", "media_id"),
+    ("collections", "file_id"),
+    ("collections_map", "episode_id"),
+    ("media_fts", "rowid"),
+];
+
+const UNUSED_PEOPLE: &str = "DELETE FROM people WHERE person_id NOT IN
+    (SELECT actor_id FROM actors WHERE actor_id IS NOT NULL
+    UNION SELECT director_id FROM directors WHERE director_id IS NOT NULL
+    UNION SELECT writer_id FROM writers WHERE writer_id IS NOT NULL)";
+const UNUSED_ALBUMS: &str = "DELETE FROM albums WHERE album_id NOT IN
+    (SELECT album_id FROM album_audio_map WHERE album_id IS NOT NULL)";
+const UNUSED_ARTISTS: &str = "DELETE FROM artists WHERE artist_id NOT IN
+    (SELECT artist_id FROM artist_audio_map WHERE artist_id IS NOT NULL
+    UNION SELECT albumartist_id FROM albumartist_audio_map WHERE albumartist_id IS NOT NULL)";
+const UNUSED_MOVIE_SETS: &str = "DELETE FROM movie_sets WHERE set_id NOT IN
+    (SELECT set_id FROM movie_sets_map WHERE set_id IS NOT NULL)";
+
+/// Columns that point at files in the thumbnail directory.
+const THUMBNAIL_COLUMNS: [(&str, &str); 10] = [
+    ("video_metadata", "poster"),
+    ("video_metadata", "thumb"),
+    ("audio_metadata", "poster"),
+    ("audio_metadata", "thumb"),
+    ("image_metadata", "resized"),
+    ("image_metadata", "thumb"),
+    ("collections", "poster"),
+    ("collections", "thumb"),
+    ("collections_map", "poster"),
+    ("collections_map", "thumb"),
+];
+
+/// What a maintenance run found, and removed unless it was a dry run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub dry_run: bool,
+    /// Stored files that no longer exist on disk.
+    pub missing_files: usize,
+    /// Rows of media that is no longer in file_metadata.
+    pub orphan_rows: usize,
+    pub unused_people: usize,
+    pub unused_albums: usize,
+    pub unused_artists: usize,
+    pub unused_thumbnails: usize,
+    pub thumbnail_bytes: u64,
+    /// Output of `PRAGMA integrity_check`, a single "ok" for a healthy database.
+    pub integrity: Vec<String>,
+    /// Bytes given back to the file system by `VACUUM`.
+    pub vacuum_bytes: u64,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.missing_files == 0
+            && self.orphan_rows == 0
+            && self.unused_people == 0
+            && self.unused_albums == 0
+            && self.unused_artists == 0
+            && self.unused_thumbnails == 0
+    }
+
+    pub fn integrity_ok(&self) -> bool {
+        self.integrity.len() == 1 && self.integrity[0] == "ok"
+    }
+}
+
+/// Clean up the library database and the thumbnail directory `thumbs`.
+///
+/// Files on disk are checked before anything is written, so pausing through `controller`
+/// never blocks the writer. The database changes run on the writer thread, a dry run rolls
+/// them back after counting and leaves the thumbnails in place. `VACUUM` runs on the
+/// writer as well, between two of its transactions. Thumbnails created since the run started are kept, their rows may
+/// not be written yet.
+pub fn run(
+    database: &Database,
+    thumbs: Option<&Path>,
+    dry_run: bool,
+    controller: &Controller,
+) -> Result<Report, String> {
+    let started = SystemTime::now();
+    let mut report = Report {
+        dry_run,
+        ..Default::default()
+    };
+
+    // queued writes are committed first, so their files are checked as well
+    database.flush();
+    let files = database
+        .read(|handle| stored_files(&*lock(&handle)?).map_err(|error| error.to_string()))?;
+    let total = files.len().max(1);
+    let mut missing = Vec::new();
+    for (i, (metadata_id, filepath)) in files.into_iter().enumerate() {
+        controller.check()?;
+        controller.set_progress(0.5 * i as f32 / total as f32);
+        if !filepath.exists() {
+            missing.push(metadata_id);
+        }
+    }
+    report.missing_files = missing.len();
+
+    controller.check()?;
+    // on the writer, so the rows of a running scan count as referenced even before they
+    // were committed
+    let (mut report, referenced) = database.write(move |handle| {
+        clean_rows(&mut *lock(&handle)?, &missing, report, dry_run)
+            .map_err(|error| error.to_string())
+    })?;
+    controller.set_progress(0.6);
+
+    if let Some(thumbs) = thumbs {
+        let entries = match std::fs::read_dir(thumbs) {
+            Ok(entries) => entries.flatten().collect(),
+            Err(error) => {
+                log::warn!(
+                    "Failed to read thumbnail directory {}: {}",
+                    thumbs.display(),
+                    error
+                );
+                Vec::new()
+            }
+        };
+        let total = entries.len().max(1);
+        for (i, entry) in entries.into_iter().enumerate() {
+            controller.check()?;
+            controller.set_progress(0.6 + 0.2 * i as f32 / total as f32);
+            let path = entry.path();
+            if !path.is_file() || referenced.contains(&path) {
+                continue;
+            }
+            let metadata = entry.metadata().ok();
+            if metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok())
+                .is_some_and(|modified| modified > started)
+            {
+                continue;
+            }
+            report.unused_thumbnails += 1;
+            report.thumbnail_bytes += metadata.map(|m| m.len()).unwrap_or(0);
+            if !dry_run {
+                if let Err(error) = std::fs::remove_file(&path) {
+                    log::warn!("Failed to delete thumbnail {}: {}", path.display(), error);
+                }
+            }
+        }
+    }
+
+    controller.check()?;
+    report.integrity = database
+        .read(|handle| integrity_check(&*lock(&handle)?).map_err(|error| error.to_string()))?;
+    controller.set_progress(0.9);
+
+    if !dry_run {
+        controller.check()?;
+        report.vacuum_bytes = database
+            .exclusive(|handle| vacuum(&*lock(&handle)?).map_err(|error| error.to_string()))?;
+    }
+    controller.set_progress(1.0);
+    log::info!("Library maintenance: {:?}", report);
+    Ok(report)
+}
+
+fn lock(handle: &Handle) -> Result<MutexGuard<'_, Connection>, String> {
+    handle
+        .lock()
+        .map_err(|error| format!("Failed to lock sql connection for use! {}", error))
+}
+
+fn stored_files(connection: &Connection) -> Result<Vec<(i64, PathBuf)>, rusqlite::Error> {
+    let mut statement = connection.prepare("SELECT metadata_id, filepath FROM file_metadata")?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            PathBuf::from(row.get::<_, String>(1)?),
+        ))
+    })?;
+    rows.collect()
+}
+
+/// Remove the `missing` files, every row left without its media file and unused people,
+/// albums and artists. Returns the counts and the thumbnail paths that are still
+/// referenced afterwards.
+fn clean_rows(
+    connection: &mut Connection,
+    missing: &[i64],
+    mut report: Report,
+    dry_run: bool,
+) -> Result<(Report, BTreeSet<PathBuf>), rusqlite::Error> {
+    // a savepoint, the writer may already be inside a transaction
+    let mut transaction = connection.savepoint()?;
+    for metadata_id in missing {
+        transaction.execute(
+            "DELETE FROM file_metadata WHERE metadata_id = ?1",
+            params![metadata_id],
+        )?;
+    }
+    for (table, column) in MEDIA_ROWS {
+        report.orphan_rows += transaction.execute(
+            &format!(
+                "DELETE FROM {} WHERE {} NOT IN (SELECT metadata_id FROM file_metadata)",
+                table, column
+            ),
+            [],
+        )?;
+    }
+    report.orphan_rows += transaction.execute(
+        "DELETE FROM collections_map WHERE collection_id NOT IN
+            (SELECT collection_id FROM collections)",
+        [],
+    )?;
+    // movie sets without movies left
+    report.orphan_rows += transaction.execute(UNUSED_MOVIE_SETS, [])?;
+    report.unused_people = transaction.execute(UNUSED_PEOPLE, [])?;
+    report.unused_albums = transaction.execute(UNUSED_ALBUMS, [])?;
+    report.unused_artists = transaction.execute(UNUSED_ARTISTS, [])?;
+
+    let mut referenced = BTreeSet::new();
+    for (table, column) in THUMBNAIL_COLUMNS {
+        let mut statement = transaction.prepare(&format!(
+            "SELECT {} FROM {} WHERE {} IS NOT NULL AND {} != ''",
+            column, table, column, column
+        ))?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        for path in rows {
+            referenced.insert(PathBuf::from(path?));
+        }
+    }
+
+    transaction.set_drop_behavior(if dry_run {
+        DropBehavior::Rollback
+    } else {
+        DropBehavior::Commit
+    });
+    transaction.finish()?;
+    Ok((report, referenced))
+}
+
+fn integrity_check(connection: &Connection) -> Result<Vec<String>, rusqlite::Error> {
+    let mut statement = connection.prepare("PRAGMA integrity_check")?;
+    let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+    rows.collect()
+}
+
+/// `VACUUM`, returns the bytes given back to the file system.
+fn vacuum(connection: &Connection) -> Result<u64, rusqlite::Error> {
+    let before = database_bytes(connection)?;
+    connection.execute_batch("VACUUM")?;
+    let after = database_bytes(connection)?;
+    Ok(before.saturating_sub(after))
+}
+
+fn database_bytes(connection: &Connection) -> Result<u64, rusqlite::Error> {
+    let pages: u64 = connection.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: u64 = connection.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok(pages * page_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(dir: &Path) -> Database {
+        let database = Database::in_memory().unwrap();
+        let thumbs = dir.join("thumbs");
+        std::fs::create_dir_all(&thumbs).unwrap();
+        for name in ["kept.png", "gone.png", "stale.png"] {
+            std::fs::write(thumbs.join(name), b"png").unwrap();
+        }
+        std::fs::write(dir.join("kept.mkv"), b"video").unwrap();
+        let dir = dir.to_path_buf();
+        database.write(move |handle| {
+            let connection = handle.lock().unwrap();
+            fill(&connection, &dir, &thumbs);
+        });
+        database
+    }
+
+    fn fill(connection: &Connection, dir: &Path, thumbs: &Path) {
+        for (metadata_id, name) in [(1, "kept"), (2, "gone")] {
+            let filepath = dir
+                .join(format!("{}.mkv", name))
+                .to_string_lossy()
+                .to_string();
+            let thumb = thumbs
+                .join(format!("{}.png", name))
+                .to_string_lossy()
+                .to_string();
+            connection
+                .execute(
+                    "INSERT INTO file_metadata (metadata_id, filepath, file_type) VALUES (?1, ?2, 2)",
+                    params![metadata_id, filepath],
+                )
+                .unwrap();
+            connection
+                .execute(
+                    "INSERT INTO video_metadata (video_id, name, title, released, thumb)
+                        VALUES (?1, ?2, ?2, 0, ?3)",
+                    params![metadata_id, name, thumb],
+                )
+                .unwrap();
+            connection
+                .execute(
+                    "INSERT INTO people (person_id, person_name) VALUES (?1, ?2)",
+                    params![metadata_id, name],
+                )
+                .unwrap();
+            connection
+                .execute(
+                    "INSERT INTO actors (actor_id, video_id) VALUES (?1, ?1)",
+                    params![metadata_id],
+                )
+                .unwrap();
+        }
+        connection
+            .execute_batch(
+                "INSERT INTO tags_media_map (media_id, tagmap_id) VALUES (2, 1);
+                INSERT INTO albums (album_id, album_name) VALUES (1, 'Unused');",
+            )
+            .unwrap();
+    }
+
+    fn count(database: &Database, table: &str) -> i64 {
+        database.read(|handle| {
+            let connection = handle.lock().unwrap();
+            connection
+                .query_row(&format!("SELECT count(*) FROM {}", table), [], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        })
+    }
+
+    #[test]
+    fn dry_run_reports_without_changing_anything() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = library(dir.path());
+        let thumbs = dir.path().join("thumbs");
+        let report = run(&database, Some(&thumbs), true, &Controller::new()).unwrap();
+        assert_eq!(report.missing_files, 1);
+        // video_metadata, actors and tags_media_map of the missing file
+        assert_eq!(report.orphan_rows, 3);
+        assert_eq!(report.unused_people, 1);
+        assert_eq!(report.unused_albums, 1);
+        assert_eq!(report.unused_thumbnails, 2);
+        assert!(report.integrity_ok());
+        assert!(!report.is_clean());
+        assert_eq!(count(&database, "file_metadata"), 2);
+        assert_eq!(count(&database, "people"), 2);
+        assert!(thumbs.join("stale.png").exists());
+    }
+
+    #[test]
+    fn cleanup_removes_orphans_and_thumbnails() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = library(dir.path());
+        let thumbs = dir.path().join("thumbs");
+        let report = run(&database, Some(&thumbs), false, &Controller::new()).unwrap();
+        assert_eq!(report.missing_files, 1);
+        assert_eq!(count(&database, "file_metadata"), 1);
+        assert_eq!(count(&database, "video_metadata"), 1);
+        assert_eq!(count(&database, "actors"), 1);
+        assert_eq!(count(&database, "people"), 1);
+        assert_eq!(count(&database, "albums"), 0);
+        assert_eq!(count(&database, "tags_media_map"), 0);
+        assert!(thumbs.join("kept.png").exists());
+        assert!(!thumbs.join("gone.png").exists());
+        assert!(!thumbs.join("stale.png").exists());
+
+        let again = run(&database, Some(&thumbs), true, &Controller::new()).unwrap();
+        assert!(again.is_clean());
+    }
+
+    #[test]
+    fn running_scan_keeps_its_thumbnails() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::open_at(&dir.path().join("metadata.sqlite")).unwrap();
+        let thumbs = dir.path().join("thumbs");
+        std::fs::create_dir_all(&thumbs).unwrap();
+        for name in ["kept.png", "gone.png", "stale.png"] {
+            std::fs::write(thumbs.join(name), b"png").unwrap();
+        }
+        std::fs::write(dir.path().join("kept.mkv"), b"video").unwrap();
+        // the scanner holds its transaction open while maintenance runs
+        let batch = database.batch();
+        let (library, scanned) = (dir.path().to_path_buf(), thumbs.clone());
+        database.write(move |handle| fill(&handle.lock().unwrap(), &library, &scanned));
+        let report = run(&database, Some(&thumbs), false, &Controller::new()).unwrap();
+        assert_eq!(report.missing_files, 1);
+        assert!(report.integrity_ok());
+        assert!(thumbs.join("kept.png").exists());
+        assert!(!thumbs.join("stale.png").exists());
+        drop(batch);
+        database.flush();
+        assert_eq!(count(&database, "file_metadata"), 1);
+    }
+
+    #[test]
+    fn cancelled_run_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = library(dir.path());
+        let controller = Controller::new();
+        controller.cancel();
+        let thumbs = dir.path().join("thumbs");
+        assert!(run(&database, Some(&thumbs), false, &controller).is_err());
+        assert_eq!(count(&database, "file_metadata"), 2);
+        assert!(thumbs.join("stale.png").exists());
+    }
+}
@@ -0,0 +1,1299 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+use rusqlite::{Connection, Transaction};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Schema version written by this build. Bump it together with a new entry in `MIGRATIONS`.
+pub const SCHEMA_VERSION: i64 = 13;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("the metadata database uses schema version {found}, but this version of media-browser only supports up to version {supported}; please update media-browser or use a different database")]
+    TooNew { found: i64, supported: i64 },
+}
+
+struct Migration {
+    version: i64,
+    description: &'static str,
+    up: fn(&Transaction) -> Result<(), rusqlite::Error>,
+}
+
+/// Ordered list of schema upgrades. Every step has to be idempotent, it may run on a
+/// database that was created before the schema was versioned.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        up: initial_schema,
+    },
+    Migration {
+        version: 2,
+        description: "full-text search index",
+        up: full_text_search,
+    },
+    Migration {
+        version: 3,
+        description: "file size for incremental rescans",
+        up: file_size,
+    },
+    Migration {
+        version: 4,
+        description: "content fingerprints to follow moved files",
+        up: file_fingerprint,
+    },
+    Migration {
+        version: 5,
+        description: "query text of saved searches",
+        up: search_query,
+    },
+    Migration {
+        version: 6,
+        description: "index of photo positions",
+        up: gps_index,
+    },
+    Migration {
+        version: 7,
+        description: "video codecs and technical search fields",
+        up: technical_properties,
+    },
+    Migration {
+        version: 8,
+        description: "typo tolerant saved searches",
+        up: search_fuzzy,
+    },
+    Migration {
+        version: 9,
+        description: "sort keys for ratings, disc numbers, capture and added times",
+        up: sort_keys,
+    },
+    Migration {
+        version: 10,
+        description: "names, folders, order and last run of saved searches",
+        up: saved_search_management,
+    },
+    Migration {
+        version: 11,
+        description: "genres, studios, countries, writers, ratings, ids and sets from NFO files",
+        up: nfo_details,
+    },
+    Migration {
+        version: 12,
+        description: "edition, source and episodes read from file names",
+        up: file_name_details,
+    },
+    Migration {
+        version: 13,
+        description: "re-read photo positions stored with broken GPS fractions",
+        up: reread_gps_positions,
+    },
+];
+
+pub fn user_version(connection: &Connection) -> Result<i64, rusqlite::Error> {
+    connection.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+pub fn has_column(
+    connection: &Connection,
+    table: &str,
+    column: &str,
+) -> Result<bool, rusqlite::Error> {
+    let mut statement = connection.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        if row.get::<_, String>(1)? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn has_tables(connection: &Connection) -> Result<bool, rusqlite::Error> {
+    let count: i64 = connection.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Path of the copy taken before upgrading `database_file` from `version`.
+pub fn backup_path(database_file: &Path, version: i64) -> PathBuf {
+    let mut name = database_file.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".v{}-{}.bak",
+        version,
+        chrono::Local::now().format("%Y%m%d%H%M%S")
+    ));
+    database_file.with_file_name(name)
+}
+
+fn backup(connection: &Connection, database_file: &Path, version: i64) -> Result<PathBuf, Error> {
+    let backup_file = backup_path(database_file, version);
+    if backup_file.exists() {
+        std::fs::remove_file(&backup_file)?;
+    }
+    connection.execute(
+        "VACUUM INTO ?1",
+        [backup_file.to_string_lossy().to_string()],
+    )?;
+    Ok(backup_file)
+}
+
+/// Bring the schema of `connection` up to `SCHEMA_VERSION`.
+///
+/// If `database_file` is given and the database already holds data, a copy is written
+/// next to it before the first upgrade step runs. Each step runs in its own transaction
+/// together with the `user_version` update, so an interrupted upgrade resumes cleanly.
+pub fn migrate(connection: &mut Connection, database_file: Option<&Path>) -> Result<(), Error> {
+    let current = user_version(connection)?;
+    if current > SCHEMA_VERSION {
+        return Err(Error::TooNew {
+            found: current,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    if current == SCHEMA_VERSION {
+        return Ok(());
+    }
+    if let Some(path) = database_file {
+        if has_tables(connection)? {
+            let backup_file = backup(connection, path, current)?;
+            log::info!(
+                "Saved a copy of the metadata database to {}",
+                backup_file.display()
+            );
+        }
+    }
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        log::info!(
+            "Upgrading metadata database to version {}: {}",
+            migration.version,
+            migration.description
+        );
+        let transaction = connection.transaction()?;
+        (migration.up)(&transaction)?;
+        transaction.pragma_update(None, "user_version", migration.version)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+fn initial_schema(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    // file_type: 0 other file, 1 image, 2, video, 3 audio
+    match connection.execute(
+        "CREATE TABLE IF NOT EXISTS file_metadata (
+            filepath TEXT NOT NULL unique NOT NULL, 
+            creation_time UNSIGNED BIG INT, 
+            modification_time UNSIGNED BIG INT, 
+            file_type INT,   
+            metadata_id INTEGER,
+            PRIMARY KEY(metadata_id AUTOINCREMENT)
+        )",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table file_metadata: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_file_metadata_filename ON file_metadata (filepath)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on file_metadata: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS video_metadata (
+            video_id INTEGER PRIMARY KEY,
+            name  TEXT NOT NULL, 
+            title  TEXT NOT NULL, 
+            released UNSIGNED BIG INT NOT NULL, 
+            poster  TEXT, 
+            thumb   TEXT,
+            subtitles BIG INT,
+            duration INT,
+            width INT,
+            height INT,
+            framerate FLOAT,
+            description TEXT
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table indices: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_video_metadata_title ON video_metadata (title)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on file_metadata: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS subtitles (
+            subtitle_id INTEGER, 
+            video_id INTEGER, 
+            subpath TEXT, 
+            PRIMARY KEY(subtitle_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_subtitles_video_id ON subtitles (video_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on subtitles: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS audiolangs (
+            audiolang_id INTEGER, 
+            video_id INTEGER, 
+            audiolang TEXT, 
+            PRIMARY KEY(audiolang_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_audiolangs_video_id ON audiolangs (video_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on audiolangs: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS sublangs (
+            sublang_id INTEGER, 
+            video_id INTEGER, 
+            sublang TEXT, 
+            PRIMARY KEY(sublang_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_sublangs_video_id ON sublangs (video_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on sublangs: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS chapters (
+            chapter_id INTEGER, 
+            video_id INTEGER, 
+            title TEXT, 
+            start DOUBLE,
+            end DOUBLE,
+            PRIMARY KEY(chapter_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_chapters_video_id ON chapters (video_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on sublangs: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+    CREATE TABLE IF NOT EXISTS people (
+        person_id INTEGER, 
+        person_name TEXT, 
+        PRIMARY KEY(person_id AUTOINCREMENT)
+    )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table people: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_people_name ON people (person_name)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on directors: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS directors (
+            entry_id INTEGER,
+            director_id INTEGER, 
+            video_id INTEGER, 
+            PRIMARY KEY(entry_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table directors: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_directors_video_id ON directors (video_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on directors: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS actors (
+            entry_id INTEGER,
+            actor_id INTEGER, 
+            video_id INTEGER, 
+            PRIMARY KEY(entry_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_actors_video_id ON actors (video_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on actors: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+    CREATE TABLE IF NOT EXISTS collections (
+        collection_id INTEGER,
+        file_id INTEGER,
+        collection_name TEXT,
+        poster TEXT,
+        thumb TEXT,
+        description TEXT,
+        path TEXT,
+        PRIMARY KEY(collection_id AUTOINCREMENT)
+    )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table collections: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS collection_file_id ON collections (file_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on collections_file_id: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS collection_collection_name ON collections (collection_name)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on collection_name: {}", error);
+            return Err(error);
+        }
+    }
+
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS collections_map (
+            entry_id INTEGER,
+            collection_id INTEGER, 
+            episode_id INTEGER, 
+            series INTEGER,
+            episode INTEGER,
+            title TEXT,
+            path TEXT,
+            poster TEXT,
+            thumb TEXT,
+            PRIMARY KEY(entry_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table collections_map: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS collection_map_episode_id ON collections_map (episode_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on collection_name: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS audio_metadata (
+            audio_id INTEGER PRIMARY KEY,
+            name  TEXT NOT NULL, 
+            title  TEXT NOT NULL, 
+            released UNSIGNED BIG INT NOT NULL, 
+            poster  TEXT, 
+            thumb   TEXT,
+            genre   TEXT,
+            composer TEXT,
+            track_id INT,
+            duration INT,
+            bitrate FLOAT
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table indices: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS albums (
+            album_id INTEGER, 
+            album_name TEXT, 
+            PRIMARY KEY(album_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_albums_name ON albums (album_name)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on albums: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+    CREATE TABLE IF NOT EXISTS album_audio_map (
+        entry_id INTEGER,
+        audio_id INTEGER,
+        album_id INTEGER,
+        PRIMARY KEY(entry_id AUTOINCREMENT) 
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table albums: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_album_audio_audio_id ON album_audio_map (audio_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on albums: {}", error);
+            return Err(error);
+        }
+    }
+
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS artists (
+            artist_id INTEGER, 
+            artist_name TEXT, 
+            PRIMARY KEY(artist_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table artists: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_artists_name_id ON artists (artist_name)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on artists: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+    CREATE TABLE IF NOT EXISTS artist_audio_map (
+        entry_id INTEGER,
+        audio_id INTEGER,
+        artist_id INTEGER, 
+        PRIMARY KEY(entry_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table artist_audio_map: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_artist_audio_map_audio_id ON artist_audio_map (audio_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on artist_audio_map: {}", error);
+            return Err(error);
+        }
+    }
+
+    match connection.execute(
+        "
+    CREATE TABLE IF NOT EXISTS albumartist_audio_map (
+        entry_id INTEGER,
+        audio_id INTEGER,
+        albumartist_id INTEGER, 
+        PRIMARY KEY(entry_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table albumartist_audio_map: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_albumartist_audio_map_audio_id ON artist_audio_map (audio_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on albumartist_audio_map: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS indexes (
+            index_id INTEGER, 
+            audio_id INTEGER, 
+            title TEXT, 
+            start DOUBLE,
+            end DOUBLE,
+            PRIMARY KEY(index_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_indexes_audio_id ON indexes (audio_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on artist_audio_map: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS lyrics (
+            lyrics_id INTEGER, 
+            audio_id INTEGER, 
+            lyricsfile TEXT, 
+            PRIMARY KEY(lyrics_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_lyrics_audio_id ON lyrics (audio_id)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on lyrics: {}", error);
+            return Err(error);
+        }
+    }
+
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS image_metadata (
+            image_id INTEGER PRIMARY KEY,
+            name     TEXT NOT NULL, 
+            path     TEXT NOT NULL, 
+            created  UNSIGNED BIG INT NOT NULL, 
+            resized  TEXT, 
+            thumb    TEXT,
+            width    INTEGER,
+            height   INTEGER,
+            Photographer TEXT,
+            LenseModel TEXT,
+            Focallength TEXT,
+            Exposuretime TEXT,
+            FNumber  TEXT,
+            GPSString  TEXT,
+            GPSLatitude DOUBLE,
+            GPSLongitude DOUBLE,
+            GPSAltitude DOUBLE
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table image_metadata: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_image_photographer ON image_metadata (Photographer)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on image_metadata: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_image_name ON image_metadata (name)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on image_metadata: {}", error);
+            return Err(error);
+        }
+    }
+
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS tags (
+            tag_id INTEGER, 
+            tag TEXT, 
+            PRIMARY KEY(tag_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    match connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_tags_tag ON tags (tag)",
+        (),
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create index on tags: {}", error);
+            return Err(error);
+        }
+    }
+
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS tags_media_map (
+            element_id INTEGER,
+            media_id INTEGER, 
+            tagmap_id INTEGER,
+            PRIMARY KEY(element_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table parameters: {}", error);
+            return Err(error);
+        }
+    }
+    /*
+            match connection.execute(
+                "CREATE INDEX IF NOT EXISTS index_tagsmediamap_media_id ON tags_media_map (media_id)", (),
+            ) {
+                Ok(_ret) => {},
+                Err(error) => {
+                    log::error!("Failed to create index on tags_media_map: {}", error);
+                    return Err(error);
+                }
+            }
+            match connection.execute(
+                "CREATE INDEX IF NOT EXISTS index_tagsmediamap_tag_id ON tags_media_map (tagmap_id)", (),
+            ) {
+                Ok(_ret) => {},
+                Err(error) => {
+                    log::error!("Failed to create index on tags_media_map: {}", error);
+                    return Err(error);
+                }
+            }
+    */
+    match connection.execute(
+        "
+        CREATE TABLE IF NOT EXISTS searches (
+            search_id INTEGER,
+            from_string  TEXT, 
+            from_value DOUBLE, 
+            from_date INTEGER,
+            to_string  TEXT, 
+            to_value DOUBLE, 
+            to_date INTEGER,
+            image  INTEGER, 
+            video  INTEGER, 
+            audio  INTEGER, 
+            filepath  INTEGER, 
+            title  INTEGER, 
+            description  INTEGER, 
+            actor  INTEGER, 
+            director  INTEGER, 
+            artist  INTEGER, 
+            album_artist  INTEGER, 
+            duration  INTEGER, 
+            creation_date  INTEGER, 
+            modification_date  INTEGER, 
+            release_date  INTEGER, 
+            lense_model  INTEGER, 
+            focal_length  INTEGER, 
+            exposure_time  INTEGER, 
+            fnumber  INTEGER, 
+            gps_latitude  INTEGER, 
+            gps_longitude  INTEGER, 
+            gps_altitude  INTEGER,
+            album  INTEGER, 
+            composer  INTEGER, 
+            genre  INTEGER,  
+            tags  INTEGER, 
+            PRIMARY KEY(search_id AUTOINCREMENT)
+        )",
+        [],
+    ) {
+        Ok(_ret) => {}
+        Err(error) => {
+            log::error!("Failed to create table image_metadata: {}", error);
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+fn full_text_search(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    super::fts::create(connection)?;
+    super::fts::rebuild(connection)
+}
+
+fn file_size(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    // 0 marks rows scanned before the size was recorded, they are compared by mtime only
+    if !has_column(connection, "file_metadata", "size")? {
+        connection.execute(
+            "ALTER TABLE file_metadata ADD COLUMN size INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn file_fingerprint(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    // NULL until the file is scanned again
+    if !has_column(connection, "file_metadata", "fingerprint")? {
+        connection.execute("ALTER TABLE file_metadata ADD COLUMN fingerprint TEXT", [])?;
+    }
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_file_metadata_fingerprint ON file_metadata (fingerprint)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn search_query(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    // NULL for searches saved with the search fields only
+    if !has_column(connection, "searches", "query")? {
+        connection.execute("ALTER TABLE searches ADD COLUMN query TEXT", [])?;
+    }
+    Ok(())
+}
+
+fn gps_index(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    // narrows near: and area: searches down before distances are computed
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_image_metadata_gps ON image_metadata (GPSLatitude, GPSLongitude)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn technical_properties(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    if !has_column(connection, "video_metadata", "codec")? {
+        connection.execute("ALTER TABLE video_metadata ADD COLUMN codec TEXT", [])?;
+    }
+    for column in [
+        "resolution",
+        "frame_rate",
+        "bitrate",
+        "codec",
+        "aspect_ratio",
+    ] {
+        if !has_column(connection, "searches", column)? {
+            connection.execute(
+                &format!("ALTER TABLE searches ADD COLUMN {} INTEGER", column),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn search_fuzzy(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    // NULL for searches saved before typos were accepted
+    if !has_column(connection, "searches", "fuzzy")? {
+        connection.execute("ALTER TABLE searches ADD COLUMN fuzzy INTEGER", [])?;
+    }
+    Ok(())
+}
+
+fn sort_keys(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    for (table, column, kind) in [
+        ("video_metadata", "rating", "FLOAT"),
+        ("audio_metadata", "disc", "INT"),
+        ("image_metadata", "taken", "UNSIGNED BIG INT"),
+    ] {
+        // 0 until the file is scanned again
+        if !has_column(connection, table, column)? {
+            connection.execute(
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN {} {} NOT NULL DEFAULT 0",
+                    table, column, kind
+                ),
+                [],
+            )?;
+        }
+    }
+    if !has_column(connection, "file_metadata", "added")? {
+        connection.execute(
+            "ALTER TABLE file_metadata ADD COLUMN added UNSIGNED BIG INT NOT NULL DEFAULT 0",
+            [],
+        )?;
+        // files scanned before count as added when they were last modified, like Kodi does
+        connection.execute(
+            "UPDATE file_metadata SET added = COALESCE(modification_time, 0)",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn saved_search_management(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    for (column, kind) in [
+        ("name", "TEXT NOT NULL DEFAULT ''"),
+        ("folder", "TEXT NOT NULL DEFAULT ''"),
+    ] {
+        if !has_column(connection, "searches", column)? {
+            connection.execute(
+                &format!("ALTER TABLE searches ADD COLUMN {} {}", column, kind),
+                [],
+            )?;
+        }
+    }
+    if !has_column(connection, "searches", "position")? {
+        connection.execute(
+            "ALTER TABLE searches ADD COLUMN position INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+        // searches saved before stay in the order they were saved in
+        connection.execute("UPDATE searches SET position = search_id", [])?;
+    }
+    for column in ["last_run", "result_count"] {
+        // 0 until the search is run again
+        if !has_column(connection, "searches", column)? {
+            connection.execute(
+                &format!(
+                    "ALTER TABLE searches ADD COLUMN {} UNSIGNED BIG INT NOT NULL DEFAULT 0",
+                    column
+                ),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn nfo_details(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    // empty until the video is scanned again
+    for (table, column) in [
+        ("video_metadata", "original_title"),
+        ("video_metadata", "sort_title"),
+        ("video_metadata", "tagline"),
+        ("video_metadata", "mpaa"),
+        ("actors", "role"),
+        ("actors", "thumb"),
+    ] {
+        if !has_column(connection, table, column)? {
+            connection.execute(
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN {} TEXT NOT NULL DEFAULT ''",
+                    table, column
+                ),
+                [],
+            )?;
+        }
+    }
+    for (table, column) in [
+        ("genres", "genre"),
+        ("studios", "studio"),
+        ("countries", "country"),
+    ] {
+        connection.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    entry_id INTEGER,
+                    video_id INTEGER,
+                    {} TEXT NOT NULL,
+                    PRIMARY KEY(entry_id AUTOINCREMENT)
+                )",
+                table, column
+            ),
+            [],
+        )?;
+    }
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS writers (
+            entry_id INTEGER,
+            writer_id INTEGER,
+            video_id INTEGER,
+            PRIMARY KEY(entry_id AUTOINCREMENT)
+        );
+        CREATE TABLE IF NOT EXISTS ratings (
+            entry_id INTEGER,
+            video_id INTEGER,
+            source TEXT NOT NULL,
+            value FLOAT NOT NULL,
+            max FLOAT NOT NULL,
+            votes INTEGER NOT NULL,
+            is_default INTEGER NOT NULL,
+            PRIMARY KEY(entry_id AUTOINCREMENT)
+        );
+        CREATE TABLE IF NOT EXISTS unique_ids (
+            entry_id INTEGER,
+            video_id INTEGER,
+            kind TEXT NOT NULL,
+            value TEXT NOT NULL,
+            is_default INTEGER NOT NULL,
+            PRIMARY KEY(entry_id AUTOINCREMENT)
+        );
+        CREATE TABLE IF NOT EXISTS movie_sets (
+            set_id INTEGER,
+            set_name TEXT NOT NULL UNIQUE,
+            overview TEXT NOT NULL DEFAULT '',
+            PRIMARY KEY(set_id AUTOINCREMENT)
+        );
+        CREATE TABLE IF NOT EXISTS movie_sets_map (
+            entry_id INTEGER,
+            set_id INTEGER,
+            video_id INTEGER,
+            PRIMARY KEY(entry_id AUTOINCREMENT)
+        );",
+    )?;
+    for table in [
+        "genres",
+        "studios",
+        "countries",
+        "writers",
+        "ratings",
+        "unique_ids",
+        "movie_sets_map",
+    ] {
+        connection.execute(
+            &format!(
+                "CREATE INDEX IF NOT EXISTS index_{}_video_id ON {} (video_id)",
+                table, table
+            ),
+            [],
+        )?;
+    }
+    // videos are looked up by their IMDb, TMDB or TVDB id
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS index_unique_ids_value ON unique_ids (value)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn file_name_details(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    for (column, kind) in [
+        ("edition", "TEXT NOT NULL DEFAULT ''"),
+        ("source", "TEXT NOT NULL DEFAULT ''"),
+        ("season", "INTEGER NOT NULL DEFAULT 0"),
+        ("episode", "INTEGER NOT NULL DEFAULT 0"),
+        ("last_episode", "INTEGER NOT NULL DEFAULT 0"),
+    ] {
+        if !has_column(connection, "video_metadata", column)? {
+            connection.execute(
+                &format!("ALTER TABLE video_metadata ADD COLUMN {} {}", column, kind),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn reread_gps_positions(connection: &Transaction) -> Result<(), rusqlite::Error> {
+    // positions were read from the seconds alone, a modification time the files can't
+    // have makes the next scan parse them again
+    connection.execute(
+        "UPDATE file_metadata SET modification_time = 0 WHERE metadata_id IN
+            (SELECT image_id FROM image_metadata WHERE GPSLatitude != 0 OR GPSLongitude != 0)",
+        [],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_exists(connection: &Connection, table: &str) -> bool {
+        connection
+            .query_row(
+                "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [table],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn fresh_database_reaches_current_version() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        migrate(&mut connection, None).unwrap();
+        assert_eq!(user_version(&connection).unwrap(), SCHEMA_VERSION);
+        assert!(table_exists(&connection, "file_metadata"));
+        assert!(table_exists(&connection, "searches"));
+        assert!(table_exists(&connection, "media_fts"));
+        assert!(has_column(&connection, "file_metadata", "size").unwrap());
+        assert!(has_column(&connection, "file_metadata", "fingerprint").unwrap());
+        assert!(has_column(&connection, "searches", "query").unwrap());
+        assert!(has_column(&connection, "video_metadata", "codec").unwrap());
+        assert!(has_column(&connection, "searches", "aspect_ratio").unwrap());
+        assert!(has_column(&connection, "searches", "fuzzy").unwrap());
+        assert!(has_column(&connection, "file_metadata", "added").unwrap());
+        assert!(has_column(&connection, "video_metadata", "rating").unwrap());
+        assert!(has_column(&connection, "audio_metadata", "disc").unwrap());
+        assert!(has_column(&connection, "image_metadata", "taken").unwrap());
+        assert!(has_column(&connection, "searches", "folder").unwrap());
+        assert!(has_column(&connection, "searches", "result_count").unwrap());
+        assert!(has_column(&connection, "video_metadata", "original_title").unwrap());
+        assert!(has_column(&connection, "actors", "role").unwrap());
+        assert!(has_column(&connection, "video_metadata", "last_episode").unwrap());
+        for table in ["genres", "studios", "countries", "writers", "ratings"] {
+            assert!(table_exists(&connection, table));
+        }
+        assert!(table_exists(&connection, "unique_ids"));
+        assert!(table_exists(&connection, "movie_sets_map"));
+        let gps_index: i64 = connection
+            .query_row(
+                "SELECT count(*) FROM sqlite_master WHERE type = 'index' AND name = 'index_image_metadata_gps'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(gps_index, 1);
+    }
+
+    #[test]
+    fn migrate_is_idempotent() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        migrate(&mut connection, None).unwrap();
+        migrate(&mut connection, None).unwrap();
+        assert_eq!(user_version(&connection).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn unversioned_database_is_upgraded() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE tags (tag_id INTEGER, tag TEXT NOT NULL, PRIMARY KEY(tag_id AUTOINCREMENT));
+                INSERT INTO tags (tag) VALUES ('keep');",
+            )
+            .unwrap();
+        migrate(&mut connection, None).unwrap();
+        assert_eq!(user_version(&connection).unwrap(), SCHEMA_VERSION);
+        let tag: String = connection
+            .query_row("SELECT tag FROM tags", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tag, "keep");
+    }
+
+    #[test]
+    fn saved_searches_keep_their_order() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE searches (search_id INTEGER, from_string TEXT, PRIMARY KEY(search_id AUTOINCREMENT));
+                INSERT INTO searches (from_string) VALUES ('first');
+                INSERT INTO searches (from_string) VALUES ('second');",
+            )
+            .unwrap();
+        migrate(&mut connection, None).unwrap();
+        let mut statement = connection
+            .prepare("SELECT from_string, name, position, last_run FROM searches ORDER BY position")
+            .unwrap();
+        let rows: Vec<(String, String, i64, i64)> = statement
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("first".to_string(), String::new(), 1, 0),
+                ("second".to_string(), String::new(), 2, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn photo_positions_are_read_again() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        migrate(&mut connection, None).unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO file_metadata (filepath, modification_time, file_type, metadata_id)
+                    VALUES ('/photos/gps.jpeg', 1700000000, 1, 1);
+                INSERT INTO file_metadata (filepath, modification_time, file_type, metadata_id)
+                    VALUES ('/photos/plain.jpeg', 1700000000, 1, 2);
+                INSERT INTO image_metadata (image_id, name, path, created, GPSLatitude, GPSLongitude)
+                    VALUES (1, 'gps', '/photos/gps.jpeg', 0, 0.0125, 0.0033);
+                INSERT INTO image_metadata (image_id, name, path, created, GPSLatitude, GPSLongitude)
+                    VALUES (2, 'plain', '/photos/plain.jpeg', 0, 0.0, 0.0);
+                PRAGMA user_version = 12;",
+            )
+            .unwrap();
+        migrate(&mut connection, None).unwrap();
+        let modified = |path: &str| -> i64 {
+            connection
+                .query_row(
+                    "SELECT modification_time FROM file_metadata WHERE filepath = ?1",
+                    [path],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        assert_eq!(modified("/photos/gps.jpeg"), 0);
+        assert_eq!(modified("/photos/plain.jpeg"), 1700000000);
+    }
+
+    #[test]
+    fn newer_database_is_rejected() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        connection
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        match migrate(&mut connection, None) {
+            Err(Error::TooNew { found, supported }) => {
+                assert_eq!(found, SCHEMA_VERSION + 1);
+                assert_eq!(supported, SCHEMA_VERSION);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
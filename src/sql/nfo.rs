@@ -0,0 +1,527 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! What the NFO files of a Kodi library add to a video: original and sort titles,
+//! tagline, age rating, genres, studios, countries, writers, the roles of the actors,
+//! ratings of several sites, ids on IMDb, TMDB or TVDB and the movie set. Season,
+//! episodes, edition and source come from the NFO file or the name of the video file.
+//!
+//! The rows are written next to those of `insert_video()` and read by `video()` and
+//! `video_by_id()`. A movie set has no file of its own, `movie_set()` returns it as a
+//! collection of its movies in the order they were released. The set of a video opens
+//! as such a collection, told apart from TV shows by its `file_id` of 0.
+
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection};
+
+use super::service::Handle;
+use super::{CollectionMetadata, EpisodeMetadata, Rating, Role, UniqueId, VideoMetadata};
+
+/// Tables with one name of a video per row, and the column of the name.
+const NAMES: [(&str, &str); 3] = [
+    ("genres", "genre"),
+    ("studios", "studio"),
+    ("countries", "country"),
+];
+
+/// Statements removing everything stored here for a video, `?1` is its video_id.
+const REMOVE: [&str; 7] = [
+    "DELETE FROM genres WHERE video_id = ?1",
+    "DELETE FROM studios WHERE video_id = ?1",
+    "DELETE FROM countries WHERE video_id = ?1",
+    "DELETE FROM writers WHERE video_id = ?1",
+    "DELETE FROM ratings WHERE video_id = ?1",
+    "DELETE FROM unique_ids WHERE video_id = ?1",
+    "DELETE FROM movie_sets_map WHERE video_id = ?1",
+];
+
+fn names<'a>(metadata: &'a VideoMetadata, table: &str) -> &'a [String] {
+    match table {
+        "genres" => &metadata.genres,
+        "studios" => &metadata.studios,
+        _ => &metadata.countries,
+    }
+}
+
+fn names_mut<'a>(metadata: &'a mut VideoMetadata, table: &str) -> &'a mut Vec<String> {
+    match table {
+        "genres" => &mut metadata.genres,
+        "studios" => &mut metadata.studios,
+        _ => &mut metadata.countries,
+    }
+}
+
+fn execute<P: rusqlite::Params>(connection: &Connection, path: &str, statement: &str, params: P) {
+    let result = connection
+        .prepare_cached(statement)
+        .and_then(|mut statement| statement.execute(params));
+    if let Err(error) = result {
+        log::error!("Failed to store NFO details of {}: {}", path, error);
+    }
+}
+
+/// Store the NFO details of a video that `insert_video()` has just written.
+pub fn insert(connection: &Connection, metadata: &VideoMetadata) {
+    let id = metadata.id;
+    let path = metadata.path.as_str();
+    execute(
+        connection,
+        path,
+        "UPDATE video_metadata SET original_title = ?2, sort_title = ?3, tagline = ?4, mpaa = ?5
+            WHERE video_id = ?1",
+        params![
+            &id,
+            &metadata.original_title,
+            &metadata.sort_title,
+            &metadata.tagline,
+            &metadata.mpaa
+        ],
+    );
+    execute(
+        connection,
+        path,
+        "UPDATE video_metadata SET season = ?2, episode = ?3, last_episode = ?4, edition = ?5,
+            source = ?6 WHERE video_id = ?1",
+        params![
+            &id,
+            &metadata.season,
+            &metadata.episode,
+            &metadata.last_episode,
+            &metadata.edition,
+            &metadata.source
+        ],
+    );
+    for (table, column) in NAMES {
+        let statement = format!(
+            "INSERT INTO {} (video_id, {}) VALUES (?1, ?2)",
+            table, column
+        );
+        for name in names(metadata, table) {
+            execute(connection, path, &statement, params![&id, name]);
+        }
+    }
+    for writer in &metadata.writers {
+        execute(
+            connection,
+            path,
+            "INSERT INTO people (person_name) SELECT ?1
+                WHERE NOT EXISTS (SELECT 1 FROM people WHERE person_name = ?1)",
+            params![writer],
+        );
+        execute(
+            connection,
+            path,
+            "INSERT INTO writers (writer_id, video_id)
+                SELECT person_id, ?1 FROM people WHERE person_name = ?2 LIMIT 1",
+            params![&id, writer],
+        );
+    }
+    // the actors themselves are stored by insert_video()
+    for role in &metadata.roles {
+        execute(
+            connection,
+            path,
+            "UPDATE actors SET role = ?3, thumb = ?4 WHERE video_id = ?1
+                AND actor_id IN (SELECT person_id FROM people WHERE person_name = ?2)",
+            params![&id, &role.actor, &role.role, &role.thumb],
+        );
+    }
+    for rating in &metadata.ratings {
+        execute(
+            connection,
+            path,
+            "INSERT INTO ratings (video_id, source, value, max, votes, is_default)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                &id,
+                &rating.source,
+                &rating.value,
+                &rating.max,
+                &rating.votes,
+                &rating.default
+            ],
+        );
+    }
+    for unique_id in &metadata.unique_ids {
+        execute(
+            connection,
+            path,
+            "INSERT INTO unique_ids (video_id, kind, value, is_default) VALUES (?1, ?2, ?3, ?4)",
+            params![&id, &unique_id.kind, &unique_id.value, &unique_id.default],
+        );
+    }
+    if !metadata.movie_set.is_empty() {
+        // the overview is kept from whichever movie of the set has one
+        execute(
+            connection,
+            path,
+            "INSERT INTO movie_sets (set_name, overview) VALUES (?1, ?2)
+                ON CONFLICT(set_name) DO UPDATE SET overview = excluded.overview
+                WHERE excluded.overview <> ''",
+            params![&metadata.movie_set, &metadata.movie_set_overview],
+        );
+        execute(
+            connection,
+            path,
+            "INSERT INTO movie_sets_map (set_id, video_id)
+                SELECT set_id, ?1 FROM movie_sets WHERE set_name = ?2",
+            params![&id, &metadata.movie_set],
+        );
+    }
+}
+
+/// Remove the NFO details of a video.
+pub fn remove(connection: &Connection, video_id: i64) {
+    for statement in REMOVE {
+        if let Err(error) = connection.execute(statement, params![&video_id]) {
+            log::error!(
+                "Failed to remove NFO details of video {}: {}",
+                video_id,
+                error
+            );
+        }
+    }
+}
+
+fn read_rows<T>(
+    connection: &Connection,
+    query: &str,
+    video_id: i64,
+    row: impl FnMut(&rusqlite::Row) -> rusqlite::Result<T>,
+) -> Vec<T> {
+    let mut statement = match connection.prepare_cached(query) {
+        Ok(statement) => statement,
+        Err(error) => {
+            log::error!("could not prepare SQL statement: {}", error);
+            return Vec::new();
+        }
+    };
+    let rows = match statement.query_map(params![&video_id], row) {
+        Ok(rows) => rows.flatten().collect(),
+        Err(error) => {
+            log::error!(
+                "Failed to read NFO details of video {}: {}",
+                video_id,
+                error
+            );
+            Vec::new()
+        }
+    };
+    rows
+}
+
+/// Read the NFO details of a video into `v`.
+pub fn read(connection: &Connection, video_id: i64, v: &mut VideoMetadata) {
+    let titles = read_rows(
+        connection,
+        "SELECT original_title, sort_title, tagline, mpaa FROM video_metadata WHERE video_id = ?1",
+        video_id,
+        |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            ))
+        },
+    );
+    if let Some((original_title, sort_title, tagline, mpaa)) = titles.into_iter().next() {
+        v.original_title = original_title;
+        v.sort_title = sort_title;
+        v.tagline = tagline;
+        v.mpaa = mpaa;
+    }
+    let release = read_rows(
+        connection,
+        "SELECT season, episode, last_episode, edition, source FROM video_metadata
+            WHERE video_id = ?1",
+        video_id,
+        |row| {
+            Ok((
+                row.get::<_, Option<i32>>(0)?.unwrap_or_default(),
+                row.get::<_, Option<i32>>(1)?.unwrap_or_default(),
+                row.get::<_, Option<i32>>(2)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            ))
+        },
+    );
+    if let Some((season, episode, last_episode, edition, source)) = release.into_iter().next() {
+        v.season = season;
+        v.episode = episode;
+        v.last_episode = last_episode;
+        v.edition = edition;
+        v.source = source;
+    }
+    for (table, column) in NAMES {
+        *names_mut(v, table) = read_rows(
+            connection,
+            &format!(
+                "SELECT {} FROM {} WHERE video_id = ?1 ORDER BY entry_id",
+                column, table
+            ),
+            video_id,
+            |row| row.get::<_, String>(0),
+        );
+    }
+    v.writers = read_rows(
+        connection,
+        "SELECT people.person_name FROM writers
+            INNER JOIN people ON people.person_id = writers.writer_id
+            WHERE writers.video_id = ?1 ORDER BY writers.entry_id",
+        video_id,
+        |row| row.get::<_, String>(0),
+    );
+    v.roles = read_rows(
+        connection,
+        "SELECT people.person_name, actors.role, actors.thumb FROM actors
+            INNER JOIN people ON people.person_id = actors.actor_id
+            WHERE actors.video_id = ?1 AND (actors.role <> '' OR actors.thumb <> '')
+            ORDER BY actors.entry_id",
+        video_id,
+        |row| {
+            Ok(Role {
+                actor: row.get(0)?,
+                role: row.get(1)?,
+                thumb: row.get(2)?,
+            })
+        },
+    );
+    v.ratings = read_rows(
+        connection,
+        "SELECT source, value, max, votes, is_default FROM ratings
+            WHERE video_id = ?1 ORDER BY entry_id",
+        video_id,
+        |row| {
+            Ok(Rating {
+                source: row.get(0)?,
+                value: row.get(1)?,
+                max: row.get(2)?,
+                votes: row.get(3)?,
+                default: row.get(4)?,
+            })
+        },
+    );
+    v.unique_ids = read_rows(
+        connection,
+        "SELECT kind, value, is_default FROM unique_ids WHERE video_id = ?1 ORDER BY entry_id",
+        video_id,
+        |row| {
+            Ok(UniqueId {
+                kind: row.get(0)?,
+                value: row.get(1)?,
+                default: row.get(2)?,
+            })
+        },
+    );
+    let sets = read_rows(
+        connection,
+        "SELECT movie_sets.set_name, movie_sets.overview FROM movie_sets_map
+            INNER JOIN movie_sets ON movie_sets.set_id = movie_sets_map.set_id
+            WHERE movie_sets_map.video_id = ?1",
+        video_id,
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+    );
+    if let Some((name, overview)) = sets.into_iter().next() {
+        v.movie_set = name;
+        v.movie_set_overview = overview;
+    }
+}
+
+/// The movies of a set as a collection, numbered in the order they were released. The
+/// collection has no file, its `file_id` is 0 and its `path` the folder its movies share;
+/// the poster is that of the first movie.
+pub fn movie_set(handle: Handle, name: &str) -> CollectionMetadata {
+    let mut collection = CollectionMetadata {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let connection = match handle.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return collection;
+        }
+    };
+    let set = connection.query_row(
+        "SELECT set_id, overview FROM movie_sets WHERE set_name = ?1",
+        params![&name],
+        |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)),
+    );
+    let (set_id, overview) = match set {
+        Ok(set) => set,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return collection,
+        Err(error) => {
+            log::error!("Failed to read movie set {}: {}", name, error);
+            return collection;
+        }
+    };
+    collection.id = set_id;
+    collection.description = overview;
+    collection.episodes = read_rows(
+        &connection,
+        "SELECT file_metadata.metadata_id, file_metadata.filepath, video_metadata.poster,
+                video_metadata.thumb, video_metadata.title
+            FROM movie_sets_map
+            INNER JOIN video_metadata ON video_metadata.video_id = movie_sets_map.video_id
+            INNER JOIN file_metadata ON file_metadata.metadata_id = movie_sets_map.video_id
+            WHERE movie_sets_map.set_id = ?1
+            ORDER BY video_metadata.released, video_metadata.title",
+        set_id as i64,
+        |row| {
+            Ok(EpisodeMetadata {
+                file_id: row.get(0)?,
+                path: PathBuf::from(row.get::<_, String>(1)?),
+                poster: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                thumb: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                title: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                ..Default::default()
+            })
+        },
+    );
+    for (index, movie) in collection.episodes.iter_mut().enumerate() {
+        movie.episode = index as i32 + 1;
+    }
+    if let Some(first) = collection.episodes.first() {
+        collection.poster = first.poster.clone();
+        collection.thumb = first.thumb.clone();
+    }
+    let mut folders = collection
+        .episodes
+        .iter()
+        .filter_map(|movie| movie.path.parent());
+    if let Some(first) = folders.next() {
+        let mut shared = first.to_path_buf();
+        for folder in folders {
+            while !folder.starts_with(&shared) {
+                if !shared.pop() {
+                    break;
+                }
+            }
+        }
+        collection.path = shared;
+    }
+    collection
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::{insert_video, update_video, video};
+    use chrono::NaiveDate;
+
+    fn connection() -> Handle {
+        std::sync::Arc::new(std::sync::Mutex::new(
+            crate::sql::connect_in_memory().unwrap(),
+        ))
+    }
+
+    fn stat() -> std::fs::Metadata {
+        std::fs::metadata(std::env::temp_dir()).unwrap()
+    }
+
+    fn movie(title: &str, year: i32, folder: &str) -> VideoMetadata {
+        VideoMetadata {
+            title: title.to_string(),
+            date: NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+            path: format!("/media/Movies/{}/{}.mkv", folder, title),
+            poster: format!("/thumbs/{}.jpg", title),
+            movie_set: "Alien Collection".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn details_are_read_back_and_replaced() {
+        let handle = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let mut heat = VideoMetadata {
+            title: "Heat".to_string(),
+            path: "/media/Movies/Heat.mkv".to_string(),
+            actors: vec!["Al Pacino".to_string(), "Robert De Niro".to_string()],
+            original_title: "Heat".to_string(),
+            sort_title: "Heat 1995".to_string(),
+            tagline: "A Los Angeles crime saga".to_string(),
+            mpaa: "Rated R".to_string(),
+            genres: vec!["Crime".to_string(), "Drama".to_string()],
+            studios: vec!["Warner Bros.".to_string()],
+            countries: vec!["United States".to_string()],
+            writers: vec!["Michael Mann".to_string()],
+            roles: vec![Role {
+                actor: "Robert De Niro".to_string(),
+                role: "Neil McCauley".to_string(),
+                thumb: "https://image.tmdb.org/t/p/original/deniro.jpg".to_string(),
+            }],
+            ratings: vec![Rating {
+                source: "imdb".to_string(),
+                value: 8.3,
+                max: 10.0,
+                votes: 750_000,
+                default: true,
+            }],
+            unique_ids: vec![UniqueId {
+                kind: "imdb".to_string(),
+                value: "tt0113277".to_string(),
+                default: true,
+            }],
+            movie_set: "Michael Mann Crime".to_string(),
+            movie_set_overview: "Crime movies by Michael Mann.".to_string(),
+            ..Default::default()
+        };
+        insert_video(handle.clone(), &mut heat, &stat(), &data);
+        let read = video(handle.clone(), &heat.path, &data);
+        assert_eq!(read.original_title, heat.original_title);
+        assert_eq!(read.sort_title, heat.sort_title);
+        assert_eq!(read.tagline, heat.tagline);
+        assert_eq!(read.mpaa, heat.mpaa);
+        assert_eq!(read.genres, heat.genres);
+        assert_eq!(read.studios, heat.studios);
+        assert_eq!(read.countries, heat.countries);
+        assert_eq!(read.writers, heat.writers);
+        assert_eq!(read.roles, heat.roles);
+        assert_eq!(read.ratings, heat.ratings);
+        assert_eq!(read.unique_ids, heat.unique_ids);
+        assert_eq!(read.movie_set, heat.movie_set);
+        assert_eq!(read.movie_set_overview, heat.movie_set_overview);
+
+        heat.genres = vec!["Thriller".to_string()];
+        heat.movie_set.clear();
+        update_video(handle.clone(), &mut heat, &stat(), &data);
+        let read = video(handle.clone(), &heat.path, &data);
+        assert_eq!(read.genres, ["Thriller"]);
+        assert_eq!(read.writers, ["Michael Mann"]);
+        assert!(read.movie_set.is_empty());
+    }
+
+    #[test]
+    fn movie_sets_are_collections_in_release_order() {
+        let handle = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let mut movies = [
+            movie("Aliens", 1986, "Aliens (1986)"),
+            movie("Alien", 1979, "Alien (1979)"),
+            movie("Alien 3", 1992, "Alien 3 (1992)"),
+        ];
+        movies[1].movie_set_overview = "The Alien franchise.".to_string();
+        for movie in movies.iter_mut() {
+            insert_video(handle.clone(), movie, &stat(), &data);
+        }
+        let set = movie_set(handle.clone(), "Alien Collection");
+        assert_eq!(set.name, "Alien Collection");
+        assert_eq!(set.description, "The Alien franchise.");
+        assert_eq!(set.file_id, 0);
+        assert_eq!(set.path, PathBuf::from("/media/Movies"));
+        assert_eq!(set.poster, "/thumbs/Alien.jpg");
+        let titles: Vec<(&str, i32)> = set
+            .episodes
+            .iter()
+            .map(|movie| (movie.title.as_str(), movie.episode))
+            .collect();
+        assert_eq!(titles, [("Alien", 1), ("Aliens", 2), ("Alien 3", 3)]);
+        assert_eq!(set.episodes[0].file_id, movies[1].id);
+        assert!(movie_set(handle, "Unknown").episodes.is_empty());
+    }
+}
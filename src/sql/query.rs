@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+use rusqlite::types::Value;
+
+/// SELECT statement assembled from fixed SQL fragments. Values are never spliced into the
+/// SQL text, they are always bound as numbered parameters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    select: String,
+    conditions: Vec<String>,
+    values: Vec<Value>,
+}
+
+impl Query {
+    /// Start with the column list and table, e.g. "SELECT video_id FROM video_metadata".
+    pub fn select(select: &str) -> Query {
+        Query {
+            select: select.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a condition with a single `?` placeholder, e.g. "duration > ?".
+    /// All conditions have to match.
+    pub fn filter(mut self, condition: &str, value: impl Into<Value>) -> Query {
+        self.values.push(value.into());
+        let placeholder = format!("?{}", self.values.len());
+        self.conditions
+            .push(condition.replacen('?', &placeholder, 1));
+        self
+    }
+
+    /// Add a condition with one `?` placeholder per value, filled in order.
+    pub fn condition(mut self, condition: &str, values: Vec<Value>) -> Query {
+        let mut numbered = String::with_capacity(condition.len());
+        let mut values = values.into_iter();
+        for c in condition.chars() {
+            if c == '?' {
+                if let Some(value) = values.next() {
+                    self.values.push(value);
+                    numbered.push_str(&format!("?{}", self.values.len()));
+                    continue;
+                }
+            }
+            numbered.push(c);
+        }
+        self.conditions.push(numbered);
+        self
+    }
+
+    /// Match `text` anywhere in `column`, treating % _ and \ in the text literally.
+    pub fn like(self, column: &str, text: &str) -> Query {
+        self.filter(
+            &format!("{} LIKE ? ESCAPE '\\'", column),
+            like_pattern(text),
+        )
+    }
+
+    pub fn sql(&self) -> String {
+        if self.conditions.is_empty() {
+            self.select.clone()
+        } else {
+            format!("{} WHERE {}", self.select, self.conditions.join(" AND "))
+        }
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn params(&self) -> rusqlite::ParamsFromIter<std::slice::Iter<'_, Value>> {
+        rusqlite::params_from_iter(self.values.iter())
+    }
+}
+
+/// LIKE pattern for `text` as a substring, escaped for `ESCAPE '\'`.
+pub fn like_pattern(text: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    const NAMES: [&str; 7] = [
+        "O'Brien",
+        "100% Pure",
+        "100 Pure",
+        "C:\\Movies\\",
+        "C:Movies",
+        "snake_case",
+        "snakeXcase",
+    ];
+
+    fn connection() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute("CREATE TABLE names (id INTEGER, name TEXT)", ())
+            .unwrap();
+        for (id, name) in NAMES.iter().enumerate() {
+            connection
+                .execute(
+                    "INSERT INTO names (id, name) VALUES (?1, ?2)",
+                    rusqlite::params![id, name],
+                )
+                .unwrap();
+        }
+        connection
+    }
+
+    fn names(connection: &Connection, query: &Query) -> Vec<String> {
+        let mut statement = connection.prepare(&query.sql()).unwrap();
+        let rows = statement
+            .query_map(query.params(), |row| row.get::<_, String>(0))
+            .unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn placeholders_are_numbered() {
+        let query = Query::select("SELECT id FROM names")
+            .filter("id > ?", 1)
+            .filter("id < ?", 5);
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM names WHERE id > ?1 AND id < ?2"
+        );
+        assert_eq!(query.values(), &[Value::Integer(1), Value::Integer(5)]);
+        assert_eq!(
+            Query::select("SELECT id FROM names").sql(),
+            "SELECT id FROM names"
+        );
+    }
+
+    #[test]
+    fn condition_numbers_every_placeholder() {
+        let query = Query::select("SELECT name FROM names")
+            .filter("id > ?", 0)
+            .condition(
+                "(name LIKE ? OR id = ?)",
+                vec![Value::Text("%Pure%".to_string()), Value::Integer(5)],
+            );
+        assert_eq!(
+            query.sql(),
+            "SELECT name FROM names WHERE id > ?1 AND (name LIKE ?2 OR id = ?3)"
+        );
+        assert_eq!(
+            names(&connection(), &query),
+            vec!["100% Pure", "100 Pure", "snake_case"]
+        );
+    }
+
+    #[test]
+    fn like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("100%"), "%100\\%%");
+        assert_eq!(like_pattern("a_b\\c"), "%a\\_b\\\\c%");
+        assert_eq!(like_pattern("O'Brien"), "%O'Brien%");
+    }
+
+    #[test]
+    fn quotes_are_matched_literally() {
+        let connection = connection();
+        let query = Query::select("SELECT name FROM names").like("name", "O'Brien");
+        assert_eq!(names(&connection, &query), vec!["O'Brien"]);
+        let query = Query::select("SELECT name FROM names").like("name", "'; DROP TABLE names; --");
+        assert!(names(&connection, &query).is_empty());
+        assert_eq!(
+            names(&connection, &Query::select("SELECT name FROM names")).len(),
+            NAMES.len()
+        );
+    }
+
+    #[test]
+    fn wildcards_are_matched_literally() {
+        let connection = connection();
+        let query = Query::select("SELECT name FROM names").like("name", "100%");
+        assert_eq!(names(&connection, &query), vec!["100% Pure"]);
+        let query = Query::select("SELECT name FROM names").like("name", "snake_case");
+        assert_eq!(names(&connection, &query), vec!["snake_case"]);
+        let query = Query::select("SELECT name FROM names").like("name", "C:\\Movies");
+        assert_eq!(names(&connection, &query), vec!["C:\\Movies\\"]);
+    }
+}
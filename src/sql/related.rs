@@ -0,0 +1,549 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Items of the library related to a video, song or photo, for the preview panel.
+//!
+//! Videos are related by their directors, actors, collections, movie sets, genres and
+//! tags, songs by their album artists, composer, genre and tags, photos by the day they
+//! were taken, the lens, the place and their tags. Every shared value adds the weight of
+//! its `Reason` to the score of the other item, so a movie with the same director and two
+//! of the same actors comes before one sharing a single tag. Only items of the same kind
+//! are offered.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+
+use super::fold::fold;
+use super::geo::{distance_km, Area, DEFAULT_RADIUS_KM};
+use super::service::Handle;
+
+/// Number of related items shown in the preview.
+pub const LIMIT: usize = 20;
+
+/// What two items have in common.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Reason {
+    Director,
+    Actor,
+    Collection,
+    Tag,
+    AlbumArtist,
+    Composer,
+    Genre,
+    SameDay,
+    Lens,
+    Nearby,
+}
+
+impl Reason {
+    /// Points added to the score of an item for every value of this kind it shares.
+    pub fn weight(&self) -> u32 {
+        match self {
+            Reason::Collection => 5,
+            Reason::Director | Reason::AlbumArtist => 4,
+            Reason::SameDay | Reason::Nearby => 3,
+            Reason::Actor | Reason::Composer => 2,
+            Reason::Tag | Reason::Genre | Reason::Lens => 1,
+        }
+    }
+}
+
+/// A value shared with the item the related items were looked up for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shared {
+    pub reason: Reason,
+    pub value: String,
+}
+
+/// An item of the library sharing at least one value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Related {
+    pub path: PathBuf,
+    pub title: String,
+    pub score: u32,
+    pub shared: Vec<Shared>,
+}
+
+// file_type of file_metadata
+const IMAGE: u8 = 1;
+const VIDEO: u8 = 2;
+const AUDIO: u8 = 3;
+
+/// Queries returning the id of every other item sharing a value with item ?1, and the
+/// shared value.
+const QUERIES: [(Reason, &[u8], &str); 11] = [
+    (
+        Reason::Director,
+        &[VIDEO],
+        "SELECT other.video_id, people.person_name FROM directors AS own
+            INNER JOIN directors AS other ON other.director_id = own.director_id
+                AND other.video_id <> own.video_id
+            INNER JOIN people ON people.person_id = own.director_id
+            WHERE own.video_id = ?1",
+    ),
+    (
+        Reason::Actor,
+        &[VIDEO],
+        "SELECT other.video_id, people.person_name FROM actors AS own
+            INNER JOIN actors AS other ON other.actor_id = own.actor_id
+                AND other.video_id <> own.video_id
+            INNER JOIN people ON people.person_id = own.actor_id
+            WHERE own.video_id = ?1",
+    ),
+    (
+        Reason::Collection,
+        &[VIDEO],
+        "SELECT other.episode_id, collections.collection_name FROM collections_map AS own
+            INNER JOIN collections_map AS other ON other.collection_id = own.collection_id
+                AND other.episode_id <> own.episode_id
+            INNER JOIN collections ON collections.collection_id = own.collection_id
+            WHERE own.episode_id = ?1",
+    ),
+    (
+        Reason::Collection,
+        &[VIDEO],
+        "SELECT other.video_id, movie_sets.set_name FROM movie_sets_map AS own
+            INNER JOIN movie_sets_map AS other ON other.set_id = own.set_id
+                AND other.video_id <> own.video_id
+            INNER JOIN movie_sets ON movie_sets.set_id = own.set_id
+            WHERE own.video_id = ?1",
+    ),
+    (
+        Reason::Tag,
+        &[IMAGE, VIDEO, AUDIO],
+        "SELECT other.media_id, tags.tag FROM tags_media_map AS own
+            INNER JOIN tags_media_map AS other ON other.tagmap_id = own.tagmap_id
+                AND other.media_id <> own.media_id
+            INNER JOIN tags ON tags.tag_id = own.tagmap_id
+            WHERE own.media_id = ?1",
+    ),
+    (
+        Reason::AlbumArtist,
+        &[AUDIO],
+        "SELECT other.audio_id, artists.artist_name FROM albumartist_audio_map AS own
+            INNER JOIN albumartist_audio_map AS other ON other.albumartist_id = own.albumartist_id
+                AND other.audio_id <> own.audio_id
+            INNER JOIN artists ON artists.artist_id = own.albumartist_id
+            WHERE own.audio_id = ?1",
+    ),
+    (
+        Reason::Composer,
+        &[AUDIO],
+        "SELECT other.audio_id, own.composer FROM audio_metadata AS own
+            INNER JOIN audio_metadata AS other ON other.composer = own.composer
+                AND other.audio_id <> own.audio_id
+            WHERE own.audio_id = ?1 AND own.composer <> ''",
+    ),
+    (
+        Reason::Genre,
+        &[AUDIO],
+        "SELECT other.audio_id, own.genre FROM audio_metadata AS own
+            INNER JOIN audio_metadata AS other ON other.genre = own.genre
+                AND other.audio_id <> own.audio_id
+            WHERE own.audio_id = ?1 AND own.genre <> ''",
+    ),
+    (
+        Reason::Genre,
+        &[VIDEO],
+        "SELECT other.video_id, own.genre FROM genres AS own
+            INNER JOIN genres AS other ON other.genre = own.genre
+                AND other.video_id <> own.video_id
+            WHERE own.video_id = ?1",
+    ),
+    (
+        Reason::SameDay,
+        &[IMAGE],
+        "SELECT other.image_id, CAST(own.created AS TEXT) FROM image_metadata AS own
+            INNER JOIN image_metadata AS other ON other.created = own.created
+                AND other.image_id <> own.image_id
+            WHERE own.image_id = ?1 AND own.created <> '1970-01-01'",
+    ),
+    (
+        Reason::Lens,
+        &[IMAGE],
+        "SELECT other.image_id, own.LenseModel FROM image_metadata AS own
+            INNER JOIN image_metadata AS other ON other.LenseModel = own.LenseModel
+                AND other.image_id <> own.image_id
+            WHERE own.image_id = ?1 AND own.LenseModel <> ''",
+    ),
+];
+
+fn add(found: &mut HashMap<i64, Vec<Shared>>, id: i64, reason: Reason, value: String) {
+    let shared = found.entry(id).or_default();
+    if !shared
+        .iter()
+        .any(|known| known.reason == reason && known.value == value)
+    {
+        shared.push(Shared { reason, value });
+    }
+}
+
+fn read_shared(
+    connection: &Connection,
+    reason: Reason,
+    query: &str,
+    id: i64,
+    found: &mut HashMap<i64, Vec<Shared>>,
+) {
+    let mut statement = match connection.prepare_cached(query) {
+        Ok(statement) => statement,
+        Err(error) => {
+            log::error!("Failed to prepare related items query: {}", error);
+            return;
+        }
+    };
+    let rows = statement.query_map(params![&id], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+    });
+    match rows {
+        Ok(rows) => {
+            for (other, value) in rows.flatten() {
+                add(found, other, reason, value.unwrap_or_default());
+            }
+        }
+        Err(error) => log::error!("Failed to read related items: {}", error),
+    }
+}
+
+/// Photos taken within `DEFAULT_RADIUS_KM` of photo `id`.
+fn read_nearby(connection: &Connection, id: i64, found: &mut HashMap<i64, Vec<Shared>>) {
+    let position = connection.query_row(
+        "SELECT GPSLatitude, GPSLongitude FROM image_metadata WHERE image_id = ?1",
+        params![&id],
+        |row| Ok((row.get::<_, Option<f64>>(0)?, row.get::<_, Option<f64>>(1)?)),
+    );
+    let (latitude, longitude) = match position {
+        Ok((Some(latitude), Some(longitude))) => (latitude, longitude),
+        Ok(_) => return,
+        Err(error) => {
+            log::error!("Failed to read position of photo {}: {}", id, error);
+            return;
+        }
+    };
+    // photos without a position store 0, 0
+    if latitude == 0.0 && longitude == 0.0 {
+        return;
+    }
+    let (test, limits) =
+        Area::around(latitude, longitude, DEFAULT_RADIUS_KM).sql("GPSLatitude", "GPSLongitude");
+    let query = format!(
+        "SELECT image_id, GPSLatitude, GPSLongitude FROM image_metadata
+            WHERE image_id <> ? AND {} AND distance_km(GPSLatitude, GPSLongitude, ?, ?) <= ?",
+        test
+    );
+    let mut values = vec![Value::Integer(id)];
+    values.extend(limits.into_iter().map(Value::Real));
+    values.extend([
+        Value::Real(latitude),
+        Value::Real(longitude),
+        Value::Real(DEFAULT_RADIUS_KM),
+    ]);
+    let mut statement = match connection.prepare(&query) {
+        Ok(statement) => statement,
+        Err(error) => {
+            log::error!("Failed to prepare nearby photos query: {}", error);
+            return;
+        }
+    };
+    let rows = statement.query_map(params_from_iter(values), |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, f64>(1)?,
+            row.get::<_, f64>(2)?,
+        ))
+    });
+    match rows {
+        Ok(rows) => {
+            for (other, other_latitude, other_longitude) in rows.flatten() {
+                let distance = distance_km(latitude, longitude, other_latitude, other_longitude);
+                add(
+                    found,
+                    other,
+                    Reason::Nearby,
+                    format!("{:.0} m", distance * 1000.0),
+                );
+            }
+        }
+        Err(error) => log::error!("Failed to read nearby photos: {}", error),
+    }
+}
+
+/// Path, kind and title of item `id`.
+fn describe(connection: &Connection, id: i64) -> Option<(PathBuf, u8, String)> {
+    let query = "SELECT file_metadata.filepath, file_metadata.file_type,
+            COALESCE(NULLIF(video_metadata.title, ''), video_metadata.name,
+                NULLIF(audio_metadata.title, ''), audio_metadata.name, image_metadata.name, '')
+        FROM file_metadata
+        LEFT JOIN video_metadata ON video_metadata.video_id = file_metadata.metadata_id
+        LEFT JOIN audio_metadata ON audio_metadata.audio_id = file_metadata.metadata_id
+        LEFT JOIN image_metadata ON image_metadata.image_id = file_metadata.metadata_id
+        WHERE file_metadata.metadata_id = ?1";
+    let mut statement = match connection.prepare_cached(query) {
+        Ok(statement) => statement,
+        Err(error) => {
+            log::error!("Failed to prepare related item query: {}", error);
+            return None;
+        }
+    };
+    match statement.query_row(params![&id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, u8>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    }) {
+        Ok((path, file_type, title)) => Some((PathBuf::from(path), file_type, title)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(error) => {
+            log::error!("Failed to read related item {}: {}", id, error);
+            None
+        }
+    }
+}
+
+/// Up to `limit` items sharing values with the item at `path`, the most related first.
+pub fn related(handle: Handle, path: &str, limit: usize) -> Vec<Related> {
+    let connection = match handle.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return Vec::new();
+        }
+    };
+    let own = connection.query_row(
+        "SELECT metadata_id, file_type FROM file_metadata WHERE filepath = ?1",
+        params![path],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, u8>(1)?)),
+    );
+    let (id, file_type) = match own {
+        Ok(own) => own,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Vec::new(),
+        Err(error) => {
+            log::error!("Failed to read {} for related items: {}", path, error);
+            return Vec::new();
+        }
+    };
+
+    let mut found = HashMap::new();
+    for (reason, file_types, query) in QUERIES {
+        if file_types.contains(&file_type) {
+            read_shared(&connection, reason, query, id, &mut found);
+        }
+    }
+    if file_type == IMAGE {
+        read_nearby(&connection, id, &mut found);
+    }
+
+    let mut related: Vec<Related> = found
+        .into_iter()
+        .filter_map(|(other, shared)| {
+            let (path, other_type, title) = describe(&connection, other)?;
+            if other_type != file_type {
+                return None;
+            }
+            Some(Related {
+                path,
+                title,
+                score: shared.iter().map(|shared| shared.reason.weight()).sum(),
+                shared,
+            })
+        })
+        .collect();
+    related.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| fold(&a.title).cmp(&fold(&b.title)))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    related.truncate(limit);
+    related
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::{
+        insert_audio, insert_collection, insert_image, insert_tag, insert_video, AudioMetadata,
+        CollectionMetadata, EpisodeMetadata, ImageMetadata, VideoMetadata,
+    };
+    use chrono::NaiveDate;
+
+    fn connection() -> Handle {
+        std::sync::Arc::new(std::sync::Mutex::new(
+            crate::sql::connect_in_memory().unwrap(),
+        ))
+    }
+
+    fn stat() -> std::fs::Metadata {
+        std::fs::metadata(std::env::temp_dir()).unwrap()
+    }
+
+    fn titles(related: &[Related]) -> Vec<&str> {
+        related.iter().map(|item| item.title.as_str()).collect()
+    }
+
+    fn movie(title: &str, director: &[&str], actors: &[&str]) -> VideoMetadata {
+        VideoMetadata {
+            title: title.to_string(),
+            path: format!("/media/{}.mkv", title),
+            director: director.iter().map(|name| name.to_string()).collect(),
+            actors: actors.iter().map(|name| name.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn videos_are_ranked_by_shared_people_collections_and_tags() {
+        let handle = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let mut movies = [
+            movie(
+                "Pulp Fiction",
+                &["Quentin Tarantino"],
+                &["Uma Thurman", "Samuel L. Jackson"],
+            ),
+            movie("Kill Bill", &["Quentin Tarantino"], &["Uma Thurman"]),
+            movie(
+                "Jackie Brown",
+                &["Quentin Tarantino"],
+                &["Samuel L. Jackson"],
+            ),
+            movie("Gattaca", &["Andrew Niccol"], &["Uma Thurman"]),
+            movie("Alien", &["Ridley Scott"], &["Sigourney Weaver"]),
+            movie("Kill Bill 2", &[], &[]),
+        ];
+        for movie in movies.iter_mut() {
+            insert_video(handle.clone(), movie, &stat(), &data);
+        }
+        insert_tag(handle.clone(), movies[0].id, "favourite".to_string());
+        insert_tag(handle.clone(), movies[4].id, "favourite".to_string());
+        let mut collection = CollectionMetadata {
+            name: "Kill Bill Collection".to_string(),
+            path: PathBuf::from("/media/Kill Bill Collection.nfo"),
+            episodes: vec![
+                EpisodeMetadata {
+                    file_id: movies[1].id,
+                    ..Default::default()
+                },
+                EpisodeMetadata {
+                    file_id: movies[5].id,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        insert_collection(handle.clone(), &mut collection, &stat(), &data);
+
+        let pulp = related(handle.clone(), "/media/Pulp Fiction.mkv", LIMIT);
+        assert_eq!(
+            titles(&pulp),
+            ["Jackie Brown", "Kill Bill", "Gattaca", "Alien"]
+        );
+        assert_eq!(pulp[0].score, 6);
+        assert_eq!(
+            pulp[3].shared,
+            [Shared {
+                reason: Reason::Tag,
+                value: "favourite".to_string()
+            }]
+        );
+        let sequel = related(handle.clone(), "/media/Kill Bill 2.mkv", LIMIT);
+        assert_eq!(titles(&sequel), ["Kill Bill"]);
+        assert_eq!(sequel[0].shared[0].reason, Reason::Collection);
+        assert_eq!(
+            titles(&related(handle.clone(), "/media/Pulp Fiction.mkv", 2)),
+            ["Jackie Brown", "Kill Bill"]
+        );
+        assert!(related(handle, "/media/missing.mkv", LIMIT).is_empty());
+    }
+
+    #[test]
+    fn movie_sets_and_genres_relate_videos() {
+        let handle = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let mut movies = [
+            movie("Alien", &[], &[]),
+            movie("Aliens", &[], &[]),
+            movie("The Thing", &[], &[]),
+            movie("Amélie", &[], &[]),
+        ];
+        movies[0].movie_set = "Alien Collection".to_string();
+        movies[1].movie_set = "Alien Collection".to_string();
+        for (movie, genres) in movies
+            .iter_mut()
+            .zip([&["Horror", "Science Fiction"][..], &["Action"], &["Horror"], &["Comedy"]])
+        {
+            movie.genres = genres.iter().map(|genre| genre.to_string()).collect();
+            insert_video(handle.clone(), movie, &stat(), &data);
+        }
+        let alien = related(handle, "/media/Alien.mkv", LIMIT);
+        assert_eq!(titles(&alien), ["Aliens", "The Thing"]);
+        assert_eq!(
+            alien[0].shared,
+            [Shared {
+                reason: Reason::Collection,
+                value: "Alien Collection".to_string()
+            }]
+        );
+        assert_eq!(alien[1].shared[0].reason, Reason::Genre);
+    }
+
+    #[test]
+    fn songs_and_photos_are_related_by_their_own_fields() {
+        let handle = connection();
+        let data = crate::scanmetadata::ScanMetaData::new();
+        for (title, composer, genre) in [
+            ("Prelude", "Bach", "Baroque"),
+            ("Fugue", "Bach", "Baroque"),
+            ("Spring", "Vivaldi", "Baroque"),
+            ("Clair de lune", "Debussy", "Impressionism"),
+        ] {
+            let mut song = AudioMetadata {
+                title: title.to_string(),
+                path: format!("/media/{}.flac", title),
+                composer: composer.to_string(),
+                genre: genre.to_string(),
+                ..Default::default()
+            };
+            insert_audio(handle.clone(), &mut song, &stat(), &data);
+        }
+        let songs = related(handle.clone(), "/media/Prelude.flac", LIMIT);
+        assert_eq!(titles(&songs), ["Fugue", "Spring"]);
+        assert_eq!(songs[0].score, 3);
+
+        let day = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        for (name, date, lens, latitude, longitude) in [
+            ("harbour", day, "Nikkor Z 24-70mm", -33.8568, 151.2153),
+            ("bridge", day, "Nikkor Z 50mm", -33.8523, 151.2108),
+            ("beach", day, "Nikkor Z 24-70mm", -33.8915, 151.2767),
+            (
+                "home",
+                NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                "",
+                0.0,
+                0.0,
+            ),
+        ] {
+            let mut photo = ImageMetadata {
+                name: name.to_string(),
+                path: format!("/media/{}.jpeg", name),
+                date,
+                lense_model: lens.to_string(),
+                gps_latitude: latitude,
+                gps_longitude: longitude,
+                ..Default::default()
+            };
+            insert_image(handle.clone(), &mut photo, &stat(), &data);
+        }
+        // the bridge is 650 m away, the beach 7 km
+        let photos = related(handle.clone(), "/media/harbour.jpeg", LIMIT);
+        assert_eq!(titles(&photos), ["bridge", "beach"]);
+        assert_eq!((photos[0].score, photos[1].score), (6, 4));
+        assert!(related(handle, "/media/home.jpeg", LIMIT).is_empty());
+    }
+}
@@ -0,0 +1,509 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The connection handle every function in `crate::sql` works on.
+pub type Handle = Arc<Mutex<Connection>>;
+
+/// Commands queued behind each other are committed together, up to this many at once.
+const BATCH_SIZE: usize = 256;
+/// A transaction held open by a `Batch` is committed at least this often.
+const COMMIT_INTERVAL: Duration = Duration::from_secs(2);
+/// Statements kept prepared on the writer connection.
+const STATEMENT_CACHE: usize = 64;
+/// Read-only connections kept open for reuse after a read.
+const IDLE_READERS: usize = 4;
+/// How long a connection waits for a lock held by another connection.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs on the writer thread, the returned reply is sent after the batch was committed.
+type Reply = Box<dyn FnOnce() + Send>;
+
+enum Command {
+    Run(Box<dyn FnOnce(Handle) -> Reply + Send>),
+    /// Commit now and run outside of a transaction.
+    Exclusive(Box<dyn FnOnce(Handle) + Send>),
+    /// Commit now and signal the sender.
+    Flush(mpsc::Sender<()>),
+    /// A `Batch` was started or dropped.
+    Hold,
+    Release,
+}
+
+/// Access to the metadata database.
+///
+/// All inserts, updates and deletes are sent to a single writer thread that commits them in
+/// batched transactions. Browsing and searching use a pool of read-only connections which,
+/// thanks to the write-ahead log, never wait for the writer. Clones share the same writer
+/// and pool.
+#[derive(Clone)]
+pub struct Database {
+    commands: mpsc::Sender<Command>,
+    readers: Arc<Readers>,
+}
+
+impl std::fmt::Debug for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("file", &self.readers.file)
+            .finish()
+    }
+}
+
+struct Readers {
+    /// `None` for an in-memory database, reads then share the writer's connection.
+    file: Option<PathBuf>,
+    shared: Option<Handle>,
+    idle: Mutex<Vec<Handle>>,
+}
+
+impl Database {
+    /// Open the database selected with `set_database_file()` or the default one.
+    pub fn open() -> Result<Database, super::migrations::Error> {
+        Database::open_at(&super::database_file())
+    }
+
+    /// Open or create `sqlite_file`, upgrade its schema and start the writer thread.
+    pub fn open_at(sqlite_file: &Path) -> Result<Database, super::migrations::Error> {
+        let connection = super::connect_at(sqlite_file)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        let journal_mode: String =
+            connection.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            log::warn!(
+                "Database {} does not use a write-ahead log ({}), reads may wait for the scanner",
+                sqlite_file.display(),
+                journal_mode
+            );
+        }
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.set_prepared_statement_cache_capacity(STATEMENT_CACHE);
+        let writer = Arc::new(Mutex::new(connection));
+        Ok(Database::start(
+            writer,
+            Readers {
+                file: Some(sqlite_file.to_path_buf()),
+                shared: None,
+                idle: Mutex::new(Vec::new()),
+            },
+        ))
+    }
+
+    /// A private in-memory database, mainly for tests.
+    pub fn in_memory() -> Result<Database, super::migrations::Error> {
+        let writer = Arc::new(Mutex::new(super::connect_in_memory()?));
+        Ok(Database::start(
+            writer.clone(),
+            Readers {
+                file: None,
+                shared: Some(writer),
+                idle: Mutex::new(Vec::new()),
+            },
+        ))
+    }
+
+    fn start(writer: Handle, readers: Readers) -> Database {
+        let (commands, receiver) = mpsc::channel();
+        if let Err(error) = std::thread::Builder::new()
+            .name("database writer".to_string())
+            .spawn(move || write_loop(writer, receiver))
+        {
+            log::error!("Failed to start the database writer thread! {}", error);
+        }
+        Database {
+            commands,
+            readers: Arc::new(readers),
+        }
+    }
+
+    /// Run `f` on a read-only connection. Writes done by `f` fail.
+    pub fn read<T>(&self, f: impl FnOnce(Handle) -> T) -> T {
+        let connection = self.readers.take();
+        let result = f(connection.clone());
+        self.readers.put_back(connection);
+        result
+    }
+
+    /// Queue `f` on the writer thread and wait until its transaction was committed.
+    /// While a `Batch` is open the result is returned as soon as `f` ran, readers see the
+    /// changes after the next commit.
+    pub fn write<T: Send + 'static>(&self, f: impl FnOnce(Handle) -> T + Send + 'static) -> T {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::Run(Box::new(move |connection| {
+            let result = f(connection);
+            Box::new(move || {
+                let _ = sender.send(result);
+            })
+        })));
+        match receiver.recv() {
+            Ok(result) => result,
+            Err(error) => panic!("The database writer dropped a command! {}", error),
+        }
+    }
+
+    /// Commit what is pending and run `f` on the writer thread outside of a transaction,
+    /// for statements like `VACUUM` that cannot run inside one. Writes queued meanwhile
+    /// wait until `f` returned, an open `Batch` continues in a new transaction afterwards.
+    pub fn exclusive<T: Send + 'static>(&self, f: impl FnOnce(Handle) -> T + Send + 'static) -> T {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::Exclusive(Box::new(move |connection| {
+            let _ = sender.send(f(connection));
+        })));
+        match receiver.recv() {
+            Ok(result) => result,
+            Err(error) => panic!("The database writer dropped a command! {}", error),
+        }
+    }
+
+    /// Queue `f` on the writer thread without waiting for it.
+    pub fn submit(&self, f: impl FnOnce(Handle) + Send + 'static) {
+        self.send(Command::Run(Box::new(move |connection| {
+            f(connection);
+            Box::new(|| {})
+        })));
+    }
+
+    /// Keep the writer's transaction open until the returned `Batch` is dropped, so all
+    /// writes in between are committed together. Large batches are still committed every
+    /// `BATCH_SIZE` commands.
+    pub fn batch(&self) -> Batch {
+        self.send(Command::Hold);
+        Batch {
+            commands: self.commands.clone(),
+        }
+    }
+
+    /// Run one of the `insert_*` or `update_*` functions on the writer thread and copy the
+    /// stored metadata, including the ids it was given, back into `metadata`.
+    pub fn store<M: Clone + Send + 'static>(
+        &self,
+        f: fn(Handle, &mut M, &std::fs::Metadata, &crate::scanmetadata::ScanMetaData),
+        metadata: &mut M,
+        statdata: &std::fs::Metadata,
+        data: &crate::scanmetadata::ScanMetaData,
+    ) {
+        let mut stored = metadata.clone();
+        let statdata = statdata.clone();
+        let data = data.clone();
+        *metadata = self.write(move |connection| {
+            f(connection, &mut stored, &statdata, &data);
+            stored
+        });
+    }
+
+    /// Wait until everything submitted so far was committed.
+    pub fn flush(&self) {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Command::Flush(sender));
+        let _ = receiver.recv();
+    }
+
+    fn send(&self, command: Command) {
+        if let Err(error) = self.commands.send(command) {
+            log::error!("The database writer thread is not running! {}", error);
+        }
+    }
+}
+
+/// Open transaction on the writer, see `Database::batch()`.
+pub struct Batch {
+    commands: mpsc::Sender<Command>,
+}
+
+impl Drop for Batch {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Release);
+    }
+}
+
+impl Readers {
+    fn take(&self) -> Handle {
+        if let Some(shared) = &self.shared {
+            return shared.clone();
+        }
+        if let Ok(mut idle) = self.idle.lock() {
+            if let Some(connection) = idle.pop() {
+                return connection;
+            }
+        }
+        let connection = match &self.file {
+            Some(file) => match open_reader(file) {
+                Ok(connection) => connection,
+                Err(error) => {
+                    log::error!("Failed to open {} for reading! {}", file.display(), error);
+                    // keep going, every query on the empty database fails and gets logged
+                    Connection::open_in_memory().expect("in-memory database")
+                }
+            },
+            None => Connection::open_in_memory().expect("in-memory database"),
+        };
+        Arc::new(Mutex::new(connection))
+    }
+
+    fn put_back(&self, connection: Handle) {
+        if self.shared.is_some() {
+            return;
+        }
+        if let Ok(mut idle) = self.idle.lock() {
+            if idle.len() < IDLE_READERS {
+                idle.push(connection);
+            }
+        }
+    }
+}
+
+fn open_reader(file: &Path) -> Result<Connection, rusqlite::Error> {
+    let connection = Connection::open_with_flags(
+        file,
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_URI
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    super::geo::register(&connection)?;
+    super::fold::register(&connection)?;
+    Ok(connection)
+}
+
+/// Transaction state of the writer thread.
+struct Writer {
+    connection: Handle,
+    /// Number of open `Batch`es.
+    holds: usize,
+    /// Commands run since the transaction was started.
+    pending: usize,
+    in_transaction: bool,
+    started: Instant,
+    /// Replies held back until the commit.
+    replies: Vec<Reply>,
+}
+
+impl Writer {
+    fn run(&mut self, command: Box<dyn FnOnce(Handle) -> Reply + Send>) {
+        if self.pending == 0 {
+            self.in_transaction = execute(&self.connection, "BEGIN IMMEDIATE");
+            self.started = Instant::now();
+        }
+        self.pending += 1;
+        let connection = self.connection.clone();
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| command(connection))) {
+            Ok(reply) if self.holds > 0 => reply(),
+            Ok(reply) => self.replies.push(reply),
+            Err(_) => {
+                log::error!("A database command panicked, continuing with the next one");
+                self.connection.clear_poison();
+            }
+        }
+        if self.pending >= BATCH_SIZE || self.started.elapsed() >= COMMIT_INTERVAL {
+            self.commit();
+        }
+    }
+
+    fn exclusive(&mut self, command: Box<dyn FnOnce(Handle) + Send>) {
+        self.commit();
+        let connection = self.connection.clone();
+        if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| command(connection))).is_err() {
+            log::error!("A database command panicked, continuing with the next one");
+            self.connection.clear_poison();
+        }
+    }
+
+    fn commit(&mut self) {
+        if self.in_transaction && !execute(&self.connection, "COMMIT") {
+            execute(&self.connection, "ROLLBACK");
+        }
+        self.in_transaction = false;
+        self.pending = 0;
+        for reply in self.replies.drain(..) {
+            reply();
+        }
+    }
+
+    /// Wait for the next command, committing whenever the queue runs empty outside of a
+    /// batch or a batch stayed open for too long.
+    fn next(&mut self, receiver: &mpsc::Receiver<Command>) -> Option<Command> {
+        loop {
+            if self.pending == 0 {
+                return receiver.recv().ok();
+            }
+            if self.holds == 0 {
+                match receiver.try_recv() {
+                    Ok(command) => return Some(command),
+                    Err(mpsc::TryRecvError::Empty) => self.commit(),
+                    Err(mpsc::TryRecvError::Disconnected) => return None,
+                }
+            } else {
+                let wait = COMMIT_INTERVAL.saturating_sub(self.started.elapsed());
+                match receiver.recv_timeout(wait) {
+                    Ok(command) => return Some(command),
+                    Err(mpsc::RecvTimeoutError::Timeout) => self.commit(),
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+                }
+            }
+        }
+    }
+}
+
+fn write_loop(connection: Handle, receiver: mpsc::Receiver<Command>) {
+    let mut writer = Writer {
+        connection,
+        holds: 0,
+        pending: 0,
+        in_transaction: false,
+        started: Instant::now(),
+        replies: Vec::new(),
+    };
+    // ends when every Database clone is gone
+    while let Some(command) = writer.next(&receiver) {
+        match command {
+            Command::Run(command) => writer.run(command),
+            Command::Exclusive(command) => writer.exclusive(command),
+            Command::Flush(sender) => {
+                writer.commit();
+                let _ = sender.send(());
+            }
+            Command::Hold => writer.holds += 1,
+            Command::Release => writer.holds = writer.holds.saturating_sub(1),
+        }
+    }
+    writer.commit();
+}
+
+fn execute(writer: &Handle, sql: &str) -> bool {
+    let connection = match writer.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return false;
+        }
+    };
+    match connection.execute_batch(sql) {
+        Ok(()) => true,
+        Err(error) => {
+            log::error!("Failed to execute {}: {}", sql, error);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(connection: Handle) -> i64 {
+        let connection = connection.lock().unwrap();
+        connection
+            .query_row("SELECT count(*) FROM tags", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn insert(connection: Handle, tag: &str) {
+        let connection = connection.lock().unwrap();
+        connection
+            .execute("INSERT INTO tags (tag) VALUES (?1)", [tag])
+            .unwrap();
+    }
+
+    #[test]
+    fn submitted_writes_are_committed() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::open_at(&dir.path().join("metadata.sqlite")).unwrap();
+        for i in 0..1000 {
+            let tag = format!("tag {}", i);
+            database.submit(move |connection| insert(connection, &tag));
+        }
+        database.flush();
+        assert_eq!(database.read(count), 1000);
+    }
+
+    #[test]
+    fn write_returns_result() {
+        let database = Database::in_memory().unwrap();
+        let rows = database.write(|connection| {
+            insert(connection.clone(), "holiday");
+            count(connection)
+        });
+        assert_eq!(rows, 1);
+        assert_eq!(database.read(count), 1);
+    }
+
+    #[test]
+    fn readers_are_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::open_at(&dir.path().join("metadata.sqlite")).unwrap();
+        let result = database.read(|connection| {
+            let connection = connection.lock().unwrap();
+            connection.execute("INSERT INTO tags (tag) VALUES ('holiday')", [])
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn reads_do_not_wait_for_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::open_at(&dir.path().join("metadata.sqlite")).unwrap();
+        database.write(|connection| insert(connection, "before"));
+        let (started, running) = mpsc::channel();
+        let (finish, finished) = mpsc::channel::<()>();
+        database.submit(move |connection| {
+            insert(connection, "during");
+            started.send(()).unwrap();
+            finished.recv().unwrap();
+        });
+        running.recv().unwrap();
+        // the writer holds an open transaction, readers still see the last commit
+        assert_eq!(database.read(count), 1);
+        finish.send(()).unwrap();
+        database.flush();
+        assert_eq!(database.read(count), 2);
+    }
+
+    #[test]
+    fn batch_is_committed_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::open_at(&dir.path().join("metadata.sqlite")).unwrap();
+        let batch = database.batch();
+        for i in 0..10 {
+            let tag = format!("tag {}", i);
+            // results come back while the batch is still open
+            database.write(move |connection| insert(connection, &tag));
+        }
+        assert_eq!(database.read(count), 0);
+        drop(batch);
+        database.flush();
+        assert_eq!(database.read(count), 10);
+    }
+
+    #[test]
+    fn vacuum_runs_during_a_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::open_at(&dir.path().join("metadata.sqlite")).unwrap();
+        let batch = database.batch();
+        database.write(|connection| insert(connection, "before"));
+        let vacuumed = database.exclusive(|connection| {
+            let connection = connection.lock().unwrap();
+            connection.execute_batch("VACUUM")
+        });
+        assert!(vacuumed.is_ok(), "{:?}", vacuumed);
+        // the batch goes on in a new transaction
+        database.write(|connection| insert(connection, "after"));
+        assert_eq!(database.read(count), 1);
+        drop(batch);
+        database.flush();
+        assert_eq!(database.read(count), 2);
+    }
+
+    #[test]
+    fn panicking_command_keeps_writer_alive() {
+        let database = Database::in_memory().unwrap();
+        database.submit(|_connection| panic!("broken command"));
+        database.write(|connection| insert(connection, "holiday"));
+        assert_eq!(database.read(count), 1);
+    }
+}
@@ -0,0 +1,426 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//
+// Modifications:
+// Copyright 2024 Alexander Schwarzkopf
+
+//! Suggestions for names typed into the search panel, taken from the people, artists,
+//! albums, genres, composers, studios, movie sets and tags of the library.
+//!
+//! `vocabulary()` reads every name once with the number of files it belongs to and folds
+//! it for comparing, `rank()` then orders the names for each keystroke: names starting with the typed text first,
+//! then names with a word starting with it ("tara" finds "Quentin Tarantino"), names
+//! containing it, and finally names one or two typos away. Case and accents are ignored,
+//! so "bjork" offers "Björk". Within each group the names used by more files come first.
+
+use rusqlite::Connection;
+
+use super::fold::fold;
+use super::service::Handle;
+use super::SearchType;
+
+/// Fields with names to suggest, in the order they are offered.
+pub const FIELDS: [SearchType; 11] = [
+    SearchType::Actor,
+    SearchType::Director,
+    SearchType::Writer,
+    SearchType::Artist,
+    SearchType::AlbumArtist,
+    SearchType::Album,
+    SearchType::Composer,
+    SearchType::Genre,
+    SearchType::Studio,
+    SearchType::MovieSet,
+    SearchType::Tag,
+];
+
+/// A name of the library and the number of files it is used by.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub field: SearchType,
+    pub name: String,
+    pub count: usize,
+    /// `name` folded by `fold()`, done once when the vocabulary is read.
+    pub folded: String,
+}
+
+/// Names with their number of files for a field.
+fn names_query(field: SearchType) -> Option<&'static str> {
+    match field {
+        SearchType::Actor => Some(
+            "SELECT people.person_name, COUNT(DISTINCT actors.video_id) FROM actors
+                INNER JOIN people ON people.person_id = actors.actor_id
+                GROUP BY people.person_id",
+        ),
+        SearchType::Director => Some(
+            "SELECT people.person_name, COUNT(DISTINCT directors.video_id) FROM directors
+                INNER JOIN people ON people.person_id = directors.director_id
+                GROUP BY people.person_id",
+        ),
+        SearchType::Artist => Some(
+            "SELECT artists.artist_name, COUNT(DISTINCT artist_audio_map.audio_id)
+                FROM artist_audio_map
+                INNER JOIN artists ON artists.artist_id = artist_audio_map.artist_id
+                GROUP BY artists.artist_id",
+        ),
+        SearchType::AlbumArtist => Some(
+            "SELECT artists.artist_name, COUNT(DISTINCT albumartist_audio_map.audio_id)
+                FROM albumartist_audio_map
+                INNER JOIN artists ON artists.artist_id = albumartist_audio_map.albumartist_id
+                GROUP BY artists.artist_id",
+        ),
+        SearchType::Album => Some(
+            "SELECT albums.album_name, COUNT(DISTINCT album_audio_map.audio_id)
+                FROM album_audio_map
+                INNER JOIN albums ON albums.album_id = album_audio_map.album_id
+                GROUP BY albums.album_id",
+        ),
+        SearchType::Composer => Some(
+            "SELECT composer, COUNT(*) FROM audio_metadata
+                WHERE composer IS NOT NULL AND composer <> '' GROUP BY composer",
+        ),
+        SearchType::Writer => Some(
+            "SELECT people.person_name, COUNT(DISTINCT writers.video_id) FROM writers
+                INNER JOIN people ON people.person_id = writers.writer_id
+                GROUP BY people.person_id",
+        ),
+        SearchType::Genre => Some(
+            "SELECT genre, COUNT(*) FROM
+                (SELECT genre FROM audio_metadata UNION ALL SELECT genre FROM genres)
+                WHERE genre IS NOT NULL AND genre <> '' GROUP BY genre",
+        ),
+        SearchType::Studio => Some(
+            "SELECT studio, COUNT(DISTINCT video_id) FROM studios
+                WHERE studio <> '' GROUP BY studio",
+        ),
+        SearchType::MovieSet => Some(
+            "SELECT movie_sets.set_name, COUNT(DISTINCT movie_sets_map.video_id)
+                FROM movie_sets_map
+                INNER JOIN movie_sets ON movie_sets.set_id = movie_sets_map.set_id
+                GROUP BY movie_sets.set_id",
+        ),
+        SearchType::Tag => Some(
+            "SELECT tags.tag, COUNT(DISTINCT tags_media_map.media_id) FROM tags_media_map
+                INNER JOIN tags ON tags.tag_id = tags_media_map.tagmap_id
+                GROUP BY tags.tag_id",
+        ),
+        _ => None,
+    }
+}
+
+fn read_names(connection: &Connection, field: SearchType, entries: &mut Vec<Entry>) {
+    let Some(query) = names_query(field) else {
+        return;
+    };
+    let mut statement = match connection.prepare(query) {
+        Ok(statement) => statement,
+        Err(error) => {
+            log::error!("Failed to prepare suggestion query: {}", error);
+            return;
+        }
+    };
+    let rows = statement.query_map([], |row| {
+        Ok((row.get::<_, Option<String>>(0)?, row.get::<_, i64>(1)?))
+    });
+    match rows {
+        Ok(rows) => {
+            for (name, count) in rows.flatten() {
+                match name {
+                    Some(name) if !name.trim().is_empty() => entries.push(Entry {
+                        field,
+                        folded: fold(&name),
+                        name,
+                        count: count.max(0) as usize,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        Err(error) => log::error!("Failed to read names for suggestions: {}", error),
+    }
+}
+
+/// Every name of `FIELDS` that is used by at least one file.
+pub fn vocabulary(handle: Handle) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let connection = match handle.lock() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!("Failed to lock sql connection for use! {}", error);
+            return entries;
+        }
+    };
+    for field in FIELDS {
+        read_names(&connection, field, &mut entries);
+    }
+    entries
+}
+
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// How well `name` matches `typed`, higher is better, None if it doesn't match at all.
+/// Both are expected folded by `fold()`.
+fn score(name: &str, typed: &str) -> Option<u8> {
+    if name.starts_with(typed) {
+        return Some(4);
+    }
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.iter().any(|word| word.starts_with(typed)) {
+        return Some(3);
+    }
+    if name.contains(typed) {
+        return Some(2);
+    }
+    // typos: compare with the start of every word, allowing for one missing or extra letter
+    let typed: Vec<char> = typed.chars().collect();
+    if typed.len() < 3 {
+        return None;
+    }
+    let allowed = if typed.len() < 6 { 1 } else { 2 };
+    for word in words {
+        let word: Vec<char> = word.chars().collect();
+        for length in typed.len().saturating_sub(1)..=typed.len() + 1 {
+            if length > word.len() {
+                break;
+            }
+            if edit_distance(&word[..length], &typed) <= allowed {
+                return Some(1);
+            }
+        }
+    }
+    None
+}
+
+/// The best `limit` entries of `fields` for `typed`, see the module documentation.
+pub fn rank(entries: &[Entry], typed: &str, fields: &[SearchType], limit: usize) -> Vec<Entry> {
+    let complete = typed.trim().to_lowercase();
+    let typed = fold(&complete);
+    if typed.is_empty() {
+        return Vec::new();
+    }
+    let mut matches: Vec<(u8, &Entry)> = entries
+        .iter()
+        .filter(|entry| fields.contains(&entry.field))
+        .filter_map(|entry| score(&entry.folded, &typed).map(|s| (s, entry)))
+        // "bjork" still offers "Björk", but a finished name offers nothing
+        .filter(|(_, entry)| entry.name.to_lowercase() != complete)
+        .collect();
+    matches.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then(b.count.cmp(&a.count))
+            .then(a.name.cmp(&b.name))
+    });
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, entry)| entry.clone())
+        .collect()
+}
+
+/// The term of a query that is being typed: where it starts, its field prefix if it has
+/// one and the text typed so far.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Term {
+    pub start: usize,
+    pub field: Option<SearchType>,
+    pub text: String,
+}
+
+/// The last term of `query`, None if the query ends with a space or a closed quote.
+pub fn last_term(query: &str) -> Option<Term> {
+    let mut start = 0;
+    let mut quoted = false;
+    for (index, c) in query.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => start = index + c.len_utf8(),
+            _ => {}
+        }
+    }
+    let mut term = &query[start..];
+    // a leading - or ( belongs to the query, not to the name
+    while let Some(rest) = term.strip_prefix(['-', '(']) {
+        start += 1;
+        term = rest;
+    }
+    let (field, text) = match term.split_once(':') {
+        Some((name, text)) => (Some(super::expression::field(name)?), text),
+        None => (None, term),
+    };
+    if !quoted && text.ends_with('"') {
+        return None;
+    }
+    let text = text.trim_start_matches('"');
+    if text.trim().is_empty() {
+        return None;
+    }
+    Some(Term {
+        start,
+        field,
+        text: text.to_string(),
+    })
+}
+
+/// `query` with `term` replaced by a field term for `entry`, ready for the next word.
+pub fn complete(query: &str, term: &Term, entry: &Entry) -> String {
+    let name = entry.name.replace('"', "");
+    let value = if name.contains(char::is_whitespace) {
+        format!("\"{}\"", name)
+    } else {
+        name
+    };
+    let field = super::expression::field_name(entry.field).unwrap_or_default();
+    format!("{}{}:{} ", &query[..term.start], field, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(field: SearchType, name: &str, count: usize) -> Entry {
+        Entry {
+            field,
+            name: name.to_string(),
+            count,
+            folded: fold(name),
+        }
+    }
+
+    fn names(entries: Vec<Entry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.name).collect()
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            entry(SearchType::Director, "Quentin Tarantino", 9),
+            entry(SearchType::Actor, "Quentin Tarantino", 4),
+            entry(SearchType::Actor, "Tara Reid", 2),
+            entry(SearchType::Actor, "Tarak Ben Ammar", 1),
+            entry(SearchType::Actor, "Uma Thurman", 6),
+            entry(SearchType::Genre, "Soundtrack", 30),
+            entry(SearchType::Tag, "favourite", 12),
+            entry(SearchType::Artist, "Björk", 5),
+        ]
+    }
+
+    #[test]
+    fn prefixes_come_before_words_and_typos() {
+        let all = FIELDS;
+        assert_eq!(
+            names(rank(&entries(), "tara", &all, 10)),
+            [
+                "Tara Reid",
+                "Tarak Ben Ammar",
+                "Quentin Tarantino",
+                "Quentin Tarantino"
+            ]
+        );
+        // the more often used director comes first
+        assert_eq!(
+            rank(&entries(), "tarantino", &all, 1)[0].field,
+            SearchType::Director
+        );
+        assert_eq!(
+            names(rank(&entries(), "tarntino", &all, 1)),
+            ["Quentin Tarantino"]
+        );
+        assert_eq!(names(rank(&entries(), "favorite", &all, 10)), ["favourite"]);
+        assert_eq!(names(rank(&entries(), "bjork", &all, 10)), ["Björk"]);
+        assert!(rank(&entries(), "BJÖRK", &all, 10).is_empty());
+        assert_eq!(names(rank(&entries(), "track", &all, 10)), ["Soundtrack"]);
+        assert!(rank(&entries(), "xyz", &all, 10).is_empty());
+        assert!(rank(&entries(), " ", &all, 10).is_empty());
+    }
+
+    #[test]
+    fn only_enabled_fields_are_offered() {
+        assert_eq!(
+            names(rank(
+                &entries(),
+                "thur",
+                &[SearchType::Tag, SearchType::Genre],
+                10
+            )),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            names(rank(&entries(), "uma", &[SearchType::Director], 10)),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            names(rank(&entries(), "fav", &[SearchType::Tag], 10)),
+            ["favourite"]
+        );
+    }
+
+    #[test]
+    fn the_last_term_is_completed() {
+        let term = last_term("heat -actor:\"Al Pa").unwrap();
+        assert_eq!(
+            term,
+            Term {
+                start: 6,
+                field: Some(SearchType::Actor),
+                text: "Al Pa".to_string()
+            }
+        );
+        let pacino = entry(SearchType::Actor, "Al Pacino", 3);
+        assert_eq!(
+            complete("heat -actor:\"Al Pa", &term, &pacino),
+            "heat -actor:\"Al Pacino\" "
+        );
+        let term = last_term("released:1995 (taran").unwrap();
+        assert_eq!(term.field, None);
+        let tarantino = entry(SearchType::Director, "Quentin Tarantino", 9);
+        assert_eq!(
+            complete("released:1995 (taran", &term, &tarantino),
+            "released:1995 (director:\"Quentin Tarantino\" "
+        );
+        assert_eq!(last_term("heat "), None);
+        assert_eq!(last_term("actor:\"Al Pacino\""), None);
+        assert_eq!(last_term("producer:ghib"), None);
+    }
+
+    #[test]
+    fn vocabulary_counts_files() {
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(
+            crate::sql::connect_in_memory().unwrap(),
+        ));
+        handle
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "INSERT INTO people (person_id, person_name) VALUES (1, 'Al Pacino'), (2, 'Unused');
+                INSERT INTO actors (video_id, actor_id) VALUES (10, 1), (11, 1);
+                INSERT INTO audio_metadata (audio_id, name, title, released, genre)
+                    VALUES (20, 'a', 'a', 0, 'Jazz'), (21, 'b', 'b', 0, 'Jazz'), (22, 'c', 'c', 0, '');
+                INSERT INTO genres (video_id, genre) VALUES (10, 'Jazz');
+                INSERT INTO studios (video_id, studio) VALUES (10, 'Warner Bros.'), (11, 'Warner Bros.');",
+            )
+            .unwrap();
+        let entries = vocabulary(handle);
+        assert_eq!(
+            entries,
+            [
+                entry(SearchType::Actor, "Al Pacino", 2),
+                entry(SearchType::Genre, "Jazz", 3),
+                entry(SearchType::Studio, "Warner Bros.", 2)
+            ]
+        );
+    }
+}
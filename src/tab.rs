@@ -85,6 +85,10 @@ pub const HOVER_DURATION: Duration = Duration::from_millis(1600);
 //TODO: best limit for search items
 const MAX_SEARCH_LATENCY: Duration = Duration::from_millis(20);
 const MAX_SEARCH_RESULTS: usize = 200;
+/// Number of database search results built at a time.
+const DATABASE_SEARCH_PAGE: usize = 100;
+/// The next page of database search results is built when the view is scrolled this far.
+const DATABASE_SEARCH_SCROLL: f32 = 0.9;
 //TODO: configurable thumbnail size?
 //const THUMBNAIL_SIZE: u32 = (ICON_SIZE_GRID as u32) * (ICON_SCALE_MAX as u32);
 
@@ -417,48 +421,159 @@ pub fn parse_desktop_file(path: &Path) -> (Option<String>, Option<String>) {
     )
 }
 
+/// Outcome of a recursive scan, shown when it is done.
+#[derive(Clone, Debug)]
+pub struct ScanSummary {
+    pub path: PathBuf,
+    pub files: usize,
+    pub duration: std::time::Duration,
+}
+
+impl ScanSummary {
+    pub fn files_per_second(&self) -> f64 {
+        let seconds = self.duration.as_secs_f64();
+        if seconds > 0.0 {
+            self.files as f64 / seconds
+        } else {
+            self.files as f64
+        }
+    }
+}
+
 pub fn scan_path_recursive(
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     tab_path: PathBuf,
-) {
+) -> ScanSummary {
+    let start = std::time::Instant::now();
     let mut data = crate::scanmetadata::ScanMetaData::new();
-    log::warn!("Scanning path {} recursively. This can take a long time. Only read operations should be done while this job is still running!", tab_path.display());
-    let files = crate::sql::files(sql_connection.clone());
+    log::warn!("Scanning path {} recursively. This can take a long time.", tab_path.display());
+    let files = database.read(crate::sql::files);
     for (k, v) in files.iter() {
         data.known_files_insert(k.to_path_buf(), v.to_owned());
     }
     let _ = scan_path_runner(
-        sql_connection.clone(),
+        database,
         &tab_path,
         IconSizes::default(),
         true,
         &mut data,
     );
-    log::warn!("Done Scanning path {} recursively.", tab_path.display());
+    remove_missing_files(database, &data);
+    database.flush();
+    let summary = ScanSummary {
+        path: tab_path,
+        files: data.scanned_files(),
+        duration: start.elapsed(),
+    };
+    log::warn!(
+        "Done Scanning path {} recursively, {} files at {:.1} files/s.",
+        summary.path.display(),
+        summary.files,
+        summary.files_per_second()
+    );
+    summary
+}
+
+/// Counts reported by `verify_path()`.
+#[derive(Clone, Debug, Default)]
+pub struct VerifySummary {
+    pub path: PathBuf,
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+/// Compare the media files below `tab_path` with the database without changing either.
+pub fn verify_path(database: &crate::sql::Database, tab_path: PathBuf) -> VerifySummary {
+    let files = database.read(crate::sql::files);
+    // posters, cover art and lyrics written next to the media files are not media of their own
+    let mut generated = std::collections::BTreeSet::new();
+    for (path, file) in files.iter() {
+        let path = crate::parsers::osstr_to_string(path.clone().into_os_string());
+        if file.file_type == 2 {
+            generated.insert(PathBuf::from(format!("{}_001.jpeg", path)));
+        } else if file.file_type == 3 {
+            generated.insert(PathBuf::from(format!("{}.png", path)));
+        }
+    }
+    let mut summary = VerifySummary {
+        path: tab_path.clone(),
+        ..Default::default()
+    };
+    let mut dirs = vec![tab_path.clone()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::warn!("failed to read directory {:?}: {}", dir, err);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with(".") {
+                continue;
+            }
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let mime = crate::mime_icon::mime_for_path(path.clone());
+            if generated.contains(&path)
+                || !(mime.type_() == mime_guess::mime::AUDIO
+                    || mime.type_() == mime_guess::mime::VIDEO
+                    || mime.type_() == mime_guess::mime::IMAGE)
+            {
+                continue;
+            }
+            match (files.get(&path), entry.metadata()) {
+                (Some(file), Ok(statdata)) if file.changed(&statdata) => summary.changed += 1,
+                (Some(_), _) => summary.unchanged += 1,
+                (None, _) => summary.added += 1,
+            }
+        }
+    }
+    summary.removed = files
+        .keys()
+        .filter(|path| path.starts_with(&tab_path) && !path.exists())
+        .count();
+    log::info!(
+        "Verified {}: {} added, {} changed, {} removed, {} unchanged",
+        tab_path.display(),
+        summary.added,
+        summary.changed,
+        summary.removed,
+        summary.unchanged
+    );
+    summary
 }
 
 pub fn scan_collection(
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
-    tab_path: &PathBuf,
+    database: &crate::sql::Database,
+    collection: &crate::sql::CollectionMetadata,
     sizes: IconSizes,
     recursive: bool,
 ) -> Vec<Item> {
     let data = crate::scanmetadata::ScanMetaData::new();
 
-    let files = crate::sql::files(sql_connection.clone());
+    let files = database.read(crate::sql::files);
     for (k, v) in files.iter() {
         data.known_files_insert(k.to_path_buf(), v.to_owned());
     }
-    // generate list of episodes
-    let metadata = crate::sql::collection(
-            sql_connection.clone(),
-            &crate::parsers::osstr_to_string(tab_path.clone().into_os_string()),
-            &data,
-        );
+    // generate list of episodes, a movie set has no file and lists its movies
+    let movie_set = collection.file_id == 0;
+    let metadata = if movie_set {
+        database.read(|connection| crate::sql::nfo::movie_set(connection, &collection.name))
+    } else {
+        let path = crate::parsers::osstr_to_string(collection.path.clone().into_os_string());
+        database.read(|connection| crate::sql::collection(connection, &path, &data))
+    };
     for e in metadata.episodes.iter() {
         let path = crate::parsers::osstr_to_string(e.path.clone().into_os_string());
-        let mut videometadata = crate::sql::video(sql_connection.clone(), &path, &data);
-        videometadata.season = e.series;
+        let mut videometadata =
+            database.read(|connection| crate::sql::video(connection, &path, &data));
+        videometadata.season = if movie_set { 0 } else { e.series };
         videometadata.episode = e.episode;
         crate::parsers::item_from_collection_episode(&mut videometadata, sizes, &data);
     }
@@ -472,21 +587,93 @@ pub fn scan_collection(
 
 
 pub fn scan_path(
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     tab_path: &PathBuf,
     sizes: IconSizes,
     recursive: bool,
 ) -> Vec<Item> {
     let data = crate::scanmetadata::ScanMetaData::new();
-    let files = crate::sql::files(sql_connection.clone());
+    let files = database.read(crate::sql::files);
     for (k, v) in files.iter() {
         data.known_files_insert(k.to_path_buf(), v.to_owned());
     }
-    scan_path_runner(sql_connection.clone(), &tab_path, sizes, recursive, &data)
+    // a browse never removes anything, the files may only be on a mount that is offline
+    scan_path_runner(database, &tab_path, sizes, recursive, &data)
+}
+
+/// Files that were moved or renamed show up as new paths. A new path whose content
+/// fingerprint matches a stored file that no longer exists takes over the stored metadata,
+/// tags and thumbnails instead of being scanned as a new file.
+///
+/// New and changed files are fingerprinted here, on the scanner thread, so the writer
+/// thread only has to store the fingerprint.
+fn relink_moved_files<'a>(
+    database: &crate::sql::Database,
+    paths: impl Iterator<Item = &'a PathBuf>,
+    data: &crate::scanmetadata::ScanMetaData,
+) {
+    for path in paths {
+        if data.known_files_contains(path.clone()) {
+            let changed = fs::metadata(path)
+                .map(|statdata| data.known_files_get(path.clone()).changed(&statdata))
+                .unwrap_or(false);
+            if changed {
+                if let Some(fingerprint) = crate::fingerprint::of_file(path) {
+                    data.fingerprints_insert(path.clone(), fingerprint);
+                }
+            }
+            continue;
+        }
+        let fingerprint = match crate::fingerprint::of_file(path) {
+            Some(fingerprint) => fingerprint,
+            None => continue,
+        };
+        data.fingerprints_insert(path.clone(), fingerprint.clone());
+        let moved = database
+            .read(|connection| crate::sql::files_by_fingerprint(connection, &fingerprint))
+            .into_iter()
+            .find(|file| !file.filepath.exists());
+        let (file, statdata) = match (moved, fs::metadata(path)) {
+            (Some(file), Ok(statdata)) => (file, statdata),
+            _ => continue,
+        };
+        log::info!("{} was moved to {}", file.filepath.display(), path.display());
+        let path = path.clone();
+        let data = data.clone();
+        database.write(move |connection| {
+            crate::sql::move_file(connection, &file, &path, &statdata, &data)
+        });
+        // the batch of the directory is still open, but the next lookups and the scanner
+        // read on connections that only see committed changes
+        database.flush();
+    }
+}
+
+/// Drop what is stored for files that were deleted since the last scan.
+fn remove_missing_files(
+    database: &crate::sql::Database,
+    data: &crate::scanmetadata::ScanMetaData,
+) {
+    // files that were re-linked to a new path are no longer known under the old one
+    let removed: Vec<crate::sql::FileMetadata> = data
+        .missing_files_take()
+        .into_iter()
+        .filter(|file| data.known_files_contains(file.filepath.clone()))
+        .collect();
+    if removed.is_empty() {
+        return;
+    }
+    log::info!("Removing {} deleted files", removed.len());
+    let data = data.clone();
+    database.submit(move |connection| {
+        for file in removed.iter() {
+            crate::sql::remove_file(connection.clone(), file, &data);
+        }
+    });
 }
 
 pub fn scan_path_runner(
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     tab_path: &PathBuf,
     sizes: IconSizes,
     recursive: bool,
@@ -504,6 +691,8 @@ pub fn scan_path_runner(
     }
     match fs::read_dir(tab_path) {
         Ok(entries) => {
+            // everything stored for this directory is committed in one transaction
+            let batch = database.batch();
             let mut all: Vec<PathBuf> = Vec::new();
             let mut nfos = Vec::new();
             let mut audios = Vec::new();
@@ -555,13 +744,15 @@ pub fn scan_path_runner(
                 }
             }
 
+            relink_moved_files(database, videos.iter().chain(&audios).chain(&images), data);
+
             for video in nfos {
                 if let ControlFlow::Break(_) = crate::parsers::scan_nfos_in_dir(
                     video,
                     &all,
                     &data,
                     sizes,
-                    sql_connection.clone(),
+                    database,
                 ) {
                     continue;
                 }
@@ -569,7 +760,7 @@ pub fn scan_path_runner(
 
             for video in videos {
                 if let ControlFlow::Break(_) =
-                    crate::parsers::scan_videos(video, &data, sizes, sql_connection.clone())
+                    crate::parsers::scan_videos(video, &data, sizes, database)
                 {
                     continue;
                 }
@@ -582,7 +773,7 @@ pub fn scan_path_runner(
                     data,
                     sizes,
                     &mut justdirs,
-                    sql_connection.clone(),
+                    database,
                 ) {
                     continue;
                 }
@@ -590,7 +781,7 @@ pub fn scan_path_runner(
 
             for audio in audios {
                 if let ControlFlow::Break(_) =
-                    crate::parsers::scan_audiotags(audio, data, sizes, sql_connection.clone())
+                    crate::parsers::scan_audiotags(audio, data, sizes, database)
                 {
                     continue;
                 }
@@ -598,7 +789,7 @@ pub fn scan_path_runner(
 
             for path in images {
                 if let ControlFlow::Break(_) =
-                    crate::parsers::scan_exif(path, data, sizes, sql_connection.clone())
+                    crate::parsers::scan_exif(path, data, sizes, database)
                 {
                     continue;
                 }
@@ -606,12 +797,24 @@ pub fn scan_path_runner(
             let tvshows = data.tvshows_clone();
             for path in tvshows {
                 if let ControlFlow::Break(_) =
-                    crate::parsers::scan_tvshow(path, data, sizes, sql_connection.clone())
+                    crate::parsers::scan_tvshow(path, data, sizes, database)
                 {
                     continue;
                 }
             }
             data.tvshows_clear();
+            data.scanned_files_add(all.len());
+            // stored files that were deleted since the last scan, removed when a recursive
+            // rescan is done
+            if recursive {
+                let removed: Vec<crate::sql::FileMetadata> = data
+                    .known_files_in(tab_path)
+                    .into_iter()
+                    .filter(|file| !file.filepath.exists())
+                    .collect();
+                data.missing_files_extend(removed);
+            }
+            drop(batch);
             for path in justdirs.iter() {
                 if recursive {
                     if let Some(dirname) = path.file_stem() {
@@ -620,7 +823,7 @@ pub fn scan_path_runner(
                             continue; // skip hidden directories when recursively scanning
                         }
                     }
-                    scan_path_runner(sql_connection.clone(), path, sizes, recursive, data);
+                    scan_path_runner(database, path, sizes, recursive, data);
                 } else {
                     if let ControlFlow::Break(_) =
                         crate::parsers::scan_directories(path.to_owned(), data, sizes)
@@ -671,22 +874,24 @@ fn sort_items_media_browser(items: Vec<Item>) -> Vec<Item> {
         ),
     });
     let mut sorted = Vec::new();
-    let mut albums: std::collections::BTreeMap<String, std::collections::BTreeMap<u32, Item>> =
-        std::collections::BTreeMap::new();
+    let mut albums: std::collections::BTreeMap<
+        String,
+        std::collections::BTreeMap<(u32, u32), Item>,
+    > = std::collections::BTreeMap::new();
     // sort audio content from the same album in order of track id
     for i in items2 {
         if let Some(audio) = i.audio_opt.as_ref() {
             //log::warn!("Processing audio {} which is track {} of {}", audio.title, audio.track_id, audio.album);
             if audio.album.len() > 0 && audio.track_id > 0 {
                 if albums.contains_key(&audio.album) {
-                    let track_id = audio.track_id;
+                    let track_id = (audio.disc, audio.track_id);
                     albums
                         .get_mut(&audio.album)
                         .unwrap()
                         .insert(track_id, i.clone());
                 } else {
                     let mut album = std::collections::BTreeMap::new();
-                    let track_id = audio.track_id;
+                    let track_id = (audio.disc, audio.track_id);
                     album.insert(track_id, i.clone());
                     albums.insert(audio.album.clone(), album);
                 }
@@ -723,16 +928,16 @@ fn sort_items_from_search(items: Vec<Item>, _search: &crate::sql::SearchData) ->
 }
 
 fn scan_search_db(
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     search: &crate::sql::SearchData,
 ) -> Vec<Item> {
     log::warn!("Searching database");
-    let items = crate::sql::search_items(sql_connection.clone(), search);
+    let items = database.read(|connection| crate::sql::search_items(connection, database, search));
     sort_items_from_search(items, search)
 }
 
 fn scan_tags(
-    sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+    database: &crate::sql::Database,
     t: crate::sql::Tag,
 ) -> Vec<Item> {
     log::warn!("Searching database for tag {}", t.tag);
@@ -744,7 +949,7 @@ fn scan_tags(
         tags: true,
         ..Default::default()
     };
-    let items = crate::sql::search_items(sql_connection.clone(), &search);
+    let items = database.read(|connection| crate::sql::search_items(connection, database, &search));
 
     sort_items_from_search(items, &search)
 }
@@ -897,6 +1102,8 @@ pub fn scan_trash(sizes: IconSizes) -> Vec<Item> {
                     video_opt: None,
                     audio_opt: None,
                     collection_opt: None,
+                    search_rank: None,
+                    search_snippet: None,
                 });
             }
         }
@@ -1008,6 +1215,7 @@ pub enum Location {
     Recents,
     Search(PathBuf, String, bool, Instant),
     DBSearch(crate::sql::SearchData),
+    SmartCollection(crate::sql::SmartCollection),
     Collection(crate::sql::CollectionMetadata),
     Trash,
 }
@@ -1027,6 +1235,9 @@ impl std::fmt::Display for Location {
                     search.display()
                 )
             }
+            Self::SmartCollection(collection) => {
+                write!(f, "smart collection {}", collection.name)
+            }
             Self::Collection(collection) => {
                 write!(
                     f,
@@ -1049,6 +1260,41 @@ impl Location {
         }
     }
 
+    /// Key the sort order of this location is remembered under, `None` for locations
+    /// which always sort the same way.
+    pub fn sort_key(&self) -> Option<String> {
+        match self {
+            Self::Network(uri, _) => Some(format!("network:{}", uri)),
+            Self::Path(path) => Some(format!("path:{}", path.display())),
+            Self::Tag(t) => Some(format!("tag:{}", t.tag)),
+            Self::Recents => Some("recents".to_string()),
+            Self::Search(..) => None,
+            // searches which were not saved share one sort order
+            Self::DBSearch(search) if search.search_id > 0 => {
+                Some(format!("search:{}", search.search_id))
+            }
+            Self::DBSearch(_) => Some("search".to_string()),
+            Self::SmartCollection(collection) => {
+                Some(format!("smart-collection:{}", collection.name))
+            }
+            Self::Collection(collection) => {
+                Some(format!("collection:{}", collection.path.display()))
+            }
+            Self::Trash => Some("trash".to_string()),
+        }
+    }
+
+    /// Sort order of this location until another one is picked.
+    pub fn default_sort(&self) -> LocationSort {
+        LocationSort {
+            heading: match self {
+                Self::DBSearch(..) => HeadingOptions::Relevance,
+                _ => HeadingOptions::MediaSpecific,
+            },
+            ascending: true,
+        }
+    }
+
     pub fn with_path(&self, path: PathBuf) -> Self {
         match self {
             Self::Path(..) => Self::Path(path),
@@ -1061,26 +1307,22 @@ impl Location {
 
     pub fn scan(
         &self,
-        sql_connection: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+        database: &crate::sql::Database,
         sizes: IconSizes,
     ) -> (Option<Item>, Vec<Item>) {
         let items = match self {
-            Self::Path(path) => scan_path(sql_connection.clone(), path, sizes, false),
+            Self::Path(path) => scan_path(database, path, sizes, false),
             Self::Search(..) => {
                 // Search is done incrementally
                 Vec::new()
             }
-            Self::Collection(collection) => scan_collection(
-                sql_connection.clone(),
-                &PathBuf::from(&collection.path),
-                sizes,
-                false,
-            ),
-            Self::Tag(t) => scan_tags(sql_connection.clone(), t.clone()),
+            Self::Collection(collection) => scan_collection(database, collection, sizes, false),
+            Self::Tag(t) => scan_tags(database, t.clone()),
             Self::Trash => scan_trash(sizes),
             Self::Recents => scan_recents(sizes),
             Self::Network(uri, _) => scan_network(uri, sizes),
-            Self::DBSearch(search) => scan_search_db(sql_connection.clone(), search),
+            Self::DBSearch(search) => scan_search_db(database, search),
+            Self::SmartCollection(collection) => scan_search_db(database, &collection.search),
         };
         let parent_item_opt = match self.path_opt() {
             Some(path) => match super::parsers::item_from_path(path, sizes) {
@@ -1117,6 +1359,7 @@ pub enum Command {
     AddNetworkDrive,
     AddToSidebar(PathBuf),
     ChangeLocation(String, Location, Option<Vec<PathBuf>>),
+    DatabaseSearchPage(Location, Instant, Vec<crate::sql::Found>, Controller),
     DropFiles(PathBuf, ClipboardPaste),
     EmptyTrash,
     Iced(TaskWrapper),
@@ -1126,6 +1369,7 @@ pub enum Command {
     OpenInNewWindow(PathBuf),
     OpenTrash,
     Preview(PreviewKind),
+    RememberSort(Location, LocationSort),
     WindowDrag,
     WindowToggleMaximize,
 }
@@ -1140,6 +1384,10 @@ pub enum Message {
     Config(TabConfig),
     ContextAction(Action),
     ContextMenu(Option<Point>),
+    DatabaseSearchFound(Location, Vec<crate::sql::Found>),
+    DatabaseSearchItems(Location, Instant, Vec<Item>),
+    DatabaseSearchMore,
+    DatabaseSearchStop,
     LocationContextMenuPoint(Option<Point>),
     LocationContextMenuIndex(Option<usize>),
     LocationMenuAction(LocationMenuAction),
@@ -1322,6 +1570,8 @@ pub struct Item {
     pub video_opt: Option<crate::sql::VideoMetadata>,
     pub audio_opt: Option<crate::sql::AudioMetadata>,
     pub collection_opt: Option<crate::sql::CollectionMetadata>,
+    pub search_rank: Option<f64>,
+    pub search_snippet: Option<String>,
 }
 
 impl Item {
@@ -1334,6 +1584,80 @@ impl Item {
         self.location_opt.as_ref()?.path_opt()
     }
 
+    /// Value of a media sort key, `None` for items without it.
+    pub fn sort_key(&self, heading: HeadingOptions) -> Option<SortKey> {
+        // 1970-01-01 is stored for media without a date
+        let day = |date: chrono::NaiveDate| {
+            (date != chrono::NaiveDate::default())
+                .then(|| {
+                    date.and_hms_opt(0, 0, 0)
+                        .map(|time| time.and_utc().timestamp())
+                })
+                .flatten()
+        };
+        let numbers = |first: i64, second: i64| Some(SortKey::Numbers([first, second]));
+        let video = self.video_opt.as_ref();
+        let audio = self.audio_opt.as_ref();
+        let image = self.image_opt.as_ref();
+        match heading {
+            HeadingOptions::ReleaseDate => video
+                .and_then(|video| day(video.date))
+                .or_else(|| audio.and_then(|audio| day(audio.date)))
+                .and_then(|date| numbers(date, 0)),
+            HeadingOptions::CaptureTime => image
+                .and_then(|image| match image.taken {
+                    0 => day(image.date),
+                    taken => Some(taken),
+                })
+                .and_then(|taken| numbers(taken, 0)),
+            HeadingOptions::Duration => video
+                .map(|video| video.duration)
+                .or_else(|| audio.map(|audio| audio.duration))
+                .filter(|duration| *duration > 0)
+                .and_then(|duration| numbers(duration as i64, 0)),
+            HeadingOptions::Resolution => video
+                .map(|video| (video.width, video.height))
+                .or_else(|| image.map(|image| (image.width, image.height)))
+                .filter(|(width, height)| *width > 0 && *height > 0)
+                .and_then(|(width, height)| numbers(width as i64 * height as i64, width as i64)),
+            HeadingOptions::TrackNumber => audio
+                .filter(|audio| audio.track_id > 0)
+                .and_then(|audio| numbers(audio.disc as i64, audio.track_id as i64)),
+            HeadingOptions::Album => audio
+                .filter(|audio| !audio.album.is_empty())
+                .map(|audio| SortKey::Text(audio.album.clone())),
+            HeadingOptions::Artist => audio
+                .and_then(|audio| audio.albumartist.first().or(audio.artist.first()))
+                .filter(|artist| !artist.is_empty())
+                .map(|artist| SortKey::Text(artist.clone())),
+            HeadingOptions::Episode => video
+                .filter(|video| video.season > 0 || video.episode > 0)
+                .and_then(|video| numbers(video.season as i64, video.episode as i64)),
+            HeadingOptions::Rating => video
+                .filter(|video| video.rating > 0.0)
+                .and_then(|video| numbers((video.rating * 100.0).round() as i64, 0)),
+            HeadingOptions::DateAdded => {
+                // files scanned before the time was recorded count as added when modified
+                let added = video
+                    .map(|video| video.added)
+                    .or_else(|| audio.map(|audio| audio.added))
+                    .or_else(|| image.map(|image| image.added))
+                    .filter(|added| *added > 0);
+                let modified = || {
+                    self.metadata
+                        .modified()?
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .ok()
+                        .map(|modified| modified.as_secs())
+                };
+                added
+                    .or_else(modified)
+                    .and_then(|added| numbers(added as i64, 0))
+            }
+            _ => None,
+        }
+    }
+
     pub fn can_gallery(&self) -> bool {
         self.mime.type_() == mime::IMAGE || self.mime.type_() == mime::TEXT
     }
@@ -1580,11 +1904,142 @@ impl Item {
                             width = video.width,
                             height = video.height
                         )));
+                        if video.framerate > 0.0 {
+                            details = details.push(widget::text::body(fl!(
+                                "item-video-frame-rate",
+                                fps = ((video.framerate * 1000.0).round() / 1000.0).to_string()
+                            )));
+                        }
+                        if video.codec.len() > 0 {
+                            details = details.push(
+                                widget::button::link(fl!(
+                                    "item-video-codec",
+                                    text = video.codec.clone()
+                                ))
+                                .on_press(crate::app::Message::LaunchSearch(
+                                    ST::Codec,
+                                    video.codec.clone(),
+                                ))
+                                .padding(0),
+                            );
+                        }
                         details = details.push(widget::text::body(fl!(
                             "item-media-runtime",
                             text = seconds_to_runtime(video.duration)
                         )));
+                        if video.season > 0 || video.episode > 0 {
+                            let mut episode = format!("S{:02}E{:02}", video.season, video.episode);
+                            if video.last_episode > video.episode {
+                                episode = format!("{}-E{:02}", episode, video.last_episode);
+                            }
+                            details = details.push(widget::text::body(fl!(
+                                "item-video-episode",
+                                text = episode
+                            )));
+                        }
+                        if !video.edition.is_empty() {
+                            details = details.push(widget::text::body(fl!(
+                                "item-video-edition",
+                                text = video.edition.clone()
+                            )));
+                        }
+                        if !video.source.is_empty() {
+                            details = details.push(widget::text::body(fl!(
+                                "item-video-source",
+                                text = video.source.clone()
+                            )));
+                        }
+                        if !video.original_title.is_empty() && video.original_title != video.title {
+                            details = details.push(widget::text::body(fl!(
+                                "item-video-original-title",
+                                text = video.original_title.clone()
+                            )));
+                        }
+                        if !video.mpaa.is_empty() {
+                            details = details.push(
+                                widget::button::link(fl!(
+                                    "item-video-certification",
+                                    text = video.mpaa.clone()
+                                ))
+                                .on_press(crate::app::Message::LaunchSearch(
+                                    ST::Certification,
+                                    video.mpaa.clone(),
+                                ))
+                                .padding(0),
+                            );
+                        }
+                        for rating in video.ratings.iter() {
+                            let value = ((rating.value * 10.0).round() / 10.0).to_string();
+                            let label = if rating.votes > 0 {
+                                fl!(
+                                    "item-video-rating-votes",
+                                    source = rating.source.clone(),
+                                    value = value,
+                                    max = rating.max.to_string(),
+                                    votes = rating.votes
+                                )
+                            } else {
+                                fl!(
+                                    "item-video-rating",
+                                    source = rating.source.clone(),
+                                    value = value,
+                                    max = rating.max.to_string()
+                                )
+                            };
+                            details = details.push(widget::text::body(label));
+                        }
+                        if !video.tagline.is_empty() {
+                            details = details.push(widget::text::body(video.tagline.clone()));
+                        }
                         details = details.push(widget::text::body(video.description.clone()));
+                        if !video.movie_set.is_empty() {
+                            details = details.push(
+                                widget::button::link(fl!(
+                                    "item-video-set",
+                                    text = video.movie_set.clone()
+                                ))
+                                .on_press(crate::app::Message::MovieSet(
+                                    video.movie_set.clone(),
+                                ))
+                                .padding(0),
+                            );
+                        }
+                        for l in video.genres.iter() {
+                            details = details.push(
+                                widget::button::link(fl!("item-media-genre", text = l.to_string()))
+                                    .on_press(crate::app::Message::LaunchSearch(
+                                        ST::Genre,
+                                        l.to_string(),
+                                    ))
+                                    .padding(0),
+                            );
+                        }
+                        for l in video.studios.iter() {
+                            details = details.push(
+                                widget::button::link(fl!(
+                                    "item-video-studio",
+                                    text = l.to_string()
+                                ))
+                                .on_press(crate::app::Message::LaunchSearch(
+                                    ST::Studio,
+                                    l.to_string(),
+                                ))
+                                .padding(0),
+                            );
+                        }
+                        for l in video.countries.iter() {
+                            details = details.push(
+                                widget::button::link(fl!(
+                                    "item-video-country",
+                                    text = l.to_string()
+                                ))
+                                .on_press(crate::app::Message::LaunchSearch(
+                                    ST::Country,
+                                    l.to_string(),
+                                ))
+                                .padding(0),
+                            );
+                        }
                         for l in video.tags.iter() {
                             details = details.push(
                                 widget::button::link(fl!("item-media-tag", text = l.tag.clone()))
@@ -1620,9 +2075,34 @@ impl Item {
                                 .padding(0),
                             );
                         }
+                        for l in video.writers.iter() {
+                            details = details.push(
+                                widget::button::link(fl!(
+                                    "item-media-writer",
+                                    text = l.to_string()
+                                ))
+                                .on_press(crate::app::Message::LaunchSearch(
+                                    ST::Writer,
+                                    l.to_string(),
+                                ))
+                                .padding(0),
+                            );
+                        }
                         for l in video.actors.iter() {
+                            let label = match video
+                                .roles
+                                .iter()
+                                .find(|role| &role.actor == l && !role.role.is_empty())
+                            {
+                                Some(role) => fl!(
+                                    "item-media-actor-role",
+                                    text = l.to_string(),
+                                    role = role.role.clone()
+                                ),
+                                None => fl!("item-media-actor", text = l.to_string()),
+                            };
                             details = details.push(
-                                widget::button::link(fl!("item-media-actor", text = l.to_string()))
+                                widget::button::link(label)
                                     .on_press(crate::app::Message::LaunchSearch(
                                         ST::Actor,
                                         l.to_string(),
@@ -1630,6 +2110,18 @@ impl Item {
                                     .padding(0),
                             );
                         }
+                        for id in video.unique_ids.iter() {
+                            let term = format!("{}:{}", id.kind, id.value);
+                            details = details.push(
+                                widget::button::link(fl!(
+                                    "item-video-id",
+                                    kind = id.kind.clone(),
+                                    text = id.value.clone()
+                                ))
+                                .on_press(crate::app::Message::LaunchSearch(ST::ExternalId, term))
+                                .padding(0),
+                            );
+                        }
                         for l in video.chapters.iter() {
                             let start = crate::parsers::timecode_to_ffmpeg_time(l.start as u32);
                             let end = crate::parsers::timecode_to_ffmpeg_time(l.end as u32);
@@ -1794,17 +2286,33 @@ impl Item {
                                 text = image.fnumber.clone()
                             )));
                         }
-                        if image.gps_latitude != 0.0 {
-                            details = details.push(widget::text::body(fl!(
-                                "item-image-gps-latitude",
-                                text = format!("{}", image.gps_latitude)
-                            )));
-                        }
-                        if image.gps_longitude != 0.0 {
-                            details = details.push(widget::text::body(fl!(
-                                "item-image-gps-longitude",
-                                text = format!("{}", image.gps_longitude)
-                            )));
+                        if image.gps_latitude != 0.0 || image.gps_longitude != 0.0 {
+                            let near = crate::sql::expression::near(
+                                f64::from(image.gps_latitude),
+                                f64::from(image.gps_longitude),
+                                crate::sql::geo::DEFAULT_RADIUS_KM,
+                            );
+                            for text in [
+                                fl!(
+                                    "item-image-gps-latitude",
+                                    text = format!("{}", image.gps_latitude)
+                                ),
+                                fl!(
+                                    "item-image-gps-longitude",
+                                    text = format!("{}", image.gps_longitude)
+                                ),
+                            ] {
+                                details = details.push(widget::tooltip(
+                                    widget::button::link(text)
+                                        .on_press(crate::app::Message::LaunchSearch(
+                                            ST::GPSLocation,
+                                            near.clone(),
+                                        ))
+                                        .padding(0),
+                                    widget::text::body(fl!("item-image-gps-near")),
+                                    widget::tooltip::Position::Bottom,
+                                ));
+                            }
                         }
                         if image.gps_altitude != 0.0 {
                             details = details.push(widget::text::body(fl!(
@@ -1896,6 +2404,17 @@ pub enum HeadingOptions {
     Size,
     TrashedOn,
     MediaSpecific,
+    Relevance,
+    ReleaseDate,
+    CaptureTime,
+    Duration,
+    Resolution,
+    TrackNumber,
+    Album,
+    Artist,
+    Episode,
+    Rating,
+    DateAdded,
 }
 
 impl fmt::Display for HeadingOptions {
@@ -1906,6 +2425,17 @@ impl fmt::Display for HeadingOptions {
             HeadingOptions::Size => write!(f, "{}", fl!("size")),
             HeadingOptions::TrashedOn => write!(f, "{}", "trashed-on".to_string()),
             HeadingOptions::MediaSpecific => write!(f, "{}", fl!("media-browser")),
+            HeadingOptions::Relevance => write!(f, "{}", fl!("relevance")),
+            HeadingOptions::ReleaseDate => write!(f, "{}", fl!("sort-by-release-date")),
+            HeadingOptions::CaptureTime => write!(f, "{}", fl!("sort-by-capture-time")),
+            HeadingOptions::Duration => write!(f, "{}", fl!("sort-by-duration")),
+            HeadingOptions::Resolution => write!(f, "{}", fl!("sort-by-resolution")),
+            HeadingOptions::TrackNumber => write!(f, "{}", fl!("sort-by-track-number")),
+            HeadingOptions::Album => write!(f, "{}", fl!("sort-by-album")),
+            HeadingOptions::Artist => write!(f, "{}", fl!("sort-by-artist")),
+            HeadingOptions::Episode => write!(f, "{}", fl!("sort-by-episode")),
+            HeadingOptions::Rating => write!(f, "{}", fl!("sort-by-rating")),
+            HeadingOptions::DateAdded => write!(f, "{}", fl!("sort-by-date-added")),
         }
     }
 }
@@ -1918,10 +2448,70 @@ impl HeadingOptions {
             HeadingOptions::Size.to_string(),
             HeadingOptions::MediaSpecific.to_string(),
             HeadingOptions::TrashedOn.to_string(),
+            HeadingOptions::Relevance.to_string(),
         ]
+        .into_iter()
+        .chain(
+            HeadingOptions::MEDIA
+                .iter()
+                .map(|heading| heading.to_string()),
+        )
+        .collect()
+    }
+
+    /// Sort keys taken from the media metadata, in the order the menus show them.
+    pub const MEDIA: [HeadingOptions; 10] = [
+        HeadingOptions::ReleaseDate,
+        HeadingOptions::CaptureTime,
+        HeadingOptions::Duration,
+        HeadingOptions::Resolution,
+        HeadingOptions::TrackNumber,
+        HeadingOptions::Album,
+        HeadingOptions::Artist,
+        HeadingOptions::Episode,
+        HeadingOptions::Rating,
+        HeadingOptions::DateAdded,
+    ];
+
+    /// Direction a heading sorts in when it is picked, dates and ratings start with the
+    /// newest or best.
+    pub fn ascending_first(&self) -> bool {
+        !matches!(
+            self,
+            HeadingOptions::Modified
+                | HeadingOptions::ReleaseDate
+                | HeadingOptions::CaptureTime
+                | HeadingOptions::Rating
+                | HeadingOptions::DateAdded
+        )
     }
 }
 
+/// Value of a media sort key, see `Item::sort_key()`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortKey {
+    Numbers([i64; 2]),
+    Text(String),
+}
+
+impl SortKey {
+    pub fn compare(&self, other: &SortKey) -> Ordering {
+        match (self, other) {
+            (SortKey::Numbers(a), SortKey::Numbers(b)) => a.cmp(b),
+            (SortKey::Text(a), SortKey::Text(b)) => LANGUAGE_SORTER.compare(a, b),
+            (SortKey::Numbers(_), SortKey::Text(_)) => Ordering::Less,
+            (SortKey::Text(_), SortKey::Numbers(_)) => Ordering::Greater,
+        }
+    }
+}
+
+/// Sort order of a location, remembered in the config under `Location::sort_key()`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct LocationSort {
+    pub heading: HeadingOptions,
+    pub ascending: bool,
+}
+
 #[derive(Clone, Debug)]
 pub enum Mode {
     App,
@@ -1964,6 +2554,65 @@ impl fmt::Debug for SearchContextWrapper {
     }
 }
 
+/// The files found by a database search, whose items are built a page at a time while
+/// the view is scrolled.
+#[derive(Debug)]
+pub struct DatabaseSearch {
+    started: Instant,
+    found: Vec<crate::sql::Found>,
+    requested: usize,
+    loading: bool,
+    first_page: bool,
+    stopped: bool,
+    controller: Controller,
+}
+
+impl DatabaseSearch {
+    fn new(found: Vec<crate::sql::Found>) -> Self {
+        Self {
+            started: Instant::now(),
+            found,
+            requested: 0,
+            loading: false,
+            first_page: true,
+            stopped: false,
+            controller: Controller::new(),
+        }
+    }
+
+    /// The files of the next page, unless a page is being built or there are no more.
+    fn next_page(&mut self) -> Option<(Vec<crate::sql::Found>, Controller)> {
+        if self.loading || !self.has_more() {
+            return None;
+        }
+        let end = (self.requested + DATABASE_SEARCH_PAGE).min(self.found.len());
+        let page = self.found[self.requested..end].to_vec();
+        self.requested = end;
+        self.loading = true;
+        Some((page, self.controller.clone()))
+    }
+
+    pub fn total(&self) -> usize {
+        self.found.len()
+    }
+
+    pub fn has_more(&self) -> bool {
+        !self.stopped && self.requested < self.found.len()
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    /// Paths of all found files, including the ones without an item yet.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.found
+            .iter()
+            .map(|found| found.file.filepath.clone())
+            .collect()
+    }
+}
+
 // TODO when creating items, pass <Arc<SelectedItems>> to each item
 // as a drag data, so that when dnd is initiated, they are all included
 pub struct Tab {
@@ -1994,6 +2643,7 @@ pub struct Tab {
     selected_clicked: bool,
     last_right_click: Option<usize>,
     search_context: Option<SearchContext>,
+    pub database_search: Option<DatabaseSearch>,
     pub known_files: Option<std::collections::BTreeMap<PathBuf, crate::sql::FileMetadata>>,
 }
 
@@ -2056,6 +2706,7 @@ fn _parse_hidden_file(path: &PathBuf) -> Vec<String> {
 impl Tab {
     pub fn new(location: Location, config: TabConfig) -> Self {
         let history = vec![location.clone()];
+        let sort = location.default_sort();
         Self {
             location,
             context_menu: None,
@@ -2070,8 +2721,8 @@ impl Tab {
             history_i: 0,
             history,
             config,
-            sort_name: HeadingOptions::MediaSpecific,
-            sort_direction: true,
+            sort_name: sort.heading,
+            sort_direction: sort.ascending,
             gallery: false,
             parent_item_opt: None,
             items_opt: None,
@@ -2083,6 +2734,7 @@ impl Tab {
             selected_clicked: false,
             last_right_click: None,
             search_context: None,
+            database_search: None,
             known_files: None,
         }
     }
@@ -2106,7 +2758,9 @@ impl Tab {
                 fl!("recents")
             }
             Location::DBSearch(search) => {
-                if search.from_string.len() > 0 && search.to_string.len() > 0 {
+                if search.name.len() > 0 {
+                    search.name.clone()
+                } else if search.from_string.len() > 0 && search.to_string.len() > 0 {
                     format!("{} {}", search.from_string, search.to_string)
                 } else if search.from_value > 0 && search.to_value > 0 {
                     format!("{} {}", search.from_value, search.to_value)
@@ -2128,6 +2782,7 @@ impl Tab {
                     format!("Search {}", search.search_id)
                 }
             }
+            Location::SmartCollection(collection) => collection.name.clone(),
             Location::Collection(collection) => {
                 format!("{}", collection.name)
             }
@@ -2156,6 +2811,18 @@ impl Tab {
         self.items_opt = Some(items);
     }
 
+    /// Build the next page of database search results, if there is one to build.
+    fn database_search_page(&mut self) -> Option<Command> {
+        let search = self.database_search.as_mut()?;
+        let (page, controller) = search.next_page()?;
+        Some(Command::DatabaseSearchPage(
+            self.location.clone(),
+            search.started,
+            page,
+            controller,
+        ))
+    }
+
     pub fn selected_locations(&self) -> Vec<Location> {
         let mut locations = Vec::new();
         if let Some(ref items) = self.items_opt {
@@ -2415,6 +3082,35 @@ impl Tab {
         v
     }
 
+    /// Sort by the order remembered for the location, or by its default one.
+    pub fn restore_sort(&mut self, sort_opt: Option<LocationSort>) {
+        let sort = sort_opt.unwrap_or_else(|| self.location.default_sort());
+        self.sort_name = sort.heading;
+        self.sort_direction = sort.ascending;
+    }
+
+    /// Media sort keys at least one of the shown items has.
+    pub fn media_sort_options(&self) -> Vec<HeadingOptions> {
+        let mut found = [false; HeadingOptions::MEDIA.len()];
+        if let Some(items) = self.items_opt.as_ref() {
+            for item in items.iter() {
+                for (i, heading) in HeadingOptions::MEDIA.iter().enumerate() {
+                    if !found[i] && item.sort_key(*heading).is_some() {
+                        found[i] = true;
+                    }
+                }
+                if found.iter().all(|found| *found) {
+                    break;
+                }
+            }
+        }
+        HeadingOptions::MEDIA
+            .iter()
+            .zip(found)
+            .filter_map(|(heading, found)| found.then_some(*heading))
+            .collect()
+    }
+
     pub fn change_location(&mut self, location: &Location, history_i_opt: Option<usize>) {
         self.location = location.clone();
         self.context_menu = None;
@@ -2424,6 +3120,7 @@ impl Tab {
         self.scroll_opt = None;
         self.select_focus = None;
         self.search_context = None;
+        self.database_search = None;
         if let Some(history_i) = history_i_opt {
             // Navigating in history
             self.history_i = history_i;
@@ -3122,6 +3819,9 @@ impl Tab {
 
             Message::Scroll(viewport) => {
                 self.scroll_opt = Some(viewport.absolute_offset());
+                if viewport.relative_offset().y >= DATABASE_SEARCH_SCROLL {
+                    commands.extend(self.database_search_page());
+                }
             }
             Message::ScrollToFocus => {
                 if let Some(offset) = self.select_focus_scroll() {
@@ -3130,6 +3830,62 @@ impl Tab {
                     ));
                 }
             }
+            Message::DatabaseSearchFound(location, found) => {
+                if location == self.location {
+                    // the previous results stay until the first page replaces them
+                    self.database_search = Some(DatabaseSearch::new(found));
+                    if self.items_opt.is_none() {
+                        self.items_opt = Some(Vec::new());
+                    }
+                    match self.database_search_page() {
+                        Some(command) => commands.push(command),
+                        None => self.set_items(Vec::new()),
+                    }
+                }
+            }
+            Message::DatabaseSearchItems(location, started, page) => {
+                if location == self.location {
+                    // pages of a search which was started again are dropped
+                    if let Some(search) = self
+                        .database_search
+                        .as_mut()
+                        .filter(|search| search.started == started)
+                    {
+                        search.loading = false;
+                        if search.first_page {
+                            search.first_page = false;
+                            self.set_items(sort_items_media_browser(page));
+                        } else {
+                            // the whole list is sorted again, so a page does not end up
+                            // sorted on its own below the previous ones
+                            let focused = self
+                                .select_focus
+                                .and_then(|i| self.items_opt.as_ref()?.get(i))
+                                .and_then(|item| item.location_opt.clone());
+                            let mut items = self.items_opt.take().unwrap_or_default();
+                            items.extend(page);
+                            let items = sort_items_media_browser(items);
+                            // selection is kept on the items, the focus has to follow its item
+                            self.select_focus = focused.and_then(|location| {
+                                items
+                                    .iter()
+                                    .position(|item| item.location_opt.as_ref() == Some(&location))
+                            });
+                            self.select_range = None;
+                            self.items_opt = Some(items);
+                        }
+                    }
+                }
+            }
+            Message::DatabaseSearchMore => {
+                commands.extend(self.database_search_page());
+            }
+            Message::DatabaseSearchStop => {
+                if let Some(search) = &mut self.database_search {
+                    search.stopped = true;
+                    search.controller.cancel();
+                }
+            }
             Message::SearchContext(location, context) => {
                 if location == self.location {
                     self.search_context = context.0;
@@ -3204,6 +3960,13 @@ impl Tab {
                 if !matches!(self.location, Location::Search(..)) {
                     self.sort_name = heading_option;
                     self.sort_direction = dir;
+                    commands.push(Command::RememberSort(
+                        self.location.clone(),
+                        LocationSort {
+                            heading: heading_option,
+                            ascending: dir,
+                        },
+                    ));
                 }
             }
             Message::Thumbnail(path, thumbnail) => {
@@ -3252,11 +4015,17 @@ impl Tab {
                     let heading_sort = if self.sort_name == heading_option {
                         !self.sort_direction
                     } else {
-                        // Default modified to descending, and others to ascending.
-                        heading_option != HeadingOptions::Modified
+                        heading_option.ascending_first()
                     };
                     self.sort_direction = heading_sort;
                     self.sort_name = heading_option;
+                    commands.push(Command::RememberSort(
+                        self.location.clone(),
+                        LocationSort {
+                            heading: heading_option,
+                            ascending: heading_sort,
+                        },
+                    ));
                 }
             }
             Message::Drop(Some((to, mut from))) => {
@@ -3473,7 +4242,7 @@ impl Tab {
                 let mut sorted = Vec::new();
                 let mut albums: std::collections::BTreeMap<
                     String,
-                    std::collections::BTreeMap<u32, (usize, &Item)>,
+                    std::collections::BTreeMap<(u32, u32), (usize, &Item)>,
                 > = std::collections::BTreeMap::new();
                 // sort audio content from the same album in order of track id
                 for (i, item) in items {
@@ -3481,14 +4250,14 @@ impl Tab {
                         //log::warn!("Processing audio {} which is track {} of {}", audio.title, audio.track_id, audio.album);
                         if audio.album.len() > 0 && audio.track_id > 0 {
                             if albums.contains_key(&audio.album) {
-                                let track_id = audio.track_id;
+                                let track_id = (audio.disc, audio.track_id);
                                 albums
                                     .get_mut(&audio.album)
                                     .unwrap()
                                     .insert(track_id, (i, item));
                             } else {
                                 let mut album = std::collections::BTreeMap::new();
-                                let track_id = audio.track_id;
+                                let track_id = (audio.disc, audio.track_id);
                                 album.insert(track_id, (i, item).clone());
                                 albums.insert(audio.album.clone(), album);
                             }
@@ -3523,6 +4292,66 @@ impl Tab {
                     }
                 });
             }
+            HeadingOptions::Relevance => items.sort_by(|a, b| {
+                // best full-text matches first, other search results keep their order
+                let rank = |x: &Item| x.search_rank.unwrap_or(f64::MAX);
+                check_reverse(rank(a.1).total_cmp(&rank(b.1)), sort_direction)
+            }),
+            HeadingOptions::ReleaseDate
+            | HeadingOptions::CaptureTime
+            | HeadingOptions::Duration
+            | HeadingOptions::Resolution
+            | HeadingOptions::TrackNumber
+            | HeadingOptions::Album
+            | HeadingOptions::Artist
+            | HeadingOptions::Episode
+            | HeadingOptions::Rating
+            | HeadingOptions::DateAdded => {
+                let mut keyed: Vec<_> = items
+                    .into_iter()
+                    .map(|(i, item)| (item.sort_key(sort_name), (i, item)))
+                    .collect();
+                keyed.sort_by(|(a_key, a), (b_key, b)| {
+                    if folders_first {
+                        match (a.1.metadata.is_dir(), b.1.metadata.is_dir()) {
+                            (true, false) => return Ordering::Less,
+                            (false, true) => return Ordering::Greater,
+                            _ => {}
+                        }
+                    }
+                    // items without the key go last in both directions
+                    let ord = match (a_key, b_key) {
+                        (Some(a_key), Some(b_key)) => {
+                            check_reverse(a_key.compare(b_key), sort_direction)
+                        }
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    };
+                    // albums of an artist and tracks of an album keep their order
+                    let within: &[HeadingOptions] = match sort_name {
+                        HeadingOptions::Artist => {
+                            &[HeadingOptions::Album, HeadingOptions::TrackNumber]
+                        }
+                        HeadingOptions::Album => &[HeadingOptions::TrackNumber],
+                        _ => &[],
+                    };
+                    within
+                        .iter()
+                        .fold(ord, |ord, heading| {
+                            ord.then_with(|| {
+                                match (a.1.sort_key(*heading), b.1.sort_key(*heading)) {
+                                    (Some(a_key), Some(b_key)) => a_key.compare(&b_key),
+                                    (Some(_), None) => Ordering::Less,
+                                    (None, Some(_)) => Ordering::Greater,
+                                    (None, None) => Ordering::Equal,
+                                }
+                            })
+                        })
+                        .then_with(|| LANGUAGE_SORTER.compare(&a.1.display_name, &b.1.display_name))
+                });
+                items = keyed.into_iter().map(|(_, item)| item).collect();
+            }
             HeadingOptions::TrashedOn => {
                 let time_deleted = |x: &Item| match &x.metadata {
                     ItemMetadata::Trash { entry, .. } => Some(entry.time_deleted),
@@ -4059,6 +4888,17 @@ impl Tab {
                         .into(),
                 );
             }
+            Location::SmartCollection(collection) => {
+                children.push(
+                    widget::button::custom(widget::text::heading(collection.name.clone()))
+                        .padding(space_xxxs)
+                        .on_press(Message::Location(Location::SmartCollection(
+                            collection.clone(),
+                        )))
+                        .class(theme::Button::Text)
+                        .into(),
+                );
+            }
         }
 
         for child in children {
@@ -4102,7 +4942,18 @@ impl Tab {
                         .into(),
                     widget::text::body(if has_hidden {
                         fl!("empty-folder-hidden")
-                    } else if matches!(self.location, Location::Search(..)) {
+                    } else if self
+                        .database_search
+                        .as_ref()
+                        .is_some_and(|search| search.is_loading())
+                    {
+                        fl!("searching")
+                    } else if matches!(
+                        self.location,
+                        Location::Search(..)
+                            | Location::DBSearch(..)
+                            | Location::SmartCollection(..)
+                    ) {
                         fl!("no-results")
                     } else {
                         fl!("empty-folder")
@@ -4233,7 +5084,10 @@ impl Tab {
                                 true,
                                 matches!(self.mode, Mode::Desktop),
                             )),
-                        widget::text::body(&item.name),
+                        widget::text::body(match &item.search_snippet {
+                            Some(snippet) => format!("{}\n{}", item.name, snippet),
+                            None => item.name.clone(),
+                        }),
                         widget::tooltip::Position::Bottom,
                     )
                     .into(),
@@ -4445,7 +5299,7 @@ impl Tab {
         let modified_width = 200.0;
         let size_width = 100.0;
         let condensed = size.width < (name_width + modified_width + size_width);
-        let is_search = matches!(self.location, Location::Search(..));
+        let is_search = matches!(self.location, Location::Search(..) | Location::DBSearch(..));
         let icon_size = if condensed || is_search {
             icon_sizes.list_condensed()
         } else {
@@ -4559,9 +5413,10 @@ impl Tab {
                             .into(),
                         widget::column::with_children(vec![
                             widget::text::body(item.display_name.clone()).into(),
-                            widget::text::caption(match item.path_opt() {
-                                Some(path) => path.display().to_string(),
-                                None => String::new(),
+                            widget::text::caption(match (&item.search_snippet, item.path_opt()) {
+                                (Some(snippet), _) => snippet.clone(),
+                                (None, Some(path)) => path.display().to_string(),
+                                (None, None) => String::new(),
                             })
                             .into(),
                         ])
@@ -4664,10 +5519,13 @@ impl Tab {
                                 .into(),
                             widget::column::with_children(vec![
                                 widget::text::body(item.display_name.clone()).into(),
-                                widget::text::caption(match item.path_opt() {
-                                    Some(path) => path.display().to_string(),
-                                    None => String::new(),
-                                })
+                                widget::text::caption(
+                                    match (&item.search_snippet, item.path_opt()) {
+                                        (Some(snippet), _) => snippet.clone(),
+                                        (None, Some(path)) => path.display().to_string(),
+                                        (None, None) => String::new(),
+                                    },
+                                )
                                 .into(),
                             ])
                             .width(Length::Fill)
@@ -4866,6 +5724,35 @@ impl Tab {
                     }
                 }
             }
+            Location::DBSearch(..) | Location::SmartCollection(..) => {
+                if let Some(search) = &self.database_search {
+                    let shown = self.items_opt().map_or(0, |items| items.len());
+                    let mut row = widget::row::with_capacity(3)
+                        .push(widget::text::body(fl!(
+                            "search-results-shown",
+                            shown = shown,
+                            total = search.total()
+                        )))
+                        .push(widget::horizontal_space())
+                        .align_y(Alignment::Center);
+                    if search.is_loading() {
+                        row = row.push(
+                            widget::button::standard(fl!("search-results-stop"))
+                                .on_press(Message::DatabaseSearchStop),
+                        );
+                    } else if search.has_more() {
+                        row = row.push(
+                            widget::button::standard(fl!("search-results-more"))
+                                .on_press(Message::DatabaseSearchMore),
+                        );
+                    }
+                    tab_column = tab_column.push(
+                        widget::layer_container(row)
+                            .padding([space_xxs, space_xs])
+                            .layer(cosmic_theme::Layer::Primary),
+                    );
+                }
+            }
             Location::Network(uri, _display_name) if uri == "network:///" => {
                 tab_column = tab_column.push(
                     widget::layer_container(widget::row::with_children(vec![
@@ -5142,7 +6029,9 @@ mod tests {
     use tempfile::TempDir;
     use test_log::test;
 
-    use super::{respond_to_scroll_direction, scan_path, Location, Message, Tab};
+    use super::{
+        respond_to_scroll_direction, scan_path, Command, HeadingOptions, Location, Message, Tab,
+    };
     use crate::{
         app::test_utils::{
             assert_eq_tab_path, empty_fs, eq_path_item, filter_dirs, read_dir_sorted, simple_fs,
@@ -5157,21 +6046,20 @@ mod tests {
         modifiers: Modifiers,
         expected_selected: &[bool],
     ) -> io::Result<()> {
-        let sql_conmnection: rusqlite::Connection = match crate::sql::connect() {
-            Ok(conn) => conn,
+        let database = match crate::sql::Database::in_memory() {
+            Ok(database) => database,
             Err(error) => {
                 log::error!("Failed to open Database! {}", error);
                 panic!("Ending the program as a database is required!");
             }
         };
-        let sql_connection = std::sync::Arc::new(std::sync::Mutex::new(sql_conmnection));
         let (_fs, mut tab) = tab_click_new(
             NUM_FILES,
             NUM_NESTED,
             NUM_DIRS,
             NUM_NESTED,
             NAME_LEN,
-            sql_connection,
+            &database,
         )?;
 
         // Simulate clicks by triggering Message::Click
@@ -5245,16 +6133,15 @@ mod tests {
         let entries = read_dir_sorted(path)?;
 
         debug!("Calling scan_path(\"{}\")", path.display());
-        let sql_conmnection: rusqlite::Connection = match crate::sql::connect() {
-            Ok(conn) => conn,
+        let database = match crate::sql::Database::in_memory() {
+            Ok(database) => database,
             Err(error) => {
                 log::error!("Failed to open Database! {}", error);
                 panic!("Ending the program as a database is required!");
             }
         };
-        let sql_connection = std::sync::Arc::new(std::sync::Mutex::new(sql_conmnection));
         let actual = scan_path(
-            sql_connection,
+            &database,
             &path.to_owned(),
             IconSizes::default(),
             false,
@@ -5282,15 +6169,14 @@ mod tests {
         assert!(!invalid_path.exists());
 
         debug!("Calling scan_path(\"{}\")", invalid_path.display());
-        let sql_conmnection: rusqlite::Connection = match crate::sql::connect() {
-            Ok(conn) => conn,
+        let database = match crate::sql::Database::in_memory() {
+            Ok(database) => database,
             Err(error) => {
                 log::error!("Failed to open Database! {}", error);
                 panic!("Ending the program as a database is required!");
             }
         };
-        let sql_connection = std::sync::Arc::new(std::sync::Mutex::new(sql_conmnection));
-        let actual = scan_path(sql_connection, &invalid_path, IconSizes::default(), false);
+        let actual = scan_path(&database, &invalid_path, IconSizes::default(), false);
 
         assert!(actual.is_empty());
 
@@ -5303,16 +6189,15 @@ mod tests {
         let path = fs.path();
 
         debug!("Calling scan_path(\"{}\")", path.display());
-        let sql_conmnection: rusqlite::Connection = match crate::sql::connect() {
-            Ok(conn) => conn,
+        let database = match crate::sql::Database::in_memory() {
+            Ok(database) => database,
             Err(error) => {
                 log::error!("Failed to open Database! {}", error);
                 panic!("Ending the program as a database is required!");
             }
         };
-        let sql_connection = std::sync::Arc::new(std::sync::Mutex::new(sql_conmnection));
         let actual = scan_path(
-            sql_connection,
+            &database,
             &path.to_owned(),
             IconSizes::default(),
             false,
@@ -5365,21 +6250,20 @@ mod tests {
 
     #[test]
     fn tab_click_double_opens_folder() -> io::Result<()> {
-        let sql_conmnection: rusqlite::Connection = match crate::sql::connect() {
-            Ok(conn) => conn,
+        let database = match crate::sql::Database::in_memory() {
+            Ok(database) => database,
             Err(error) => {
                 log::error!("Failed to open Database! {}", error);
                 panic!("Ending the program as a database is required!");
             }
         };
-        let sql_connection = std::sync::Arc::new(std::sync::Mutex::new(sql_conmnection));
         let (fs, mut tab) = tab_click_new(
             NUM_FILES,
             NUM_NESTED,
             NUM_DIRS,
             NUM_NESTED,
             NAME_LEN,
-            sql_connection,
+            &database,
         )?;
         let path = fs.path();
 
@@ -5541,6 +6425,190 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn media_sort_keys_order_items() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let database = crate::sql::Database::in_memory().expect("in-memory database");
+        // (file, disc, track), the notes have no track number
+        let tracks = [
+            ("one.txt", 2, 1),
+            ("two.txt", 1, 2),
+            ("three.txt", 1, 1),
+            ("notes.txt", 0, 0),
+        ];
+        for (name, ..) in tracks {
+            fs::write(path.join(name), b"audio")?;
+        }
+        let mut tab = Tab::new(Location::Path(path.into()), TabConfig::default());
+        tab.set_items(scan_path(
+            &database,
+            &path.to_path_buf(),
+            IconSizes::default(),
+            false,
+        ));
+        for item in tab
+            .items_opt_mut()
+            .expect("tab should be populated with Items")
+        {
+            let (_, disc, track_id) = tracks
+                .iter()
+                .find(|(name, ..)| item.name == *name)
+                .copied()
+                .expect("scanned file");
+            item.audio_opt = Some(crate::sql::AudioMetadata {
+                album: "Album".to_string(),
+                disc,
+                track_id,
+                ..Default::default()
+            });
+        }
+        let sorted = |tab: &Tab| -> Vec<String> {
+            tab.column_sort()
+                .expect("sorted items")
+                .into_iter()
+                .map(|(_, item)| item.name.clone())
+                .collect()
+        };
+
+        let commands = tab.update(
+            Message::ToggleSort(HeadingOptions::TrackNumber),
+            Modifiers::empty(),
+        );
+        assert!(matches!(
+            commands.as_slice(),
+            [Command::RememberSort(Location::Path(_), sort)]
+                if sort.heading == HeadingOptions::TrackNumber && sort.ascending
+        ));
+        assert_eq!(
+            sorted(&tab),
+            ["three.txt", "two.txt", "one.txt", "notes.txt"]
+        );
+        // files without the key stay last when the order is reversed
+        tab.update(
+            Message::ToggleSort(HeadingOptions::TrackNumber),
+            Modifiers::empty(),
+        );
+        assert_eq!(
+            sorted(&tab),
+            ["one.txt", "two.txt", "three.txt", "notes.txt"]
+        );
+        // the same album keeps the track order
+        tab.update(
+            Message::SetSort(HeadingOptions::Album, false),
+            Modifiers::empty(),
+        );
+        assert_eq!(
+            sorted(&tab),
+            ["three.txt", "two.txt", "one.txt", "notes.txt"]
+        );
+
+        let media_sorts = tab.media_sort_options();
+        assert!(media_sorts.contains(&HeadingOptions::TrackNumber));
+        assert!(media_sorts.contains(&HeadingOptions::Album));
+        assert!(media_sorts.contains(&HeadingOptions::DateAdded));
+        assert!(!media_sorts.contains(&HeadingOptions::ReleaseDate));
+        assert!(!media_sorts.contains(&HeadingOptions::Rating));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_counts_library_changes() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let database = crate::sql::Database::in_memory().expect("in-memory database");
+        let data = crate::scanmetadata::ScanMetaData::new();
+        for name in ["kept.jpg", "edited.jpg", "new.jpg"] {
+            fs::write(path.join(name), b"jpeg")?;
+        }
+        let statdata = fs::metadata(path.join("kept.jpg"))?;
+        for name in ["kept.jpg", "edited.jpg", "deleted.jpg"] {
+            let file = path.join(name).to_string_lossy().to_string();
+            let statdata = statdata.clone();
+            let data = data.clone();
+            database.write(move |connection| {
+                crate::sql::insert_file(connection, &file, &statdata, 1, &data)
+            });
+        }
+        fs::write(path.join("edited.jpg"), b"a longer jpeg")?;
+
+        let summary = super::verify_path(&database, path.to_path_buf());
+        assert_eq!(summary.added, 1);
+        assert_eq!(summary.changed, 1);
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.unchanged, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn browsing_keeps_missing_files() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        let database = crate::sql::Database::in_memory().expect("in-memory database");
+        let data = crate::scanmetadata::ScanMetaData::new();
+        fs::write(path.join("kept.jpg"), b"jpeg")?;
+        let statdata = fs::metadata(path.join("kept.jpg"))?;
+        let missing = path.join("offline.jpg");
+        let file = missing.to_string_lossy().to_string();
+        database.write(move |connection| {
+            crate::sql::insert_file(connection, &file, &statdata, 1, &data)
+        });
+
+        let _ = scan_path(&database, &path.to_path_buf(), IconSizes::default(), false);
+        database.flush();
+        assert!(database.read(crate::sql::files).contains_key(&missing));
+
+        let _ = super::scan_path_recursive(&database, path.to_path_buf());
+        assert!(!database.read(crate::sql::files).contains_key(&missing));
+
+        Ok(())
+    }
+
+    #[test]
+    fn moved_file_is_relinked() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+        // readers of an in-memory database share the writer's connection and would see
+        // uncommitted changes
+        let dir = tempfile::tempdir()?;
+        let database = crate::sql::Database::open_at(&dir.path().join("metadata.sqlite"))
+            .expect("database file");
+        let data = crate::scanmetadata::ScanMetaData::new();
+        let before = path.join("before.jpg");
+        fs::write(&before, b"jpeg")?;
+        let file = before.to_string_lossy().to_string();
+        let statdata = fs::metadata(&before)?;
+        let fingerprint = crate::fingerprint::of_file(&before).expect("fingerprint");
+        data.fingerprints_insert(before.clone(), fingerprint);
+        let writer_data = data.clone();
+        let metadata_id = database.write(move |connection| {
+            crate::sql::insert_file(connection, &file, &statdata, 1, &writer_data)
+        });
+        fs::create_dir(path.join("moved"))?;
+        let after = path.join("moved").join("after.jpg");
+        fs::rename(&before, &after)?;
+        // a copy of a file that still exists is a new file
+        let copy = path.join("copy.jpg");
+        fs::copy(&after, &copy)?;
+
+        // like a scan of the directory
+        let batch = database.batch();
+        super::relink_moved_files(&database, [after.clone(), copy.clone()].iter(), &data);
+        assert!(data.known_files_contains(after.clone()));
+        assert!(!data.known_files_contains(before));
+        assert!(!data.known_files_contains(copy));
+        let stored = database.read(|connection| {
+            crate::sql::file(connection, &after.to_string_lossy())
+        });
+        assert_eq!(stored.metadata_id, metadata_id as i64);
+        assert_eq!(stored.filepath, after);
+        drop(batch);
+
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -13,6 +13,12 @@ pub fn calculate_hash<T: Hash>(t: &T) -> u64 {
     s.finish()
 }
 
+/// Directory only media-browser writes thumbnails and posters to. `None` if they go to the
+/// shared ~/.thumbs directory, which must not be cleaned up.
+pub fn thumbnail_dir() -> Option<std::path::PathBuf> {
+    dirs::data_local_dir().map(|pb| pb.join("media-browser").join("thumbs"))
+}
+
 pub fn thumbnail_path(path: &std::path::PathBuf) -> std::path::PathBuf {
     let thumbpath;
     let hashvalue: u64 = calculate_hash(path);